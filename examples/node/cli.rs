@@ -16,13 +16,15 @@ use rustyline::Editor;
 use std::cmp::min;
 use std::collections::HashSet;
 use std::path::Path;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use uniffi_lipalightninglib::{
-    ActionRequiredItem, Activity, Amount, ChannelCloseInfo, ChannelCloseState, DecodedData,
-    FailedSwapInfo, FeatureFlag, FiatValue, IncomingPaymentInfo, InvoiceCreationMetadata,
+    ActionRequiredItem, Activity, Amount, AutoResolutionPolicy, AutoResolutionStrategy,
+    ChannelCloseInfo, ChannelCloseState, DecodedData, ExchangeRateAlertDirection, FailedSwapInfo,
+    FeatureFlag, FeePriority, FiatValue, IncomingPaymentInfo, InvoiceCreationMetadata,
     InvoiceDetails, LightningNode, LiquidityLimit, LnUrlPayDetails, LnUrlWithdrawDetails,
-    MaxRoutingFeeMode, Offer, OfferInfo, OutgoingPaymentInfo, PaymentInfo, PaymentMetadata,
-    RangeHit, Recipient, TzConfig,
+    MaxLspFee, MaxRoutingFeeMode, Offer, OfferInfo, OnchainFeeRates, OutgoingPaymentInfo,
+    PaymentInfo, PaymentMetadata, RangeHit, ReceiveRecommendation, Recipient, SwapDepositIssue,
+    TzConfig,
 };
 
 pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
@@ -54,6 +56,11 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                 "n" | "nodeinfo" => {
                     node_info(node);
                 }
+                "listchannels" => {
+                    if let Err(message) = list_channels(node) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
                 "walletpubkeyid" => {
                     if let Err(message) = wallet_pubkey_id(node) {
                         println!("{}", format!("{message:#}").red());
@@ -62,6 +69,11 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                 "lspfee" => {
                     lsp_fee(node);
                 }
+                "maxlspfee" => {
+                    if let Err(message) = max_lsp_fee(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
                 "calculatelspfee" => {
                     if let Err(message) = calculate_lsp_fee(node, &mut words) {
                         println!("{}", format!("{message:#}").red());
@@ -86,6 +98,26 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                         println!("{}", format!("{message:#}").red());
                     }
                 }
+                "setsecondarycurrencies" => {
+                    if let Err(message) = set_secondary_currencies(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
+                "addalert" => {
+                    if let Err(message) = add_exchange_rate_alert(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
+                "listalerts" => {
+                    if let Err(message) = list_exchange_rate_alerts(node) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
+                "removealert" => {
+                    if let Err(message) = remove_exchange_rate_alert(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
                 "changetimezone" => {
                     if let Err(message) = change_timezone(node, &mut words) {
                         println!("{}", format!("{message:#}").red());
@@ -96,6 +128,11 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                         println!("{}", format!("{message:#}").red());
                     }
                 }
+                "cancelinvoice" => {
+                    if let Err(message) = cancel_invoice(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
                 "d" | "decodedata" => {
                     if let Err(message) = decode_data(node, &mut words) {
                         println!("{}", format!("{message:#}").red());
@@ -113,6 +150,11 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                         println!("{}", format!("{message:#}").red());
                     }
                 }
+                "advisereceive" => {
+                    if let Err(message) = advise_receive(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
                 "getinvoiceaffordability" => {
                     if let Err(message) = get_invoice_affordability(node, &mut words) {
                         println!("{}", format!("{message:#}").red());
@@ -123,6 +165,11 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                         println!("{}", format!("{message:#}").red());
                     }
                 }
+                "cancelqueuedpayment" => {
+                    if let Err(message) = cancel_queued_payment(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
                 "payopeninvoice" => {
                     if let Err(message) = pay_open_invoice(node, &mut words) {
                         println!("{}", format!("{message:#}").red());
@@ -133,6 +180,11 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                         println!("{}", format!("{message:#}").red());
                     }
                 }
+                "listswaps" => {
+                    if let Err(message) = list_swaps(node) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
                 "listfailedswaps" => {
                     if let Err(message) = list_failed_swaps(node) {
                         println!("{}", format!("{message:#}").red());
@@ -206,6 +258,11 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                         println!("{}", format!("{message:#}").red())
                     }
                 }
+                "dismisscancelledinvoicepayment" => {
+                    if let Err(message) = dismiss_cancelled_invoice_payment(node, &mut words) {
+                        println!("{}", format!("{message:#}").red())
+                    }
+                }
                 "l" | "listactivities" => {
                     if let Err(message) = list_activities(node, &mut words) {
                         println!("{}", format!("{message:#}").red());
@@ -244,6 +301,11 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                         println!("{}", format!("{message:#}").red());
                     }
                 }
+                "balancehistory" => {
+                    if let Err(message) = balance_history(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
                 "swaponchaintolightning" => {
                     if let Err(message) = swap_onchain_to_lightning(node) {
                         println!("{}", format!("{message:#}").red());
@@ -259,6 +321,16 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                         println!("{}", format!("{message:#}").red());
                     }
                 }
+                "bumpfee" => {
+                    if let Err(message) = bump_fee(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
+                "autoresolve" => {
+                    if let Err(message) = auto_resolve(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
                 "clearwalletinfo" => {
                     if let Err(message) = clear_wallet_info(node) {
                         println!("{}", format!("{message:#}").red());
@@ -269,6 +341,11 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                         println!("{}", format!("{message:#}").red());
                     }
                 }
+                "sendonchain" => {
+                    if let Err(message) = send_onchain(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
                 "registerlightningaddress" => match node.lightning_address().register() {
                     Ok(address) => println!("{address}"),
                     Err(message) => println!("{}", format!("{message:#}").red()),
@@ -317,6 +394,11 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                     Ok(status) => println!("{status:?}"),
                     Err(message) => println!("{}", format!("{message:#}").red()),
                 },
+                "notificationlog" => {
+                    if let Err(message) = notification_log(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
                 "foreground" => {
                     node.config().foreground();
                 }
@@ -328,6 +410,16 @@ pub(crate) fn poll_for_user_input(node: &LightningNode, log_file_path: &str) {
                         println!("{}", format!("{message:#}").red());
                     }
                 }
+                "listlsps" => {
+                    if let Err(message) = list_lsps(node) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
+                "switchlsp" => {
+                    if let Err(message) = switch_lsp(node, &mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
                 "stop" => {
                     break;
                 }
@@ -350,8 +442,13 @@ fn setup_editor(history_path: &Path) -> Editor<CommandHinter, DefaultHistory> {
     let mut hints = HashSet::new();
     hints.insert(CommandHint::new("n", "n"));
     hints.insert(CommandHint::new("nodeinfo", "nodeinfo"));
+    hints.insert(CommandHint::new("listchannels", "listchannels"));
     hints.insert(CommandHint::new("walletpubkeyid", "walletpubkeyid"));
     hints.insert(CommandHint::new("lspfee", "lspfee"));
+    hints.insert(CommandHint::new(
+        "maxlspfee [off | <max fee in SAT or -> [max fee permyriad]]",
+        "maxlspfee ",
+    ));
     hints.insert(CommandHint::new(
         "calculatelspfee <amount in SAT>",
         "calculatelspfee ",
@@ -366,6 +463,16 @@ fn setup_editor(history_path: &Path) -> Editor<CommandHinter, DefaultHistory> {
         "changecurrency <currency code>",
         "changecurrency ",
     ));
+    hints.insert(CommandHint::new(
+        "setsecondarycurrencies [currency code]...",
+        "setsecondarycurrencies ",
+    ));
+    hints.insert(CommandHint::new(
+        "addalert <currency code> <above|below> <price in minor units>",
+        "addalert ",
+    ));
+    hints.insert(CommandHint::new("listalerts", "listalerts"));
+    hints.insert(CommandHint::new("removealert <id>", "removealert "));
     hints.insert(CommandHint::new(
         "changetimezone [timezone offset in mins] [timezone id]",
         "changetimezone ",
@@ -376,6 +483,7 @@ fn setup_editor(history_path: &Path) -> Editor<CommandHinter, DefaultHistory> {
         "invoice <amount in SAT> [description]",
         "invoice ",
     ));
+    hints.insert(CommandHint::new("cancelinvoice <hash>", "cancelinvoice "));
     hints.insert(CommandHint::new("d <data>", "d "));
     hints.insert(CommandHint::new("decodedata <data>", "decodedata "));
     hints.insert(CommandHint::new(
@@ -386,6 +494,10 @@ fn setup_editor(history_path: &Path) -> Editor<CommandHinter, DefaultHistory> {
         "getmaxroutingfeemode <payment amount in SAT>",
         "getmaxroutingfeemode ",
     ));
+    hints.insert(CommandHint::new(
+        "advisereceive [amount in SAT]...",
+        "advisereceive ",
+    ));
     hints.insert(CommandHint::new(
         "getinvoiceaffordability <amount in SAT>",
         "getinvoiceaffordability ",
@@ -396,6 +508,10 @@ fn setup_editor(history_path: &Path) -> Editor<CommandHinter, DefaultHistory> {
         "payopeninvoice <invoice> <amount in SAT>",
         "payopeninvoice ",
     ));
+    hints.insert(CommandHint::new(
+        "cancelqueuedpayment <id>",
+        "cancelqueuedpayment ",
+    ));
     hints.insert(CommandHint::new(
         "paylnurlp <lnurlp> <amount in SAT> [comment]",
         "paylnurlp ",
@@ -406,13 +522,14 @@ fn setup_editor(history_path: &Path) -> Editor<CommandHinter, DefaultHistory> {
     ));
 
     hints.insert(CommandHint::new("getswapaddress", "getswapaddress"));
+    hints.insert(CommandHint::new("listswaps", "listswaps"));
     hints.insert(CommandHint::new("listfailedswaps", "listfailedswaps"));
     hints.insert(CommandHint::new(
         "getfailedswapresolvingfees <swap address>",
         "getfailedswapresolvingfees ",
     ));
     hints.insert(CommandHint::new(
-        "refundfailedswap <swap address> <to address>",
+        "refundfailedswap <swap address> <to address> [fee priority]",
         "refundfailedswap ",
     ));
 
@@ -441,6 +558,10 @@ fn setup_editor(history_path: &Path) -> Editor<CommandHinter, DefaultHistory> {
         "hidefailedswapitem <swap address>",
         "hidefailedswapitem ",
     ));
+    hints.insert(CommandHint::new(
+        "dismisscancelledinvoicepayment <hash>",
+        "dismisscancelledinvoicepayment ",
+    ));
 
     hints.insert(CommandHint::new(
         "o [number of activities = 10] [fun mode = false]",
@@ -489,9 +610,28 @@ fn setup_editor(history_path: &Path) -> Editor<CommandHinter, DefaultHistory> {
         "personalnote <payment hash> [note]",
         "personalnote ",
     ));
-    hints.insert(CommandHint::new("sweep <address>", "sweep "));
+    hints.insert(CommandHint::new(
+        "balancehistory [number of days = 7]",
+        "balancehistory ",
+    ));
+    hints.insert(CommandHint::new("sweep <address> [fee priority]", "sweep "));
+    hints.insert(CommandHint::new(
+        "bumpfee <txid> <sat per vbyte>",
+        "bumpfee ",
+    ));
+    hints.insert(CommandHint::new(
+        "autoresolve [off | swap <max fee permyriad> | sweep <address> <max fee permyriad>]",
+        "autoresolve ",
+    ));
     hints.insert(CommandHint::new("clearwalletinfo", "clearwalletinfo"));
-    hints.insert(CommandHint::new("clearwallet <address>", "clearwallet "));
+    hints.insert(CommandHint::new(
+        "clearwallet <address> [fee priority]",
+        "clearwallet ",
+    ));
+    hints.insert(CommandHint::new(
+        "sendonchain <address> <amount in SAT> [fee priority]",
+        "sendonchain ",
+    ));
     hints.insert(CommandHint::new(
         "getchannelcloseresolvingfees",
         "getchannelcloseresolvingfees",
@@ -502,9 +642,15 @@ fn setup_editor(history_path: &Path) -> Editor<CommandHinter, DefaultHistory> {
     ));
     hints.insert(CommandHint::new("logdebug", "logdebug"));
     hints.insert(CommandHint::new("health", "health"));
+    hints.insert(CommandHint::new(
+        "notificationlog [number of outcomes = 10]",
+        "notificationlog ",
+    ));
     hints.insert(CommandHint::new("foreground", "foreground"));
     hints.insert(CommandHint::new("background", "background"));
     hints.insert(CommandHint::new("closechannels", "closechannels"));
+    hints.insert(CommandHint::new("listlsps", "listlsps"));
+    hints.insert(CommandHint::new("switchlsp <lsp id>", "switchlsp "));
     hints.insert(CommandHint::new("stop", "stop"));
     hints.insert(CommandHint::new("help", "help"));
     hints.insert(CommandHint::new("h", "h"));
@@ -518,30 +664,40 @@ fn setup_editor(history_path: &Path) -> Editor<CommandHinter, DefaultHistory> {
 
 fn help() {
     println!("  n | nodeinfo");
+    println!("  listchannels");
     println!("  walletpubkeyid");
     println!("  lspfee");
+    println!("  maxlspfee [off | <max fee in SAT or -> [max fee permyriad]]");
     println!("  calculatelspfee <amount in SAT>");
     println!("  calculateswaplspfee <amount in SAT>");
     println!("  paymentamountlimits");
     println!("  exchangerates");
     println!("  listcurrencies");
     println!("  changecurrency <currency code>");
+    println!("  setsecondarycurrencies [currency code]...");
+    println!("  addalert <currency code> <above|below> <price in minor units>");
+    println!("  listalerts");
+    println!("  removealert <id>");
     println!("  changetimezone [timezone offset in mins] [timezone id]");
     println!();
     println!("  i | invoice <amount in SAT> [description]");
+    println!("  cancelinvoice <hash>");
     println!("  d | decodedata <data>");
     println!("  parsephonenumber <phone number>");
     println!("  getmaxroutingfeemode <payment amount in SAT>");
+    println!("  advisereceive [amount in SAT]...");
     println!("  getinvoiceaffordability <amount in SAT>");
     println!("  p | payinvoice <invoice>");
     println!("  payopeninvoice <invoice> <amount in SAT>");
+    println!("  cancelqueuedpayment <id>");
     println!("  paylnurlp <lnurlp> <amount in SAT> [comment]");
     println!("  withdrawlnurlw <lnurlw> <amount in SAT>");
     println!();
     println!("  getswapaddress");
+    println!("  listswaps");
     println!("  listfailedswaps");
     println!("  getfailedswapresolvingfees <swap address>");
-    println!("  refundfailedswap <swap address> <to address>");
+    println!("  refundfailedswap <swap address> <to address> [fee priority]");
     println!();
     println!("  registertopup <IBAN> <currency> <provider> [email] [referral code]");
     println!("  resettopup");
@@ -553,6 +709,7 @@ fn help() {
     println!("  listactionableitems");
     println!("  hidechannelcloseitem");
     println!("  hidefailedswapitem <swap address>");
+    println!("  dismisscancelledinvoicepayment <hash>");
     println!();
     println!("  o | overview [number of activities = 10] [fun mode = false]");
     println!("  l | listactivities [number of activities = 2]");
@@ -565,21 +722,33 @@ fn help() {
     println!("  queryverifiedphonenumber");
     println!("  paymentuuid <payment hash>");
     println!("  personalnote <payment hash> [note]");
+    println!("  balancehistory [number of days = 7]");
     println!();
     println!("  getchannelcloseresolvingfees");
-    println!("  sweep <address>");
+    println!("  sweep <address> [fee priority]");
+    println!("  bumpfee <txid> <sat per vbyte>");
+    println!(
+        "  autoresolve [off | swap <max fee permyriad> | sweep <address> <max fee permyriad>]"
+    );
     println!("  swaponchaintolightning");
     println!("  clearwalletinfo");
-    println!("  clearwallet <address>");
+    println!("  clearwallet <address> [fee priority]");
+    println!("  sendonchain <address> <amount in SAT> [fee priority]");
     println!();
+    println!(
+        "  fee priority: fastest, halfhour (default), hour, economy, minimum or <sat per vbyte>"
+    );
     println!("  setfeatureflag <feature> <enabled>");
     println!("  logdebug");
     println!("  health");
+    println!("  notificationlog [number of outcomes = 10]");
     println!();
     println!("  foreground");
     println!("  background");
     println!();
     println!("  closechannels");
+    println!("  listlsps");
+    println!("  switchlsp <lsp id>");
     println!();
     println!("  stop");
 }
@@ -594,6 +763,48 @@ fn lsp_fee(node: &LightningNode) {
         "Fee rate: {}%",
         lsp_fee.channel_fee_permyriad as f64 / 100f64
     );
+    let valid_for = lsp_fee
+        .valid_until
+        .duration_since(SystemTime::now())
+        .unwrap_or_default();
+    println!("Valid for: {} min", valid_for.as_secs() / 60);
+}
+
+fn max_lsp_fee(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
+    let max_lsp_fee = match words.next() {
+        None => {
+            match node.config().get_max_lsp_fee()? {
+                Some(max_lsp_fee) => println!(
+                    "Max fee: {:?} SAT, max fee permyriad: {:?}",
+                    max_lsp_fee.max_fee_sat, max_lsp_fee.max_fee_permyriad
+                ),
+                None => println!("No limit on LSP fees"),
+            }
+            return Ok(());
+        }
+        Some("off") => None,
+        Some(max_fee_sat) => {
+            let max_fee_sat = match max_fee_sat {
+                "-" => None,
+                max_fee_sat => Some(
+                    max_fee_sat
+                        .parse()
+                        .context("Max fee should be a positive integer number")?,
+                ),
+            };
+            let max_fee_permyriad = words
+                .next()
+                .map(|p| p.parse())
+                .transpose()
+                .context("Max fee permyriad should be a positive integer number")?;
+            Some(MaxLspFee {
+                max_fee_sat,
+                max_fee_permyriad,
+            })
+        }
+    };
+    node.config().set_max_lsp_fee(max_lsp_fee)?;
+    Ok(())
 }
 
 fn calculate_lsp_fee(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
@@ -684,6 +895,87 @@ fn node_info(node: &LightningNode) {
     );
 }
 
+fn notification_log(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
+    let number_of_outcomes = words
+        .next()
+        .unwrap_or("10")
+        .parse()
+        .context("Number of outcomes should be a positive integer number")?;
+    let outcomes = node.util().list_notification_outcomes(number_of_outcomes)?;
+    if outcomes.is_empty() {
+        println!("No handled notifications");
+    }
+
+    for outcome in outcomes {
+        let received_at: DateTime<Local> = outcome.received_at.into();
+        let duplicate = if outcome.is_duplicate {
+            " (duplicate)"
+        } else {
+            ""
+        };
+        println!(
+            "{} {}{duplicate} in {} ms",
+            received_at.format("%Y-%m-%d %H:%M:%S"),
+            outcome.payload_kind.bold(),
+            outcome.duration.as_millis()
+        );
+        if let Some(notification) = outcome.notification {
+            println!("      {notification:?}");
+        }
+        if let Some(error) = outcome.error {
+            println!("      {}", error.red());
+        }
+    }
+    Ok(())
+}
+
+fn list_channels(node: &LightningNode) -> Result<()> {
+    let channels = node.util().list_channels()?;
+    if channels.is_empty() {
+        println!("No channels");
+    }
+
+    for channel in channels {
+        println!("{} ({:?})", channel.funding_txid.bold(), channel.state);
+        if let Some(short_channel_id) = channel.short_channel_id {
+            println!("      Short channel id: {short_channel_id}");
+        }
+        if let Some(counterparty) = channel.counterparty_pubkey {
+            println!("      Counterparty:     {counterparty}");
+        }
+        println!(
+            "      Capacity:         {}",
+            amount_to_string(&channel.capacity)
+        );
+        println!(
+            "      Local balance:    {}",
+            amount_to_string(&channel.local_balance)
+        );
+        println!(
+            "      Remote balance:   {}",
+            amount_to_string(&channel.remote_balance)
+        );
+        println!(
+            "      Reserve:          {}",
+            amount_to_string(&channel.reserve)
+        );
+        if let Some(lsp_opening_fee) = channel.lsp_opening_fee {
+            println!(
+                "      LSP opening fee:  {}",
+                amount_to_string(&lsp_opening_fee)
+            );
+        }
+        if let Some(closing_txid) = channel.closing_txid {
+            println!("      Closing txid:     {closing_txid}");
+        }
+        if let Some(close_reason) = channel.close_reason {
+            println!("      Close reason:     {close_reason:?}");
+        }
+    }
+
+    Ok(())
+}
+
 fn wallet_pubkey_id(node: &LightningNode) -> Result<()> {
     let wallet_pubkey_id = node.util().query_wallet_pubkey_id()?;
 
@@ -697,10 +989,11 @@ fn get_exchange_rate(node: &LightningNode) {
         Some(r) => {
             let dt: DateTime<Utc> = r.updated_at.into();
             println!(
-                "{}: {} SAT - updated at {} UTC",
+                "{}: {} SAT - updated at {} UTC by {}",
                 r.currency_code,
                 r.rate,
-                dt.format("%d/%m/%Y %T")
+                dt.format("%d/%m/%Y %T"),
+                r.source
             );
         }
         None => {
@@ -723,6 +1016,78 @@ fn change_currency(node: &LightningNode, words: &mut dyn Iterator<Item = &str>)
     Ok(())
 }
 
+fn set_secondary_currencies(
+    node: &LightningNode,
+    words: &mut dyn Iterator<Item = &str>,
+) -> Result<()> {
+    let fiat_currencies = words.map(String::from).collect();
+    node.config()
+        .set_secondary_fiat_currencies(fiat_currencies)?;
+    Ok(())
+}
+
+fn add_exchange_rate_alert(
+    node: &LightningNode,
+    words: &mut dyn Iterator<Item = &str>,
+) -> Result<()> {
+    let currency_code = words.next().ok_or(anyhow!("Currency code is required"))?;
+    let direction = match words.next() {
+        Some("above") => ExchangeRateAlertDirection::Above,
+        Some("below") => ExchangeRateAlertDirection::Below,
+        _ => bail!("Direction must be either `above` or `below`"),
+    };
+    let threshold_minor_units: u64 = words
+        .next()
+        .ok_or(anyhow!("Price is required"))?
+        .parse()
+        .context("Price should be a positive integer number")?;
+    let id = node.config().add_exchange_rate_alert(
+        currency_code.to_string(),
+        threshold_minor_units,
+        direction,
+    )?;
+    println!("Added alert with id {id}");
+    Ok(())
+}
+
+fn list_exchange_rate_alerts(node: &LightningNode) -> Result<()> {
+    let alerts = node.config().list_exchange_rate_alerts()?;
+    if alerts.is_empty() {
+        println!("No exchange rate alerts");
+    }
+    for alert in alerts {
+        let last_triggered_at = match alert.last_triggered_at {
+            Some(t) => {
+                let t: DateTime<Utc> = t.into();
+                t.format("%d/%m/%Y %T UTC").to_string()
+            }
+            None => "never".to_string(),
+        };
+        println!(
+            "{}: {} {:?} {} (armed: {}, last triggered: {last_triggered_at})",
+            alert.id,
+            alert.currency_code,
+            alert.direction,
+            alert.threshold_minor_units,
+            alert.armed
+        );
+    }
+    Ok(())
+}
+
+fn remove_exchange_rate_alert(
+    node: &LightningNode,
+    words: &mut dyn Iterator<Item = &str>,
+) -> Result<()> {
+    let id: u64 = words
+        .next()
+        .ok_or(anyhow!("Id is required"))?
+        .parse()
+        .context("Id should be a positive integer number")?;
+    node.config().remove_exchange_rate_alert(id)?;
+    Ok(())
+}
+
 fn change_timezone(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
     let timezone_utc_offset_mins: i32 = words
         .next()
@@ -769,6 +1134,25 @@ fn create_invoice(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -
     Ok(())
 }
 
+fn cancel_invoice(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
+    let hash = words.next().ok_or(anyhow!("Hash is required"))?;
+    node.lightning().bolt11().cancel(hash.to_string())?;
+    Ok(())
+}
+
+fn cancel_queued_payment(
+    node: &LightningNode,
+    words: &mut dyn Iterator<Item = &str>,
+) -> Result<()> {
+    let id: u64 = words
+        .next()
+        .ok_or(anyhow!("Id is required"))?
+        .parse()
+        .context("Id should be a positive integer number")?;
+    node.lightning().outbox().cancel(id)?;
+    Ok(())
+}
+
 fn decode_data(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
     let data = words.next().ok_or(anyhow!("Data is required"))?;
 
@@ -918,6 +1302,93 @@ fn get_max_routing_fee_mode(
     Ok(())
 }
 
+fn advise_receive(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
+    let expected_amounts_sat = words
+        .map(|w| w.parse().context("Couldn't parse amount as u64"))
+        .collect::<Result<Vec<u64>>>()?;
+
+    let advice = node.lightning().advise_receive(expected_amounts_sat)?;
+
+    match advice.recommendation {
+        ReceiveRecommendation::NoChannelNeeded => println!("No channel open needed"),
+        ReceiveRecommendation::OpenChannelNow { channel_size } => println!(
+            "Accept a channel open now by receiving at least {} at once",
+            amount_to_string(&channel_size)
+        ),
+        ReceiveRecommendation::OpenOnDemand => println!("Let channels be opened on demand"),
+    }
+    println!(
+        "Inbound capacity: {}",
+        amount_to_string(&advice.inbound_capacity)
+    );
+    println!(
+        "Expected receives: {}",
+        advice
+            .expected_amounts
+            .iter()
+            .map(amount_to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    for (name, estimate) in [
+        ("Cheaper offer", advice.cheaper_offer),
+        ("Longer valid offer", advice.longer_valid_offer),
+    ] {
+        let valid_until: DateTime<Local> = estimate.lsp_fee.valid_until.into();
+        println!("{name} (valid until {valid_until}):");
+        println!(
+            "      On demand fees: {}",
+            amount_to_string(&estimate.on_demand_fees)
+        );
+        println!(
+            "      Open now fees:  {}",
+            amount_to_string(&estimate.open_now_fees)
+        );
+    }
+
+    Ok(())
+}
+
+fn list_lsps(node: &LightningNode) -> Result<()> {
+    for lsp in node.lsp().list()? {
+        let current = if lsp.is_current { " (current)" } else { "" };
+        println!("{}{current}", lsp.name.bold());
+        println!("      Id:          {}", lsp.id);
+        println!("      Host:        {}", lsp.host);
+        println!(
+            "      Routing fee: {} + {} ppm",
+            amount_to_string(&lsp.routing_base_fee),
+            lsp.routing_fee_ppm
+        );
+        println!("      Min HTLC:    {}", amount_to_string(&lsp.min_htlc));
+        for params in lsp.opening_fee_params_list {
+            println!(
+                "      Opening fee: {} sats min, {}% (valid until {})",
+                params.min_msat / 1_000,
+                params.proportional as f64 / 10_000f64,
+                params.valid_until
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn switch_lsp(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
+    let lsp_id = words.next().ok_or(anyhow!("LSP id is required"))?;
+
+    let switch_info = node.lsp().prepare_switch(lsp_id.to_string())?;
+    println!(
+        "Switching to {}, moving {} on-chain",
+        switch_info.to_lsp.name,
+        amount_to_string(&switch_info.funds_moved_onchain)
+    );
+    node.lsp().switch(switch_info)?;
+    println!("Switched LSP. Funds from closed channels can be swapped to lightning once confirmed");
+
+    Ok(())
+}
+
 fn get_invoice_affordability(
     node: &LightningNode,
     words: &mut dyn Iterator<Item = &str>,
@@ -1004,6 +1475,48 @@ fn get_swap_address(node: &LightningNode) -> Result<()> {
     Ok(())
 }
 
+fn list_swaps(node: &LightningNode) -> Result<()> {
+    let swaps = node.onchain().swap().list()?;
+
+    println!("Total of {} swaps\n", swaps.len().to_string().bold());
+    for swap in swaps {
+        let created_at: DateTime<Local> = swap.created_at.time.into();
+        println!("Swap created at {created_at}:");
+        println!("      Address:            {}", swap.address);
+        println!("      State:              {:?}", swap.state);
+        println!(
+            "      Deposited amount:   {}",
+            amount_to_string(&swap.deposited_amount)
+        );
+        println!(
+            "      Paid amount:        {}",
+            amount_to_string(&swap.paid_amount)
+        );
+        for txid in swap.confirmed_deposit_txids {
+            println!("      Confirmed deposit:  {txid}");
+        }
+        for txid in swap.unconfirmed_deposit_txids {
+            println!("      Pending deposit:    {txid}");
+        }
+        for txid in swap.refund_txids {
+            println!("      Refund:             {txid}");
+        }
+        if let Some(confirmations) = swap.confirmations {
+            println!("      Confirmations:      {confirmations}");
+        }
+        match swap.blocks_until_lock_timeout {
+            Some(blocks) => println!("      Lock timeout in:    {blocks} blocks"),
+            None => println!(
+                "      Lock timeout:       {} blocks after confirmation",
+                swap.lock_timeout_blocks
+            ),
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
 fn list_failed_swaps(node: &LightningNode) -> Result<()> {
     let failed_swaps = failed_swap_from_actions_required_list(&node.actions_required().list()?);
 
@@ -1030,6 +1543,7 @@ fn refund_failed_swap(node: &LightningNode, words: &mut dyn Iterator<Item = &str
     let swap_address = words.next().ok_or(anyhow!("Swap address is required"))?;
     let to_address = words.next().ok_or(anyhow!("To address is required"))?;
     let address_data = get_bitcoin_address_data(node.util().decode_data(to_address.to_string())?)?;
+    let fee_priority = parse_fee_priority(words.next())?;
 
     let failed_swaps = failed_swap_from_actions_required_list(
         &node
@@ -1046,7 +1560,7 @@ fn refund_failed_swap(node: &LightningNode, words: &mut dyn Iterator<Item = &str
     let resolve_failed_swap_info = node
         .onchain()
         .swap()
-        .prepare_sweep(failed_swap, address_data)
+        .prepare_sweep(failed_swap, address_data, fee_priority)
         .map_err(|e| anyhow!("Failed to prepare the resolution of the failed swap: {e}"))?;
     let txid = node
         .onchain()
@@ -1237,6 +1751,50 @@ fn list_actionable_items(node: &LightningNode) -> Result<()> {
                     amount_to_string(&available_funds)
                 );
             }
+            ActionRequiredItem::CancelledInvoicePaid {
+                incoming_payment_info,
+            } => {
+                println!("A payment was received to a cancelled invoice");
+                println!(
+                    "      Hash:            {}",
+                    incoming_payment_info.payment_info.hash
+                );
+                println!(
+                    "      Amount:          {}",
+                    amount_to_string(&incoming_payment_info.payment_info.amount)
+                );
+            }
+            ActionRequiredItem::StuckTransaction { stuck_transaction } => {
+                let broadcast_at: DateTime<Local> = stuck_transaction.broadcast_at.time.into();
+                println!("A failed swap refund is stuck");
+                println!("      Txid:            {}", stuck_transaction.txid);
+                println!("      Swap address:    {}", stuck_transaction.swap_address);
+                println!(
+                    "      Broadcast at:    {}",
+                    broadcast_at.format("%d/%m/%Y %T")
+                );
+                println!(
+                    "      Fee rate:        {} sat/vbyte (recommended: {} sat/vbyte)",
+                    stuck_transaction.sat_per_vbyte, stuck_transaction.recommended_sat_per_vbyte
+                );
+            }
+            ActionRequiredItem::SwapDepositOutOfRange { deposit } => {
+                let cause = match deposit.issue {
+                    SwapDepositIssue::BelowMinimum => "below the minimum",
+                    SwapDepositIssue::AboveMaximum => "above the maximum",
+                };
+                println!("A deposit to a swap address is {cause} and won't be swapped");
+                println!("      Address:         {}", deposit.address);
+                println!(
+                    "      Deposited:       {}",
+                    amount_to_string(&deposit.deposited_amount)
+                );
+                println!(
+                    "      Accepted range:  {} - {}",
+                    amount_to_string(&deposit.min_deposit),
+                    amount_to_string(&deposit.max_deposit)
+                );
+            }
         }
         println!();
     }
@@ -1331,6 +1889,26 @@ fn print_activity(activity: Activity) -> Result<()> {
             Ok(())
         }
         Activity::ChannelClose { channel_close_info } => print_channel_close(channel_close_info),
+        Activity::QueuedPayment {
+            queued_payment_info,
+        } => {
+            println!();
+            println!("{} payment {}", "Queued".bold(), queued_payment_info.id);
+            println!(
+                "      Amount:           {}",
+                amount_to_string(&queued_payment_info.amount)
+            );
+            println!(
+                "      Recipient:        {:?}",
+                queued_payment_info.recipient
+            );
+            let expires_at: DateTime<Utc> = queued_payment_info.expires_at.time.into();
+            println!(
+                "      Expires at:       {}",
+                expires_at.format("%d/%m/%Y %T UTC")
+            );
+            Ok(())
+        }
     }
 }
 
@@ -1431,11 +2009,35 @@ fn set_personal_note(node: &LightningNode, words: &mut dyn Iterator<Item = &str>
     Ok(())
 }
 
+fn balance_history(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
+    let days: u64 = words
+        .next()
+        .unwrap_or("7")
+        .parse()
+        .context("Number of days should be a positive integer number")?;
+    let day = Duration::from_secs(24 * 60 * 60);
+    let to = SystemTime::now();
+    let from = to - day * days as u32;
+    for point in node.activities().balance_history(from, to, day)? {
+        let at: DateTime<Local> = point.at.time.into();
+        println!(
+            "{}: {}",
+            at.format("%d/%m/%Y"),
+            amount_to_string(&point.balance)
+        );
+    }
+    Ok(())
+}
+
 fn sweep(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
     let address = words.next().ok_or(anyhow!("Address is required"))?;
     let address_data = node.util().decode_data(address.to_string())?;
     let address_data = get_bitcoin_address_data(address_data)?;
-    let sweep_info = node.onchain().channel_close().prepare_sweep(address_data)?;
+    let fee_priority = parse_fee_priority(words.next())?;
+    let sweep_info = node
+        .onchain()
+        .channel_close()
+        .prepare_sweep(address_data, fee_priority)?;
     println!("     Payout address: {}", sweep_info.address);
     println!(
         "             Amount: {}",
@@ -1446,12 +2048,91 @@ fn sweep(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<
         amount_to_string(&sweep_info.onchain_fee_amount)
     );
     println!("  On-chain fee rate: {}", sweep_info.onchain_fee_rate);
+    print_fee_rates(&sweep_info.fee_rates);
 
     let txid = node.onchain().channel_close().sweep(sweep_info)?;
     println!("     Transaction Id: {txid}");
     Ok(())
 }
 
+fn parse_fee_priority(word: Option<&str>) -> Result<FeePriority> {
+    let fee_priority = match word {
+        None | Some("halfhour") => FeePriority::HalfHour,
+        Some("fastest") => FeePriority::Fastest,
+        Some("hour") => FeePriority::Hour,
+        Some("economy") => FeePriority::Economy,
+        Some("minimum") => FeePriority::Minimum,
+        Some(sat_per_vbyte) => FeePriority::Custom {
+            sat_per_vbyte: sat_per_vbyte
+                .parse()
+                .context("Fee priority should be a priority name or a fee rate")?,
+        },
+    };
+    Ok(fee_priority)
+}
+
+fn print_fee_rates(fee_rates: &OnchainFeeRates) {
+    println!("Recommended fee rates (sat/vbyte):");
+    println!("            Fastest: {}", fee_rates.fastest);
+    println!("          Half hour: {}", fee_rates.half_hour);
+    println!("               Hour: {}", fee_rates.hour);
+    println!("            Economy: {}", fee_rates.economy);
+    println!("            Minimum: {}", fee_rates.minimum);
+}
+
+fn bump_fee(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
+    let txid = words.next().ok_or(anyhow!("Txid is required"))?;
+    let sat_per_vbyte: u32 = words
+        .next()
+        .ok_or(anyhow!("Fee rate is required"))?
+        .parse()
+        .context("Fee rate should be a positive integer number")?;
+    let txid = node
+        .onchain()
+        .bump_stuck_transaction_fee(txid.to_string(), sat_per_vbyte)?;
+    println!("Replacement transaction id: {txid}");
+    Ok(())
+}
+
+fn auto_resolve(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
+    let parse_max_fee = |words: &mut dyn Iterator<Item = &str>| -> Result<u32> {
+        words
+            .next()
+            .ok_or(anyhow!("Max fee permyriad is required"))?
+            .parse()
+            .context("Max fee permyriad should be a positive integer number")
+    };
+    let policy = match words.next() {
+        None => {
+            match node.onchain().get_auto_resolution_policy()? {
+                Some(policy) => println!(
+                    "{:?} once fees are at most {} permyriad",
+                    policy.strategy, policy.max_fee_permyriad
+                ),
+                None => println!("Automatic resolution is disabled"),
+            }
+            return Ok(());
+        }
+        Some("off") => None,
+        Some("swap") => Some(AutoResolutionPolicy {
+            strategy: AutoResolutionStrategy::SwapToLightning,
+            max_fee_permyriad: parse_max_fee(words)?,
+        }),
+        Some("sweep") => {
+            let address = words.next().ok_or(anyhow!("Address is required"))?;
+            Some(AutoResolutionPolicy {
+                strategy: AutoResolutionStrategy::SweepToAddress {
+                    address: address.to_string(),
+                },
+                max_fee_permyriad: parse_max_fee(words)?,
+            })
+        }
+        Some(other) => return Err(anyhow!("Unknown auto resolution strategy: {other}")),
+    };
+    node.onchain().set_auto_resolution_policy(policy)?;
+    Ok(())
+}
+
 fn clear_wallet_info(node: &LightningNode) -> Result<()> {
     match node
         .onchain()
@@ -1463,7 +2144,10 @@ fn clear_wallet_info(node: &LightningNode) -> Result<()> {
         RangeHit::Above { max } => bail!("Balance is above max: {}", amount_to_string(&max)),
     };
 
-    let clear_wallet_info = node.onchain().reverse_swap().prepare_clear_wallet()?;
+    let clear_wallet_info = node
+        .onchain()
+        .reverse_swap()
+        .prepare_clear_wallet(FeePriority::HalfHour)?;
 
     println!("Clear Wallet Information:");
     println!(
@@ -1482,14 +2166,19 @@ fn clear_wallet_info(node: &LightningNode) -> Result<()> {
         "      Swap Fee: {}",
         amount_to_string(&clear_wallet_info.swap_fee)
     );
+    print_fee_rates(&clear_wallet_info.fee_rates);
 
     Ok(())
 }
 
 fn clear_wallet(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
     let address = words.next().ok_or(anyhow!("Address is required"))?;
+    let fee_priority = parse_fee_priority(words.next())?;
 
-    let clear_wallet_info = node.onchain().reverse_swap().prepare_clear_wallet()?;
+    let clear_wallet_info = node
+        .onchain()
+        .reverse_swap()
+        .prepare_clear_wallet(fee_priority)?;
 
     let result = node.util().decode_data(address.to_string())?;
     if let DecodedData::OnchainAddress {
@@ -1506,6 +2195,52 @@ fn clear_wallet(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) ->
     Ok(())
 }
 
+fn send_onchain(node: &LightningNode, words: &mut dyn Iterator<Item = &str>) -> Result<()> {
+    let address = words.next().ok_or(anyhow!("Address is required"))?;
+    let amount_sat: u64 = words
+        .next()
+        .ok_or(anyhow!("Amount is required"))?
+        .parse()
+        .context("Amount should be a positive integer number")?;
+    let fee_priority = parse_fee_priority(words.next())?;
+
+    let onchain_address_details = match node.util().decode_data(address.to_string())? {
+        DecodedData::OnchainAddress {
+            onchain_address_details,
+        } => onchain_address_details,
+        _ => bail!("Provided data is not an on-chain address"),
+    };
+
+    let send_info = node.onchain().reverse_swap().prepare_send(
+        amount_sat,
+        onchain_address_details,
+        fee_priority,
+    )?;
+    println!("On-chain Send Information:");
+    println!("      Amount: {}", amount_to_string(&send_info.amount));
+    println!(
+        "      Sender Amount: {}",
+        amount_to_string(&send_info.sender_amount)
+    );
+    println!(
+        "      Total Estimated Fees: {}",
+        amount_to_string(&send_info.total_estimated_fees)
+    );
+    println!(
+        "      Lockup Fee: {}",
+        amount_to_string(&send_info.lockup_fee)
+    );
+    println!(
+        "      Claim Fee: {}",
+        amount_to_string(&send_info.claim_fee)
+    );
+    println!("      Swap Fee: {}", amount_to_string(&send_info.swap_fee));
+
+    node.onchain().reverse_swap().send(send_info)?;
+
+    Ok(())
+}
+
 fn offer_to_string(offer: Offer) -> String {
     let updated_at: DateTime<Utc> = offer.exchange_rate.updated_at.into();
     let currency_code = offer.exchange_rate.currency_code;
@@ -1525,8 +2260,9 @@ fn offer_to_string(offer: Offer) -> String {
 
 fn fiat_value_to_string(value: &FiatValue) -> String {
     let converted_at: DateTime<Utc> = value.converted_at.into();
+    let stale = if value.is_stale { " (stale)" } else { "" };
     format!(
-        "{:.2} {} as of {}",
+        "{:.2} {} as of {}{stale}",
         value.minor_units as f64 / 100f64,
         value.currency_code,
         converted_at.format("%d/%m/%Y %T UTC"),
@@ -1538,7 +2274,12 @@ fn amount_to_string(amount: &Amount) -> String {
         Some(fiat) => fiat_value_to_string(fiat),
         None => "exchange rate unknown".to_string(),
     };
-    format!("{} SAT ({fiat})", amount.sats)
+    let fiat_secondary = amount
+        .fiat_secondary
+        .iter()
+        .map(|f| format!(", {:.2} {}", f.minor_units as f64 / 100f64, f.currency_code))
+        .collect::<String>();
+    format!("{} SAT ({fiat}{fiat_secondary})", amount.sats)
 }
 
 fn get_registered_topup(node: &LightningNode) -> Result<()> {
@@ -1598,7 +2339,7 @@ fn get_failed_swap_resolving_fees(
     let resolving_fees = node
         .onchain()
         .swap()
-        .determine_resolving_fees(failed_swap)?;
+        .determine_resolving_fees(failed_swap, FeePriority::HalfHour)?;
 
     let resolving_fees = match resolving_fees {
         None => {
@@ -1636,7 +2377,10 @@ fn get_failed_swap_resolving_fees(
 }
 
 fn get_channel_close_resolving_fees(node: &LightningNode) -> Result<()> {
-    let resolving_fees = node.onchain().channel_close().determine_resolving_fees()?;
+    let resolving_fees = node
+        .onchain()
+        .channel_close()
+        .determine_resolving_fees(FeePriority::HalfHour)?;
 
     let resolving_fees = match resolving_fees {
         None => {
@@ -1677,7 +2421,7 @@ fn swap_onchain_to_lightning(node: &LightningNode) -> Result<()> {
     let resolving_fees = node
         .onchain()
         .channel_close()
-        .determine_resolving_fees()?
+        .determine_resolving_fees(FeePriority::HalfHour)?
         .ok_or(anyhow!(
             "Channel funds cannot be resolved as they are too little"
         ))?;
@@ -1731,12 +2475,25 @@ fn hide_failed_swap(node: &LightningNode, words: &mut dyn Iterator<Item = &str>)
         .map_err(Into::into)
 }
 
+fn dismiss_cancelled_invoice_payment(
+    node: &LightningNode,
+    words: &mut dyn Iterator<Item = &str>,
+) -> Result<()> {
+    let hash = words.next().ok_or(anyhow!("Hash is required"))?;
+    node.actions_required()
+        .dismiss_cancelled_invoice_payment(hash.to_string())
+        .map_err(Into::into)
+}
+
 fn offer_info_from_actions_required_list(list: &[ActionRequiredItem]) -> Vec<OfferInfo> {
     list.iter()
         .filter_map(|i| match i {
             ActionRequiredItem::UncompletedOffer { offer } => Some(offer.clone()),
             ActionRequiredItem::UnresolvedFailedSwap { .. } => None,
             ActionRequiredItem::ChannelClosesFundsAvailable { .. } => None,
+            ActionRequiredItem::CancelledInvoicePaid { .. } => None,
+            ActionRequiredItem::StuckTransaction { .. } => None,
+            ActionRequiredItem::SwapDepositOutOfRange { .. } => None,
         })
         .collect::<Vec<_>>()
 }
@@ -1747,6 +2504,8 @@ fn failed_swap_from_actions_required_list(list: &[ActionRequiredItem]) -> Vec<Fa
             ActionRequiredItem::UncompletedOffer { .. } => None,
             ActionRequiredItem::UnresolvedFailedSwap { failed_swap } => Some(failed_swap.clone()),
             ActionRequiredItem::ChannelClosesFundsAvailable { .. } => None,
+            ActionRequiredItem::CancelledInvoicePaid { .. } => None,
+            ActionRequiredItem::StuckTransaction { .. } => None,
         })
         .collect::<Vec<_>>()
 }
@@ -8,8 +8,8 @@ mod print_events_handler;
 use crate::print_events_handler::PrintEventsHandler;
 
 use uniffi_lipalightninglib::{
-    mnemonic_to_secret, recover_lightning_node, BreezSdkConfig, LightningNode, MaxRoutingFeeConfig,
-    ReceiveLimitsConfig, RemoteServicesConfig,
+    mnemonic_to_secret, recover_lightning_node, BreezSdkConfig, ExchangeRateStalenessConfig,
+    LightningNode, MaxRoutingFeeConfig, ReceiveLimitsConfig, RemoteServicesConfig,
 };
 use uniffi_lipalightninglib::{LightningNodeConfig, TzConfig};
 
@@ -91,6 +91,11 @@ fn main() {
             min_receive_channel_open_fee_multiplier: 2.0,
         },
         topup_referral_code_max_length: 40,
+        exchange_rate_staleness_config: ExchangeRateStalenessConfig {
+            stale_after: Duration::from_secs(30 * 60),
+            discard_after: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+        },
+        stuck_transaction_threshold: Duration::from_secs(3 * 60 * 60),
     };
 
     let node = LightningNode::new(config, events).unwrap();
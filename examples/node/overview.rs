@@ -111,6 +111,17 @@ fn print_activity(activity: Activity) -> Result<()> {
             ..
         } => print_outgoing_payment(outgoing_payment_info),
         Activity::ChannelClose { channel_close_info } => print_channel_close(channel_close_info),
+        Activity::QueuedPayment {
+            queued_payment_info,
+        } => {
+            let amount = queued_payment_info.amount.sats.separate_with_commas();
+            let amount = format!("−{amount}");
+            let line = format!(" ⏳ {:<15} {amount:>7}", "Queued")
+                .italic()
+                .dimmed();
+            println!("{line}");
+            Ok(())
+        }
     }
 }
 
@@ -170,7 +181,9 @@ fn print_incoming_payment(payment: IncomingPaymentInfo) -> Result<()> {
     let line = match payment.payment_info.payment_state {
         PaymentState::Succeeded => line.normal(),
         PaymentState::Created | PaymentState::Retried => line.italic().dimmed(),
-        PaymentState::Failed | PaymentState::InvoiceExpired => line.dimmed().strikethrough(),
+        PaymentState::Failed | PaymentState::InvoiceExpired | PaymentState::Cancelled => {
+            line.dimmed().strikethrough()
+        }
     };
     println!("{link_1}{line}");
 
@@ -213,7 +226,9 @@ fn print_outgoing_payment(payment: OutgoingPaymentInfo) -> Result<()> {
     let line = match payment.payment_info.payment_state {
         PaymentState::Succeeded => line.normal(),
         PaymentState::Created | PaymentState::Retried => line.italic().dimmed(),
-        PaymentState::Failed | PaymentState::InvoiceExpired => line.dimmed().strikethrough(),
+        PaymentState::Failed | PaymentState::InvoiceExpired | PaymentState::Cancelled => {
+            line.dimmed().strikethrough()
+        }
     };
     println!(" {line}");
 
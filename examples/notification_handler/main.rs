@@ -15,8 +15,9 @@ use std::collections::HashSet;
 use std::env;
 use std::time::Duration;
 use uniffi_lipalightninglib::{
-    handle_notification, mnemonic_to_secret, BreezSdkConfig, LightningNodeConfig,
-    MaxRoutingFeeConfig, NotificationToggles, ReceiveLimitsConfig, RemoteServicesConfig, TzConfig,
+    handle_notification, handle_notification_localized, mnemonic_to_secret, BreezSdkConfig,
+    ExchangeRateStalenessConfig, LightningNodeConfig, MaxRoutingFeeConfig, NotificationToggles,
+    ReceiveLimitsConfig, RemoteServicesConfig, TzConfig,
 };
 
 static BASE_DIR: &str = ".3l_node";
@@ -57,6 +58,22 @@ fn main() {
                         println!("{}", format!("{message:#}").red());
                     }
                 }
+                "reverse_swap_status_update" => {
+                    if let Err(message) = start_reverse_swap_status_update(&mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
+                "payment_failed" => {
+                    if let Err(message) = start_payment_failed(&mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
+                "lnurl_withdraw_completed" => {
+                    if let Err(message) = start_lnurl_withdraw_completed(&mut words) {
+                        println!("{}", format!("{message:#}").red());
+                    }
+                }
+                "backup_reminder" => start_backup_reminder(),
                 "stop" => {
                     break;
                 }
@@ -88,6 +105,16 @@ fn setup_editor() -> Editor<CommandHinter, DefaultHistory> {
         "lnurl_pay_request <amount_msat> <id> <recipient> [payer_comment]",
         "lnurl_pay_request ",
     ));
+    hints.insert(CommandHint::new(
+        "reverse_swap_status_update <id> [lockup_address]",
+        "reverse_swap_status_update ",
+    ));
+    hints.insert(CommandHint::new("payment_failed <hash>", "payment_failed "));
+    hints.insert(CommandHint::new(
+        "lnurl_withdraw_completed <hash>",
+        "lnurl_withdraw_completed ",
+    ));
+    hints.insert(CommandHint::new("backup_reminder", "backup_reminder"));
     hints.insert(CommandHint::new("stop", "stop"));
     let hinter = CommandHinter { hints };
 
@@ -155,6 +182,11 @@ fn get_config() -> LightningNodeConfig {
             min_receive_channel_open_fee_multiplier: 2.0,
         },
         topup_referral_code_max_length: 40,
+        exchange_rate_staleness_config: ExchangeRateStalenessConfig {
+            stale_after: Duration::from_secs(30 * 60),
+            discard_after: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+        },
+        stuck_transaction_threshold: Duration::from_secs(3 * 60 * 60),
     }
 }
 
@@ -163,6 +195,10 @@ fn help() {
     println!("  payment_received <hash>");
     println!("  address_txs_confirmed <address>");
     println!("  lnurl_pay_request <amount_msat> <id> <recipient> [payer_comment]");
+    println!("  reverse_swap_status_update <id> [lockup_address]");
+    println!("  payment_failed <hash>");
+    println!("  lnurl_withdraw_completed <hash>");
+    println!("  backup_reminder");
     println!("  stop");
 }
 
@@ -188,11 +224,7 @@ fn start_payment_received(words: &mut dyn Iterator<Item = &str>) -> Result<()> {
     let notification = handle_notification(
         config,
         notification_payload,
-        NotificationToggles {
-            payment_received_is_enabled: true,
-            address_txs_confirmed_is_enabled: true,
-            lnurl_pay_request_is_enabled: true,
-        },
+        all_notifications_enabled(),
         Duration::from_secs(60),
     )
     .unwrap();
@@ -224,11 +256,7 @@ fn start_address_txs_confirmed(words: &mut dyn Iterator<Item = &str>) -> Result<
     let notification = handle_notification(
         config,
         notification_payload,
-        NotificationToggles {
-            payment_received_is_enabled: true,
-            address_txs_confirmed_is_enabled: true,
-            lnurl_pay_request_is_enabled: true,
-        },
+        all_notifications_enabled(),
         Duration::from_secs(60),
     )
     .unwrap();
@@ -272,11 +300,7 @@ fn start_lnurl_pay_request(words: &mut dyn Iterator<Item = &str>) -> Result<()>
     let notification = handle_notification(
         config,
         notification_payload,
-        NotificationToggles {
-            payment_received_is_enabled: true,
-            address_txs_confirmed_is_enabled: true,
-            lnurl_pay_request_is_enabled: true,
-        },
+        all_notifications_enabled(),
         Duration::from_secs(60),
     )
     .unwrap();
@@ -285,3 +309,110 @@ fn start_lnurl_pay_request(words: &mut dyn Iterator<Item = &str>) -> Result<()>
 
     Ok(())
 }
+
+fn start_reverse_swap_status_update(words: &mut dyn Iterator<Item = &str>) -> Result<()> {
+    let id = words.next().ok_or(anyhow!("Reverse swap id is required"))?;
+    let lockup_address = match words.next() {
+        Some(address) => format!("\"{address}\""),
+        None => "null".to_string(),
+    };
+
+    println!("Starting a handle_notification(reverse_swap_status_update) test run.");
+    println!("Environment: {}", ENVIRONMENT.as_str());
+    println!("Reverse swap we are interested in: {id}");
+    println!();
+
+    let notification_payload = format!(
+        "{{
+         \"template\": \"reverse_swap_status_update\",
+         \"data\": {{
+          \"id\": \"{id}\",
+          \"lockup_address\": {lockup_address}
+         }}
+        }}"
+    );
+    run_notification(notification_payload);
+
+    Ok(())
+}
+
+fn start_payment_failed(words: &mut dyn Iterator<Item = &str>) -> Result<()> {
+    let hash = words.next().ok_or(anyhow!("Payment hash is required"))?;
+
+    println!("Starting a handle_notification(payment_failed) test run.");
+    println!("Environment: {}", ENVIRONMENT.as_str());
+    println!("Payment hash we are looking for: {hash}");
+    println!();
+
+    let notification_payload = format!(
+        "{{
+         \"template\": \"payment_failed\",
+         \"data\": {{
+          \"payment_hash\": \"{hash}\"
+         }}
+        }}"
+    );
+    run_notification(notification_payload);
+
+    Ok(())
+}
+
+fn start_lnurl_withdraw_completed(words: &mut dyn Iterator<Item = &str>) -> Result<()> {
+    let hash = words.next().ok_or(anyhow!("Payment hash is required"))?;
+
+    println!("Starting a handle_notification(lnurl_withdraw_completed) test run.");
+    println!("Environment: {}", ENVIRONMENT.as_str());
+    println!("Payment hash we are looking for: {hash}");
+    println!();
+
+    let notification_payload = format!(
+        "{{
+         \"template\": \"lnurl_withdraw_completed\",
+         \"data\": {{
+          \"payment_hash\": \"{hash}\"
+         }}
+        }}"
+    );
+    run_notification(notification_payload);
+
+    Ok(())
+}
+
+fn start_backup_reminder() {
+    println!("Starting a handle_notification(backup_reminder) test run.");
+    println!("Environment: {}", ENVIRONMENT.as_str());
+    println!();
+
+    run_notification("{\"template\": \"backup_reminder\"}".to_string());
+}
+
+fn run_notification(notification_payload: String) {
+    match handle_notification_localized(
+        get_config(),
+        notification_payload,
+        all_notifications_enabled(),
+        Duration::from_secs(60),
+        "en".to_string(),
+    ) {
+        Ok(localized) => {
+            println!("The returned notification is {:?}", localized.notification);
+            if let Some(text) = localized.text {
+                println!("{}", text.title.bold());
+                println!("{}", text.body);
+            }
+        }
+        Err(e) => println!("{}", format!("{e:#}").red()),
+    }
+}
+
+fn all_notifications_enabled() -> NotificationToggles {
+    NotificationToggles {
+        payment_received_is_enabled: true,
+        address_txs_confirmed_is_enabled: true,
+        lnurl_pay_request_is_enabled: true,
+        reverse_swap_status_update_is_enabled: true,
+        payment_failed_is_enabled: true,
+        lnurl_withdraw_completed_is_enabled: true,
+        backup_reminder_is_enabled: true,
+    }
+}
@@ -51,17 +51,17 @@ use breez_sdk_core::PaymentDetails::Ln;
 pub use breez_sdk_core::{
     parse_invoice, BitcoinAddressData, BreezEvent, ClosedChannelPaymentDetails, ConnectRequest,
     EnvironmentType, EventListener, GreenlightCredentials, GreenlightNodeConfig, HealthCheckStatus,
-    InputType, InvoicePaidDetails, LNInvoice, ListPaymentsRequest, LnPaymentDetails, LnUrlPayError,
-    LnUrlPayRequest, LnUrlPayRequestData, LnUrlWithdrawError, LnUrlWithdrawRequest,
-    LnUrlWithdrawRequestData, LnUrlWithdrawResult, MetadataItem, Network, NodeConfig,
-    OnchainPaymentLimitsResponse, OpenChannelFeeRequest, OpeningFeeParams, OpeningFeeParamsMenu,
-    PayOnchainRequest, Payment, PaymentDetails, PaymentFailedData, PaymentStatus, PaymentType,
-    PaymentTypeFilter, PrepareOnchainPaymentRequest, PrepareOnchainPaymentResponse,
-    PrepareRedeemOnchainFundsRequest, PrepareRefundRequest, ReceiveOnchainRequest,
-    ReceivePaymentRequest, ReceivePaymentResponse, RedeemOnchainFundsRequest, RefundRequest,
-    ReportIssueRequest, ReportPaymentFailureDetails, ReverseSwapFeesRequest, ReverseSwapStatus,
-    SendPaymentRequest, SignMessageRequest, SwapAmountType, SwapInfo, SwapStatus,
-    UnspentTransactionOutput,
+    InputType, InvoicePaidDetails, LNInvoice, ListPaymentsRequest, ListSwapsRequest,
+    LnPaymentDetails, LnUrlPayError, LnUrlPayRequest, LnUrlPayRequestData, LnUrlWithdrawError,
+    LnUrlWithdrawRequest, LnUrlWithdrawRequestData, LnUrlWithdrawResult, MetadataItem, Network,
+    NodeConfig, OnchainPaymentLimitsResponse, OpenChannelFeeRequest, OpeningFeeParams,
+    OpeningFeeParamsMenu, PayOnchainRequest, Payment, PaymentDetails, PaymentFailedData,
+    PaymentStatus, PaymentType, PaymentTypeFilter, PrepareOnchainPaymentRequest,
+    PrepareOnchainPaymentResponse, PrepareRedeemOnchainFundsRequest, PrepareRefundRequest,
+    ReceiveOnchainRequest, ReceivePaymentRequest, ReceivePaymentResponse,
+    RedeemOnchainFundsRequest, RefundRequest, ReportIssueRequest, ReportPaymentFailureDetails,
+    ReverseSwapFeesRequest, ReverseSwapStatus, SendPaymentRequest, SignMessageRequest,
+    SwapAmountType, SwapInfo, SwapStatus, UnspentTransactionOutput,
 };
 use breez_sdk_core::{
     ChannelState, Config, LspInformation, NodeState, OpenChannelFeeResponse, PayOnchainResponse,
@@ -806,6 +806,10 @@ impl BreezServices {
         }])
     }
 
+    pub async fn fetch_lsp_info(&self, id: String) -> SdkResult<Option<LspInformation>> {
+        Ok(self.list_lsps().await?.into_iter().find(|lsp| lsp.id == id))
+    }
+
     pub async fn connect_lsp(&self, _lsp_id: String) -> SdkResult<()> {
         Ok(())
     }
@@ -951,13 +955,16 @@ impl BreezServices {
         let total_fees = ((req.amount_sat as f64) / 100.0 * SWAP_FEE_PERCENTAGE) as u64
             + fees_lockup
             + fees_claim;
-        let recipient_amount_sat = req.amount_sat - total_fees;
+        let (sender_amount_sat, recipient_amount_sat) = match req.amount_type {
+            SwapAmountType::Send => (req.amount_sat, req.amount_sat - total_fees),
+            SwapAmountType::Receive => (req.amount_sat + total_fees, req.amount_sat),
+        };
         Ok(PrepareOnchainPaymentResponse {
             fees_hash: "this-should-be-a-hash.dummy".to_string(),
             fees_percentage: SWAP_FEE_PERCENTAGE,
             fees_lockup,
             fees_claim,
-            sender_amount_sat: req.amount_sat,
+            sender_amount_sat,
             recipient_amount_sat,
             total_fees,
         })
@@ -1017,6 +1024,14 @@ impl BreezServices {
         Ok(PayOnchainResponse { reverse_swap_info })
     }
 
+    pub async fn list_swaps(&self, req: ListSwapsRequest) -> SdkResult<Vec<SwapInfo>> {
+        let swaps = SWAPS.lock().await.clone();
+        Ok(swaps
+            .into_iter()
+            .filter(|swap| req.status.as_ref().is_none_or(|s| s.contains(&swap.status)))
+            .collect())
+    }
+
     pub async fn list_refundables(&self) -> SdkResult<Vec<SwapInfo>> {
         let swaps = SWAPS.lock().await.clone();
         Ok(swaps
@@ -1075,7 +1090,7 @@ impl BreezServices {
 
     pub async fn execute_dev_command(&self, command: String) -> SdkResult<String> {
         match command.as_str() {
-            "listpeerchannels" => Ok("This is a mock response for listpeerchannels".to_string()),
+            "listpeerchannels" => Ok(list_peer_channels_mock()),
             "listpayments" => Ok(format!("{:?}", PAYMENTS.lock().unwrap().clone())),
             _ => panic!("Command {command} not implemented in mock yet"),
         }
@@ -1533,6 +1548,26 @@ async fn close_channel_with_largest_balance() {
     }
 }
 
+fn list_peer_channels_mock() -> String {
+    let channels = CHANNELS
+        .lock()
+        .unwrap()
+        .iter()
+        .enumerate()
+        .map(|(i, channel)| {
+            format!(
+                r#"{{"peer_id":"{LSP_PUBKEY}","state":2,"short_channel_id":"{}x1x0","channel_id":"{i:064x}","funding_txid":"{:064x}","opener":1,"to_us_msat":{{"msat":{}}},"total_msat":{{"msat":{}}},"our_reserve_msat":{{"msat":0}},"their_reserve_msat":{{"msat":0}}}}"#,
+                800_000 + i,
+                i + 1,
+                channel.local_balance_msat,
+                channel.capacity_msat,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    format!(r#"{{"channels":[{channels}]}}"#)
+}
+
 fn close_channel(channel: Channel) {
     let now = Utc::now().timestamp();
     PAYMENTS.lock().unwrap().push(Payment {
@@ -1,10 +1,13 @@
+use crate::activities::Activities;
 use crate::amount::{AsSats, ToAmount};
 use crate::errors::Result;
 use crate::fiat_topup::FiatTopup;
 use crate::locker::Locker;
-use crate::onchain::Onchain;
+use crate::onchain::{FeePriority, Onchain};
 use crate::support::Support;
-use crate::{ActionRequiredItem, FailedSwapInfo, RuntimeErrorCode, CLN_DUST_LIMIT_SAT};
+use crate::{
+    ActionRequiredItem, FailedSwapInfo, PaymentState, RuntimeErrorCode, CLN_DUST_LIMIT_SAT,
+};
 use breez_sdk_core::{BitcoinAddressData, Network};
 use perro::ResultTrait;
 use std::ops::Not;
@@ -12,6 +15,7 @@ use std::sync::Arc;
 
 pub struct ActionsRequired {
     support: Arc<Support>,
+    activities: Arc<Activities>,
     fiat_topup: Arc<FiatTopup>,
     onchain: Arc<Onchain>,
 }
@@ -19,11 +23,13 @@ pub struct ActionsRequired {
 impl ActionsRequired {
     pub(crate) fn new(
         support: Arc<Support>,
+        activities: Arc<Activities>,
         fiat_topup: Arc<FiatTopup>,
         onchain: Arc<Onchain>,
     ) -> Self {
         Self {
             support,
+            activities,
             fiat_topup,
             onchain,
         }
@@ -35,6 +41,9 @@ impl ActionsRequired {
     /// * Uncompleted offers (either available for collection or failed).
     /// * Unresolved failed swaps.
     /// * Available funds resulting from channel closes.
+    /// * Payments received to cancelled invoices.
+    /// * Failed swap refunds that are stuck unconfirmed.
+    /// * Deposits to swap addresses outside the accepted range.
     ///
     /// Requires network: **yes**
     pub fn list(&self) -> Result<Vec<ActionRequiredItem>> {
@@ -64,6 +73,7 @@ impl ActionsRequired {
                                 label: None,
                                 message: None,
                             },
+                            FeePriority::HalfHour,
                         )
                         .is_ok()
             })
@@ -71,10 +81,41 @@ impl ActionsRequired {
 
         let available_channel_closes_funds = self.support.get_node_info()?.onchain_balance;
 
+        let cancelled_invoice_hashes = self
+            .support
+            .data_store
+            .lock_unwrap()
+            .retrieve_undismissed_cancelled_invoice_hashes()?;
+        let mut cancelled_invoices_paid = Vec::new();
+        for hash in cancelled_invoice_hashes {
+            let incoming_payment_info = self.activities.get_incoming_payment(hash)?;
+            if incoming_payment_info.payment_info.payment_state == PaymentState::Succeeded {
+                cancelled_invoices_paid.push(ActionRequiredItem::CancelledInvoicePaid {
+                    incoming_payment_info,
+                });
+            }
+        }
+
+        let stuck_transactions = self
+            .onchain
+            .list_stuck_transactions()?
+            .into_iter()
+            .map(|stuck_transaction| ActionRequiredItem::StuckTransaction { stuck_transaction });
+
+        let out_of_range_swap_deposits = self
+            .onchain
+            .swap()
+            .list_out_of_range_deposits()?
+            .into_iter()
+            .map(|deposit| ActionRequiredItem::SwapDepositOutOfRange { deposit });
+
         let mut action_required_items: Vec<ActionRequiredItem> = uncompleted_offers
             .into_iter()
             .map(Into::into)
             .chain(failed_swaps.into_iter().map(Into::into))
+            .chain(cancelled_invoices_paid)
+            .chain(stuck_transactions)
+            .chain(out_of_range_swap_deposits)
             .collect();
 
         // CLN currently forces a min-emergency onchain balance of 546 (the dust limit)
@@ -103,7 +144,7 @@ impl ActionsRequired {
                 Some(amount) if amount == available_channel_closes_funds.sats => self
                     .onchain
                     .channel_close()
-                    .determine_resolving_fees()?
+                    .determine_resolving_fees(FeePriority::HalfHour)?
                     .is_some(),
                 _ => true,
             };
@@ -112,7 +153,7 @@ impl ActionsRequired {
                 action_required_items.push(ActionRequiredItem::ChannelClosesFundsAvailable {
                     available_funds: available_funds_sats
                         .as_sats()
-                        .to_amount_down(&self.support.get_exchange_rate()),
+                        .to_amount_down(&self.support.get_display_rates()),
                 });
             }
         }
@@ -162,4 +203,18 @@ impl ActionsRequired {
             .store_hidden_unresolved_failed_swap(&failed_swap_info.address)?;
         Ok(())
     }
+
+    /// Hides the [`ActionRequiredItem::CancelledInvoicePaid`] item of the given payment, once the
+    /// user has acknowledged the payment received to a cancelled invoice.
+    ///
+    /// Parameters:
+    /// * `payment_hash` - hex representation of the payment hash of the cancelled invoice
+    ///
+    /// Requires network: **no**
+    pub fn dismiss_cancelled_invoice_payment(&self, payment_hash: String) -> Result<()> {
+        self.support
+            .data_store
+            .lock_unwrap()
+            .dismiss_cancelled_invoice_payment(&payment_hash)
+    }
 }
@@ -1,6 +1,11 @@
-use crate::amount::{AsSats, ToAmount};
+use crate::amount::{AsSats, DisplayRates, ToAmount};
+use crate::balance_history::{
+    accumulate_balances, balance_change_msat, sample_times, store_failed_swap_deposit,
+    BalanceHistoryPoint, BALANCE_CHANGE_RESCAN_MARGIN,
+};
 use crate::data_store::CreatedInvoice;
 use crate::errors::Result;
+use crate::lightning::outbox::list_queued_payments;
 use crate::locker::Locker;
 use crate::node_config::WithTimezone;
 use crate::support::Support;
@@ -9,17 +14,18 @@ use crate::{
     fill_payout_fee, filter_out_and_log_corrupted_activities,
     filter_out_and_log_corrupted_payments, Activity, ChannelCloseInfo, ChannelCloseState,
     IncomingPaymentInfo, InvoiceDetails, ListActivitiesResponse, OutgoingPaymentInfo, PaymentInfo,
-    PaymentState, ReverseSwapInfo, RuntimeErrorCode, SwapInfo,
+    PaymentState, ReverseSwapInfo, ReverseSwapStage, ReverseSwapTimelineEvent, RuntimeErrorCode,
+    SwapInfo,
 };
 use breez_sdk_core::{
     parse_invoice, ClosedChannelPaymentDetails, ListPaymentsRequest, PaymentDetails, PaymentStatus,
     PaymentTypeFilter,
 };
-use perro::{invalid_input, permanent_failure, MapToError, OptionToError};
+use perro::{ensure, invalid_input, permanent_failure, MapToError, OptionToError};
 use std::cmp::{min, Reverse};
 use std::collections::HashSet;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub struct Activities {
     support: Arc<Support>,
@@ -116,10 +122,15 @@ impl Activities {
                     paid_amount: (in_progress_swap.unconfirmed_sats
                         + in_progress_swap.confirmed_sats)
                         .as_sats()
-                        .to_amount_down(&self.support.get_exchange_rate()),
+                        .to_amount_down(&self.support.get_display_rates()),
                 },
             })
         }
+        pending_activities.extend(list_queued_payments(&self.support)?.into_iter().map(
+            |queued_payment_info| Activity::QueuedPayment {
+                queued_payment_info,
+            },
+        ));
         pending_activities.sort_by_cached_key(|m| Reverse(m.get_time()));
 
         Ok(ListActivitiesResponse {
@@ -223,7 +234,8 @@ impl Activities {
                 .ok_or(invalid_input("Swap activity without incoming payment info")),
             Activity::ReverseSwap { .. }
             | Activity::OutgoingPayment { .. }
-            | Activity::ChannelClose { .. } => invalid_input!("Activity not incoming payment"),
+            | Activity::ChannelClose { .. }
+            | Activity::QueuedPayment { .. } => invalid_input!("Activity not incoming payment"),
         }
     }
 
@@ -245,6 +257,7 @@ impl Activities {
             Activity::OfferClaim { .. }
             | Activity::IncomingPayment { .. }
             | Activity::ChannelClose { .. }
+            | Activity::QueuedPayment { .. }
             | Activity::Swap { .. } => invalid_input!("Activity not incoming payment"),
         }
     }
@@ -266,6 +279,174 @@ impl Activities {
             .update_personal_note(&payment_hash, note.as_deref())
     }
 
+    /// Get the balance of the wallet over time, e.g. to display it in a chart.
+    ///
+    /// The balance is reconstructed from the payment history. The balance changes are cached
+    /// locally, so only recent payments need to be processed again.
+    /// The history covers the lightning balance plus the on-chain funds: funds moved on-chain by
+    /// a channel close and funds deposited to a failed swap count until they are swept or
+    /// swapped back to lightning.
+    ///
+    /// Parameters:
+    /// * `from` - the time of the first point of the series
+    /// * `to` - the time after which no more points are added to the series
+    /// * `resolution` - the interval between two points of the series
+    ///
+    /// Requires network: **no**
+    pub fn balance_history(
+        &self,
+        from: SystemTime,
+        to: SystemTime,
+        resolution: Duration,
+    ) -> Result<Vec<BalanceHistoryPoint>> {
+        const MAX_BALANCE_HISTORY_POINTS: u128 = 10_000;
+        ensure!(
+            !resolution.is_zero(),
+            invalid_input("Resolution must not be zero")
+        );
+        let span = to
+            .duration_since(from)
+            .map_to_invalid_input("`from` must not be after `to`")?;
+        ensure!(
+            span.as_nanos() / resolution.as_nanos() < MAX_BALANCE_HISTORY_POINTS,
+            invalid_input(format!(
+                "Too many points requested, at most {MAX_BALANCE_HISTORY_POINTS} are supported"
+            ))
+        );
+
+        self.update_balance_changes()?;
+
+        let times = sample_times(from, to, resolution)
+            .into_iter()
+            .map(|t| {
+                t.duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .map_to_invalid_input("Times before the unix epoch are not supported")
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let first_time = *times.first().ok_or_permanent_failure("No points sampled")?;
+        let last_time = *times.last().ok_or_permanent_failure("No points sampled")?;
+
+        let current_rates = self.support.get_exchange_rates();
+        let user_preferences = self.support.user_preferences.lock_unwrap().clone();
+
+        let data_store = self.support.data_store.lock_unwrap();
+        let initial_balance_msat = data_store.retrieve_balance_msat_at(first_time)?;
+        let changes = data_store.retrieve_balance_changes(first_time, last_time)?;
+        let balances = accumulate_balances(initial_balance_msat, &changes, &times);
+        times
+            .into_iter()
+            .zip(balances)
+            .map(|(time, balance_msat)| {
+                let historic_rates = data_store.retrieve_exchange_rates_at(time)?;
+                let rates = if historic_rates.is_empty() {
+                    &current_rates
+                } else {
+                    &historic_rates
+                };
+                let display_rates = DisplayRates::select(
+                    rates,
+                    &user_preferences.fiat_currency,
+                    &user_preferences.secondary_fiat_currencies,
+                );
+                Ok(BalanceHistoryPoint {
+                    at: unix_timestamp_to_system_time(time)
+                        .with_timezone(user_preferences.timezone_config.clone()),
+                    balance: balance_msat.as_msats().to_amount_down(&display_rates),
+                })
+            })
+            .collect()
+    }
+
+    /// Caches the balance changes of the payments made since shortly before the last update,
+    /// settles the ones that were still pending and caches the deposits of failed swaps.
+    fn update_balance_changes(&self) -> Result<()> {
+        let (last_change_time, pending_hashes) = {
+            let data_store = self.support.data_store.lock_unwrap();
+            (
+                data_store.retrieve_last_balance_change_time()?,
+                data_store.retrieve_pending_balance_change_hashes()?,
+            )
+        };
+
+        let list_payments_request = ListPaymentsRequest {
+            filters: Some(vec![
+                PaymentTypeFilter::Sent,
+                PaymentTypeFilter::Received,
+                PaymentTypeFilter::ClosedChannel,
+            ]),
+            metadata_filters: None,
+            from_timestamp: last_change_time
+                .map(|t| t.saturating_sub(BALANCE_CHANGE_RESCAN_MARGIN.as_secs()) as i64),
+            to_timestamp: None,
+            include_failures: Some(false),
+            limit: None,
+            offset: None,
+        };
+        let mut payments = self
+            .support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.list_payments(list_payments_request))
+            .map_to_runtime_error(RuntimeErrorCode::NodeUnavailable, "Failed to list payments")?;
+        for hash in pending_hashes {
+            let payment = self
+                .support
+                .rt
+                .handle()
+                .block_on(self.support.sdk.payment_by_hash(hash.clone()))
+                .map_to_runtime_error(
+                    RuntimeErrorCode::NodeUnavailable,
+                    "Failed to get payment by hash",
+                )?;
+            match payment {
+                Some(payment) => payments.push(payment),
+                None => self
+                    .support
+                    .data_store
+                    .lock_unwrap()
+                    .delete_balance_change(&hash)?,
+            }
+        }
+        // Once refunded, the confirmed amount of a failed swap is no longer known, so the deposit
+        // is only cached while unresolved (and by the resolution itself)
+        let unresolved_failed_swaps = self
+            .support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.list_refundables())
+            .map_to_runtime_error(
+                RuntimeErrorCode::NodeUnavailable,
+                "Failed to list refundable failed swaps",
+            )?
+            .into_iter()
+            .filter(|s| s.refund_tx_ids.is_empty() && s.confirmed_sats > 0);
+
+        let mut data_store = self.support.data_store.lock_unwrap();
+        for swap in unresolved_failed_swaps {
+            store_failed_swap_deposit(
+                &mut data_store,
+                &swap.bitcoin_address,
+                swap.created_at as u64,
+                swap.confirmed_sats,
+            )?;
+        }
+        for payment in payments {
+            let balance_change_msat = balance_change_msat(&payment);
+            match payment.status {
+                PaymentStatus::Failed => data_store.delete_balance_change(&payment.id)?,
+                PaymentStatus::Pending | PaymentStatus::Complete => data_store
+                    .store_balance_change(
+                        &payment.id,
+                        payment.payment_time as u64,
+                        balance_change_msat,
+                        payment.status == PaymentStatus::Pending,
+                    )?,
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn activity_from_breez_payment(
         &self,
         breez_payment: breez_sdk_core::Payment,
@@ -322,8 +503,11 @@ impl Activities {
         let (exchange_rate, tz_config, personal_note, offer, received_on, received_lnurl_comment) =
             match local_payment_data {
                 Some(data) => (
-                    data.exchange_rate
-                        .or_else(|| self.support.get_exchange_rate()),
+                    self.support.get_payment_display_rates(
+                        &payment_details.payment_hash,
+                        data.exchange_rate
+                            .or_else(|| self.support.get_exchange_rate()),
+                    )?,
                     data.user_preferences.map(|u| u.timezone_config).unwrap_or(
                         self.support
                             .user_preferences
@@ -337,7 +521,7 @@ impl Activities {
                     data.received_lnurl_comment,
                 ),
                 None => (
-                    self.support.get_exchange_rate(),
+                    self.support.get_display_rates(),
                     self.support
                         .user_preferences
                         .lock_unwrap()
@@ -399,14 +583,48 @@ impl Activities {
                 swap_info,
             })
         } else if let Some(ref s) = payment_details.reverse_swap_info {
+            let timeline = self
+                .support
+                .data_store
+                .lock_unwrap()
+                .retrieve_reverse_swap_timeline(&s.id)?;
+            let claim_confirmed_height = timeline
+                .iter()
+                .find(|e| e.stage == ReverseSwapStage::ClaimTxConfirmed)
+                .and_then(|e| e.block_height);
+            let claim_confirmations = match claim_confirmed_height {
+                Some(confirmed_height) => {
+                    let block_height = self
+                        .support
+                        .sdk
+                        .node_info()
+                        .map_to_runtime_error(
+                            RuntimeErrorCode::NodeUnavailable,
+                            "Couldn't fetch node info",
+                        )?
+                        .block_height;
+                    Some(block_height.saturating_sub(confirmed_height) + 1)
+                }
+                None => None,
+            };
             let reverse_swap_info = ReverseSwapInfo {
                 paid_onchain_amount: s.onchain_amount_sat.as_sats().to_amount_up(&exchange_rate),
                 swap_fees_amount: (breez_payment.amount_msat
                     - s.onchain_amount_sat.as_sats().msats)
                     .as_msats()
                     .to_amount_up(&exchange_rate),
+                lockup_txid: s.lockup_txid.clone(),
                 claim_txid: s.claim_txid.clone(),
                 status: s.status,
+                timeline: timeline
+                    .into_iter()
+                    .map(|e| ReverseSwapTimelineEvent {
+                        stage: e.stage,
+                        txid: e.txid,
+                        at: e.occurred_at.map(|t| t.with_timezone(tz_config.clone())),
+                    })
+                    .collect(),
+                claim_confirmations,
             };
             let outgoing_payment_info = OutgoingPaymentInfo::new(
                 breez_payment,
@@ -468,7 +686,7 @@ impl Activities {
         let amount = breez_payment
             .amount_msat
             .as_msats()
-            .to_amount_up(&self.support.get_exchange_rate());
+            .to_amount_up(&self.support.get_display_rates());
 
         let user_preferences = self.support.user_preferences.lock_unwrap();
 
@@ -505,9 +723,12 @@ impl Activities {
                 "Invalid invoice obtained from local db: {}",
                 created_invoice.invoice
             ))?;
-        let invoice_details = InvoiceDetails::from_ln_invoice(invoice.clone(), &None);
+        let invoice_details =
+            InvoiceDetails::from_ln_invoice(invoice.clone(), &DisplayRates::default());
 
-        let payment_state = if SystemTime::now() > invoice_details.expiry_timestamp {
+        let payment_state = if created_invoice.cancelled {
+            PaymentState::Cancelled
+        } else if SystemTime::now() > invoice_details.expiry_timestamp {
             PaymentState::InvoiceExpired
         } else {
             PaymentState::Created
@@ -519,8 +740,11 @@ impl Activities {
             .lock_unwrap()
             .retrieve_payment_info(&invoice_details.payment_hash)?
             .ok_or_permanent_failure("Locally created invoice doesn't have local payment data")?;
-        let invoice_details =
-            InvoiceDetails::from_ln_invoice(invoice, &local_payment_data.exchange_rate);
+        let exchange_rate = self.support.get_payment_display_rates(
+            &invoice_details.payment_hash,
+            local_payment_data.exchange_rate,
+        )?;
+        let invoice_details = InvoiceDetails::from_ln_invoice(invoice, &exchange_rate);
         // For receiving payments, we use the invoice timestamp.
         let timezone_config = local_payment_data
             .user_preferences
@@ -539,19 +763,17 @@ impl Activities {
             .channel_opening_fees
             .unwrap_or_default()
             .as_msats()
-            .to_amount_up(&local_payment_data.exchange_rate);
+            .to_amount_up(&exchange_rate);
         let requested_amount = invoice_details
             .amount
             .clone()
             .ok_or_permanent_failure("Locally created invoice doesn't include an amount")?
             .sats
             .as_sats()
-            .to_amount_down(&local_payment_data.exchange_rate);
+            .to_amount_down(&exchange_rate);
 
         let amount = requested_amount.clone().sats - lsp_fees.sats;
-        let amount = amount
-            .as_sats()
-            .to_amount_down(&local_payment_data.exchange_rate);
+        let amount = amount.as_sats().to_amount_down(&exchange_rate);
 
         let personal_note = local_payment_data.personal_note;
 
@@ -2,6 +2,7 @@ use crate::payment::{IncomingPaymentInfo, OutgoingPaymentInfo, PaymentInfo};
 use crate::{Amount, Offer, SwapInfo, TzTime};
 
 use crate::reverse_swap::ReverseSwapInfo;
+use crate::QueuedPaymentInfo;
 use breez_sdk_core::ReverseSwapStatus;
 use std::time::SystemTime;
 
@@ -40,6 +41,11 @@ pub enum Activity {
     ChannelClose {
         channel_close_info: ChannelCloseInfo,
     },
+    /// An outgoing payment waiting in the [`Outbox`](crate::Outbox) to be sent.
+    /// Always pending.
+    QueuedPayment {
+        queued_payment_info: QueuedPaymentInfo,
+    },
 }
 
 impl Activity {
@@ -63,7 +69,7 @@ impl Activity {
                 outgoing_payment_info,
                 ..
             } => Some(&outgoing_payment_info.payment_info),
-            Activity::ChannelClose { .. } => None,
+            Activity::ChannelClose { .. } | Activity::QueuedPayment { .. } => None,
         }
     }
 
@@ -85,6 +91,9 @@ impl Activity {
                 incoming_payment_info: None,
                 swap_info,
             } => swap_info.created_at.time,
+            Activity::QueuedPayment {
+                queued_payment_info,
+            } => queued_payment_info.queued_at.time,
             _ => SystemTime::now(),
         }
     }
@@ -109,6 +118,7 @@ impl Activity {
                 incoming_payment_info: None,
                 ..
             } => true,
+            Activity::QueuedPayment { .. } => true,
             _ => false,
         }
     }
@@ -1,5 +1,6 @@
+use crate::exchange_rate_provider::is_older_than;
 use crate::ExchangeRate;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 #[derive(Clone, Copy)]
 pub(crate) struct Sats {
@@ -78,6 +79,11 @@ pub struct FiatValue {
     /// Sats per major unit
     pub rate: u32,
     pub converted_at: SystemTime,
+    /// Whether the exchange rate used was already stale when the value was computed
+    /// (see [`ExchangeRateStalenessConfig`](crate::ExchangeRateStalenessConfig)).
+    /// Fiat values of past payments computed using the exchange rates at the time of the payment
+    /// are never stale.
+    pub is_stale: bool,
 }
 
 /// A sat amount accompanied by its fiat value in a specific fiat currency
@@ -85,6 +91,10 @@ pub struct FiatValue {
 pub struct Amount {
     pub sats: u64,
     pub fiat: Option<FiatValue>,
+    /// Fiat values in the secondary fiat currencies configured through
+    /// [`Config::set_secondary_fiat_currencies`](crate::Config::set_secondary_fiat_currencies).
+    /// Currencies without a known exchange rate are omitted.
+    pub fiat_secondary: Vec<FiatValue>,
 }
 
 impl Amount {
@@ -93,28 +103,76 @@ impl Amount {
     }
 }
 
+/// The exchange rates used to get the fiat values of an [`Amount`].
+#[derive(Debug, Default, PartialEq, Clone)]
+pub(crate) struct DisplayRates {
+    /// Rate of the user's selected fiat currency.
+    pub primary: Option<ExchangeRate>,
+    /// Rates of the secondary fiat currencies, in the order they were configured.
+    pub secondary: Vec<ExchangeRate>,
+    /// Rates older than this produce fiat values flagged as stale.
+    pub stale_after: Option<Duration>,
+}
+
+impl DisplayRates {
+    /// Picks the rates of the given currencies out of `rates`.
+    pub fn select(
+        rates: &[ExchangeRate],
+        fiat_currency: &str,
+        secondary_fiat_currencies: &[String],
+    ) -> Self {
+        let find = |currency_code: &str| rates.iter().find(|r| r.currency_code == currency_code);
+        DisplayRates {
+            primary: find(fiat_currency).cloned(),
+            secondary: secondary_fiat_currencies
+                .iter()
+                .filter_map(|c| find(c).cloned())
+                .collect(),
+            stale_after: None,
+        }
+    }
+
+    /// Flags fiat values computed using rates older than `stale_after` as stale.
+    pub fn with_stale_after(self, stale_after: Duration) -> Self {
+        DisplayRates {
+            stale_after: Some(stale_after),
+            ..self
+        }
+    }
+}
+
+impl From<Option<ExchangeRate>> for DisplayRates {
+    fn from(primary: Option<ExchangeRate>) -> Self {
+        DisplayRates {
+            primary,
+            secondary: Vec::new(),
+            stale_after: None,
+        }
+    }
+}
+
 pub(crate) trait ToAmount {
-    fn to_amount_up(self, rate: &Option<ExchangeRate>) -> Amount;
-    fn to_amount_down(self, rate: &Option<ExchangeRate>) -> Amount;
+    fn to_amount_up(self, rates: &DisplayRates) -> Amount;
+    fn to_amount_down(self, rates: &DisplayRates) -> Amount;
 }
 
 impl ToAmount for Sats {
-    fn to_amount_up(self, rate: &Option<ExchangeRate>) -> Amount {
-        msats_to_amount(Rounding::Up, self.msats, rate)
+    fn to_amount_up(self, rates: &DisplayRates) -> Amount {
+        msats_to_amount(Rounding::Up, self.msats, rates)
     }
 
-    fn to_amount_down(self, rate: &Option<ExchangeRate>) -> Amount {
-        msats_to_amount(Rounding::Down, self.msats, rate)
+    fn to_amount_down(self, rates: &DisplayRates) -> Amount {
+        msats_to_amount(Rounding::Down, self.msats, rates)
     }
 }
 
 impl ToAmount for Msats {
-    fn to_amount_up(self, rate: &Option<ExchangeRate>) -> Amount {
-        msats_to_amount(Rounding::Up, self.msats, rate)
+    fn to_amount_up(self, rates: &DisplayRates) -> Amount {
+        msats_to_amount(Rounding::Up, self.msats, rates)
     }
 
-    fn to_amount_down(self, rate: &Option<ExchangeRate>) -> Amount {
-        msats_to_amount(Rounding::Down, self.msats, rate)
+    fn to_amount_down(self, rates: &DisplayRates) -> Amount {
+        msats_to_amount(Rounding::Down, self.msats, rates)
     }
 }
 
@@ -131,15 +189,25 @@ fn round(msat: u64, rounding: Rounding) -> u64 {
     }
 }
 
-fn msats_to_amount(rounding: Rounding, msats: u64, rate: &Option<ExchangeRate>) -> Amount {
+fn msats_to_amount(rounding: Rounding, msats: u64, rates: &DisplayRates) -> Amount {
     let sats = round(msats, rounding);
-    let fiat = rate.as_ref().map(|rate| FiatValue {
+    let now = SystemTime::now();
+    let to_fiat_value = |rate: &ExchangeRate| FiatValue {
         minor_units: round(msats * 100 / rate.rate as u64, rounding),
         currency_code: rate.currency_code.clone(),
         rate: rate.rate,
         converted_at: rate.updated_at,
-    });
-    Amount { sats, fiat }
+        is_stale: rates
+            .stale_after
+            .is_some_and(|stale_after| is_older_than(rate, stale_after, now)),
+    };
+    let fiat = rates.primary.as_ref().map(to_fiat_value);
+    let fiat_secondary = rates.secondary.iter().map(to_fiat_value).collect();
+    Amount {
+        sats,
+        fiat,
+        fiat_secondary,
+    }
 }
 
 #[cfg(test)]
@@ -169,7 +237,9 @@ mod tests {
     #[test]
     fn rounding_to_amount_up() {
         let now = SystemTime::now();
-        let amount = 12349123u64.as_msats().to_amount_up(&None);
+        let amount = 12349123u64
+            .as_msats()
+            .to_amount_up(&DisplayRates::default());
         assert_eq!(amount.sats, 12350);
         assert!(amount.fiat.is_none());
 
@@ -177,8 +247,9 @@ mod tests {
             currency_code: "EUR".to_string(),
             rate: 4256,
             updated_at: now,
+            source: "lipa".to_string(),
         };
-        let amount = 12349123u64.as_msats().to_amount_up(&Some(rate));
+        let amount = 12349123u64.as_msats().to_amount_up(&Some(rate).into());
         assert_eq!(amount.sats, 12350);
         assert!(amount.fiat.is_some());
         let fiat = amount.fiat.unwrap();
@@ -190,7 +261,9 @@ mod tests {
     #[test]
     fn rounding_to_amount_down() {
         let now = SystemTime::now();
-        let amount = 12349123u64.as_msats().to_amount_down(&None);
+        let amount = 12349123u64
+            .as_msats()
+            .to_amount_down(&DisplayRates::default());
         assert_eq!(amount.sats, 12349);
         assert!(amount.fiat.is_none());
 
@@ -198,8 +271,9 @@ mod tests {
             currency_code: "EUR".to_string(),
             rate: 4256,
             updated_at: now,
+            source: "lipa".to_string(),
         };
-        let amount = 12349123u64.as_msats().to_amount_down(&Some(rate));
+        let amount = 12349123u64.as_msats().to_amount_down(&Some(rate).into());
         assert_eq!(amount.sats, 12349);
         assert!(amount.fiat.is_some());
         let fiat = amount.fiat.unwrap();
@@ -208,6 +282,58 @@ mod tests {
         assert_eq!(fiat.converted_at, now);
     }
 
+    #[test]
+    fn secondary_fiat_values() {
+        let now = SystemTime::now();
+        let rate = |currency_code: &str, rate: u32| ExchangeRate {
+            currency_code: currency_code.to_string(),
+            rate,
+            updated_at: now,
+            source: "lipa".to_string(),
+        };
+        let rates = vec![rate("EUR", 4256), rate("CHF", 4000), rate("USD", 3900)];
+
+        let display_rates = DisplayRates::select(
+            &rates,
+            "EUR",
+            &["USD".to_string(), "GBP".to_string(), "CHF".to_string()],
+        );
+        let amount = 12349123u64.as_msats().to_amount_down(&display_rates);
+        assert_eq!(amount.fiat.unwrap().minor_units, 290);
+        assert_eq!(amount.fiat_secondary.len(), 2);
+        assert_eq!(amount.fiat_secondary[0].currency_code, "USD");
+        assert_eq!(amount.fiat_secondary[0].minor_units, 316);
+        assert_eq!(amount.fiat_secondary[1].currency_code, "CHF");
+        assert_eq!(amount.fiat_secondary[1].minor_units, 308);
+
+        let display_rates = DisplayRates::select(&rates, "GBP", &[]);
+        let amount = 12349123u64.as_msats().to_amount_down(&display_rates);
+        assert!(amount.fiat.is_none());
+        assert!(amount.fiat_secondary.is_empty());
+    }
+
+    #[test]
+    fn stale_fiat_values() {
+        let now = SystemTime::now();
+        let rate = |currency_code: &str, age_secs: u64| ExchangeRate {
+            currency_code: currency_code.to_string(),
+            rate: 4000,
+            updated_at: now - Duration::from_secs(age_secs),
+            source: "lipa".to_string(),
+        };
+        let rates = vec![rate("EUR", 60), rate("CHF", 7200)];
+
+        let display_rates = DisplayRates::select(&rates, "EUR", &["CHF".to_string()]);
+        let amount = 1000u64.as_sats().to_amount_down(&display_rates);
+        assert!(!amount.fiat.unwrap().is_stale);
+        assert!(!amount.fiat_secondary[0].is_stale);
+
+        let display_rates = display_rates.with_stale_after(Duration::from_secs(3600));
+        let amount = 1000u64.as_sats().to_amount_down(&display_rates);
+        assert!(!amount.fiat.unwrap().is_stale);
+        assert!(amount.fiat_secondary[0].is_stale);
+    }
+
     #[test]
     fn rounding_msats_to_sats() {
         let msats = 12349123u64.as_msats();
@@ -0,0 +1,170 @@
+use crate::data_store::DataStore;
+use crate::errors::Result;
+use crate::{Amount, TzTime};
+
+use breez_sdk_core::{Payment, PaymentType};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Payments may show up in the payment history of the node some time after they happened (e.g.
+/// after a sync), so the history is re-scanned from this long before the last balance change.
+pub(crate) const BALANCE_CHANGE_RESCAN_MARGIN: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The balance of the wallet at a point in time.
+#[derive(PartialEq, Debug)]
+pub struct BalanceHistoryPoint {
+    pub at: TzTime,
+    /// The lightning balance plus the on-chain funds (from channel closes and failed swaps). The
+    /// fiat values are computed using the exchange rates that were known at that time.
+    pub balance: Amount,
+}
+
+/// A balance change caused by a payment, as cached in the local db.
+pub(crate) struct BalanceChange {
+    /// Unix timestamp in seconds.
+    pub changed_at: u64,
+    pub balance_change_msat: i64,
+}
+
+/// The impact a payment has on the balance of the wallet.
+///
+/// Channel closes only move funds from lightning to on-chain, so they don't change the balance.
+/// The on-chain funds count until they are spent, see [`store_onchain_spend`].
+pub(crate) fn balance_change_msat(payment: &Payment) -> i64 {
+    match payment.payment_type {
+        PaymentType::Received => payment.amount_msat as i64,
+        PaymentType::Sent => -((payment.amount_msat + payment.fee_msat) as i64),
+        PaymentType::ClosedChannel => 0,
+    }
+}
+
+/// Caches the funds deposited to the address of a failed swap, which stay on-chain until the
+/// failed swap is resolved.
+///
+/// Parameters:
+/// * `deposited_at` - unix timestamp in seconds
+pub(crate) fn store_failed_swap_deposit(
+    data_store: &mut DataStore,
+    swap_address: &str,
+    deposited_at: u64,
+    amount_sat: u64,
+) -> Result<()> {
+    data_store.store_balance_change(
+        &format!("failed-swap:{swap_address}"),
+        deposited_at,
+        (amount_sat * 1_000) as i64,
+        false,
+    )
+}
+
+/// Caches on-chain funds leaving the wallet, e.g. by a sweep or by a swap back to lightning.
+/// Funds swapped back to lightning count again once they are received.
+///
+/// Parameters:
+/// * `txid` - the id of the spending transaction
+/// * `amount_sat` - the total amount spent, including the on-chain fee
+pub(crate) fn store_onchain_spend(
+    data_store: &mut DataStore,
+    txid: &str,
+    amount_sat: u64,
+) -> Result<()> {
+    let spent_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    data_store.store_balance_change(
+        &format!("onchain-spend:{txid}"),
+        spent_at,
+        -((amount_sat * 1_000) as i64),
+        false,
+    )
+}
+
+/// Splits the `[from, to]` interval into points `resolution` apart, starting at `from`.
+pub(crate) fn sample_times(
+    from: SystemTime,
+    to: SystemTime,
+    resolution: Duration,
+) -> Vec<SystemTime> {
+    let mut times = Vec::new();
+    let mut time = from;
+    while time <= to {
+        times.push(time);
+        time += resolution;
+    }
+    times
+}
+
+/// Computes the balance at every one of the given (ordered) `times`.
+///
+/// Parameters:
+/// * `initial_balance_msat` - the balance right before the first balance change
+/// * `changes` - the balance changes, ordered by time
+/// * `times` - unix timestamps in seconds
+pub(crate) fn accumulate_balances(
+    initial_balance_msat: i64,
+    changes: &[BalanceChange],
+    times: &[u64],
+) -> Vec<u64> {
+    let mut balance_msat = initial_balance_msat;
+    let mut changes = changes.iter().peekable();
+    times
+        .iter()
+        .map(|time| {
+            while let Some(change) = changes.next_if(|c| c.changed_at <= *time) {
+                balance_msat += change.balance_change_msat;
+            }
+            balance_msat.max(0) as u64
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accumulate_balances() {
+        let change = |changed_at, balance_change_msat| BalanceChange {
+            changed_at,
+            balance_change_msat,
+        };
+        let changes = vec![
+            change(10, 5_000),
+            change(10, 1_000),
+            change(25, -2_500),
+            change(40, 10_000),
+        ];
+
+        assert_eq!(
+            accumulate_balances(1_000, &changes, &[0, 10, 20, 30, 40, 50]),
+            vec![1_000, 7_000, 7_000, 4_500, 14_500, 14_500]
+        );
+        assert_eq!(
+            accumulate_balances(0, &[change(5, -1_000)], &[0, 10]),
+            vec![0, 0]
+        );
+        assert!(accumulate_balances(0, &changes, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_sample_times() {
+        let from = SystemTime::UNIX_EPOCH;
+        let resolution = Duration::from_secs(60);
+
+        let times = sample_times(from, from + Duration::from_secs(150), resolution);
+        assert_eq!(
+            times,
+            vec![
+                from,
+                from + Duration::from_secs(60),
+                from + Duration::from_secs(120)
+            ]
+        );
+
+        let times = sample_times(from, from + Duration::from_secs(120), resolution);
+        assert_eq!(times.len(), 3);
+
+        let times = sample_times(from, from, resolution);
+        assert_eq!(times, vec![from]);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::BreezHealthCheckStatus;
+use crate::{
+    BreezHealthCheckStatus, ChannelCloseReason, ExchangeRate, ExchangeRateAlert,
+    ResolvedFundsSource, SwapDepositIssue,
+};
 
 /// Asynchronous events that the consumer of this library might be interested in handling are delivered through this interface.
 /// These callbacks will only be called once per event.
@@ -9,17 +12,21 @@ pub trait EventsCallback: Send + Sync {
     /// * `payment_hash` - can be used cross-reference this claimed payment with a previously issued invoice.
     fn payment_received(&self, payment_hash: String);
 
-    /// This callback will be called when a channel has started closing
-    /// *WARNING* This will currently never be called as the Breez SDK doesn't support channel closed events. Thus, for now, it can be ignored.
+    /// This callback will be called when a channel has started closing.
     ///
-    /// On the MVP version of lipa wallet, this event is unexpected and is likely to result in funds moving
-    /// on-chain, thus becoming unavailable. If this happens, the user should be informed of the problem and that he
-    /// should contact lipa.
+    /// Channel closes are detected by comparing the channels of the node on every periodic sync,
+    /// so this can be called some minutes after the close started. Channels that were opened and
+    /// closed between two syncs aren't reported.
+    ///
+    /// Unless the close was requested using [`Lsp::switch`](crate::Lsp::switch), this event is
+    /// unexpected and results in funds moving on-chain, thus becoming unavailable. If this
+    /// happens, the user should be informed that the funds can be recovered through
+    /// [`ChannelClose`](crate::ChannelClose).
     ///
     /// Parameters:
     /// * `channel_id` - Channel ID encoded in hexadecimal.
-    /// * `reason` - provides a reason for the close
-    fn channel_closed(&self, channel_id: String, reason: String);
+    /// * `reason` - a best-effort reason for the close
+    fn channel_closed(&self, channel_id: String, reason: ChannelCloseReason);
 
     /// This callback will be called when a payment has been successfully sent (the payee received the funds)
     ///
@@ -34,6 +41,15 @@ pub trait EventsCallback: Send + Sync {
     /// * `payment_hash` - the hash of the payment can be used to cross-reference this event to the payment that has failed
     fn payment_failed(&self, payment_hash: String);
 
+    /// This callback will be called when a payment queued in the [`Outbox`](crate::Outbox)
+    /// has been dropped, either because it expired or because sending it failed. Payments whose
+    /// send attempt was interrupted by the app being stopped are looked up on the node after the
+    /// next start and only reported here if they failed or expired without being sent.
+    ///
+    /// Parameters:
+    /// * `queued_payment_id` - the id returned when the payment was queued
+    fn queued_payment_failed(&self, queued_payment_id: u64);
+
     /// This callback will be called when an incoming swap has completed.
     ///
     /// Parameters:
@@ -41,6 +57,16 @@ pub trait EventsCallback: Send + Sync {
     ///   this swap.
     fn swap_received(&self, payment_hash: String);
 
+    /// This callback will be called when funds outside the range accepted by the swap service
+    /// are sent to a swap address. It's called as soon as the deposit is seen, even if it isn't
+    /// confirmed yet. The funds won't be swapped into LN and will have to be recovered once the
+    /// deposit confirms.
+    ///
+    /// Parameters:
+    /// * `swap_address` - the address the funds were sent to
+    /// * `issue` - whether too little or too much was sent
+    fn swap_deposit_out_of_range(&self, swap_address: String, issue: SwapDepositIssue);
+
     /// This callback will be called when a TX of an on-chain send has been broadcast to the Bitcoin network.
     ///
     /// Parameters:
@@ -69,6 +95,32 @@ pub trait EventsCallback: Send + Sync {
     /// * `status` - the new status
     fn breez_health_status_changed_to(&self, status: BreezHealthCheckStatus);
 
+    /// This callback will be called when an exchange rate update crosses the threshold of an
+    /// alert added through [`Config::add_exchange_rate_alert`](crate::Config::add_exchange_rate_alert).
+    /// The alert won't trigger again until the price has moved back beyond the threshold.
+    ///
+    /// Parameters:
+    /// * `alert` - the triggered alert
+    /// * `exchange_rate` - the exchange rate that triggered the alert
+    fn exchange_rate_alert_triggered(&self, alert: ExchangeRateAlert, exchange_rate: ExchangeRate);
+
+    /// This callback will be called when the exchange rate of the selected fiat currency becomes
+    /// stale or fresh again (see [`ExchangeRateStalenessConfig`](crate::ExchangeRateStalenessConfig)).
+    /// Exchange rates become stale if they can't be updated, e.g. because the exchange rate
+    /// providers are unavailable.
+    ///
+    /// Parameters:
+    /// * `is_stale` - whether the exchange rate is stale now
+    fn exchange_rate_staleness_changed_to(&self, is_stale: bool);
+
+    /// This callback will be called when funds were resolved according to the
+    /// [`AutoResolutionPolicy`](crate::AutoResolutionPolicy).
+    ///
+    /// Parameters:
+    /// * `source` - the funds that were resolved
+    /// * `txid` - the id of the resolving tx
+    fn funds_auto_resolved(&self, source: ResolvedFundsSource, txid: String);
+
     /// This callback will be called every time a sync cycle is performed.
     /// It can be used as a trigger to update the balance and activities list.
     fn synced(&self);
@@ -0,0 +1,620 @@
+use crate::amount::{AsSats, DisplayRates, ToAmount};
+use crate::data_store::KnownChannel;
+use crate::errors::Result;
+use crate::{Amount, RuntimeErrorCode};
+
+use breez_sdk_core::{
+    BreezServices, ListPaymentsRequest, PaymentDetails, PaymentType, PaymentTypeFilter,
+};
+use num_enum::TryFromPrimitive;
+use perro::{permanent_failure, MapToError};
+use serde_json::Value;
+
+/// The state of a channel as listed by [`Util::list_channels`](crate::Util::list_channels).
+#[derive(PartialEq, Eq, Debug, TryFromPrimitive, Clone, Copy)]
+#[repr(u8)]
+pub enum ChannelState {
+    /// The channel is being opened and can't be used yet.
+    Opening,
+    /// The channel can be used to send and receive payments.
+    Active,
+    /// The channel is being closed. Its funds are moving on-chain.
+    Closing,
+    /// The channel is closed. Its funds are on-chain.
+    Closed,
+}
+
+impl ChannelState {
+    pub(crate) fn is_open(self) -> bool {
+        matches!(self, ChannelState::Opening | ChannelState::Active)
+    }
+}
+
+/// Why a channel was closed, as far as it can be told from the node.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ChannelCloseReason {
+    /// Both sides agreed on closing the channel.
+    Cooperative,
+    /// The local node published its commitment tx.
+    ForceClosedByUs,
+    /// The counterparty published its commitment tx.
+    ForceClosedByPeer,
+    /// The channel was closed to switch to another LSP using [`Lsp::switch`](crate::Lsp::switch).
+    LspMigration,
+    /// The close was only noticed once it was settled on-chain and the node no longer tells how.
+    Unknown,
+}
+
+/// Information about a single channel of the node.
+///
+/// Channels that are closed and no longer tracked by the node are only known from their close,
+/// so their capacity, remote balance and reserve are zero and their counterparty is unknown.
+#[derive(PartialEq, Debug)]
+pub struct ChannelInfo {
+    /// The id of the funding tx. Identifies the channel across its whole lifetime.
+    pub funding_txid: String,
+    pub channel_id: Option<String>,
+    /// Only available once the funding tx is confirmed.
+    pub short_channel_id: Option<String>,
+    /// The node id of the counterparty.
+    pub counterparty_pubkey: Option<String>,
+    pub state: ChannelState,
+    pub capacity: Amount,
+    pub local_balance: Amount,
+    pub remote_balance: Amount,
+    /// The part of the local balance that has to stay in the channel and can't be sent.
+    pub reserve: Amount,
+    /// The fee paid to the LSP for opening the channel, if it was opened by the LSP.
+    ///
+    /// The node doesn't record which payment caused which channel open, so the fees of received
+    /// payments are matched to LSP opened channels in the order they happened. If the number of
+    /// such payments and channels doesn't match, the fee is unknown.
+    pub lsp_opening_fee: Option<Amount>,
+    pub closing_txid: Option<String>,
+    /// Only set for channels that are closing or closed.
+    pub close_reason: Option<ChannelCloseReason>,
+}
+
+/// A channel as listed by the `listpeerchannels` command of the node.
+#[derive(PartialEq, Debug)]
+pub(crate) struct PeerChannel {
+    pub funding_txid: Option<String>,
+    pub channel_id: Option<String>,
+    pub short_channel_id: Option<String>,
+    pub peer_id: Option<String>,
+    pub state: ChannelState,
+    pub opened_by_peer: bool,
+    pub close_reason: Option<ChannelCloseReason>,
+    pub total_msat: u64,
+    pub to_us_msat: u64,
+    pub reserve_msat: u64,
+}
+
+/// A channel as known from its [`breez_sdk_core::PaymentDetails::ClosedChannel`] payment.
+pub(crate) struct ClosedChannel {
+    pub funding_txid: String,
+    pub short_channel_id: Option<String>,
+    pub closing_txid: Option<String>,
+    pub state: breez_sdk_core::ChannelState,
+    pub local_balance_msat: u64,
+}
+
+/// Parses the output of the `listpeerchannels` dev command.
+///
+/// The command returns the serialized gRPC response of the node, in which byte fields may be
+/// serialized as hex strings or as arrays of bytes, and amounts as plain numbers or as objects.
+pub(crate) fn parse_peer_channels(listpeerchannels_output: &str) -> Result<Vec<PeerChannel>> {
+    let output: Value = serde_json::from_str(listpeerchannels_output)
+        .map_to_permanent_failure("Failed to parse listpeerchannels output")?;
+    let channels = output
+        .get("channels")
+        .and_then(Value::as_array)
+        .ok_or_else(|| permanent_failure("listpeerchannels output has no channels"))?;
+
+    Ok(channels
+        .iter()
+        .map(|channel| {
+            let state = to_channel_state(channel.get("state"));
+            PeerChannel {
+                funding_txid: hex_field(channel, "funding_txid"),
+                channel_id: hex_field(channel, "channel_id"),
+                short_channel_id: channel
+                    .get("short_channel_id")
+                    .and_then(Value::as_str)
+                    .map(String::from),
+                peer_id: hex_field(channel, "peer_id"),
+                state,
+                opened_by_peer: is_remote_side(channel.get("opener")),
+                close_reason: (!state.is_open()).then(|| to_close_reason(channel)),
+                total_msat: msat_field(channel, "total_msat"),
+                to_us_msat: msat_field(channel, "to_us_msat"),
+                // `our_reserve_msat` is the reserve the local node has to keep, while
+                // `their_reserve_msat` is the one the local node requires the peer to keep.
+                reserve_msat: msat_field(channel, "our_reserve_msat"),
+            }
+        })
+        .collect())
+}
+
+fn hex_field(channel: &Value, field: &str) -> Option<String> {
+    match channel.get(field)? {
+        Value::String(hex) => Some(hex.clone()),
+        Value::Array(bytes) => bytes
+            .iter()
+            .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .map(hex::encode),
+        _ => None,
+    }
+}
+
+fn msat_field(channel: &Value, field: &str) -> u64 {
+    match channel.get(field) {
+        Some(Value::Object(amount)) => amount.get("msat").and_then(Value::as_u64).unwrap_or(0),
+        Some(value) => value.as_u64().unwrap_or(0),
+        None => 0,
+    }
+}
+
+fn to_channel_state(state: Option<&Value>) -> ChannelState {
+    // Numbering of `ListpeerchannelsChannelsState` in the node's gRPC interface.
+    let state = match state {
+        Some(Value::Number(n)) => n.as_u64().unwrap_or(u64::MAX),
+        Some(Value::String(s)) => match s.to_uppercase().as_str() {
+            "OPENINGD" => 0,
+            "CHANNELD_AWAITING_LOCKIN" => 1,
+            "CHANNELD_NORMAL" => 2,
+            "CHANNELD_SHUTTING_DOWN" => 3,
+            "CLOSINGD_SIGEXCHANGE" => 4,
+            "CLOSINGD_COMPLETE" => 5,
+            "AWAITING_UNILATERAL" => 6,
+            "FUNDING_SPEND_SEEN" => 7,
+            "ONCHAIN" => 8,
+            "DUALOPEND_OPEN_INIT" => 9,
+            "DUALOPEND_AWAITING_LOCKIN" => 10,
+            "CHANNELD_AWAITING_SPLICE" => 11,
+            _ => u64::MAX,
+        },
+        _ => u64::MAX,
+    };
+    match state {
+        0 | 1 | 9 | 10 | 12 | 13 => ChannelState::Opening,
+        2 | 11 => ChannelState::Active,
+        8 => ChannelState::Closed,
+        _ => ChannelState::Closing,
+    }
+}
+
+fn to_close_reason(channel: &Value) -> ChannelCloseReason {
+    match channel.get("state").and_then(Value::as_u64) {
+        Some(3..=5) => return ChannelCloseReason::Cooperative,
+        Some(6) => return ChannelCloseReason::ForceClosedByUs,
+        _ => {}
+    }
+    match channel.get("state").and_then(Value::as_str) {
+        Some("CHANNELD_SHUTTING_DOWN" | "CLOSINGD_SIGEXCHANGE" | "CLOSINGD_COMPLETE") => {
+            return ChannelCloseReason::Cooperative
+        }
+        Some("AWAITING_UNILATERAL") => return ChannelCloseReason::ForceClosedByUs,
+        _ => {}
+    }
+
+    // Once the close tx is seen, the node reports in its status which tx it's tracking.
+    let status = channel
+        .get("status")
+        .and_then(Value::as_array)
+        .map(|status| {
+            status
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default();
+    if status.contains("mutual close") {
+        ChannelCloseReason::Cooperative
+    } else if status.contains("our own unilateral close") {
+        ChannelCloseReason::ForceClosedByUs
+    } else if status.contains("their unilateral close") {
+        ChannelCloseReason::ForceClosedByPeer
+    } else {
+        ChannelCloseReason::Unknown
+    }
+}
+
+fn is_remote_side(side: Option<&Value>) -> bool {
+    match side {
+        Some(Value::Number(n)) => n.as_u64() == Some(1),
+        Some(Value::String(s)) => s.eq_ignore_ascii_case("remote"),
+        _ => false,
+    }
+}
+
+fn funding_block_height(short_channel_id: Option<&String>) -> u64 {
+    short_channel_id
+        .and_then(|id| id.split('x').next())
+        .and_then(|height| height.parse().ok())
+        .unwrap_or(u64::MAX)
+}
+
+/// Merges the channels currently tracked by the node with the channels known from their close.
+///
+/// `opening_fees_msat` are the non-zero fees of received payments, oldest first.
+pub(crate) fn merge_channels(
+    peer_channels: Vec<PeerChannel>,
+    closed_channels: Vec<ClosedChannel>,
+    opening_fees_msat: &[u64],
+    rate: &DisplayRates,
+) -> Vec<ChannelInfo> {
+    let mut channels = Vec::new();
+    let mut opened_by_lsp = Vec::new();
+
+    for channel in peer_channels {
+        let funding_txid = channel.funding_txid.unwrap_or_default();
+        let closing_txid = closed_channels
+            .iter()
+            .find(|c| c.funding_txid == funding_txid)
+            .and_then(|c| c.closing_txid.clone());
+        if channel.opened_by_peer {
+            opened_by_lsp.push(channels.len());
+        }
+        channels.push(ChannelInfo {
+            funding_txid,
+            channel_id: channel.channel_id,
+            short_channel_id: channel.short_channel_id,
+            counterparty_pubkey: channel.peer_id,
+            state: channel.state,
+            capacity: channel.total_msat.as_msats().to_amount_down(rate),
+            local_balance: channel.to_us_msat.as_msats().to_amount_down(rate),
+            remote_balance: channel
+                .total_msat
+                .saturating_sub(channel.to_us_msat)
+                .as_msats()
+                .to_amount_down(rate),
+            reserve: channel.reserve_msat.as_msats().to_amount_up(rate),
+            lsp_opening_fee: None,
+            closing_txid,
+            close_reason: channel.close_reason,
+        });
+    }
+
+    for closed in closed_channels {
+        if channels
+            .iter()
+            .any(|c| c.funding_txid == closed.funding_txid)
+        {
+            continue;
+        }
+        // Channels of the node are only ever opened by the LSP.
+        opened_by_lsp.push(channels.len());
+        channels.push(ChannelInfo {
+            funding_txid: closed.funding_txid,
+            channel_id: None,
+            short_channel_id: closed.short_channel_id,
+            counterparty_pubkey: None,
+            state: match closed.state {
+                breez_sdk_core::ChannelState::Closed => ChannelState::Closed,
+                _ => ChannelState::Closing,
+            },
+            capacity: 0_u64.as_sats().to_amount_down(rate),
+            local_balance: closed.local_balance_msat.as_msats().to_amount_down(rate),
+            remote_balance: 0_u64.as_sats().to_amount_down(rate),
+            reserve: 0_u64.as_sats().to_amount_down(rate),
+            lsp_opening_fee: None,
+            closing_txid: closed.closing_txid,
+            close_reason: Some(ChannelCloseReason::Unknown),
+        });
+    }
+
+    if opened_by_lsp.len() == opening_fees_msat.len() {
+        opened_by_lsp.sort_by_key(|i| funding_block_height(channels[*i].short_channel_id.as_ref()));
+        for (i, fee_msat) in opened_by_lsp.into_iter().zip(opening_fees_msat) {
+            channels[i].lsp_opening_fee = Some(fee_msat.as_msats().to_amount_up(rate));
+        }
+    }
+
+    channels
+}
+
+/// Fetches the channels of the node, including the closed ones.
+pub(crate) async fn fetch_channels(
+    sdk: &BreezServices,
+    rate: &DisplayRates,
+) -> Result<Vec<ChannelInfo>> {
+    let listpeerchannels_output = sdk
+        .execute_dev_command("listpeerchannels".to_string())
+        .await
+        .map_to_runtime_error(
+            RuntimeErrorCode::NodeUnavailable,
+            "Couldn't execute `listpeerchannels` command",
+        )?;
+    let peer_channels = parse_peer_channels(&listpeerchannels_output)?;
+
+    let mut payments = sdk
+        .list_payments(ListPaymentsRequest {
+            filters: Some(vec![
+                PaymentTypeFilter::Received,
+                PaymentTypeFilter::ClosedChannel,
+            ]),
+            metadata_filters: None,
+            from_timestamp: None,
+            to_timestamp: None,
+            include_failures: Some(false),
+            limit: None,
+            offset: None,
+        })
+        .await
+        .map_to_runtime_error(RuntimeErrorCode::NodeUnavailable, "Failed to list payments")?;
+    payments.sort_by_key(|p| p.payment_time);
+
+    let opening_fees_msat = payments
+        .iter()
+        .filter(|p| p.payment_type == PaymentType::Received && p.fee_msat > 0)
+        .map(|p| p.fee_msat)
+        .collect::<Vec<_>>();
+    let closed_channels = payments
+        .into_iter()
+        .filter_map(|p| match p.details {
+            PaymentDetails::ClosedChannel { data } => Some(ClosedChannel {
+                funding_txid: data.funding_txid,
+                short_channel_id: data.short_channel_id,
+                closing_txid: data.closing_txid,
+                state: data.state,
+                local_balance_msat: p.amount_msat,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    Ok(merge_channels(
+        peer_channels,
+        closed_channels,
+        &opening_fees_msat,
+        rate,
+    ))
+}
+
+/// Replaces the close reason of channels that were closed for switching LSPs.
+pub(crate) fn apply_lsp_migrations(channels: &mut [ChannelInfo], known_channels: &[KnownChannel]) {
+    for channel in channels.iter_mut().filter(|c| c.close_reason.is_some()) {
+        let closed_for_migration = known_channels
+            .iter()
+            .any(|k| k.funding_txid == channel.funding_txid && k.closing_for_lsp_migration);
+        if closed_for_migration {
+            channel.close_reason = Some(ChannelCloseReason::LspMigration);
+        }
+    }
+}
+
+/// Compares the current channels with the ones recorded on the previous sync.
+///
+/// Returns the channel id and close reason of every channel that was open on the previous sync and
+/// is closing or closed now, along with the states to record for the next sync. Channels that
+/// weren't recorded as open, e.g. because they were opened and closed between two syncs, aren't
+/// reported.
+pub(crate) fn detect_channel_closes(
+    known_channels: &[KnownChannel],
+    mut channels: Vec<ChannelInfo>,
+) -> (Vec<(String, ChannelCloseReason)>, Vec<KnownChannel>) {
+    apply_lsp_migrations(&mut channels, known_channels);
+
+    let mut closes = Vec::new();
+    let mut updated_channels = Vec::new();
+    for channel in channels {
+        let known_channel = known_channels
+            .iter()
+            .find(|k| k.funding_txid == channel.funding_txid);
+        if let Some(known_channel) = known_channel {
+            if known_channel.state == channel.state {
+                continue;
+            }
+            if known_channel.state.is_open() && !channel.state.is_open() {
+                let channel_id = channel
+                    .channel_id
+                    .clone()
+                    .or_else(|| known_channel.channel_id.clone())
+                    .unwrap_or_else(|| channel.funding_txid.clone());
+                closes.push((
+                    channel_id,
+                    channel.close_reason.unwrap_or(ChannelCloseReason::Unknown),
+                ));
+            }
+        }
+        updated_channels.push(KnownChannel {
+            closing_for_lsp_migration: known_channel.is_some_and(|k| k.closing_for_lsp_migration),
+            channel_id: channel
+                .channel_id
+                .or_else(|| known_channel.and_then(|k| k.channel_id.clone())),
+            funding_txid: channel.funding_txid,
+            state: channel.state,
+        });
+    }
+
+    (closes, updated_channels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LISTPEERCHANNELS_OUTPUT: &str = r#"{
+        "channels": [
+            {
+                "peer_id": [3, 20, 194],
+                "peer_connected": true,
+                "state": 2,
+                "short_channel_id": "830000x1200x1",
+                "channel_id": "aa01",
+                "funding_txid": "bb01",
+                "opener": 1,
+                "to_us_msat": { "msat": 40000000 },
+                "total_msat": { "msat": 100000000 },
+                "our_reserve_msat": { "msat": 1000000 },
+                "their_reserve_msat": { "msat": 2000000 }
+            },
+            {
+                "peer_id": "0314c2",
+                "state": "ONCHAIN",
+                "short_channel_id": "820000x15x0",
+                "channel_id": "aa02",
+                "funding_txid": "bb02",
+                "opener": "remote",
+                "status": ["ONCHAIN:Tracking their unilateral close"],
+                "to_us_msat": 5000000,
+                "total_msat": 20000000,
+                "our_reserve_msat": 0,
+                "their_reserve_msat": 0
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_peer_channels() {
+        let channels = parse_peer_channels(LISTPEERCHANNELS_OUTPUT).unwrap();
+        assert_eq!(
+            channels,
+            vec![
+                PeerChannel {
+                    funding_txid: Some("bb01".to_string()),
+                    channel_id: Some("aa01".to_string()),
+                    short_channel_id: Some("830000x1200x1".to_string()),
+                    peer_id: Some("0314c2".to_string()),
+                    state: ChannelState::Active,
+                    opened_by_peer: true,
+                    close_reason: None,
+                    total_msat: 100_000_000,
+                    to_us_msat: 40_000_000,
+                    reserve_msat: 1_000_000,
+                },
+                PeerChannel {
+                    funding_txid: Some("bb02".to_string()),
+                    channel_id: Some("aa02".to_string()),
+                    short_channel_id: Some("820000x15x0".to_string()),
+                    peer_id: Some("0314c2".to_string()),
+                    state: ChannelState::Closed,
+                    opened_by_peer: true,
+                    close_reason: Some(ChannelCloseReason::ForceClosedByPeer),
+                    total_msat: 20_000_000,
+                    to_us_msat: 5_000_000,
+                    reserve_msat: 0,
+                },
+            ]
+        );
+
+        assert!(parse_peer_channels("not json").is_err());
+    }
+
+    #[test]
+    fn test_merge_channels() {
+        let rate = DisplayRates::default();
+        let peer_channels = parse_peer_channels(LISTPEERCHANNELS_OUTPUT).unwrap();
+        let closed_channels = vec![
+            ClosedChannel {
+                funding_txid: "bb02".to_string(),
+                short_channel_id: Some("820000x15x0".to_string()),
+                closing_txid: Some("cc02".to_string()),
+                state: breez_sdk_core::ChannelState::Closed,
+                local_balance_msat: 5_000_000,
+            },
+            ClosedChannel {
+                funding_txid: "bb03".to_string(),
+                short_channel_id: Some("810000x7x0".to_string()),
+                closing_txid: Some("cc03".to_string()),
+                state: breez_sdk_core::ChannelState::PendingClose,
+                local_balance_msat: 3_000_000,
+            },
+        ];
+
+        let channels = merge_channels(
+            peer_channels,
+            closed_channels,
+            &[1_000_000, 2_000_000, 3_000_000],
+            &rate,
+        );
+        assert_eq!(channels.len(), 3);
+
+        assert_eq!(channels[0].state, ChannelState::Active);
+        assert_eq!(channels[0].remote_balance.sats, 60_000);
+        assert_eq!(channels[0].closing_txid, None);
+        assert_eq!(channels[0].lsp_opening_fee.as_ref().unwrap().sats, 3_000);
+
+        assert_eq!(channels[1].closing_txid, Some("cc02".to_string()));
+        assert_eq!(channels[1].lsp_opening_fee.as_ref().unwrap().sats, 2_000);
+
+        assert_eq!(channels[2].funding_txid, "bb03");
+        assert_eq!(channels[2].state, ChannelState::Closing);
+        assert_eq!(channels[2].counterparty_pubkey, None);
+        assert_eq!(channels[2].local_balance.sats, 3_000);
+        assert_eq!(channels[2].lsp_opening_fee.as_ref().unwrap().sats, 1_000);
+
+        // Fees can't be matched if the number of channels opened by the LSP differs.
+        let channels = merge_channels(
+            parse_peer_channels(LISTPEERCHANNELS_OUTPUT).unwrap(),
+            Vec::new(),
+            &[1_000_000],
+            &rate,
+        );
+        assert!(channels.iter().all(|c| c.lsp_opening_fee.is_none()));
+    }
+
+    #[test]
+    fn test_detect_channel_closes() {
+        let known_channel = |funding_txid: &str, state, closing_for_lsp_migration| KnownChannel {
+            funding_txid: funding_txid.to_string(),
+            channel_id: None,
+            state,
+            closing_for_lsp_migration,
+        };
+        let channels = || {
+            merge_channels(
+                parse_peer_channels(LISTPEERCHANNELS_OUTPUT).unwrap(),
+                Vec::new(),
+                &[],
+                &DisplayRates::default(),
+            )
+        };
+
+        // Nothing is reported before the channels were recorded.
+        let (closes, updated_channels) = detect_channel_closes(&[], channels());
+        assert!(closes.is_empty());
+        assert_eq!(updated_channels.len(), 2);
+        assert_eq!(updated_channels[1].channel_id, Some("aa02".to_string()));
+
+        let known_channels = vec![
+            known_channel("bb01", ChannelState::Active, false),
+            known_channel("bb02", ChannelState::Active, false),
+        ];
+        let (closes, updated_channels) = detect_channel_closes(&known_channels, channels());
+        assert_eq!(
+            closes,
+            vec![("aa02".to_string(), ChannelCloseReason::ForceClosedByPeer)]
+        );
+        assert_eq!(
+            updated_channels,
+            vec![KnownChannel {
+                funding_txid: "bb02".to_string(),
+                channel_id: Some("aa02".to_string()),
+                state: ChannelState::Closed,
+                closing_for_lsp_migration: false,
+            }]
+        );
+
+        let known_channels = vec![
+            known_channel("bb01", ChannelState::Active, false),
+            known_channel("bb02", ChannelState::Active, true),
+        ];
+        let (closes, _) = detect_channel_closes(&known_channels, channels());
+        assert_eq!(
+            closes,
+            vec![("aa02".to_string(), ChannelCloseReason::LspMigration)]
+        );
+
+        // Closes are only reported once.
+        let known_channels = vec![
+            known_channel("bb01", ChannelState::Active, false),
+            known_channel("bb02", ChannelState::Closing, false),
+        ];
+        let (closes, _) = detect_channel_closes(&known_channels, channels());
+        assert!(closes.is_empty());
+    }
+}
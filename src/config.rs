@@ -1,12 +1,17 @@
 use crate::errors::Result;
+use crate::exchange_rate_alert::{ExchangeRateAlert, ExchangeRateAlertDirection};
+use crate::exchange_rate_provider::ExchangeRateProvider;
 use crate::locker::Locker;
+use crate::lsp::MaxLspFee;
 use crate::support::Support;
 use crate::{with_status, AnalyticsConfig, EnableStatus, FeatureFlag, RuntimeErrorCode, TzConfig};
 use crow::{CountryCode, LanguageCode};
 use log::info;
-use perro::{MapToError, ResultTrait};
+use perro::{ensure, invalid_input, MapToError, ResultTrait};
+use std::collections::HashSet;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub struct Config {
     support: Arc<Support>,
@@ -31,6 +36,184 @@ impl Config {
         Ok(())
     }
 
+    /// Set the secondary fiat currencies (ISO 4217 currency codes).
+    /// Every [`Amount`](crate::Amount) will also carry its fiat values in these currencies
+    /// in [`Amount::fiat_secondary`](crate::Amount::fiat_secondary), in the given order.
+    /// The method [`Config::list_currencies`] can be used to list supported codes.
+    ///
+    /// Parameters:
+    /// * `fiat_currencies` - the secondary currencies, an empty list disables them
+    ///
+    /// Requires network: **no**
+    pub fn set_secondary_fiat_currencies(&self, fiat_currencies: Vec<String>) -> Result<()> {
+        let unique_currencies = fiat_currencies.iter().collect::<HashSet<_>>();
+        ensure!(
+            unique_currencies.len() == fiat_currencies.len(),
+            invalid_input("Secondary fiat currencies contain duplicates")
+        );
+        self.support
+            .data_store
+            .lock_unwrap()
+            .store_secondary_fiat_currencies(&fiat_currencies)?;
+        self.support
+            .user_preferences
+            .lock_unwrap()
+            .secondary_fiat_currencies = fiat_currencies;
+        Ok(())
+    }
+
+    /// Get the secondary fiat currencies set through [`Config::set_secondary_fiat_currencies`].
+    ///
+    /// Requires network: **no**
+    pub fn get_secondary_fiat_currencies(&self) -> Vec<String> {
+        self.support
+            .user_preferences
+            .lock_unwrap()
+            .secondary_fiat_currencies
+            .clone()
+    }
+
+    /// Register an additional source of exchange rates. It will only be queried once it's part of
+    /// the chain set through [`Config::set_exchange_rate_provider_chain`].
+    /// Registering a provider under an already registered name replaces the previous one.
+    ///
+    /// Parameters:
+    /// * `name` - name identifying the provider, recorded in [`ExchangeRate::source`](crate::ExchangeRate::source).
+    ///   The name "lipa" is reserved for the default provider.
+    /// * `provider` - the provider
+    ///
+    /// Requires network: **no**
+    pub fn register_exchange_rate_provider(
+        &self,
+        name: String,
+        provider: Box<dyn ExchangeRateProvider>,
+    ) -> Result<()> {
+        self.support
+            .exchange_rate_providers
+            .register(name, provider)
+    }
+
+    /// Set the order in which exchange rate providers are queried. If a provider fails or
+    /// provides stale exchange rates, the next one is queried.
+    /// The registered providers are kept in memory only, so they have to be registered and the
+    /// chain has to be set again after every start. By default, only the "lipa" provider is used.
+    ///
+    /// Parameters:
+    /// * `provider_names` - names of the registered providers, in order of preference
+    /// * `staleness_threshold` - the maximum age of exchange rates a provider can provide
+    ///   before falling back to the next provider. If not set, any exchange rates are accepted.
+    ///
+    /// Requires network: **no**
+    pub fn set_exchange_rate_provider_chain(
+        &self,
+        provider_names: Vec<String>,
+        staleness_threshold: Option<Duration>,
+    ) -> Result<()> {
+        self.support
+            .exchange_rate_providers
+            .set_chain(provider_names, staleness_threshold)
+    }
+
+    /// Get the order in which exchange rate providers are queried.
+    ///
+    /// Requires network: **no**
+    pub fn get_exchange_rate_provider_chain(&self) -> Vec<String> {
+        self.support.exchange_rate_providers.get_chain()
+    }
+
+    /// Add a price alert. [`EventsCallback::exchange_rate_alert_triggered`](crate::EventsCallback::exchange_rate_alert_triggered)
+    /// will be called once an exchange rate update crosses the threshold. If the price is
+    /// already beyond the threshold, the alert triggers on the next exchange rate update.
+    /// An alert stays active until it's removed, but it only triggers again after the price has
+    /// moved back beyond the threshold by 1%.
+    ///
+    /// Parameters:
+    /// * `currency_code` - the fiat currency (ISO 4217 currency code) the threshold is denominated in
+    /// * `threshold_minor_units` - price of one bitcoin in minor units of the currency
+    ///   (e.g. 100k CHF is 10000000)
+    /// * `direction` - whether the price has to rise above or fall below the threshold
+    ///
+    /// Returns the id of the alert.
+    ///
+    /// Requires network: **no**
+    pub fn add_exchange_rate_alert(
+        &self,
+        currency_code: String,
+        threshold_minor_units: u64,
+        direction: ExchangeRateAlertDirection,
+    ) -> Result<u64> {
+        ensure!(
+            threshold_minor_units > 0,
+            invalid_input("The threshold must be greater than zero")
+        );
+        self.support
+            .data_store
+            .lock_unwrap()
+            .store_exchange_rate_alert(&currency_code, threshold_minor_units, direction)
+    }
+
+    /// List the price alerts added through [`Config::add_exchange_rate_alert`].
+    ///
+    /// Requires network: **no**
+    pub fn list_exchange_rate_alerts(&self) -> Result<Vec<ExchangeRateAlert>> {
+        self.support
+            .data_store
+            .lock_unwrap()
+            .retrieve_exchange_rate_alerts()
+    }
+
+    /// Remove a price alert.
+    ///
+    /// Parameters:
+    /// * `id` - the id returned by [`Config::add_exchange_rate_alert`]
+    ///
+    /// Requires network: **no**
+    pub fn remove_exchange_rate_alert(&self, id: u64) -> Result<()> {
+        let deleted = self
+            .support
+            .data_store
+            .lock_unwrap()
+            .delete_exchange_rate_alert(id)?;
+        ensure!(
+            deleted,
+            invalid_input(format!("No exchange rate alert with id {id}"))
+        );
+        Ok(())
+    }
+
+    /// Set a limit on the fees charged by the LSP for opening channels. Creating invoices and
+    /// swap-ins that would exceed it fail with
+    /// [`RuntimeErrorCode::LspFeeAboveLimit`](crate::RuntimeErrorCode::LspFeeAboveLimit).
+    /// Incoming LNURL-pay payments requiring a new channel are rejected regardless of the limit.
+    ///
+    /// Parameters:
+    /// * `max_lsp_fee` - the limit to apply, or `None` to accept any fee
+    ///
+    /// Requires network: **no**
+    pub fn set_max_lsp_fee(&self, max_lsp_fee: Option<MaxLspFee>) -> Result<()> {
+        if let Some(max_lsp_fee) = &max_lsp_fee {
+            ensure!(
+                max_lsp_fee.max_fee_sat.is_some() || max_lsp_fee.max_fee_permyriad.is_some(),
+                invalid_input("At least one limit must be set")
+            );
+            ensure!(
+                max_lsp_fee.max_fee_permyriad.is_none_or(|p| p <= 10_000),
+                invalid_input("The fee limit can't exceed the received amount")
+            );
+        }
+        self.support
+            .data_store
+            .lock_unwrap()
+            .append_max_lsp_fee(max_lsp_fee.as_ref())
+    }
+
+    /// Get the limit set through [`Config::set_max_lsp_fee`], if any.
+    ///
+    /// Requires network: **no**
+    pub fn get_max_lsp_fee(&self) -> Result<Option<MaxLspFee>> {
+        self.support.data_store.lock_unwrap().retrieve_max_lsp_fee()
+    }
+
     /// Set the timezone config.
     ///
     /// Parameters:
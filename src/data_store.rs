@@ -1,14 +1,28 @@
 use crate::analytics::AnalyticsConfig;
+use crate::balance_history::BalanceChange;
+use crate::channel::ChannelState;
 use crate::errors::Result;
+use crate::exchange_rate_alert::{ExchangeRateAlert, ExchangeRateAlertDirection};
+use crate::exchange_rate_provider::POCKET_EXCHANGE_RATE_SOURCE;
+use crate::lsp::MaxLspFee;
 use crate::migrations::migrate;
-use crate::{EnableStatus, ExchangeRate, Offer, PocketOfferError, TzConfig, UserPreferences};
-
+use crate::notification_handling::Notification;
+use crate::notification_log::NotificationOutcome;
+use crate::onchain::{AutoResolutionPolicy, AutoResolutionStrategy};
+use crate::reverse_swap::ReverseSwapStage;
+use crate::{
+    EnableStatus, ExchangeRate, Offer, PocketOfferError, SwapDepositIssue, TzConfig,
+    UserPreferences,
+};
+
+use breez_sdk_core::{OpeningFeeParams, ReverseSwapStatus};
 use chrono::{DateTime, Utc};
 use crow::FiatTopupSetupInfo;
 use crow::{PermanentFailureCode, TemporaryFailureCode};
 use log::debug;
+use num_enum::TryFromPrimitive;
 use perro::MapToError;
-use rusqlite::{backup, params, Connection, OptionalExtension, Params, Row};
+use rusqlite::{backup, params, Connection, OptionalExtension, Params, Row, TransactionBehavior};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub(crate) const BACKUP_DB_FILENAME_SUFFIX: &str = ".backup";
@@ -23,6 +37,61 @@ pub(crate) struct LocalPaymentData {
     pub received_lnurl_comment: Option<String>,
 }
 
+/// The state of a channel as recorded on the last sync, used to detect channel closes.
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) struct KnownChannel {
+    pub funding_txid: String,
+    pub channel_id: Option<String>,
+    pub state: ChannelState,
+    /// Whether the channel is being closed to switch to another LSP.
+    pub closing_for_lsp_migration: bool,
+}
+
+/// The LSP fee offers cached for receiving payments.
+#[derive(PartialEq, Eq, Debug, TryFromPrimitive, Clone, Copy)]
+#[repr(u8)]
+pub(crate) enum LspFeeOfferKind {
+    /// The cheapest offer, used for invoices.
+    Cheaper,
+    /// The offer valid for 48 hours, used for swaps.
+    LongerValid,
+}
+
+/// Which earlier handlings of the same payload make a notification a duplicate.
+#[derive(Clone)]
+pub(crate) struct DeduplicationWindow {
+    /// Successful handlings received at or after this time.
+    pub handled_since: SystemTime,
+    /// Handlings still in progress that were received at or after this time.
+    pub started_since: SystemTime,
+}
+
+/// The result of [`DataStore::start_notification_handling`].
+#[derive(PartialEq, Debug)]
+pub(crate) enum NotificationHandlingStart {
+    /// Handling was recorded as in progress under the given log entry.
+    Started { log_id: i64 },
+    /// The payload was already handled successfully, returning the given notification.
+    AlreadyHandled(Notification),
+    /// The payload is being handled by another delivery.
+    InProgress,
+}
+
+/// The kind of a [`Notification`] recorded in the notification log.
+#[derive(PartialEq, Eq, Debug, TryFromPrimitive, Clone, Copy)]
+#[repr(u8)]
+enum NotificationKind {
+    Bolt11PaymentReceived,
+    OnchainPaymentSwappedIn,
+    OnchainPaymentSwappedOut,
+    OnchainPaymentSwapOutUpdated,
+    OnchainDepositOutOfRange,
+    LnurlInvoiceCreated,
+    LnurlWithdrawCompleted,
+    OutgoingPaymentFailed,
+    LocalDataBackedUp,
+}
+
 #[derive(Clone, Copy)]
 pub(crate) enum BackupStatus {
     Complete,
@@ -40,6 +109,64 @@ pub(crate) struct CreatedInvoice {
     pub hash: String,
     pub invoice: String,
     pub channel_opening_fees: Option<u64>,
+    pub cancelled: bool,
+}
+
+/// A payment waiting in the outbox to be sent once the node is available.
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) enum OutboxPayment {
+    Bolt11 {
+        invoice: String,
+        amount_sat: Option<u64>,
+    },
+    LnUrlPay {
+        /// JSON serialized [`LnUrlPayRequestData`](breez_sdk_core::LnUrlPayRequestData).
+        request_data: String,
+        amount_sat: u64,
+        comment: Option<String>,
+    },
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) struct OutboxItem {
+    pub id: u64,
+    pub payment: OutboxPayment,
+    pub queued_at: SystemTime,
+    pub expires_at: SystemTime,
+}
+
+#[derive(PartialEq, Eq, Debug, TryFromPrimitive, Clone, Copy)]
+#[repr(u8)]
+pub(crate) enum OutboxItemStatus {
+    Queued,
+    Sending,
+    Sent,
+    Failed,
+    Expired,
+    Cancelled,
+}
+
+/// A broadcast transaction sweeping funds from channel closes or refunding a failed swap.
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) struct SweepTransaction {
+    pub txid: String,
+    /// The address of the failed swap whose funds are swept.
+    pub swap_address: String,
+    pub to_address: String,
+    pub sat_per_vbyte: u32,
+    pub broadcast_at: SystemTime,
+}
+
+/// A stage reached by a reverse swap, as recorded in its timeline.
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) struct ReverseSwapTimelineEntry {
+    pub stage: ReverseSwapStage,
+    pub txid: Option<String>,
+    /// The height of the block confirming the claim tx. Only set for
+    /// [`ReverseSwapStage::ClaimTxConfirmed`], if known.
+    pub block_height: Option<u32>,
+    /// When the stage was observed. `None` for stages implied by a later stage.
+    pub occurred_at: Option<SystemTime>,
 }
 
 impl DataStore {
@@ -97,6 +224,7 @@ impl DataStore {
                     currency_code,
                     rate,
                     updated_at,
+                    ..
                 },
             topup_value_minor_units,
             topup_value_sats,
@@ -140,7 +268,7 @@ impl DataStore {
             SELECT timezone_id, timezone_utc_offset_secs, payments.fiat_currency, h.rate, h.updated_at,  \
             o.pocket_id, o.fiat_currency, o.rate, o.exchanged_at, o.topup_value_minor_units, \
             o.exchange_fee_minor_units, o.exchange_fee_rate_permyriad, o.error, o.topup_value_sats, \
-            payments.personal_note, payments.received_on, payments.received_lnurl_comment \
+            payments.personal_note, payments.received_on, payments.received_lnurl_comment, h.source \
             FROM payments \
             LEFT JOIN exchange_rates_history h on payments.exchange_rates_history_snapshot_id=h.snapshot_id \
                 AND payments.fiat_currency=h.fiat_currency \
@@ -194,12 +322,12 @@ impl DataStore {
             .prepare(
                 "\
             SELECT * FROM ( \
-                SELECT hash, invoice, channel_opening_fees \
+                SELECT hash, invoice, channel_opening_fees, cancelled_at IS NOT NULL \
                 FROM created_invoices \
                 WHERE invoice_expiry_timestamp >= ?1) \
             UNION \
             SELECT * FROM ( \
-                SELECT hash, invoice, channel_opening_fees \
+                SELECT hash, invoice, channel_opening_fees, cancelled_at IS NOT NULL \
                 FROM created_invoices \
                 WHERE invoice_expiry_timestamp < ?1 \
                 ORDER BY id DESC \
@@ -212,6 +340,7 @@ impl DataStore {
                     hash: r.get(0)?,
                     invoice: r.get(1)?,
                     channel_opening_fees: r.get(2)?,
+                    cancelled: r.get(3)?,
                 })
             })
             .map_to_permanent_failure("Failed to bind parameter to prepared SQL query")?
@@ -224,7 +353,7 @@ impl DataStore {
             .conn
             .prepare(
                 "\
-            SELECT invoice, channel_opening_fees \
+            SELECT invoice, channel_opening_fees, cancelled_at IS NOT NULL \
             FROM created_invoices \
             WHERE hash=?1;
         ",
@@ -237,6 +366,7 @@ impl DataStore {
                     hash: hash.to_string(),
                     invoice: r.get(0)?,
                     channel_opening_fees: r.get(1)?,
+                    cancelled: r.get(2)?,
                 })
             })
             .map_to_permanent_failure("Failed to bind parameter to prepared SQL query")?
@@ -245,6 +375,56 @@ impl DataStore {
         Ok(invoice_iter.next())
     }
 
+    /// Marks a created invoice as cancelled.
+    ///
+    /// Returns `false` if no non-cancelled invoice with the given hash exists.
+    pub fn cancel_created_invoice(&mut self, hash: &str) -> Result<bool> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_to_permanent_failure("Time went backwards")?
+            .as_secs();
+
+        self.backup_status = BackupStatus::WaitingForBackup;
+        let updated_rows = self
+            .conn
+            .execute(
+                "\
+            UPDATE created_invoices \
+            SET cancelled_at = ?2 \
+            WHERE hash = ?1 AND cancelled_at IS NULL\
+            ",
+                params![hash, now],
+            )
+            .map_to_permanent_failure("Failed to cancel created invoice in local db")?;
+        Ok(updated_rows > 0)
+    }
+
+    /// Returns the hashes of cancelled invoices for which a payment received after the
+    /// cancellation hasn't been dismissed yet.
+    pub fn retrieve_undismissed_cancelled_invoice_hashes(&self) -> Result<Vec<String>> {
+        self.query_map(
+            "\
+            SELECT hash FROM created_invoices \
+            WHERE cancelled_at IS NOT NULL AND cancelled_payment_dismissed = 0 \
+            ORDER BY id DESC\
+            ",
+            [],
+            |r| r.get(0),
+        )
+        .map_to_permanent_failure("Failed to query cancelled invoices")
+    }
+
+    pub fn dismiss_cancelled_invoice_payment(&mut self, hash: &str) -> Result<()> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        self.conn
+            .execute(
+                "UPDATE created_invoices SET cancelled_payment_dismissed = 1 WHERE hash = ?1",
+                params![hash],
+            )
+            .map_to_permanent_failure("Failed to dismiss cancelled invoice payment in local db")?;
+        Ok(())
+    }
+
     pub fn update_personal_note(
         &mut self,
         payment_hash: &str,
@@ -287,16 +467,17 @@ impl DataStore {
         currency_code: &str,
         rate: u32,
         updated_at: SystemTime,
+        source: &str,
     ) -> Result<()> {
         self.backup_status = BackupStatus::WaitingForBackup;
         let dt: DateTime<Utc> = updated_at.into();
         self.conn
             .execute(
                 "\
-                REPLACE INTO exchange_rates (fiat_currency, rate, updated_at) \
-                VALUES (?1, ?2, ?3)
+                REPLACE INTO exchange_rates (fiat_currency, rate, updated_at, source) \
+                VALUES (?1, ?2, ?3, ?4)
                 ",
-                (currency_code, rate, dt),
+                (currency_code, rate, dt, source),
             )
             .map_to_invalid_input("Failed to update exchange rate in db")?;
 
@@ -307,7 +488,7 @@ impl DataStore {
         self.conn
             .prepare(
                 " \
-            SELECT fiat_currency, rate, updated_at \
+            SELECT fiat_currency, rate, updated_at, source \
             FROM exchange_rates \
             ",
             )
@@ -430,1098 +611,2954 @@ impl DataStore {
         .map_to_permanent_failure("Failed to query lightning addresses")
     }
 
-    fn query_map<T, P, F>(
-        &self,
-        statement: &str,
-        params: P,
-        from_row: F,
-    ) -> rusqlite::Result<Vec<T>>
-    where
-        P: Params,
-        F: Fn(&Row) -> rusqlite::Result<T>,
-    {
+    pub fn store_outbox_payment(
+        &mut self,
+        payment: &OutboxPayment,
+        expires_at: SystemTime,
+    ) -> Result<u64> {
+        let (invoice, request_data, amount_sat, comment) = match payment {
+            OutboxPayment::Bolt11 {
+                invoice,
+                amount_sat,
+            } => (Some(invoice), None, *amount_sat, None),
+            OutboxPayment::LnUrlPay {
+                request_data,
+                amount_sat,
+                comment,
+            } => (
+                None,
+                Some(request_data),
+                Some(*amount_sat),
+                comment.as_ref(),
+            ),
+        };
+        let queued_at = to_unix_secs(SystemTime::now())?;
+        let expires_at = to_unix_secs(expires_at)?;
+
+        self.backup_status = BackupStatus::WaitingForBackup;
         self.conn
-            .prepare(statement)?
-            .query_map(params, from_row)?
-            .collect()
+            .execute(
+                "\
+            INSERT INTO outbox (invoice, lnurl_pay_request_data, amount_sat, comment, status, queued_at, expires_at) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)\
+            ",
+                params![
+                    invoice,
+                    request_data,
+                    amount_sat,
+                    comment,
+                    OutboxItemStatus::Queued as u8,
+                    queued_at,
+                    expires_at
+                ],
+            )
+            .map_to_permanent_failure("Failed to store outbox payment to local db")?;
+        Ok(self.conn.last_insert_rowid() as u64)
     }
 
-    pub fn store_hidden_channel_close_onchain_funds_amount_sat(
+    /// Returns all outbox payments that are queued or are being sent, oldest first.
+    pub fn retrieve_pending_outbox_payments(&self) -> Result<Vec<OutboxItem>> {
+        self.query_map(
+            "\
+            SELECT id, invoice, lnurl_pay_request_data, amount_sat, comment, queued_at, expires_at \
+            FROM outbox \
+            WHERE status IN (?1, ?2) \
+            ORDER BY id\
+            ",
+            params![
+                OutboxItemStatus::Queued as u8,
+                OutboxItemStatus::Sending as u8
+            ],
+            outbox_item_from_row,
+        )
+        .map_to_permanent_failure("Failed to query outbox payments")
+    }
+
+    /// Changes the status of an outbox payment if it currently is in status `from`.
+    ///
+    /// Returns `false` if the payment doesn't exist or isn't in status `from`.
+    pub fn update_outbox_payment_status(
         &mut self,
-        amount_sat: u64,
+        id: u64,
+        from: OutboxItemStatus,
+        to: OutboxItemStatus,
+        payment_hash: Option<&str>,
+    ) -> Result<bool> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        let updated_rows = self
+            .conn
+            .execute(
+                "\
+            UPDATE outbox \
+            SET status = ?3, payment_hash = COALESCE(?4, payment_hash) \
+            WHERE id = ?1 AND status = ?2\
+            ",
+                params![id, from as u8, to as u8, payment_hash],
+            )
+            .map_to_permanent_failure("Failed to update outbox payment status in local db")?;
+        Ok(updated_rows > 0)
+    }
+
+    /// Returns the ids of all outbox payments with status [`OutboxItemStatus::Sending`].
+    ///
+    /// Used on startup to find payments whose send attempt got interrupted.
+    pub fn retrieve_sending_outbox_payment_ids(&self) -> Result<Vec<u64>> {
+        self.query_map(
+            "SELECT id FROM outbox WHERE status = ?1",
+            params![OutboxItemStatus::Sending as u8],
+            |row| row.get(0),
+        )
+        .map_to_permanent_failure("Failed to query interrupted outbox payments")
+    }
+
+    pub fn store_exchange_rate_alert(
+        &mut self,
+        currency_code: &str,
+        threshold_minor_units: u64,
+        direction: ExchangeRateAlertDirection,
+    ) -> Result<u64> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        self.conn
+            .execute(
+                "\
+            INSERT INTO exchange_rate_alerts (currency_code, threshold_minor_units, direction) \
+            VALUES (?1, ?2, ?3)\
+            ",
+                params![currency_code, threshold_minor_units, direction as u8],
+            )
+            .map_to_permanent_failure("Failed to store exchange rate alert to local db")?;
+        Ok(self.conn.last_insert_rowid() as u64)
+    }
+
+    pub fn retrieve_exchange_rate_alerts(&self) -> Result<Vec<ExchangeRateAlert>> {
+        self.query_map(
+            "\
+            SELECT id, currency_code, threshold_minor_units, direction, armed, last_triggered_at \
+            FROM exchange_rate_alerts \
+            ORDER BY id\
+            ",
+            [],
+            exchange_rate_alert_from_row,
+        )
+        .map_to_permanent_failure("Failed to query exchange rate alerts")
+    }
+
+    pub fn update_exchange_rate_alert_state(
+        &mut self,
+        id: u64,
+        armed: bool,
+        last_triggered_at: Option<SystemTime>,
     ) -> Result<()> {
+        let last_triggered_at = last_triggered_at
+            .map(|t| t.duration_since(UNIX_EPOCH).map(|d| d.as_secs()))
+            .transpose()
+            .map_to_permanent_failure("Trigger time is before the unix epoch")?;
         self.backup_status = BackupStatus::WaitingForBackup;
         self.conn
             .execute(
-                "INSERT INTO hidden_channel_close_amount (amount_sat) VALUES (?1)",
-                params![amount_sat],
+                "UPDATE exchange_rate_alerts SET armed = ?2, last_triggered_at = ?3 WHERE id = ?1",
+                params![id, armed, last_triggered_at],
             )
-            .map_to_permanent_failure("Failed to store hidden channel close amount in db")?;
+            .map_to_permanent_failure("Failed to update exchange rate alert in local db")?;
         Ok(())
     }
 
-    pub fn retrieve_hidden_channel_close_onchain_funds_amount_sat(
-        &mut self,
-    ) -> Result<Option<u64>> {
+    /// Returns `false` if no alert with the given id exists.
+    pub fn delete_exchange_rate_alert(&mut self, id: u64) -> Result<bool> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        let deleted_rows = self
+            .conn
+            .execute(
+                "DELETE FROM exchange_rate_alerts WHERE id = ?1",
+                params![id],
+            )
+            .map_to_permanent_failure("Failed to delete exchange rate alert from local db")?;
+        Ok(deleted_rows > 0)
+    }
+
+    pub fn store_sweep_transaction(&mut self, transaction: &SweepTransaction) -> Result<()> {
+        let broadcast_at = transaction
+            .broadcast_at
+            .duration_since(UNIX_EPOCH)
+            .map_to_permanent_failure("Broadcast time is before the unix epoch")?
+            .as_secs();
+        self.backup_status = BackupStatus::WaitingForBackup;
         self.conn
-            .query_row(
-                "SELECT amount_sat FROM hidden_channel_close_amount ORDER BY id DESC LIMIT 1",
-                (),
-                |r| r.get(0),
+            .execute(
+                "\
+            INSERT INTO sweep_transactions (txid, swap_address, to_address, sat_per_vbyte, broadcast_at) \
+            VALUES (?1, ?2, ?3, ?4, ?5)\
+            ",
+                params![
+                    transaction.txid,
+                    transaction.swap_address,
+                    transaction.to_address,
+                    transaction.sat_per_vbyte,
+                    broadcast_at
+                ],
             )
-            .optional()
-            .map_to_permanent_failure("Failed to query last hidden channel close amount")
+            .map_to_permanent_failure("Failed to store sweep transaction to local db")?;
+        Ok(())
     }
 
-    pub fn store_hidden_unresolved_failed_swap(&mut self, swap_address: &str) -> Result<()> {
+    /// Returns the sweep transactions that are neither confirmed nor replaced, oldest first.
+    pub fn retrieve_unconfirmed_sweep_transactions(&self) -> Result<Vec<SweepTransaction>> {
+        self.query_map(
+            "\
+            SELECT txid, swap_address, to_address, sat_per_vbyte, broadcast_at \
+            FROM sweep_transactions \
+            WHERE confirmed = 0 AND replaced_by IS NULL \
+            ORDER BY broadcast_at\
+            ",
+            [],
+            sweep_transaction_from_row,
+        )
+        .map_to_permanent_failure("Failed to query sweep transactions")
+    }
+
+    pub fn mark_sweep_transaction_confirmed(&mut self, txid: &str) -> Result<()> {
         self.backup_status = BackupStatus::WaitingForBackup;
         self.conn
             .execute(
-                "INSERT INTO hidden_failed_swaps (swap_address) VALUES (?1)",
-                params![swap_address],
+                "UPDATE sweep_transactions SET confirmed = 1 WHERE txid = ?1",
+                params![txid],
             )
-            .map_to_permanent_failure("Failed to store hidden failed swap in db")?;
+            .map_to_permanent_failure("Failed to mark sweep transaction as confirmed")?;
         Ok(())
     }
 
-    pub fn retrieve_hidden_unresolved_failed_swaps(&self) -> Result<Vec<String>> {
-        Ok(self
+    /// Records that `replacement` replaced the transaction `txid` (e.g. to bump its fee).
+    pub fn store_sweep_transaction_replacement(
+        &mut self,
+        txid: &str,
+        replacement: &SweepTransaction,
+    ) -> Result<()> {
+        self.store_sweep_transaction(replacement)?;
+        self.conn
+            .execute(
+                "UPDATE sweep_transactions SET replaced_by = ?2 WHERE txid = ?1",
+                params![txid, replacement.txid],
+            )
+            .map_to_permanent_failure("Failed to mark sweep transaction as replaced")?;
+        Ok(())
+    }
+
+    /// Records that a reverse swap reached a stage. Stages that were already recorded are only
+    /// updated with the time they were observed at, if it wasn't known yet.
+    pub fn store_reverse_swap_stage(
+        &mut self,
+        reverse_swap_id: &str,
+        entry: &ReverseSwapTimelineEntry,
+    ) -> Result<()> {
+        let occurred_at = entry.occurred_at.map(to_unix_secs).transpose()?;
+        let changed_rows = self
             .conn
-            .prepare("SELECT swap_address FROM hidden_failed_swaps")
-            .map_to_permanent_failure("Failed to prepare query for hidden failed swaps")?
-            .query_map([], |row| row.get(0))
-            .map_to_permanent_failure("Failed to query for hidden failed swaps")?
-            .filter_map(|r| r.ok())
-            .collect::<Vec<_>>())
+            .execute(
+                "\
+            INSERT INTO reverse_swap_timeline (reverse_swap_id, stage, txid, block_height, occurred_at) \
+            VALUES (?1, ?2, ?3, ?4, ?5) \
+            ON CONFLICT (reverse_swap_id, stage) DO UPDATE SET occurred_at = excluded.occurred_at \
+            WHERE occurred_at IS NULL AND excluded.occurred_at IS NOT NULL\
+            ",
+                params![
+                    reverse_swap_id,
+                    entry.stage as u8,
+                    entry.txid,
+                    entry.block_height,
+                    occurred_at
+                ],
+            )
+            .map_to_permanent_failure("Failed to store reverse swap stage to local db")?;
+        if changed_rows > 0 {
+            self.backup_status = BackupStatus::WaitingForBackup;
+        }
+        Ok(())
     }
 
-    pub fn store_selected_fiat_currency(&mut self, fiat_currency: &str) -> Result<()> {
+    /// Returns the recorded stages of a reverse swap, in the order they are reached.
+    pub fn retrieve_reverse_swap_timeline(
+        &self,
+        reverse_swap_id: &str,
+    ) -> Result<Vec<ReverseSwapTimelineEntry>> {
+        self.query_map(
+            "\
+            SELECT stage, txid, block_height, occurred_at \
+            FROM reverse_swap_timeline \
+            WHERE reverse_swap_id = ?1 \
+            ORDER BY stage\
+            ",
+            params![reverse_swap_id],
+            reverse_swap_timeline_entry_from_row,
+        )
+        .map_to_permanent_failure("Failed to query reverse swap timeline")
+    }
+
+    /// Stores a new auto resolution policy replacing the previous one. `None` disables automatic
+    /// resolution.
+    pub fn append_auto_resolution_policy(
+        &mut self,
+        policy: Option<&AutoResolutionPolicy>,
+    ) -> Result<()> {
         self.backup_status = BackupStatus::WaitingForBackup;
+        let (enabled, sweep_address, max_fee_permyriad) = match policy {
+            Some(policy) => match &policy.strategy {
+                AutoResolutionStrategy::SwapToLightning => (true, None, policy.max_fee_permyriad),
+                AutoResolutionStrategy::SweepToAddress { address } => {
+                    (true, Some(address.clone()), policy.max_fee_permyriad)
+                }
+            },
+            None => (false, None, 0),
+        };
         self.conn
             .execute(
-                "INSERT INTO fiat_currency (fiat_currency) VALUES (?1)",
-                params![fiat_currency],
+                "\
+                INSERT INTO auto_resolution_policy (enabled, sweep_address, max_fee_permyriad) \
+                VALUES (?1, ?2, ?3)\
+                ",
+                params![enabled, sweep_address, max_fee_permyriad],
             )
-            .map_to_permanent_failure("Failed to store fiat currency in db")?;
+            .map_to_permanent_failure("Failed to add auto resolution policy to db")?;
         Ok(())
     }
 
-    pub fn retrieve_last_set_fiat_currency(&self) -> Result<Option<String>> {
-        self.conn
+    pub fn retrieve_auto_resolution_policy(&self) -> Result<Option<AutoResolutionPolicy>> {
+        Ok(self
+            .conn
             .query_row(
-                "SELECT fiat_currency FROM fiat_currency ORDER BY id DESC LIMIT 1",
+                "\
+                SELECT enabled, sweep_address, max_fee_permyriad \
+                FROM auto_resolution_policy \
+                ORDER BY id DESC LIMIT 1\
+                ",
                 (),
-                |r| r.get(0),
+                auto_resolution_policy_from_row,
             )
             .optional()
-            .map_to_permanent_failure("Failed to query last hidden channel close amount")
+            .map_to_permanent_failure("Failed to query auto resolution policy")?
+            .flatten())
     }
-}
-
-fn lightning_address_from_row(row: &Row) -> rusqlite::Result<(String, EnableStatus)> {
-    let address = row.get(0)?;
-    let enable_status: u8 = row.get(1)?;
-    let enable_status = EnableStatus::try_from(enable_status).map_err(|e| {
-        rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Integer, Box::new(e))
-    })?;
-    Ok((address, enable_status))
-}
 
-// Store all provided exchange rates.
-// For every row it takes ~13 bytes (4 + 3 + 2 + 4), if we have 100 fiat currencies it adds 1300 bytes.
-// For 1000 payments it will add ~1 MB.
-fn insert_exchange_rate_snapshot(
-    connection: &Connection,
-    exchange_rates: Vec<ExchangeRate>,
-) -> Result<Option<u64>> {
-    if exchange_rates.is_empty() {
-        return Ok(None);
+    pub fn store_known_channel(&mut self, channel: &KnownChannel) -> Result<()> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        self.conn
+            .execute(
+                "\
+                INSERT OR REPLACE INTO known_channels (funding_txid, channel_id, state, closing_for_lsp_migration) \
+                VALUES (?1, ?2, ?3, ?4)\
+                ",
+                params![
+                    channel.funding_txid,
+                    channel.channel_id,
+                    channel.state as u8,
+                    channel.closing_for_lsp_migration
+                ],
+            )
+            .map_to_permanent_failure("Failed to store known channel to local db")?;
+        Ok(())
     }
-    let snapshot_id = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .map_to_permanent_failure("Failed to obtain duration since unix epoch")?
-        .as_secs();
-    for exchange_rate in exchange_rates {
-        let updated_at: DateTime<Utc> = exchange_rate.updated_at.into();
-        connection
+
+    /// Sets whether a known channel is closing for an LSP migration, leaving its state as is.
+    pub fn set_closing_for_lsp_migration(
+        &mut self,
+        funding_txid: &str,
+        closing_for_lsp_migration: bool,
+    ) -> Result<()> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        self.conn
             .execute(
                 "\
-                INSERT INTO exchange_rates_history (snapshot_id, fiat_currency, rate, updated_at) \
-                VALUES (?1, ?2, ?3, ?4)",
-                (
-                    snapshot_id,
-                    exchange_rate.currency_code,
-                    exchange_rate.rate,
-                    updated_at,
-                ),
+                UPDATE known_channels \
+                SET closing_for_lsp_migration = ?2 \
+                WHERE funding_txid = ?1\
+                ",
+                params![funding_txid, closing_for_lsp_migration],
             )
-            .map_to_invalid_input("Failed to insert exchange rate history in db")?;
+            .map_to_permanent_failure("Failed to update known channel in local db")?;
+        Ok(())
     }
-    Ok(Some(snapshot_id))
-}
 
-fn exchange_rate_from_row(row: &Row) -> rusqlite::Result<ExchangeRate> {
-    let fiat_currency: String = row.get(0)?;
-    let rate: u32 = row.get(1)?;
-    let updated_at: chrono::DateTime<chrono::Utc> = row.get(2)?;
-    Ok(ExchangeRate {
+    pub fn retrieve_known_channels(&self) -> Result<Vec<KnownChannel>> {
+        self.query_map(
+            "\
+            SELECT funding_txid, channel_id, state, closing_for_lsp_migration \
+            FROM known_channels\
+            ",
+            [],
+            known_channel_from_row,
+        )
+        .map_to_permanent_failure("Failed to query known channels")
+    }
+
+    /// Stores the offer of the given kind, replacing the previous one.
+    pub fn store_lsp_fee_offer(
+        &mut self,
+        kind: LspFeeOfferKind,
+        fee_params: &OpeningFeeParams,
+    ) -> Result<()> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        self.conn
+            .execute(
+                "\
+                REPLACE INTO lsp_fee_offers (kind, min_msat, proportional, valid_until, max_idle_time, max_client_to_self_delay, promise) \
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)\
+                ",
+                params![
+                    kind as u8,
+                    fee_params.min_msat,
+                    fee_params.proportional,
+                    fee_params.valid_until,
+                    fee_params.max_idle_time,
+                    fee_params.max_client_to_self_delay,
+                    fee_params.promise
+                ],
+            )
+            .map_to_permanent_failure("Failed to store LSP fee offer in db")?;
+        Ok(())
+    }
+
+    pub fn retrieve_lsp_fee_offer(
+        &self,
+        kind: LspFeeOfferKind,
+    ) -> Result<Option<OpeningFeeParams>> {
+        self.conn
+            .query_row(
+                "\
+                SELECT min_msat, proportional, valid_until, max_idle_time, max_client_to_self_delay, promise \
+                FROM lsp_fee_offers \
+                WHERE kind = ?1\
+                ",
+                params![kind as u8],
+                opening_fee_params_from_row,
+            )
+            .optional()
+            .map_to_permanent_failure("Failed to query LSP fee offer")
+    }
+
+    /// Stores a new LSP fee limit replacing the previous one. `None` removes the limit.
+    pub fn append_max_lsp_fee(&mut self, max_lsp_fee: Option<&MaxLspFee>) -> Result<()> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        let (max_fee_sat, max_fee_permyriad) = match max_lsp_fee {
+            Some(max_lsp_fee) => (max_lsp_fee.max_fee_sat, max_lsp_fee.max_fee_permyriad),
+            None => (None, None),
+        };
+        self.conn
+            .execute(
+                "\
+                INSERT INTO max_lsp_fee (max_fee_sat, max_fee_permyriad) \
+                VALUES (?1, ?2)\
+                ",
+                params![max_fee_sat, max_fee_permyriad],
+            )
+            .map_to_permanent_failure("Failed to add LSP fee limit to db")?;
+        Ok(())
+    }
+
+    pub fn retrieve_max_lsp_fee(&self) -> Result<Option<MaxLspFee>> {
+        Ok(self
+            .conn
+            .query_row(
+                "\
+                SELECT max_fee_sat, max_fee_permyriad \
+                FROM max_lsp_fee \
+                ORDER BY id DESC LIMIT 1\
+                ",
+                (),
+                max_lsp_fee_from_row,
+            )
+            .optional()
+            .map_to_permanent_failure("Failed to query LSP fee limit")?
+            .flatten())
+    }
+
+    /// Records the outcome of handling a notification. Outcomes received before `retain_since` are
+    /// removed.
+    pub fn store_notification_outcome(
+        &mut self,
+        outcome: &NotificationOutcome,
+        retain_since: SystemTime,
+    ) -> Result<()> {
+        // The log only serves diagnostics and deduplication on this device, so it isn't backed up
+        let received_at = to_unix_secs(outcome.received_at)?;
+        let (notification_kind, amount_sat, payment_hash, address, detail) =
+            match &outcome.notification {
+                Some(notification) => notification_to_columns(notification),
+                None => (None, None, None, None, None),
+            };
+        let tx = self
+            .conn
+            .transaction()
+            .map_to_permanent_failure("Failed to begin SQL transaction")?;
+        tx.execute(
+            "\
+            INSERT INTO notification_log (payload_hash, payload_kind, received_at, duration_ms, is_duplicate, notification_kind, amount_sat, payment_hash, address, detail, error) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)\
+            ",
+            params![
+                outcome.payload_hash,
+                outcome.payload_kind,
+                received_at,
+                outcome.duration.as_millis() as u64,
+                outcome.is_duplicate,
+                notification_kind.map(|k| k as u8),
+                amount_sat,
+                payment_hash,
+                address,
+                detail,
+                outcome.error
+            ],
+        )
+        .map_to_permanent_failure("Failed to store notification outcome in db")?;
+        tx.execute(
+            "DELETE FROM notification_log WHERE received_at < ?1",
+            params![to_unix_secs(retain_since)?],
+        )
+        .map_to_permanent_failure("Failed to delete old notification outcomes")?;
+        tx.commit()
+            .map_to_permanent_failure("Failed to commit SQL transaction")
+    }
+
+    /// Retrieves the most recent notification outcomes, most recent first.
+    pub fn retrieve_notification_outcomes(&self, limit: u32) -> Result<Vec<NotificationOutcome>> {
+        self.query_map(
+            "\
+            SELECT payload_hash, payload_kind, received_at, duration_ms, is_duplicate, notification_kind, amount_sat, payment_hash, address, detail, error \
+            FROM notification_log \
+            ORDER BY id DESC \
+            LIMIT ?1\
+            ",
+            params![limit],
+            notification_outcome_from_row,
+        )
+        .map_to_permanent_failure("Failed to query notification outcomes")
+    }
+
+    /// Records that a notification is being handled, as an entry without notification or error.
+    ///
+    /// If a `deduplication` window is given and an earlier handling of the same payload falls
+    /// within it, nothing is recorded and that handling is returned instead. The check and the
+    /// record happen in one transaction, so concurrent deliveries can't both start.
+    pub fn start_notification_handling(
+        &mut self,
+        payload_hash: &str,
+        payload_kind: &str,
+        received_at: SystemTime,
+        deduplication: Option<DeduplicationWindow>,
+    ) -> Result<NotificationHandlingStart> {
+        // The log only serves diagnostics and deduplication on this device, so it isn't backed up
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_to_permanent_failure("Failed to begin SQL transaction")?;
+        if let Some(window) = deduplication {
+            let handled = tx
+                .query_row(
+                    "\
+                    SELECT payload_hash, payload_kind, received_at, duration_ms, is_duplicate, notification_kind, amount_sat, payment_hash, address, detail, error \
+                    FROM notification_log \
+                    WHERE payload_hash = ?1 AND received_at >= ?2 AND notification_kind IS NOT NULL \
+                    ORDER BY id DESC LIMIT 1\
+                    ",
+                    params![payload_hash, to_unix_secs(window.handled_since)?],
+                    notification_outcome_from_row,
+                )
+                .optional()
+                .map_to_permanent_failure("Failed to query notification result")?
+                .and_then(|o| o.notification);
+            if let Some(notification) = handled {
+                return Ok(NotificationHandlingStart::AlreadyHandled(notification));
+            }
+            let in_progress = tx
+                .query_row(
+                    "\
+                    SELECT EXISTS ( \
+                        SELECT 1 FROM notification_log \
+                        WHERE payload_hash = ?1 AND received_at >= ?2 AND is_duplicate = 0 \
+                        AND notification_kind IS NULL AND error IS NULL \
+                    )\
+                    ",
+                    params![payload_hash, to_unix_secs(window.started_since)?],
+                    |row| row.get::<_, bool>(0),
+                )
+                .map_to_permanent_failure("Failed to query notifications in progress")?;
+            if in_progress {
+                return Ok(NotificationHandlingStart::InProgress);
+            }
+        }
+        tx.execute(
+            "\
+            INSERT INTO notification_log (payload_hash, payload_kind, received_at, duration_ms, is_duplicate) \
+            VALUES (?1, ?2, ?3, 0, 0)\
+            ",
+            params![payload_hash, payload_kind, to_unix_secs(received_at)?],
+        )
+        .map_to_permanent_failure("Failed to store notification outcome in db")?;
+        let log_id = tx.last_insert_rowid();
+        tx.commit()
+            .map_to_permanent_failure("Failed to commit SQL transaction")?;
+        Ok(NotificationHandlingStart::Started { log_id })
+    }
+
+    /// Completes the log entry of a notification started with
+    /// [`DataStore::start_notification_handling`] with its outcome. Outcomes received before
+    /// `retain_since` are removed.
+    pub fn finish_notification_handling(
+        &mut self,
+        log_id: i64,
+        outcome: &NotificationOutcome,
+        retain_since: SystemTime,
+    ) -> Result<()> {
+        let (notification_kind, amount_sat, payment_hash, address, detail) =
+            match &outcome.notification {
+                Some(notification) => notification_to_columns(notification),
+                None => (None, None, None, None, None),
+            };
+        let tx = self
+            .conn
+            .transaction()
+            .map_to_permanent_failure("Failed to begin SQL transaction")?;
+        tx.execute(
+            "\
+            UPDATE notification_log \
+            SET duration_ms = ?2, notification_kind = ?3, amount_sat = ?4, payment_hash = ?5, address = ?6, detail = ?7, error = ?8 \
+            WHERE id = ?1\
+            ",
+            params![
+                log_id,
+                outcome.duration.as_millis() as u64,
+                notification_kind.map(|k| k as u8),
+                amount_sat,
+                payment_hash,
+                address,
+                detail,
+                outcome.error
+            ],
+        )
+        .map_to_permanent_failure("Failed to store notification outcome in db")?;
+        tx.execute(
+            "DELETE FROM notification_log WHERE received_at < ?1",
+            params![to_unix_secs(retain_since)?],
+        )
+        .map_to_permanent_failure("Failed to delete old notification outcomes")?;
+        tx.commit()
+            .map_to_permanent_failure("Failed to commit SQL transaction")
+    }
+
+    fn query_map<T, P, F>(
+        &self,
+        statement: &str,
+        params: P,
+        from_row: F,
+    ) -> rusqlite::Result<Vec<T>>
+    where
+        P: Params,
+        F: Fn(&Row) -> rusqlite::Result<T>,
+    {
+        self.conn
+            .prepare(statement)?
+            .query_map(params, from_row)?
+            .collect()
+    }
+
+    pub fn store_hidden_channel_close_onchain_funds_amount_sat(
+        &mut self,
+        amount_sat: u64,
+    ) -> Result<()> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        self.conn
+            .execute(
+                "INSERT INTO hidden_channel_close_amount (amount_sat) VALUES (?1)",
+                params![amount_sat],
+            )
+            .map_to_permanent_failure("Failed to store hidden channel close amount in db")?;
+        Ok(())
+    }
+
+    pub fn retrieve_hidden_channel_close_onchain_funds_amount_sat(
+        &mut self,
+    ) -> Result<Option<u64>> {
+        self.conn
+            .query_row(
+                "SELECT amount_sat FROM hidden_channel_close_amount ORDER BY id DESC LIMIT 1",
+                (),
+                |r| r.get(0),
+            )
+            .optional()
+            .map_to_permanent_failure("Failed to query last hidden channel close amount")
+    }
+
+    pub fn store_hidden_unresolved_failed_swap(&mut self, swap_address: &str) -> Result<()> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        self.conn
+            .execute(
+                "INSERT INTO hidden_failed_swaps (swap_address) VALUES (?1)",
+                params![swap_address],
+            )
+            .map_to_permanent_failure("Failed to store hidden failed swap in db")?;
+        Ok(())
+    }
+
+    pub fn retrieve_hidden_unresolved_failed_swaps(&self) -> Result<Vec<String>> {
+        Ok(self
+            .conn
+            .prepare("SELECT swap_address FROM hidden_failed_swaps")
+            .map_to_permanent_failure("Failed to prepare query for hidden failed swaps")?
+            .query_map([], |row| row.get(0))
+            .map_to_permanent_failure("Failed to query for hidden failed swaps")?
+            .filter_map(|r| r.ok())
+            .collect::<Vec<_>>())
+    }
+
+    pub fn store_selected_fiat_currency(&mut self, fiat_currency: &str) -> Result<()> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        self.conn
+            .execute(
+                "INSERT INTO fiat_currency (fiat_currency) VALUES (?1)",
+                params![fiat_currency],
+            )
+            .map_to_permanent_failure("Failed to store fiat currency in db")?;
+        Ok(())
+    }
+
+    pub fn retrieve_last_set_fiat_currency(&self) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT fiat_currency FROM fiat_currency ORDER BY id DESC LIMIT 1",
+                (),
+                |r| r.get(0),
+            )
+            .optional()
+            .map_to_permanent_failure("Failed to query last hidden channel close amount")
+    }
+
+    pub fn store_secondary_fiat_currencies(&mut self, fiat_currencies: &[String]) -> Result<()> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        let tx = self
+            .conn
+            .transaction()
+            .map_to_permanent_failure("Failed to begin SQL transaction")?;
+        tx.execute("DELETE FROM secondary_fiat_currencies", ())
+            .map_to_permanent_failure("Failed to clear secondary fiat currencies in db")?;
+        for (position, fiat_currency) in fiat_currencies.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO secondary_fiat_currencies (position, fiat_currency) VALUES (?1, ?2)",
+                params![position, fiat_currency],
+            )
+            .map_to_permanent_failure("Failed to store secondary fiat currency in db")?;
+        }
+        tx.commit()
+            .map_to_permanent_failure("Failed to commit the db transaction")
+    }
+
+    pub fn retrieve_secondary_fiat_currencies(&self) -> Result<Vec<String>> {
+        self.query_map(
+            "SELECT fiat_currency FROM secondary_fiat_currencies ORDER BY position",
+            [],
+            |row| row.get(0),
+        )
+        .map_to_permanent_failure("Failed to query secondary fiat currencies")
+    }
+
+    /// Retrieves the snapshot of all exchange rates taken when the payment info was stored.
+    pub fn retrieve_payment_exchange_rates(&self, payment_hash: &str) -> Result<Vec<ExchangeRate>> {
+        self.query_map(
+            "\
+            SELECT DISTINCT h.fiat_currency, h.rate, h.updated_at, h.source \
+            FROM payments \
+            JOIN exchange_rates_history h ON payments.exchange_rates_history_snapshot_id=h.snapshot_id \
+            WHERE payments.hash=?1 \
+            ",
+            [payment_hash],
+            exchange_rate_from_row,
+        )
+        .map_to_permanent_failure("Failed to query payment exchange rates")
+    }
+
+    /// Retrieves the latest snapshot of exchange rates taken at or before the given time.
+    ///
+    /// Parameters:
+    /// * `time` - unix timestamp in seconds
+    pub fn retrieve_exchange_rates_at(&self, time: u64) -> Result<Vec<ExchangeRate>> {
+        self.query_map(
+            "\
+            SELECT fiat_currency, rate, updated_at, source \
+            FROM exchange_rates_history \
+            WHERE snapshot_id=(SELECT MAX(snapshot_id) FROM exchange_rates_history WHERE snapshot_id<=?1) \
+            ",
+            [time],
+            exchange_rate_from_row,
+        )
+        .map_to_permanent_failure("Failed to query exchange rates history")
+    }
+
+    pub fn store_balance_change(
+        &mut self,
+        payment_hash: &str,
+        changed_at: u64,
+        balance_change_msat: i64,
+        pending: bool,
+    ) -> Result<()> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        self.conn
+            .execute(
+                "\
+                REPLACE INTO balance_changes (payment_hash, changed_at, balance_change_msat, pending) \
+                VALUES (?1, ?2, ?3, ?4)\
+                ",
+                params![payment_hash, changed_at, balance_change_msat, pending],
+            )
+            .map_to_permanent_failure("Failed to store balance change in db")?;
+        Ok(())
+    }
+
+    pub fn delete_balance_change(&mut self, payment_hash: &str) -> Result<()> {
+        self.backup_status = BackupStatus::WaitingForBackup;
+        self.conn
+            .execute(
+                "DELETE FROM balance_changes WHERE payment_hash=?1",
+                params![payment_hash],
+            )
+            .map_to_permanent_failure("Failed to delete balance change from db")?;
+        Ok(())
+    }
+
+    pub fn retrieve_last_balance_change_time(&self) -> Result<Option<u64>> {
+        self.conn
+            .query_row("SELECT MAX(changed_at) FROM balance_changes", (), |r| {
+                r.get(0)
+            })
+            .map_to_permanent_failure("Failed to query last balance change time")
+    }
+
+    pub fn retrieve_pending_balance_change_hashes(&self) -> Result<Vec<String>> {
+        self.query_map(
+            "SELECT payment_hash FROM balance_changes WHERE pending=1",
+            [],
+            |row| row.get(0),
+        )
+        .map_to_permanent_failure("Failed to query pending balance changes")
+    }
+
+    /// Retrieves the balance resulting from all settled balance changes up to the given time.
+    ///
+    /// Parameters:
+    /// * `time` - unix timestamp in seconds
+    pub fn retrieve_balance_msat_at(&self, time: u64) -> Result<i64> {
+        self.conn
+            .query_row(
+                "\
+                SELECT COALESCE(SUM(balance_change_msat), 0) FROM balance_changes \
+                WHERE changed_at<=?1 AND pending=0\
+                ",
+                [time],
+                |r| r.get(0),
+            )
+            .map_to_permanent_failure("Failed to query balance")
+    }
+
+    /// Retrieves the settled balance changes in the `(from, to]` interval, ordered by time.
+    ///
+    /// Parameters:
+    /// * `from` - unix timestamp in seconds
+    /// * `to` - unix timestamp in seconds
+    pub fn retrieve_balance_changes(&self, from: u64, to: u64) -> Result<Vec<BalanceChange>> {
+        self.query_map(
+            "\
+            SELECT changed_at, balance_change_msat FROM balance_changes \
+            WHERE changed_at>?1 AND changed_at<=?2 AND pending=0 \
+            ORDER BY changed_at\
+            ",
+            [from, to],
+            balance_change_from_row,
+        )
+        .map_to_permanent_failure("Failed to query balance changes")
+    }
+}
+
+fn lightning_address_from_row(row: &Row) -> rusqlite::Result<(String, EnableStatus)> {
+    let address = row.get(0)?;
+    let enable_status: u8 = row.get(1)?;
+    let enable_status = EnableStatus::try_from(enable_status).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Integer, Box::new(e))
+    })?;
+    Ok((address, enable_status))
+}
+
+fn exchange_rate_alert_from_row(row: &Row) -> rusqlite::Result<ExchangeRateAlert> {
+    let direction: u8 = row.get(3)?;
+    let direction = ExchangeRateAlertDirection::try_from(direction).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Integer, Box::new(e))
+    })?;
+    let last_triggered_at: Option<u64> = row.get(5)?;
+    Ok(ExchangeRateAlert {
+        id: row.get(0)?,
+        currency_code: row.get(1)?,
+        threshold_minor_units: row.get(2)?,
+        direction,
+        armed: row.get(4)?,
+        last_triggered_at: last_triggered_at.map(|t| UNIX_EPOCH + Duration::from_secs(t)),
+    })
+}
+
+fn sweep_transaction_from_row(row: &Row) -> rusqlite::Result<SweepTransaction> {
+    let broadcast_at: u64 = row.get(4)?;
+    Ok(SweepTransaction {
+        txid: row.get(0)?,
+        swap_address: row.get(1)?,
+        to_address: row.get(2)?,
+        sat_per_vbyte: row.get(3)?,
+        broadcast_at: UNIX_EPOCH + Duration::from_secs(broadcast_at),
+    })
+}
+
+fn auto_resolution_policy_from_row(row: &Row) -> rusqlite::Result<Option<AutoResolutionPolicy>> {
+    let enabled: bool = row.get(0)?;
+    if !enabled {
+        return Ok(None);
+    }
+    let strategy = match row.get::<_, Option<String>>(1)? {
+        Some(address) => AutoResolutionStrategy::SweepToAddress { address },
+        None => AutoResolutionStrategy::SwapToLightning,
+    };
+    Ok(Some(AutoResolutionPolicy {
+        strategy,
+        max_fee_permyriad: row.get(2)?,
+    }))
+}
+
+fn to_unix_secs(time: SystemTime) -> Result<u64> {
+    Ok(time
+        .duration_since(UNIX_EPOCH)
+        .map_to_permanent_failure("Time is before the unix epoch")?
+        .as_secs())
+}
+
+type NotificationColumns = (
+    Option<NotificationKind>,
+    Option<u64>,
+    Option<String>,
+    Option<String>,
+    Option<u8>,
+);
+
+fn notification_to_columns(notification: &Notification) -> NotificationColumns {
+    match notification.clone() {
+        Notification::Bolt11PaymentReceived {
+            amount_sat,
+            payment_hash,
+        } => (
+            Some(NotificationKind::Bolt11PaymentReceived),
+            Some(amount_sat),
+            Some(payment_hash),
+            None,
+            None,
+        ),
+        Notification::OnchainPaymentSwappedIn {
+            amount_sat,
+            payment_hash,
+        } => (
+            Some(NotificationKind::OnchainPaymentSwappedIn),
+            Some(amount_sat),
+            Some(payment_hash),
+            None,
+            None,
+        ),
+        Notification::OnchainPaymentSwappedOut {
+            amount_sat,
+            payment_hash,
+        } => (
+            Some(NotificationKind::OnchainPaymentSwappedOut),
+            amount_sat,
+            payment_hash,
+            None,
+            None,
+        ),
+        Notification::OnchainPaymentSwapOutUpdated {
+            amount_sat,
+            payment_hash,
+            status,
+        } => (
+            Some(NotificationKind::OnchainPaymentSwapOutUpdated),
+            Some(amount_sat),
+            Some(payment_hash),
+            None,
+            Some(reverse_swap_status_to_u8(&status)),
+        ),
+        Notification::OnchainDepositOutOfRange {
+            address,
+            amount_sat,
+            issue,
+        } => (
+            Some(NotificationKind::OnchainDepositOutOfRange),
+            Some(amount_sat),
+            None,
+            Some(address),
+            Some(issue as u8),
+        ),
+        Notification::LnurlInvoiceCreated { amount_sat } => (
+            Some(NotificationKind::LnurlInvoiceCreated),
+            Some(amount_sat),
+            None,
+            None,
+            None,
+        ),
+        Notification::LnurlWithdrawCompleted {
+            amount_sat,
+            payment_hash,
+        } => (
+            Some(NotificationKind::LnurlWithdrawCompleted),
+            Some(amount_sat),
+            Some(payment_hash),
+            None,
+            None,
+        ),
+        Notification::OutgoingPaymentFailed {
+            amount_sat,
+            payment_hash,
+        } => (
+            Some(NotificationKind::OutgoingPaymentFailed),
+            Some(amount_sat),
+            Some(payment_hash),
+            None,
+            None,
+        ),
+        Notification::LocalDataBackedUp {} => (
+            Some(NotificationKind::LocalDataBackedUp),
+            None,
+            None,
+            None,
+            None,
+        ),
+    }
+}
+
+fn reverse_swap_status_to_u8(status: &ReverseSwapStatus) -> u8 {
+    match status {
+        ReverseSwapStatus::Initial => 0,
+        ReverseSwapStatus::InProgress => 1,
+        ReverseSwapStatus::Cancelled => 2,
+        ReverseSwapStatus::CompletedSeen => 3,
+        ReverseSwapStatus::CompletedConfirmed => 4,
+    }
+}
+
+fn reverse_swap_status_from_u8(status: u8) -> Option<ReverseSwapStatus> {
+    match status {
+        0 => Some(ReverseSwapStatus::Initial),
+        1 => Some(ReverseSwapStatus::InProgress),
+        2 => Some(ReverseSwapStatus::Cancelled),
+        3 => Some(ReverseSwapStatus::CompletedSeen),
+        4 => Some(ReverseSwapStatus::CompletedConfirmed),
+        _ => None,
+    }
+}
+
+fn notification_outcome_from_row(row: &Row) -> rusqlite::Result<NotificationOutcome> {
+    let received_at: u64 = row.get(2)?;
+    let duration_ms: u64 = row.get(3)?;
+    let notification = match row.get::<_, Option<u8>>(5)? {
+        Some(kind) => {
+            let kind = NotificationKind::try_from(kind).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    5,
+                    rusqlite::types::Type::Integer,
+                    Box::new(e),
+                )
+            })?;
+            Some(notification_from_row(kind, row)?)
+        }
+        None => None,
+    };
+    Ok(NotificationOutcome {
+        payload_hash: row.get(0)?,
+        payload_kind: row.get(1)?,
+        received_at: UNIX_EPOCH + Duration::from_secs(received_at),
+        duration: Duration::from_millis(duration_ms),
+        is_duplicate: row.get(4)?,
+        notification,
+        error: row.get(10)?,
+    })
+}
+
+fn notification_from_row(kind: NotificationKind, row: &Row) -> rusqlite::Result<Notification> {
+    let amount_sat = || row.get::<_, u64>(6);
+    let payment_hash = || row.get::<_, String>(7);
+    let detail = || row.get::<_, u8>(9);
+    let invalid_detail = |detail: u8| rusqlite::Error::IntegralValueOutOfRange(9, detail as i64);
+    Ok(match kind {
+        NotificationKind::Bolt11PaymentReceived => Notification::Bolt11PaymentReceived {
+            amount_sat: amount_sat()?,
+            payment_hash: payment_hash()?,
+        },
+        NotificationKind::OnchainPaymentSwappedIn => Notification::OnchainPaymentSwappedIn {
+            amount_sat: amount_sat()?,
+            payment_hash: payment_hash()?,
+        },
+        NotificationKind::OnchainPaymentSwappedOut => Notification::OnchainPaymentSwappedOut {
+            amount_sat: row.get(6)?,
+            payment_hash: row.get(7)?,
+        },
+        NotificationKind::OnchainPaymentSwapOutUpdated => {
+            let status = detail()?;
+            Notification::OnchainPaymentSwapOutUpdated {
+                amount_sat: amount_sat()?,
+                payment_hash: payment_hash()?,
+                status: reverse_swap_status_from_u8(status)
+                    .ok_or_else(|| invalid_detail(status))?,
+            }
+        }
+        NotificationKind::OnchainDepositOutOfRange => {
+            let issue = detail()?;
+            Notification::OnchainDepositOutOfRange {
+                address: row.get(8)?,
+                amount_sat: amount_sat()?,
+                issue: SwapDepositIssue::try_from(issue).map_err(|_| invalid_detail(issue))?,
+            }
+        }
+        NotificationKind::LnurlInvoiceCreated => Notification::LnurlInvoiceCreated {
+            amount_sat: amount_sat()?,
+        },
+        NotificationKind::LnurlWithdrawCompleted => Notification::LnurlWithdrawCompleted {
+            amount_sat: amount_sat()?,
+            payment_hash: payment_hash()?,
+        },
+        NotificationKind::OutgoingPaymentFailed => Notification::OutgoingPaymentFailed {
+            amount_sat: amount_sat()?,
+            payment_hash: payment_hash()?,
+        },
+        NotificationKind::LocalDataBackedUp => Notification::LocalDataBackedUp {},
+    })
+}
+
+fn max_lsp_fee_from_row(row: &Row) -> rusqlite::Result<Option<MaxLspFee>> {
+    let max_fee_sat: Option<u64> = row.get(0)?;
+    let max_fee_permyriad: Option<u32> = row.get(1)?;
+    if max_fee_sat.is_none() && max_fee_permyriad.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(MaxLspFee {
+        max_fee_sat,
+        max_fee_permyriad,
+    }))
+}
+
+fn opening_fee_params_from_row(row: &Row) -> rusqlite::Result<OpeningFeeParams> {
+    Ok(OpeningFeeParams {
+        min_msat: row.get(0)?,
+        proportional: row.get(1)?,
+        valid_until: row.get(2)?,
+        max_idle_time: row.get(3)?,
+        max_client_to_self_delay: row.get(4)?,
+        promise: row.get(5)?,
+    })
+}
+
+fn known_channel_from_row(row: &Row) -> rusqlite::Result<KnownChannel> {
+    let state: u8 = row.get(2)?;
+    let state = ChannelState::try_from(state).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Integer, Box::new(e))
+    })?;
+    Ok(KnownChannel {
+        funding_txid: row.get(0)?,
+        channel_id: row.get(1)?,
+        state,
+        closing_for_lsp_migration: row.get(3)?,
+    })
+}
+
+fn reverse_swap_timeline_entry_from_row(row: &Row) -> rusqlite::Result<ReverseSwapTimelineEntry> {
+    let stage: u8 = row.get(0)?;
+    let stage = ReverseSwapStage::try_from(stage).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Integer, Box::new(e))
+    })?;
+    let occurred_at: Option<u64> = row.get(3)?;
+    Ok(ReverseSwapTimelineEntry {
+        stage,
+        txid: row.get(1)?,
+        block_height: row.get(2)?,
+        occurred_at: occurred_at.map(|t| UNIX_EPOCH + Duration::from_secs(t)),
+    })
+}
+
+fn outbox_item_from_row(row: &Row) -> rusqlite::Result<OutboxItem> {
+    let id = row.get(0)?;
+    let invoice: Option<String> = row.get(1)?;
+    let request_data: Option<String> = row.get(2)?;
+    let amount_sat: Option<u64> = row.get(3)?;
+    let comment = row.get(4)?;
+    let queued_at: u64 = row.get(5)?;
+    let expires_at: u64 = row.get(6)?;
+
+    let payment = match (invoice, request_data, amount_sat) {
+        (Some(invoice), None, amount_sat) => OutboxPayment::Bolt11 {
+            invoice,
+            amount_sat,
+        },
+        (None, Some(request_data), Some(amount_sat)) => OutboxPayment::LnUrlPay {
+            request_data,
+            amount_sat,
+            comment,
+        },
+        _ => {
+            return Err(rusqlite::Error::InvalidColumnType(
+                1,
+                "invoice".to_string(),
+                rusqlite::types::Type::Null,
+            ))
+        }
+    };
+
+    Ok(OutboxItem {
+        id,
+        payment,
+        queued_at: UNIX_EPOCH + Duration::from_secs(queued_at),
+        expires_at: UNIX_EPOCH + Duration::from_secs(expires_at),
+    })
+}
+
+// Store all provided exchange rates.
+// For every row it takes ~13 bytes (4 + 3 + 2 + 4), if we have 100 fiat currencies it adds 1300 bytes.
+// For 1000 payments it will add ~1 MB.
+fn insert_exchange_rate_snapshot(
+    connection: &Connection,
+    exchange_rates: Vec<ExchangeRate>,
+) -> Result<Option<u64>> {
+    if exchange_rates.is_empty() {
+        return Ok(None);
+    }
+    let snapshot_id = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_to_permanent_failure("Failed to obtain duration since unix epoch")?
+        .as_secs();
+    for exchange_rate in exchange_rates {
+        let updated_at: DateTime<Utc> = exchange_rate.updated_at.into();
+        connection
+            .execute(
+                "\
+                INSERT INTO exchange_rates_history (snapshot_id, fiat_currency, rate, updated_at, source) \
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                (
+                    snapshot_id,
+                    exchange_rate.currency_code,
+                    exchange_rate.rate,
+                    updated_at,
+                    exchange_rate.source,
+                ),
+            )
+            .map_to_invalid_input("Failed to insert exchange rate history in db")?;
+    }
+    Ok(Some(snapshot_id))
+}
+
+fn balance_change_from_row(row: &Row) -> rusqlite::Result<BalanceChange> {
+    Ok(BalanceChange {
+        changed_at: row.get(0)?,
+        balance_change_msat: row.get(1)?,
+    })
+}
+
+fn exchange_rate_from_row(row: &Row) -> rusqlite::Result<ExchangeRate> {
+    let fiat_currency: String = row.get(0)?;
+    let rate: u32 = row.get(1)?;
+    let updated_at: chrono::DateTime<chrono::Utc> = row.get(2)?;
+    let source: String = row.get(3)?;
+    Ok(ExchangeRate {
         currency_code: fiat_currency,
         rate,
         updated_at: SystemTime::from(updated_at),
+        source,
+    })
+}
+
+fn offer_from_row(row: &Row) -> rusqlite::Result<Option<Offer>> {
+    if let Some(id) = row.get(5)? {
+        let fiat_currency: String = row.get(6)?;
+        let rate: u32 = row.get(7)?;
+        let exchanged_at: chrono::DateTime<chrono::Utc> = row.get(8)?;
+        let exchanged_at = SystemTime::from(exchanged_at);
+        let topup_value_minor_units: u64 = row.get(9)?;
+        let exchange_fee_minor_units: u64 = row.get(10)?;
+        let exchange_fee_rate_permyriad: u16 = row.get(11)?;
+        let topup_value_sats: Option<u64> = row.get(13)?;
+
+        let exchange_rate = ExchangeRate {
+            currency_code: fiat_currency,
+            rate,
+            updated_at: exchanged_at,
+            source: POCKET_EXCHANGE_RATE_SOURCE.to_string(),
+        };
+
+        return Ok(Some(Offer {
+            id,
+            exchange_rate: exchange_rate.clone(),
+            topup_value_minor_units,
+            topup_value_sats,
+            exchange_fee_minor_units,
+            exchange_fee_rate_permyriad,
+            lightning_payout_fee: None,
+            error: to_offer_error(row.get(12)?),
+        }));
+    }
+
+    Ok(None)
+}
+
+fn local_payment_data_from_row(row: &Row) -> rusqlite::Result<LocalPaymentData> {
+    let user_preferences = user_preferences_from_row(row)?;
+    let exchange_rate = payment_exchange_rate_from_row(row)?;
+    let offer = offer_from_row(row)?;
+    let personal_note = row.get(14)?;
+    let received_on = row.get(15)?;
+    let received_lnurl_comment = row.get(16)?;
+
+    Ok(LocalPaymentData {
+        user_preferences,
+        exchange_rate,
+        offer,
+        personal_note,
+        received_on,
+        received_lnurl_comment,
+    })
+}
+
+fn user_preferences_from_row(row: &Row) -> rusqlite::Result<Option<UserPreferences>> {
+    let timezone_id: Option<String> = row.get(0)?;
+    let timezone_utc_offset_secs: Option<i32> = row.get(1)?;
+    let fiat_currency = row.get(2)?;
+
+    match (timezone_id, timezone_utc_offset_secs, fiat_currency) {
+        (Some(timezone_id), Some(timezone_utc_offset_secs), Some(fiat_currency)) => {
+            Ok(Some(UserPreferences {
+                fiat_currency,
+                timezone_config: TzConfig {
+                    timezone_id,
+                    timezone_utc_offset_secs,
+                },
+                secondary_fiat_currencies: Vec::new(),
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn payment_exchange_rate_from_row(row: &Row) -> rusqlite::Result<Option<ExchangeRate>> {
+    let currency_code: Option<String> = row.get(2)?;
+    let rate: Option<u32> = row.get(3)?;
+    let updated_at: Option<DateTime<Utc>> = row.get(4)?;
+    let source: Option<String> = row.get(17)?;
+
+    match (currency_code, rate, updated_at, source) {
+        (Some(currency_code), Some(rate), Some(updated_at), Some(source)) => {
+            Ok(Some(ExchangeRate {
+                currency_code,
+                rate,
+                updated_at: SystemTime::from(updated_at),
+                source,
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+pub fn from_offer_error(error: Option<PocketOfferError>) -> Option<String> {
+    error.map(|e| match e {
+        PocketOfferError::TemporaryFailure { code } => match code {
+            TemporaryFailureCode::NoRoute => "no_route".to_string(),
+            TemporaryFailureCode::InvoiceExpired => "invoice_expired".to_string(),
+            TemporaryFailureCode::Unexpected => "error".to_string(),
+            TemporaryFailureCode::Unknown { msg } => msg,
+        },
+        PocketOfferError::PermanentFailure { code } => match code {
+            PermanentFailureCode::ThresholdExceeded => "threshold_exceeded".to_string(),
+            PermanentFailureCode::OrderInactive => "order_inactive".to_string(),
+            PermanentFailureCode::CompaniesUnsupported => "companies_unsupported".to_string(),
+            PermanentFailureCode::CountryUnsupported => "country_unsupported".to_string(),
+            PermanentFailureCode::OtherRiskDetected => "other_risk_detected".to_string(),
+            PermanentFailureCode::CustomerRequested => "customer_requested".to_string(),
+            PermanentFailureCode::AccountNotMatching => "account_not_matching".to_string(),
+            PermanentFailureCode::PayoutExpired => "payout_expired".to_string(),
+        },
+    })
+}
+
+pub fn to_offer_error(code: Option<String>) -> Option<PocketOfferError> {
+    code.map(|c| match &*c {
+        "no_route" => PocketOfferError::TemporaryFailure {
+            code: TemporaryFailureCode::NoRoute,
+        },
+        "invoice_expired" => PocketOfferError::TemporaryFailure {
+            code: TemporaryFailureCode::InvoiceExpired,
+        },
+        "error" => PocketOfferError::TemporaryFailure {
+            code: TemporaryFailureCode::Unexpected,
+        },
+        "threshold_exceeded" => PocketOfferError::PermanentFailure {
+            code: PermanentFailureCode::ThresholdExceeded,
+        },
+        "order_inactive" => PocketOfferError::PermanentFailure {
+            code: PermanentFailureCode::OrderInactive,
+        },
+        "companies_unsupported" => PocketOfferError::PermanentFailure {
+            code: PermanentFailureCode::CompaniesUnsupported,
+        },
+        "country_unsupported" => PocketOfferError::PermanentFailure {
+            code: PermanentFailureCode::CountryUnsupported,
+        },
+        "other_risk_detected" => PocketOfferError::PermanentFailure {
+            code: PermanentFailureCode::OtherRiskDetected,
+        },
+        "customer_requested" => PocketOfferError::PermanentFailure {
+            code: PermanentFailureCode::CustomerRequested,
+        },
+        "account_not_matching" => PocketOfferError::PermanentFailure {
+            code: PermanentFailureCode::AccountNotMatching,
+        },
+        "payout_expired" => PocketOfferError::PermanentFailure {
+            code: PermanentFailureCode::PayoutExpired,
+        },
+        e => PocketOfferError::TemporaryFailure {
+            code: TemporaryFailureCode::Unknown { msg: e.to_string() },
+        },
     })
 }
 
-fn offer_from_row(row: &Row) -> rusqlite::Result<Option<Offer>> {
-    if let Some(id) = row.get(5)? {
-        let fiat_currency: String = row.get(6)?;
-        let rate: u32 = row.get(7)?;
-        let exchanged_at: chrono::DateTime<chrono::Utc> = row.get(8)?;
-        let exchanged_at = SystemTime::from(exchanged_at);
-        let topup_value_minor_units: u64 = row.get(9)?;
-        let exchange_fee_minor_units: u64 = row.get(10)?;
-        let exchange_fee_rate_permyriad: u16 = row.get(11)?;
-        let topup_value_sats: Option<u64> = row.get(13)?;
+fn fiat_topup_info_from_row(row: &Row) -> rusqlite::Result<Option<FiatTopupSetupInfo>> {
+    Ok(Some(FiatTopupSetupInfo {
+        order_id: row.get(0)?,
+        debitor_iban: row.get(1)?,
+        creditor_reference: row.get(2)?,
+        creditor_iban: row.get(3)?,
+        creditor_bank_name: row.get(4)?,
+        creditor_bank_street: row.get(5)?,
+        creditor_bank_postal_code: row.get(6)?,
+        creditor_bank_town: row.get(7)?,
+        creditor_bank_country: row.get(8)?,
+        creditor_bank_bic: row.get(9)?,
+        creditor_name: row.get(10)?,
+        creditor_street: row.get(11)?,
+        creditor_postal_code: row.get(12)?,
+        creditor_town: row.get(13)?,
+        creditor_country: row.get(14)?,
+        currency: row.get(15)?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::channel::ChannelState;
+    use crate::data_store::{
+        CreatedInvoice, DataStore, DeduplicationWindow, KnownChannel, LocalPaymentData,
+        LspFeeOfferKind, NotificationHandlingStart, OutboxItemStatus, OutboxPayment,
+        ReverseSwapTimelineEntry, SweepTransaction,
+    };
+    use crate::exchange_rate_alert::{ExchangeRateAlert, ExchangeRateAlertDirection};
+    use crate::lsp::MaxLspFee;
+    use crate::node_config::TzConfig;
+    use crate::onchain::{AutoResolutionPolicy, AutoResolutionStrategy};
+    use crate::reverse_swap::ReverseSwapStage;
+    use crate::support::fee_params;
+    use crate::{
+        EnableStatus, ExchangeRate, Notification, NotificationOutcome, Offer, PocketOfferError,
+        SwapDepositIssue, UserPreferences,
+    };
+
+    use crate::analytics::AnalyticsConfig;
+    use breez_sdk_core::{OpeningFeeParams, ReverseSwapStatus};
+    use crow::FiatTopupSetupInfo;
+    use crow::TopupError::TemporaryFailure;
+    use crow::{PermanentFailureCode, TemporaryFailureCode};
+    use std::fs;
+    use std::thread::sleep;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+    const TEST_DB_PATH: &str = ".3l_local_test";
+
+    #[test]
+    fn test_store_payment_info() {
+        let db_name = String::from("db.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+
+        let user_preferences = UserPreferences {
+            fiat_currency: "EUR".to_string(),
+            timezone_config: TzConfig {
+                timezone_id: "Bern".to_string(),
+                timezone_utc_offset_secs: -1234,
+            },
+            secondary_fiat_currencies: Vec::new(),
+        };
+
+        let exchange_rates = vec![
+            ExchangeRate {
+                currency_code: "EUR".to_string(),
+                rate: 4123,
+                updated_at: SystemTime::now(),
+                source: "lipa".to_string(),
+            },
+            ExchangeRate {
+                currency_code: "USD".to_string(),
+                rate: 3950,
+                updated_at: SystemTime::now(),
+                source: "lipa".to_string(),
+            },
+        ];
         let exchange_rate = ExchangeRate {
-            currency_code: fiat_currency,
-            rate,
-            updated_at: exchanged_at,
+            currency_code: "EUR".to_string(),
+            rate: 5123,
+            updated_at: SystemTime::now(),
+            source: "lipa".to_string(),
+        };
+        let offer = Offer {
+            id: "id".to_string(),
+            exchange_rate: exchange_rate.clone(),
+            topup_value_minor_units: 51245,
+            topup_value_sats: Some(2625281),
+            exchange_fee_minor_units: 123,
+            exchange_fee_rate_permyriad: 50,
+            lightning_payout_fee: None,
+            error: Some(TemporaryFailure {
+                code: TemporaryFailureCode::NoRoute,
+            }),
         };
 
-        return Ok(Some(Offer {
-            id,
+        let exchange_rate = ExchangeRate {
+            currency_code: "EUR".to_string(),
+            rate: 5123,
+            updated_at: SystemTime::now(),
+            source: "lipa".to_string(),
+        };
+        let offer_no_error = Offer {
+            id: "id".to_string(),
             exchange_rate: exchange_rate.clone(),
-            topup_value_minor_units,
-            topup_value_sats,
-            exchange_fee_minor_units,
-            exchange_fee_rate_permyriad,
+            topup_value_minor_units: 51245,
+            topup_value_sats: Some(2625281),
+            exchange_fee_minor_units: 123,
+            exchange_fee_rate_permyriad: 50,
             lightning_payout_fee: None,
-            error: to_offer_error(row.get(12)?),
-        }));
-    }
+            error: None,
+        };
 
-    Ok(None)
-}
+        data_store
+            .store_payment_info(
+                "hash",
+                user_preferences.clone(),
+                Vec::new(),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
 
-fn local_payment_data_from_row(row: &Row) -> rusqlite::Result<LocalPaymentData> {
-    let user_preferences = user_preferences_from_row(row)?;
-    let exchange_rate = payment_exchange_rate_from_row(row)?;
-    let offer = offer_from_row(row)?;
-    let personal_note = row.get(14)?;
-    let received_on = row.get(15)?;
-    let received_lnurl_comment = row.get(16)?;
+        // The second call will not fail.
+        data_store
+            .store_payment_info(
+                "hash",
+                user_preferences.clone(),
+                exchange_rates.clone(),
+                Some(offer.clone()),
+                None,
+                None,
+            )
+            .unwrap();
 
-    Ok(LocalPaymentData {
-        user_preferences,
-        exchange_rate,
-        offer,
-        personal_note,
-        received_on,
-        received_lnurl_comment,
-    })
-}
+        data_store
+            .store_payment_info(
+                "hash - no offer",
+                user_preferences.clone(),
+                exchange_rates.clone(),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
 
-fn user_preferences_from_row(row: &Row) -> rusqlite::Result<Option<UserPreferences>> {
-    let timezone_id: Option<String> = row.get(0)?;
-    let timezone_utc_offset_secs: Option<i32> = row.get(1)?;
-    let fiat_currency = row.get(2)?;
+        data_store
+            .store_payment_info(
+                "hash - no error",
+                user_preferences.clone(),
+                exchange_rates,
+                Some(offer_no_error.clone()),
+                Some("received_on".to_string()),
+                Some("received_lnurl_comment".to_string()),
+            )
+            .unwrap();
+
+        assert!(data_store
+            .retrieve_payment_info("non existent hash")
+            .unwrap()
+            .is_none());
+
+        let local_payment_data = data_store.retrieve_payment_info("hash").unwrap().unwrap();
+        assert_eq!(local_payment_data.offer.unwrap(), offer);
+        assert_eq!(
+            local_payment_data.user_preferences.as_ref().unwrap(),
+            &user_preferences.clone()
+        );
+        assert_eq!(
+            local_payment_data
+                .exchange_rate
+                .as_ref()
+                .unwrap()
+                .currency_code,
+            user_preferences.fiat_currency
+        );
+        assert_eq!(
+            local_payment_data.exchange_rate.as_ref().unwrap().rate,
+            4123
+        );
+
+        assert_eq!(
+            data_store
+                .retrieve_payment_exchange_rates("hash")
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(data_store
+            .retrieve_payment_exchange_rates("non existent hash")
+            .unwrap()
+            .is_empty());
+
+        let local_payment_data = data_store
+            .retrieve_payment_info("hash - no offer")
+            .unwrap()
+            .unwrap();
+        assert!(local_payment_data.offer.is_none());
+        assert_eq!(
+            local_payment_data.user_preferences.as_ref().unwrap(),
+            &user_preferences.clone()
+        );
+        assert_eq!(
+            local_payment_data
+                .exchange_rate
+                .as_ref()
+                .unwrap()
+                .currency_code,
+            user_preferences.fiat_currency
+        );
+        assert_eq!(
+            local_payment_data.exchange_rate.as_ref().unwrap().rate,
+            4123
+        );
+
+        let local_payment_data = data_store
+            .retrieve_payment_info("hash - no error")
+            .unwrap()
+            .unwrap();
+        assert_eq!(local_payment_data.offer.as_ref().unwrap(), &offer_no_error);
+        assert_eq!(
+            local_payment_data.received_on.as_ref().unwrap(),
+            "received_on"
+        );
+        assert_eq!(
+            local_payment_data.received_lnurl_comment.as_ref().unwrap(),
+            "received_lnurl_comment"
+        );
 
-    match (timezone_id, timezone_utc_offset_secs, fiat_currency) {
-        (Some(timezone_id), Some(timezone_utc_offset_secs), Some(fiat_currency)) => {
-            Ok(Some(UserPreferences {
-                fiat_currency,
-                timezone_config: TzConfig {
-                    timezone_id,
-                    timezone_utc_offset_secs,
-                },
-            }))
-        }
-        _ => Ok(None),
-    }
-}
+        let mut local_payment_data_with_note = local_payment_data.clone();
+        local_payment_data_with_note.personal_note = Some(String::from("a note"));
+        data_store
+            .update_personal_note("hash - no error", Some("a note"))
+            .unwrap();
+        let local_payment_data_with_note_from_store = data_store
+            .retrieve_payment_info("hash - no error")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            local_payment_data_with_note_from_store,
+            local_payment_data_with_note
+        );
 
-fn payment_exchange_rate_from_row(row: &Row) -> rusqlite::Result<Option<ExchangeRate>> {
-    let currency_code: Option<String> = row.get(2)?;
-    let rate: Option<u32> = row.get(3)?;
-    let updated_at: Option<DateTime<Utc>> = row.get(4)?;
+        data_store
+            .update_personal_note("hash - no error", None)
+            .unwrap();
+        let local_payment_data_without_note_from_store = data_store
+            .retrieve_payment_info("hash - no error")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            local_payment_data_without_note_from_store,
+            local_payment_data
+        );
 
-    match (currency_code, rate, updated_at) {
-        (Some(currency_code), Some(rate), Some(updated_at)) => Ok(Some(ExchangeRate {
-            currency_code,
-            rate,
-            updated_at: SystemTime::from(updated_at),
-        })),
-        _ => Ok(None),
+        data_store
+            .update_personal_note("hash - no local data", Some("a note"))
+            .unwrap();
+        let local_payment_data_with_note_from_store = data_store
+            .retrieve_payment_info("hash - no local data")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            local_payment_data_with_note_from_store,
+            LocalPaymentData {
+                user_preferences: None,
+                exchange_rate: None,
+                offer: None,
+                personal_note: Some(String::from("a note")),
+                received_on: None,
+                received_lnurl_comment: None,
+            }
+        );
     }
-}
-
-pub fn from_offer_error(error: Option<PocketOfferError>) -> Option<String> {
-    error.map(|e| match e {
-        PocketOfferError::TemporaryFailure { code } => match code {
-            TemporaryFailureCode::NoRoute => "no_route".to_string(),
-            TemporaryFailureCode::InvoiceExpired => "invoice_expired".to_string(),
-            TemporaryFailureCode::Unexpected => "error".to_string(),
-            TemporaryFailureCode::Unknown { msg } => msg,
-        },
-        PocketOfferError::PermanentFailure { code } => match code {
-            PermanentFailureCode::ThresholdExceeded => "threshold_exceeded".to_string(),
-            PermanentFailureCode::OrderInactive => "order_inactive".to_string(),
-            PermanentFailureCode::CompaniesUnsupported => "companies_unsupported".to_string(),
-            PermanentFailureCode::CountryUnsupported => "country_unsupported".to_string(),
-            PermanentFailureCode::OtherRiskDetected => "other_risk_detected".to_string(),
-            PermanentFailureCode::CustomerRequested => "customer_requested".to_string(),
-            PermanentFailureCode::AccountNotMatching => "account_not_matching".to_string(),
-            PermanentFailureCode::PayoutExpired => "payout_expired".to_string(),
-        },
-    })
-}
+    #[test]
+    fn test_offer_storage() {
+        let db_name = String::from("offers.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
 
-pub fn to_offer_error(code: Option<String>) -> Option<PocketOfferError> {
-    code.map(|c| match &*c {
-        "no_route" => PocketOfferError::TemporaryFailure {
+        // Temporary failures
+        let offer_no_route = build_offer_with_error(PocketOfferError::TemporaryFailure {
             code: TemporaryFailureCode::NoRoute,
-        },
-        "invoice_expired" => PocketOfferError::TemporaryFailure {
+        });
+        store_payment_with_offer_and_test(offer_no_route, &mut data_store, "offer_no_route");
+
+        let offer_invoice_expired = build_offer_with_error(PocketOfferError::TemporaryFailure {
             code: TemporaryFailureCode::InvoiceExpired,
-        },
-        "error" => PocketOfferError::TemporaryFailure {
+        });
+        store_payment_with_offer_and_test(
+            offer_invoice_expired,
+            &mut data_store,
+            "offer_invoice_expired",
+        );
+
+        let offer_unexpected = build_offer_with_error(PocketOfferError::TemporaryFailure {
             code: TemporaryFailureCode::Unexpected,
-        },
-        "threshold_exceeded" => PocketOfferError::PermanentFailure {
+        });
+        store_payment_with_offer_and_test(offer_unexpected, &mut data_store, "offer_unexpected");
+
+        let offer_unknown = build_offer_with_error(PocketOfferError::TemporaryFailure {
+            code: TemporaryFailureCode::Unknown { msg: "Test".into() },
+        });
+        store_payment_with_offer_and_test(offer_unknown, &mut data_store, "offer_unknown");
+
+        // Permanent failures
+        let offer_threshold_exceeded = build_offer_with_error(PocketOfferError::PermanentFailure {
             code: PermanentFailureCode::ThresholdExceeded,
-        },
-        "order_inactive" => PocketOfferError::PermanentFailure {
+        });
+        store_payment_with_offer_and_test(
+            offer_threshold_exceeded,
+            &mut data_store,
+            "offer_threshold_exceeded",
+        );
+
+        let offer_order_inactive = build_offer_with_error(PocketOfferError::PermanentFailure {
             code: PermanentFailureCode::OrderInactive,
-        },
-        "companies_unsupported" => PocketOfferError::PermanentFailure {
-            code: PermanentFailureCode::CompaniesUnsupported,
-        },
-        "country_unsupported" => PocketOfferError::PermanentFailure {
+        });
+        store_payment_with_offer_and_test(
+            offer_order_inactive.clone(),
+            &mut data_store,
+            "offer_order_inactive",
+        );
+
+        let offer_companies_unsupported =
+            build_offer_with_error(PocketOfferError::PermanentFailure {
+                code: PermanentFailureCode::CompaniesUnsupported,
+            });
+        store_payment_with_offer_and_test(
+            offer_companies_unsupported,
+            &mut data_store,
+            "offer_companies_unsupported",
+        );
+
+        let offer_country_unsuported = build_offer_with_error(PocketOfferError::PermanentFailure {
             code: PermanentFailureCode::CountryUnsupported,
-        },
-        "other_risk_detected" => PocketOfferError::PermanentFailure {
-            code: PermanentFailureCode::OtherRiskDetected,
-        },
-        "customer_requested" => PocketOfferError::PermanentFailure {
-            code: PermanentFailureCode::CustomerRequested,
-        },
-        "account_not_matching" => PocketOfferError::PermanentFailure {
-            code: PermanentFailureCode::AccountNotMatching,
-        },
-        "payout_expired" => PocketOfferError::PermanentFailure {
-            code: PermanentFailureCode::PayoutExpired,
-        },
-        e => PocketOfferError::TemporaryFailure {
-            code: TemporaryFailureCode::Unknown { msg: e.to_string() },
-        },
-    })
-}
+        });
+        store_payment_with_offer_and_test(
+            offer_country_unsuported,
+            &mut data_store,
+            "offer_country_unsuported",
+        );
 
-fn fiat_topup_info_from_row(row: &Row) -> rusqlite::Result<Option<FiatTopupSetupInfo>> {
-    Ok(Some(FiatTopupSetupInfo {
-        order_id: row.get(0)?,
-        debitor_iban: row.get(1)?,
-        creditor_reference: row.get(2)?,
-        creditor_iban: row.get(3)?,
-        creditor_bank_name: row.get(4)?,
-        creditor_bank_street: row.get(5)?,
-        creditor_bank_postal_code: row.get(6)?,
-        creditor_bank_town: row.get(7)?,
-        creditor_bank_country: row.get(8)?,
-        creditor_bank_bic: row.get(9)?,
-        creditor_name: row.get(10)?,
-        creditor_street: row.get(11)?,
-        creditor_postal_code: row.get(12)?,
-        creditor_town: row.get(13)?,
-        creditor_country: row.get(14)?,
-        currency: row.get(15)?,
-    }))
-}
+        let offer_other_risk_detected =
+            build_offer_with_error(PocketOfferError::PermanentFailure {
+                code: PermanentFailureCode::OtherRiskDetected,
+            });
+        store_payment_with_offer_and_test(
+            offer_other_risk_detected,
+            &mut data_store,
+            "offer_other_risk_detected",
+        );
 
-#[cfg(test)]
-mod tests {
-    use crate::data_store::{CreatedInvoice, DataStore, LocalPaymentData};
-    use crate::node_config::TzConfig;
-    use crate::{EnableStatus, ExchangeRate, Offer, PocketOfferError, UserPreferences};
+        let offer_customer_requested = build_offer_with_error(PocketOfferError::PermanentFailure {
+            code: PermanentFailureCode::CustomerRequested,
+        });
+        store_payment_with_offer_and_test(
+            offer_customer_requested,
+            &mut data_store,
+            "offer_customer_requested",
+        );
 
-    use crate::analytics::AnalyticsConfig;
-    use crow::FiatTopupSetupInfo;
-    use crow::TopupError::TemporaryFailure;
-    use crow::{PermanentFailureCode, TemporaryFailureCode};
-    use std::fs;
-    use std::thread::sleep;
-    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+        let offer_account_not_matching =
+            build_offer_with_error(PocketOfferError::PermanentFailure {
+                code: PermanentFailureCode::AccountNotMatching,
+            });
+        store_payment_with_offer_and_test(
+            offer_account_not_matching,
+            &mut data_store,
+            "offer_account_not_matching",
+        );
 
-    const TEST_DB_PATH: &str = ".3l_local_test";
+        let offer_payout_expired = build_offer_with_error(PocketOfferError::PermanentFailure {
+            code: PermanentFailureCode::PayoutExpired,
+        });
+        store_payment_with_offer_and_test(
+            offer_payout_expired,
+            &mut data_store,
+            "offer_payout_expired",
+        );
+    }
 
-    #[test]
-    fn test_store_payment_info() {
-        let db_name = String::from("db.db3");
-        reset_db(&db_name);
-        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+    fn build_offer_with_error(error: PocketOfferError) -> Offer {
+        let exchange_rate = ExchangeRate {
+            currency_code: "EUR".to_string(),
+            rate: 5123,
+            updated_at: SystemTime::now(),
+            source: "lipa".to_string(),
+        };
+        Offer {
+            id: "id".to_string(),
+            exchange_rate: exchange_rate.clone(),
+            topup_value_minor_units: 51245,
+            topup_value_sats: Some(2625281),
+            exchange_fee_minor_units: 123,
+            exchange_fee_rate_permyriad: 50,
+            lightning_payout_fee: None,
+            error: Some(error),
+        }
+    }
 
+    fn store_payment_with_offer_and_test(offer: Offer, data_store: &mut DataStore, hash: &str) {
         let user_preferences = UserPreferences {
             fiat_currency: "EUR".to_string(),
             timezone_config: TzConfig {
                 timezone_id: "Bern".to_string(),
                 timezone_utc_offset_secs: -1234,
             },
+            secondary_fiat_currencies: Vec::new(),
         };
 
         let exchange_rates = vec![
             ExchangeRate {
                 currency_code: "EUR".to_string(),
-                rate: 4123,
+                rate: 123,
                 updated_at: SystemTime::now(),
+                source: "lipa".to_string(),
             },
             ExchangeRate {
                 currency_code: "USD".to_string(),
-                rate: 3950,
+                rate: 234,
                 updated_at: SystemTime::now(),
+                source: "lipa".to_string(),
             },
         ];
-        let exchange_rate = ExchangeRate {
-            currency_code: "EUR".to_string(),
-            rate: 5123,
-            updated_at: SystemTime::now(),
-        };
-        let offer = Offer {
-            id: "id".to_string(),
-            exchange_rate: exchange_rate.clone(),
-            topup_value_minor_units: 51245,
-            topup_value_sats: Some(2625281),
-            exchange_fee_minor_units: 123,
-            exchange_fee_rate_permyriad: 50,
-            lightning_payout_fee: None,
-            error: Some(TemporaryFailure {
-                code: TemporaryFailureCode::NoRoute,
-            }),
-        };
-
-        let exchange_rate = ExchangeRate {
-            currency_code: "EUR".to_string(),
-            rate: 5123,
-            updated_at: SystemTime::now(),
-        };
-        let offer_no_error = Offer {
-            id: "id".to_string(),
-            exchange_rate: exchange_rate.clone(),
-            topup_value_minor_units: 51245,
-            topup_value_sats: Some(2625281),
-            exchange_fee_minor_units: 123,
-            exchange_fee_rate_permyriad: 50,
-            lightning_payout_fee: None,
-            error: None,
-        };
 
         data_store
             .store_payment_info(
-                "hash",
+                hash,
                 user_preferences.clone(),
-                Vec::new(),
-                None,
+                exchange_rates,
+                Some(offer.clone()),
                 None,
                 None,
             )
             .unwrap();
 
-        // The second call will not fail.
+        assert_eq!(
+            data_store
+                .retrieve_payment_info(hash)
+                .unwrap()
+                .unwrap()
+                .offer
+                .unwrap(),
+            offer
+        );
+    }
+
+    #[test]
+    fn test_exchange_rate_storage() {
+        let db_name = String::from("rates.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+
+        assert!(data_store.get_all_exchange_rates().unwrap().is_empty());
+
         data_store
-            .store_payment_info(
-                "hash",
-                user_preferences.clone(),
-                exchange_rates.clone(),
-                Some(offer.clone()),
-                None,
-                None,
+            .update_exchange_rate(
+                "USD",
+                1234,
+                SystemTime::UNIX_EPOCH + Duration::from_secs(10),
+                "lipa",
             )
             .unwrap();
+        let rates = data_store.get_all_exchange_rates().unwrap();
+        let usd_rate = rates.iter().find(|r| r.currency_code == "USD").unwrap();
+        assert_eq!(usd_rate.rate, 1234);
+        assert_eq!(
+            usd_rate.updated_at,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(10)
+        );
+
+        sleep(Duration::from_secs(2));
 
         data_store
-            .store_payment_info(
-                "hash - no offer",
-                user_preferences.clone(),
-                exchange_rates.clone(),
-                None,
-                None,
-                None,
+            .update_exchange_rate(
+                "EUR",
+                5678,
+                SystemTime::UNIX_EPOCH + Duration::from_secs(20),
+                "lipa",
             )
             .unwrap();
+        let rates = data_store.get_all_exchange_rates().unwrap();
+        let usd_rate = rates.iter().find(|r| r.currency_code == "USD").unwrap();
+        let eur_rate = rates.iter().find(|r| r.currency_code == "EUR").unwrap();
+        assert_eq!(usd_rate.rate, 1234);
+        assert_eq!(
+            usd_rate.updated_at,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(10)
+        );
+        assert_eq!(eur_rate.rate, 5678);
+        assert_eq!(
+            eur_rate.updated_at,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(20)
+        );
+
+        sleep(Duration::from_secs(2));
 
         data_store
-            .store_payment_info(
-                "hash - no error",
-                user_preferences.clone(),
-                exchange_rates,
-                Some(offer_no_error.clone()),
-                Some("received_on".to_string()),
-                Some("received_lnurl_comment".to_string()),
+            .update_exchange_rate(
+                "USD",
+                4321,
+                SystemTime::UNIX_EPOCH + Duration::from_secs(30),
+                "lipa",
             )
             .unwrap();
+        let rates = data_store.get_all_exchange_rates().unwrap();
+        let usd_rate = rates.iter().find(|r| r.currency_code == "USD").unwrap();
+        let eur_rate = rates.iter().find(|r| r.currency_code == "EUR").unwrap();
+        assert_eq!(usd_rate.rate, 4321);
+        assert_eq!(
+            usd_rate.updated_at,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(30)
+        );
+        assert_eq!(eur_rate.rate, 5678);
+        assert_eq!(
+            eur_rate.updated_at,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(20)
+        );
+    }
 
-        assert!(data_store
-            .retrieve_payment_info("non existent hash")
+    #[test]
+    fn test_invoice_persistence() {
+        let db_name = String::from("invoice_persistence.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+
+        assert!(data_store.retrieve_created_invoices(5).unwrap().is_empty());
+
+        let future = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
             .unwrap()
-            .is_none());
+            .as_secs()
+            + 1000;
 
-        let local_payment_data = data_store.retrieve_payment_info("hash").unwrap().unwrap();
-        assert_eq!(local_payment_data.offer.unwrap(), offer);
+        let expired_invoice = CreatedInvoice {
+            hash: "hash1".to_string(),
+            invoice: "invoice1".to_string(),
+            channel_opening_fees: Some(25000000),
+            cancelled: false,
+        };
+        let pending_invoice = CreatedInvoice {
+            hash: "hash2".to_string(),
+            invoice: "invoice2".to_string(),
+            channel_opening_fees: None,
+            cancelled: false,
+        };
+
+        data_store
+            .store_created_invoice(
+                expired_invoice.hash.as_str(),
+                expired_invoice.invoice.as_str(),
+                &expired_invoice.channel_opening_fees,
+                123,
+            )
+            .unwrap();
         assert_eq!(
-            local_payment_data.user_preferences.as_ref().unwrap(),
-            &user_preferences.clone()
+            data_store.retrieve_created_invoices(5).unwrap(),
+            vec![expired_invoice.clone()]
         );
+
+        data_store
+            .store_created_invoice(
+                pending_invoice.hash.as_str(),
+                pending_invoice.invoice.as_str(),
+                &pending_invoice.channel_opening_fees,
+                future,
+            )
+            .unwrap();
+        let mut invoices = data_store.retrieve_created_invoices(5).unwrap();
+        invoices.sort_by_key(|i| i.hash.clone());
         assert_eq!(
-            local_payment_data
-                .exchange_rate
-                .as_ref()
-                .unwrap()
-                .currency_code,
-            user_preferences.fiat_currency
+            invoices,
+            vec![expired_invoice.clone(), pending_invoice.clone()]
         );
+
+        let mut invoices = data_store.retrieve_created_invoices(1).unwrap();
+        invoices.sort_by_key(|i| i.hash.clone());
         assert_eq!(
-            local_payment_data.exchange_rate.as_ref().unwrap().rate,
-            4123
+            invoices,
+            vec![expired_invoice.clone(), pending_invoice.clone()]
+        );
+
+        let invoices = data_store.retrieve_created_invoices(0).unwrap();
+        assert_eq!(invoices, vec![pending_invoice.clone()]);
+
+        assert!(data_store
+            .retrieve_created_invoice_by_hash("hash0")
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            data_store
+                .retrieve_created_invoice_by_hash(expired_invoice.hash.as_str())
+                .unwrap(),
+            Some(expired_invoice)
         );
+        assert_eq!(
+            data_store
+                .retrieve_created_invoice_by_hash(pending_invoice.hash.as_str())
+                .unwrap(),
+            Some(pending_invoice)
+        );
+    }
+
+    #[test]
+    fn test_invoice_cancellation() {
+        let db_name = String::from("invoice_cancellation.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+
+        let future = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 1000;
+
+        let mut invoice = CreatedInvoice {
+            hash: "hash1".to_string(),
+            invoice: "invoice1".to_string(),
+            channel_opening_fees: None,
+            cancelled: false,
+        };
+        data_store
+            .store_created_invoice(
+                invoice.hash.as_str(),
+                invoice.invoice.as_str(),
+                &invoice.channel_opening_fees,
+                future,
+            )
+            .unwrap();
 
-        let local_payment_data = data_store
-            .retrieve_payment_info("hash - no offer")
+        assert!(!data_store.cancel_created_invoice("hash0").unwrap());
+        assert!(data_store
+            .retrieve_undismissed_cancelled_invoice_hashes()
             .unwrap()
-            .unwrap();
-        assert!(local_payment_data.offer.is_none());
+            .is_empty());
+
+        assert!(data_store.cancel_created_invoice(&invoice.hash).unwrap());
+        assert!(!data_store.cancel_created_invoice(&invoice.hash).unwrap());
+
+        invoice.cancelled = true;
         assert_eq!(
-            local_payment_data.user_preferences.as_ref().unwrap(),
-            &user_preferences.clone()
+            data_store.retrieve_created_invoices(0).unwrap(),
+            vec![invoice.clone()]
         );
         assert_eq!(
-            local_payment_data
-                .exchange_rate
-                .as_ref()
-                .unwrap()
-                .currency_code,
-            user_preferences.fiat_currency
+            data_store
+                .retrieve_created_invoice_by_hash(&invoice.hash)
+                .unwrap(),
+            Some(invoice.clone())
         );
         assert_eq!(
-            local_payment_data.exchange_rate.as_ref().unwrap().rate,
-            4123
+            data_store
+                .retrieve_undismissed_cancelled_invoice_hashes()
+                .unwrap(),
+            vec![invoice.hash.clone()]
         );
 
-        let local_payment_data = data_store
-            .retrieve_payment_info("hash - no error")
+        data_store
+            .dismiss_cancelled_invoice_payment(&invoice.hash)
+            .unwrap();
+        assert!(data_store
+            .retrieve_undismissed_cancelled_invoice_hashes()
             .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_fiat_topup_info_persistence() {
+        let db_name = String::from("fiat_topup_info_persistence");
+        reset_db(&db_name);
+        let data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+
+        assert_eq!(data_store.retrieve_latest_fiat_topup_info().unwrap(), None);
+
+        let mut fiat_topup_info = FiatTopupSetupInfo {
+            order_id: "961b8ee9-74cc-4844-9fe8-b02ce0702663".to_string(),
+            debitor_iban: "CH4889144919566329178".to_string(),
+            creditor_reference: "8584-9931-ABCD".to_string(),
+            creditor_iban: "DE2089144126222826294".to_string(),
+            creditor_bank_name: "Example Bank".to_string(),
+            creditor_bank_street: "Bankingstreet 21".to_string(),
+            creditor_bank_postal_code: "2121".to_string(),
+            creditor_bank_town: "Example Town".to_string(),
+            creditor_bank_country: "CH".to_string(),
+            creditor_bank_bic: "VA7373".to_string(),
+            creditor_name: "John Doe".to_string(),
+            creditor_street: "Doestreet 21".to_string(),
+            creditor_postal_code: "2112".to_string(),
+            creditor_town: "Creditor Town".to_string(),
+            creditor_country: "DE".to_string(),
+            currency: "EUR".to_string(),
+        };
+
+        data_store
+            .store_fiat_topup_info(fiat_topup_info.clone())
             .unwrap();
-        assert_eq!(local_payment_data.offer.as_ref().unwrap(), &offer_no_error);
-        assert_eq!(
-            local_payment_data.received_on.as_ref().unwrap(),
-            "received_on"
-        );
         assert_eq!(
-            local_payment_data.received_lnurl_comment.as_ref().unwrap(),
-            "received_lnurl_comment"
+            data_store.retrieve_latest_fiat_topup_info().unwrap(),
+            Some(fiat_topup_info.clone())
         );
 
-        let mut local_payment_data_with_note = local_payment_data.clone();
-        local_payment_data_with_note.personal_note = Some(String::from("a note"));
+        fiat_topup_info.order_id = "361dd7f8-c7b7-4871-b906-b67fa3ed9b55".to_string();
+
         data_store
-            .update_personal_note("hash - no error", Some("a note"))
-            .unwrap();
-        let local_payment_data_with_note_from_store = data_store
-            .retrieve_payment_info("hash - no error")
-            .unwrap()
+            .store_fiat_topup_info(fiat_topup_info.clone())
             .unwrap();
         assert_eq!(
-            local_payment_data_with_note_from_store,
-            local_payment_data_with_note
+            data_store.retrieve_latest_fiat_topup_info().unwrap(),
+            Some(fiat_topup_info)
+        );
+    }
+
+    #[test]
+    fn test_persisting_analytics_config() {
+        let db_name = String::from("analytics_config.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+
+        assert_eq!(
+            data_store.retrieve_analytics_config().unwrap(),
+            AnalyticsConfig::Enabled
         );
 
         data_store
-            .update_personal_note("hash - no error", None)
-            .unwrap();
-        let local_payment_data_without_note_from_store = data_store
-            .retrieve_payment_info("hash - no error")
-            .unwrap()
+            .append_analytics_config(AnalyticsConfig::Disabled)
             .unwrap();
         assert_eq!(
-            local_payment_data_without_note_from_store,
-            local_payment_data
+            data_store.retrieve_analytics_config().unwrap(),
+            AnalyticsConfig::Disabled
         );
 
         data_store
-            .update_personal_note("hash - no local data", Some("a note"))
-            .unwrap();
-        let local_payment_data_with_note_from_store = data_store
-            .retrieve_payment_info("hash - no local data")
-            .unwrap()
+            .append_analytics_config(AnalyticsConfig::Enabled)
             .unwrap();
         assert_eq!(
-            local_payment_data_with_note_from_store,
-            LocalPaymentData {
-                user_preferences: None,
-                exchange_rate: None,
-                offer: None,
-                personal_note: Some(String::from("a note")),
-                received_on: None,
-                received_lnurl_comment: None,
-            }
+            data_store.retrieve_analytics_config().unwrap(),
+            AnalyticsConfig::Enabled
         );
     }
+
     #[test]
-    fn test_offer_storage() {
-        let db_name = String::from("offers.db3");
+    fn test_storing_lightning_address() {
+        let db_name = String::from("lightning_addresses.db3");
         reset_db(&db_name);
         let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+        let addresses = data_store.retrieve_lightning_addresses().unwrap();
+        assert!(addresses.is_empty());
 
-        // Temporary failures
-        let offer_no_route = build_offer_with_error(PocketOfferError::TemporaryFailure {
-            code: TemporaryFailureCode::NoRoute,
-        });
-        store_payment_with_offer_and_test(offer_no_route, &mut data_store, "offer_no_route");
-
-        let offer_invoice_expired = build_offer_with_error(PocketOfferError::TemporaryFailure {
-            code: TemporaryFailureCode::InvoiceExpired,
-        });
-        store_payment_with_offer_and_test(
-            offer_invoice_expired,
-            &mut data_store,
-            "offer_invoice_expired",
+        // Store an address.
+        data_store
+            .store_lightning_address("satoshi@lipa.swiss")
+            .unwrap();
+        let addresses = data_store.retrieve_lightning_addresses().unwrap();
+        assert_eq!(
+            addresses,
+            vec![("satoshi@lipa.swiss".to_string(), EnableStatus::Enabled)]
         );
 
-        let offer_unexpected = build_offer_with_error(PocketOfferError::TemporaryFailure {
-            code: TemporaryFailureCode::Unexpected,
-        });
-        store_payment_with_offer_and_test(offer_unexpected, &mut data_store, "offer_unexpected");
-
-        let offer_unknown = build_offer_with_error(PocketOfferError::TemporaryFailure {
-            code: TemporaryFailureCode::Unknown { msg: "Test".into() },
-        });
-        store_payment_with_offer_and_test(offer_unknown, &mut data_store, "offer_unknown");
-
-        // Permanent failures
-        let offer_threshold_exceeded = build_offer_with_error(PocketOfferError::PermanentFailure {
-            code: PermanentFailureCode::ThresholdExceeded,
-        });
-        store_payment_with_offer_and_test(
-            offer_threshold_exceeded,
-            &mut data_store,
-            "offer_threshold_exceeded",
+        // Storing the same address.
+        data_store
+            .store_lightning_address("satoshi@lipa.swiss")
+            .unwrap();
+        let addresses = data_store.retrieve_lightning_addresses().unwrap();
+        assert_eq!(
+            addresses,
+            vec![("satoshi@lipa.swiss".to_string(), EnableStatus::Enabled)]
         );
 
-        let offer_order_inactive = build_offer_with_error(PocketOfferError::PermanentFailure {
-            code: PermanentFailureCode::OrderInactive,
-        });
-        store_payment_with_offer_and_test(
-            offer_order_inactive.clone(),
-            &mut data_store,
-            "offer_order_inactive",
+        // Disabling the address.
+        data_store
+            .update_lightning_address("satoshi@lipa.swiss", EnableStatus::FeatureDisabled)
+            .unwrap();
+        let addresses = data_store.retrieve_lightning_addresses().unwrap();
+        assert_eq!(
+            addresses,
+            vec![(
+                "satoshi@lipa.swiss".to_string(),
+                EnableStatus::FeatureDisabled
+            )]
         );
 
-        let offer_companies_unsupported =
-            build_offer_with_error(PocketOfferError::PermanentFailure {
-                code: PermanentFailureCode::CompaniesUnsupported,
-            });
-        store_payment_with_offer_and_test(
-            offer_companies_unsupported,
-            &mut data_store,
-            "offer_companies_unsupported",
+        // Storing the same address enables it back.
+        data_store
+            .store_lightning_address("satoshi@lipa.swiss")
+            .unwrap();
+        let addresses = data_store.retrieve_lightning_addresses().unwrap();
+        assert_eq!(
+            addresses,
+            vec![("satoshi@lipa.swiss".to_string(), EnableStatus::Enabled)]
         );
 
-        let offer_country_unsuported = build_offer_with_error(PocketOfferError::PermanentFailure {
-            code: PermanentFailureCode::CountryUnsupported,
-        });
-        store_payment_with_offer_and_test(
-            offer_country_unsuported,
-            &mut data_store,
-            "offer_country_unsuported",
+        // Storing another address.
+        data_store
+            .store_lightning_address("finney@lipa.swiss")
+            .unwrap();
+        let addresses = data_store.retrieve_lightning_addresses().unwrap();
+        assert_eq!(
+            addresses,
+            vec![
+                ("satoshi@lipa.swiss".to_string(), EnableStatus::Enabled),
+                ("finney@lipa.swiss".to_string(), EnableStatus::Enabled),
+            ]
         );
+    }
 
-        let offer_other_risk_detected =
-            build_offer_with_error(PocketOfferError::PermanentFailure {
-                code: PermanentFailureCode::OtherRiskDetected,
-            });
-        store_payment_with_offer_and_test(
-            offer_other_risk_detected,
-            &mut data_store,
-            "offer_other_risk_detected",
-        );
+    #[test]
+    fn test_storing_channel_close_hidden_amount() {
+        let db_name = String::from("hidden_channel_closes.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
 
-        let offer_customer_requested = build_offer_with_error(PocketOfferError::PermanentFailure {
-            code: PermanentFailureCode::CustomerRequested,
-        });
-        store_payment_with_offer_and_test(
-            offer_customer_requested,
-            &mut data_store,
-            "offer_customer_requested",
+        assert_eq!(
+            data_store
+                .retrieve_hidden_channel_close_onchain_funds_amount_sat()
+                .unwrap(),
+            None
         );
 
-        let offer_account_not_matching =
-            build_offer_with_error(PocketOfferError::PermanentFailure {
-                code: PermanentFailureCode::AccountNotMatching,
-            });
-        store_payment_with_offer_and_test(
-            offer_account_not_matching,
-            &mut data_store,
-            "offer_account_not_matching",
+        data_store
+            .store_hidden_channel_close_onchain_funds_amount_sat(50)
+            .unwrap();
+        assert_eq!(
+            data_store
+                .retrieve_hidden_channel_close_onchain_funds_amount_sat()
+                .unwrap(),
+            Some(50)
         );
 
-        let offer_payout_expired = build_offer_with_error(PocketOfferError::PermanentFailure {
-            code: PermanentFailureCode::PayoutExpired,
-        });
-        store_payment_with_offer_and_test(
-            offer_payout_expired,
-            &mut data_store,
-            "offer_payout_expired",
+        data_store
+            .store_hidden_channel_close_onchain_funds_amount_sat(2000)
+            .unwrap();
+        assert_eq!(
+            data_store
+                .retrieve_hidden_channel_close_onchain_funds_amount_sat()
+                .unwrap(),
+            Some(2000)
         );
     }
 
-    fn build_offer_with_error(error: PocketOfferError) -> Offer {
-        let exchange_rate = ExchangeRate {
-            currency_code: "EUR".to_string(),
-            rate: 5123,
-            updated_at: SystemTime::now(),
-        };
-        Offer {
-            id: "id".to_string(),
-            exchange_rate: exchange_rate.clone(),
-            topup_value_minor_units: 51245,
-            topup_value_sats: Some(2625281),
-            exchange_fee_minor_units: 123,
-            exchange_fee_rate_permyriad: 50,
-            lightning_payout_fee: None,
-            error: Some(error),
-        }
-    }
-
-    fn store_payment_with_offer_and_test(offer: Offer, data_store: &mut DataStore, hash: &str) {
-        let user_preferences = UserPreferences {
-            fiat_currency: "EUR".to_string(),
-            timezone_config: TzConfig {
-                timezone_id: "Bern".to_string(),
-                timezone_utc_offset_secs: -1234,
-            },
-        };
+    #[test]
+    fn test_storing_hidden_failed_swap() {
+        let db_name = String::from("hidden_failed_swaps.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
 
-        let exchange_rates = vec![
-            ExchangeRate {
-                currency_code: "EUR".to_string(),
-                rate: 123,
-                updated_at: SystemTime::now(),
-            },
-            ExchangeRate {
-                currency_code: "USD".to_string(),
-                rate: 234,
-                updated_at: SystemTime::now(),
-            },
-        ];
+        assert!(data_store
+            .retrieve_hidden_unresolved_failed_swaps()
+            .unwrap()
+            .is_empty());
 
         data_store
-            .store_payment_info(
-                hash,
-                user_preferences.clone(),
-                exchange_rates,
-                Some(offer.clone()),
-                None,
-                None,
-            )
+            .store_hidden_unresolved_failed_swap("address1")
             .unwrap();
+        assert_eq!(
+            data_store
+                .retrieve_hidden_unresolved_failed_swaps()
+                .unwrap(),
+            vec!["address1".to_string()]
+        );
 
+        data_store
+            .store_hidden_unresolved_failed_swap("address2")
+            .unwrap();
         assert_eq!(
             data_store
-                .retrieve_payment_info(hash)
-                .unwrap()
-                .unwrap()
-                .offer
+                .retrieve_hidden_unresolved_failed_swaps()
                 .unwrap(),
-            offer
+            vec!["address1".to_string(), "address2".to_string()]
         );
     }
 
     #[test]
-    fn test_exchange_rate_storage() {
-        let db_name = String::from("rates.db3");
+    fn test_secondary_fiat_currencies() {
+        let db_name = String::from("secondary_fiat_currencies.db3");
         reset_db(&db_name);
         let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
 
-        assert!(data_store.get_all_exchange_rates().unwrap().is_empty());
+        assert!(data_store
+            .retrieve_secondary_fiat_currencies()
+            .unwrap()
+            .is_empty());
 
+        let currencies = vec!["USD".to_string(), "CHF".to_string()];
         data_store
-            .update_exchange_rate(
-                "USD",
-                1234,
-                SystemTime::UNIX_EPOCH + Duration::from_secs(10),
-            )
+            .store_secondary_fiat_currencies(&currencies)
             .unwrap();
-        let rates = data_store.get_all_exchange_rates().unwrap();
-        let usd_rate = rates.iter().find(|r| r.currency_code == "USD").unwrap();
-        assert_eq!(usd_rate.rate, 1234);
         assert_eq!(
-            usd_rate.updated_at,
-            SystemTime::UNIX_EPOCH + Duration::from_secs(10)
+            data_store.retrieve_secondary_fiat_currencies().unwrap(),
+            currencies
         );
 
-        sleep(Duration::from_secs(2));
+        let currencies = vec!["GBP".to_string()];
+        data_store
+            .store_secondary_fiat_currencies(&currencies)
+            .unwrap();
+        assert_eq!(
+            data_store.retrieve_secondary_fiat_currencies().unwrap(),
+            currencies
+        );
+
+        data_store.store_secondary_fiat_currencies(&[]).unwrap();
+        assert!(data_store
+            .retrieve_secondary_fiat_currencies()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_balance_changes() {
+        let db_name = String::from("balance_changes.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+
+        assert!(data_store
+            .retrieve_last_balance_change_time()
+            .unwrap()
+            .is_none());
+        assert_eq!(data_store.retrieve_balance_msat_at(100).unwrap(), 0);
 
         data_store
-            .update_exchange_rate(
-                "EUR",
-                5678,
-                SystemTime::UNIX_EPOCH + Duration::from_secs(20),
-            )
+            .store_balance_change("hash1", 10, 5_000, false)
             .unwrap();
-        let rates = data_store.get_all_exchange_rates().unwrap();
-        let usd_rate = rates.iter().find(|r| r.currency_code == "USD").unwrap();
-        let eur_rate = rates.iter().find(|r| r.currency_code == "EUR").unwrap();
-        assert_eq!(usd_rate.rate, 1234);
+        data_store
+            .store_balance_change("hash2", 20, -2_000, false)
+            .unwrap();
+        data_store
+            .store_balance_change("hash3", 30, -1_000, true)
+            .unwrap();
+
         assert_eq!(
-            usd_rate.updated_at,
-            SystemTime::UNIX_EPOCH + Duration::from_secs(10)
+            data_store.retrieve_last_balance_change_time().unwrap(),
+            Some(30)
         );
-        assert_eq!(eur_rate.rate, 5678);
         assert_eq!(
-            eur_rate.updated_at,
-            SystemTime::UNIX_EPOCH + Duration::from_secs(20)
+            data_store.retrieve_pending_balance_change_hashes().unwrap(),
+            vec!["hash3".to_string()]
         );
+        assert_eq!(data_store.retrieve_balance_msat_at(9).unwrap(), 0);
+        assert_eq!(data_store.retrieve_balance_msat_at(10).unwrap(), 5_000);
+        assert_eq!(data_store.retrieve_balance_msat_at(30).unwrap(), 3_000);
 
-        sleep(Duration::from_secs(2));
+        let changes = data_store.retrieve_balance_changes(10, 30).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].changed_at, 20);
+        assert_eq!(changes[0].balance_change_msat, -2_000);
 
+        // The pending payment settles
         data_store
-            .update_exchange_rate(
-                "USD",
-                4321,
-                SystemTime::UNIX_EPOCH + Duration::from_secs(30),
-            )
+            .store_balance_change("hash3", 30, -1_000, false)
             .unwrap();
-        let rates = data_store.get_all_exchange_rates().unwrap();
-        let usd_rate = rates.iter().find(|r| r.currency_code == "USD").unwrap();
-        let eur_rate = rates.iter().find(|r| r.currency_code == "EUR").unwrap();
-        assert_eq!(usd_rate.rate, 4321);
-        assert_eq!(
-            usd_rate.updated_at,
-            SystemTime::UNIX_EPOCH + Duration::from_secs(30)
-        );
-        assert_eq!(eur_rate.rate, 5678);
+        assert!(data_store
+            .retrieve_pending_balance_change_hashes()
+            .unwrap()
+            .is_empty());
+        assert_eq!(data_store.retrieve_balance_msat_at(30).unwrap(), 2_000);
+
+        data_store.delete_balance_change("hash3").unwrap();
+        assert_eq!(data_store.retrieve_balance_msat_at(30).unwrap(), 3_000);
         assert_eq!(
-            eur_rate.updated_at,
-            SystemTime::UNIX_EPOCH + Duration::from_secs(20)
+            data_store.retrieve_last_balance_change_time().unwrap(),
+            Some(20)
         );
     }
 
     #[test]
-    fn test_invoice_persistence() {
-        let db_name = String::from("invoice_persistence.db3");
+    fn test_exchange_rate_alerts() {
+        let db_name = String::from("exchange_rate_alerts.db3");
         reset_db(&db_name);
         let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
 
-        assert!(data_store.retrieve_created_invoices(5).unwrap().is_empty());
-
-        let future = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
+        assert!(data_store
+            .retrieve_exchange_rate_alerts()
             .unwrap()
-            .as_secs()
-            + 1000;
+            .is_empty());
 
-        let expired_invoice = CreatedInvoice {
-            hash: "hash1".to_string(),
-            invoice: "invoice1".to_string(),
-            channel_opening_fees: Some(25000000),
+        let id_chf = data_store
+            .store_exchange_rate_alert("CHF", 10_000_000, ExchangeRateAlertDirection::Above)
+            .unwrap();
+        let id_eur = data_store
+            .store_exchange_rate_alert("EUR", 5_000_000, ExchangeRateAlertDirection::Below)
+            .unwrap();
+
+        let alerts = data_store.retrieve_exchange_rate_alerts().unwrap();
+        assert_eq!(
+            alerts,
+            vec![
+                ExchangeRateAlert {
+                    id: id_chf,
+                    currency_code: "CHF".to_string(),
+                    threshold_minor_units: 10_000_000,
+                    direction: ExchangeRateAlertDirection::Above,
+                    armed: true,
+                    last_triggered_at: None,
+                },
+                ExchangeRateAlert {
+                    id: id_eur,
+                    currency_code: "EUR".to_string(),
+                    threshold_minor_units: 5_000_000,
+                    direction: ExchangeRateAlertDirection::Below,
+                    armed: true,
+                    last_triggered_at: None,
+                },
+            ]
+        );
+
+        let triggered_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        data_store
+            .update_exchange_rate_alert_state(id_chf, false, Some(triggered_at))
+            .unwrap();
+        let alerts = data_store.retrieve_exchange_rate_alerts().unwrap();
+        assert!(!alerts[0].armed);
+        assert_eq!(alerts[0].last_triggered_at, Some(triggered_at));
+        assert!(alerts[1].armed);
+
+        assert!(data_store.delete_exchange_rate_alert(id_eur).unwrap());
+        assert!(!data_store.delete_exchange_rate_alert(id_eur).unwrap());
+        assert_eq!(data_store.retrieve_exchange_rate_alerts().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_sweep_transactions() {
+        let db_name = String::from("sweep_transactions.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+
+        let sweep = SweepTransaction {
+            txid: "txid1".to_string(),
+            swap_address: "swap address 1".to_string(),
+            to_address: "address".to_string(),
+            sat_per_vbyte: 5,
+            broadcast_at: UNIX_EPOCH + Duration::from_secs(1_000),
         };
-        let pending_invoice = CreatedInvoice {
-            hash: "hash2".to_string(),
-            invoice: "invoice2".to_string(),
-            channel_opening_fees: None,
+        let refund = SweepTransaction {
+            txid: "txid2".to_string(),
+            swap_address: "swap address 2".to_string(),
+            to_address: "address".to_string(),
+            sat_per_vbyte: 3,
+            broadcast_at: UNIX_EPOCH + Duration::from_secs(2_000),
         };
+        data_store.store_sweep_transaction(&sweep).unwrap();
+        data_store.store_sweep_transaction(&refund).unwrap();
+        assert_eq!(
+            data_store
+                .retrieve_unconfirmed_sweep_transactions()
+                .unwrap(),
+            vec![sweep.clone(), refund.clone()]
+        );
 
+        let bumped_refund = SweepTransaction {
+            txid: "txid3".to_string(),
+            sat_per_vbyte: 10,
+            broadcast_at: UNIX_EPOCH + Duration::from_secs(3_000),
+            ..refund
+        };
         data_store
-            .store_created_invoice(
-                expired_invoice.hash.as_str(),
-                expired_invoice.invoice.as_str(),
-                &expired_invoice.channel_opening_fees,
-                123,
-            )
+            .store_sweep_transaction_replacement("txid2", &bumped_refund)
+            .unwrap();
+        data_store
+            .mark_sweep_transaction_confirmed("txid1")
             .unwrap();
         assert_eq!(
-            data_store.retrieve_created_invoices(5).unwrap(),
-            vec![expired_invoice.clone()]
+            data_store
+                .retrieve_unconfirmed_sweep_transactions()
+                .unwrap(),
+            vec![bumped_refund]
         );
+    }
+
+    #[test]
+    fn test_reverse_swap_timeline() {
+        let db_name = String::from("reverse_swap_timeline.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+
+        assert!(data_store
+            .retrieve_reverse_swap_timeline("id")
+            .unwrap()
+            .is_empty());
 
+        let claim_broadcast = ReverseSwapTimelineEntry {
+            stage: ReverseSwapStage::ClaimTxBroadcast,
+            txid: Some("claim txid".to_string()),
+            block_height: None,
+            occurred_at: Some(UNIX_EPOCH + Duration::from_secs(2_000)),
+        };
+        let initiated = ReverseSwapTimelineEntry {
+            stage: ReverseSwapStage::Initiated,
+            txid: None,
+            block_height: None,
+            occurred_at: None,
+        };
         data_store
-            .store_created_invoice(
-                pending_invoice.hash.as_str(),
-                pending_invoice.invoice.as_str(),
-                &pending_invoice.channel_opening_fees,
-                future,
+            .store_reverse_swap_stage("id", &claim_broadcast)
+            .unwrap();
+        data_store
+            .store_reverse_swap_stage("id", &initiated)
+            .unwrap();
+        data_store
+            .store_reverse_swap_stage(
+                "other id",
+                &ReverseSwapTimelineEntry {
+                    stage: ReverseSwapStage::Cancelled,
+                    txid: None,
+                    block_height: None,
+                    occurred_at: Some(UNIX_EPOCH + Duration::from_secs(1_500)),
+                },
+            )
+            .unwrap();
+        // Stages recorded without a time get the time they are observed at later.
+        let observed_initiated = ReverseSwapTimelineEntry {
+            occurred_at: Some(UNIX_EPOCH + Duration::from_secs(3_000)),
+            ..initiated.clone()
+        };
+        data_store
+            .store_reverse_swap_stage("id", &observed_initiated)
+            .unwrap();
+        data_store
+            .store_reverse_swap_stage("id", &initiated)
+            .unwrap();
+        // Times already recorded are kept as they are.
+        data_store
+            .store_reverse_swap_stage(
+                "id",
+                &ReverseSwapTimelineEntry {
+                    occurred_at: Some(UNIX_EPOCH + Duration::from_secs(4_000)),
+                    ..claim_broadcast.clone()
+                },
             )
             .unwrap();
-        let mut invoices = data_store.retrieve_created_invoices(5).unwrap();
-        invoices.sort_by_key(|i| i.hash.clone());
-        assert_eq!(
-            invoices,
-            vec![expired_invoice.clone(), pending_invoice.clone()]
-        );
 
-        let mut invoices = data_store.retrieve_created_invoices(1).unwrap();
-        invoices.sort_by_key(|i| i.hash.clone());
         assert_eq!(
-            invoices,
-            vec![expired_invoice.clone(), pending_invoice.clone()]
+            data_store.retrieve_reverse_swap_timeline("id").unwrap(),
+            vec![observed_initiated, claim_broadcast]
         );
+    }
 
-        let invoices = data_store.retrieve_created_invoices(0).unwrap();
-        assert_eq!(invoices, vec![pending_invoice.clone()]);
+    #[test]
+    fn test_storing_fiat_currency() {
+        let db_name = String::from("fiat_currency.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
 
         assert!(data_store
-            .retrieve_created_invoice_by_hash("hash0")
+            .retrieve_last_set_fiat_currency()
             .unwrap()
             .is_none());
+
+        data_store.store_selected_fiat_currency("EUR").unwrap();
         assert_eq!(
             data_store
-                .retrieve_created_invoice_by_hash(expired_invoice.hash.as_str())
+                .retrieve_last_set_fiat_currency()
+                .unwrap()
                 .unwrap(),
-            Some(expired_invoice)
+            "EUR"
         );
+
+        data_store.store_selected_fiat_currency("CHF").unwrap();
         assert_eq!(
             data_store
-                .retrieve_created_invoice_by_hash(pending_invoice.hash.as_str())
+                .retrieve_last_set_fiat_currency()
+                .unwrap()
                 .unwrap(),
-            Some(pending_invoice)
+            "CHF"
         );
     }
 
     #[test]
-    fn test_fiat_topup_info_persistence() {
-        let db_name = String::from("fiat_topup_info_persistence");
+    fn test_outbox_persistence() {
+        let db_name = String::from("outbox.db3");
         reset_db(&db_name);
-        let data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
 
-        assert_eq!(data_store.retrieve_latest_fiat_topup_info().unwrap(), None);
+        assert!(data_store
+            .retrieve_pending_outbox_payments()
+            .unwrap()
+            .is_empty());
 
-        let mut fiat_topup_info = FiatTopupSetupInfo {
-            order_id: "961b8ee9-74cc-4844-9fe8-b02ce0702663".to_string(),
-            debitor_iban: "CH4889144919566329178".to_string(),
-            creditor_reference: "8584-9931-ABCD".to_string(),
-            creditor_iban: "DE2089144126222826294".to_string(),
-            creditor_bank_name: "Example Bank".to_string(),
-            creditor_bank_street: "Bankingstreet 21".to_string(),
-            creditor_bank_postal_code: "2121".to_string(),
-            creditor_bank_town: "Example Town".to_string(),
-            creditor_bank_country: "CH".to_string(),
-            creditor_bank_bic: "VA7373".to_string(),
-            creditor_name: "John Doe".to_string(),
-            creditor_street: "Doestreet 21".to_string(),
-            creditor_postal_code: "2112".to_string(),
-            creditor_town: "Creditor Town".to_string(),
-            creditor_country: "DE".to_string(),
-            currency: "EUR".to_string(),
+        let expires_at = SystemTime::UNIX_EPOCH + Duration::from_secs(2_000_000_000);
+        let bolt11_payment = OutboxPayment::Bolt11 {
+            invoice: "invoice".to_string(),
+            amount_sat: None,
         };
-
-        data_store
-            .store_fiat_topup_info(fiat_topup_info.clone())
+        let lnurl_payment = OutboxPayment::LnUrlPay {
+            request_data: "{}".to_string(),
+            amount_sat: 1000,
+            comment: Some("comment".to_string()),
+        };
+        let bolt11_id = data_store
+            .store_outbox_payment(&bolt11_payment, expires_at)
             .unwrap();
+        let lnurl_id = data_store
+            .store_outbox_payment(&lnurl_payment, expires_at)
+            .unwrap();
+
+        let items = data_store.retrieve_pending_outbox_payments().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].id, bolt11_id);
+        assert_eq!(items[0].payment, bolt11_payment);
+        assert_eq!(items[0].expires_at, expires_at);
+        assert_eq!(items[1].id, lnurl_id);
+        assert_eq!(items[1].payment, lnurl_payment);
+
+        assert!(data_store
+            .update_outbox_payment_status(
+                bolt11_id,
+                OutboxItemStatus::Queued,
+                OutboxItemStatus::Sending,
+                None,
+            )
+            .unwrap());
+        assert!(!data_store
+            .update_outbox_payment_status(
+                bolt11_id,
+                OutboxItemStatus::Queued,
+                OutboxItemStatus::Cancelled,
+                None,
+            )
+            .unwrap());
         assert_eq!(
-            data_store.retrieve_latest_fiat_topup_info().unwrap(),
-            Some(fiat_topup_info.clone())
+            data_store.retrieve_pending_outbox_payments().unwrap().len(),
+            2
         );
 
-        fiat_topup_info.order_id = "361dd7f8-c7b7-4871-b906-b67fa3ed9b55".to_string();
+        assert!(data_store
+            .update_outbox_payment_status(
+                lnurl_id,
+                OutboxItemStatus::Queued,
+                OutboxItemStatus::Cancelled,
+                None,
+            )
+            .unwrap());
+        let items = data_store.retrieve_pending_outbox_payments().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, bolt11_id);
 
-        data_store
-            .store_fiat_topup_info(fiat_topup_info.clone())
-            .unwrap();
         assert_eq!(
-            data_store.retrieve_latest_fiat_topup_info().unwrap(),
-            Some(fiat_topup_info)
+            data_store.retrieve_sending_outbox_payment_ids().unwrap(),
+            vec![bolt11_id]
         );
     }
 
+    fn reset_db(db_name: &str) {
+        let _ = fs::create_dir(TEST_DB_PATH);
+        let _ = fs::remove_file(format!("{TEST_DB_PATH}/{db_name}"));
+    }
+
     #[test]
-    fn test_persisting_analytics_config() {
-        let db_name = String::from("analytics_config.db3");
+    fn test_auto_resolution_policy() {
+        let db_name = String::from("auto_resolution_policy.db3");
         reset_db(&db_name);
         let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
 
-        assert_eq!(
-            data_store.retrieve_analytics_config().unwrap(),
-            AnalyticsConfig::Enabled
-        );
+        assert_eq!(data_store.retrieve_auto_resolution_policy().unwrap(), None);
 
+        let swap_policy = AutoResolutionPolicy {
+            strategy: AutoResolutionStrategy::SwapToLightning,
+            max_fee_permyriad: 300,
+        };
         data_store
-            .append_analytics_config(AnalyticsConfig::Disabled)
+            .append_auto_resolution_policy(Some(&swap_policy))
             .unwrap();
         assert_eq!(
-            data_store.retrieve_analytics_config().unwrap(),
-            AnalyticsConfig::Disabled
+            data_store.retrieve_auto_resolution_policy().unwrap(),
+            Some(swap_policy)
         );
 
+        let sweep_policy = AutoResolutionPolicy {
+            strategy: AutoResolutionStrategy::SweepToAddress {
+                address: "bc1qaddress".to_string(),
+            },
+            max_fee_permyriad: 1_000,
+        };
         data_store
-            .append_analytics_config(AnalyticsConfig::Enabled)
+            .append_auto_resolution_policy(Some(&sweep_policy))
             .unwrap();
         assert_eq!(
-            data_store.retrieve_analytics_config().unwrap(),
-            AnalyticsConfig::Enabled
+            data_store.retrieve_auto_resolution_policy().unwrap(),
+            Some(sweep_policy)
         );
+
+        data_store.append_auto_resolution_policy(None).unwrap();
+        assert_eq!(data_store.retrieve_auto_resolution_policy().unwrap(), None);
     }
 
     #[test]
-    fn test_storing_lightning_address() {
-        let db_name = String::from("lightning_addresses.db3");
+    fn test_notification_log() {
+        let db_name = String::from("notification_log.db3");
         reset_db(&db_name);
         let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
-        let addresses = data_store.retrieve_lightning_addresses().unwrap();
-        assert!(addresses.is_empty());
 
-        // Store an address.
-        data_store
-            .store_lightning_address("satoshi@lipa.swiss")
-            .unwrap();
-        let addresses = data_store.retrieve_lightning_addresses().unwrap();
+        let now = UNIX_EPOCH
+            + Duration::from_secs(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+            );
+        let an_hour_ago = now - Duration::from_secs(60 * 60);
+        assert!(data_store
+            .retrieve_notification_outcomes(10)
+            .unwrap()
+            .is_empty());
+
+        let window = Some(DeduplicationWindow {
+            handled_since: an_hour_ago,
+            started_since: an_hour_ago,
+        });
+        let mut start = |payload_hash: &str, received_at, window| {
+            data_store
+                .start_notification_handling(payload_hash, "PaymentReceived", received_at, window)
+                .unwrap()
+        };
+
+        let failed_id = match start("hash", an_hour_ago, window.clone()) {
+            NotificationHandlingStart::Started { log_id } => log_id,
+            start => panic!("Unexpected start {start:?}"),
+        };
         assert_eq!(
-            addresses,
-            vec![("satoshi@lipa.swiss".to_string(), EnableStatus::Enabled)]
+            start("hash", an_hour_ago, window.clone()),
+            NotificationHandlingStart::InProgress
         );
 
-        // Storing the same address.
-        data_store
-            .store_lightning_address("satoshi@lipa.swiss")
-            .unwrap();
-        let addresses = data_store.retrieve_lightning_addresses().unwrap();
+        let failed = NotificationOutcome {
+            payload_hash: "hash".to_string(),
+            payload_kind: "PaymentReceived".to_string(),
+            received_at: an_hour_ago,
+            duration: Duration::from_millis(1500),
+            is_duplicate: false,
+            notification: None,
+            error: Some("RuntimeError: ExpectedPaymentNotReceived".to_string()),
+        };
         assert_eq!(
-            addresses,
-            vec![("satoshi@lipa.swiss".to_string(), EnableStatus::Enabled)]
+            data_store.retrieve_notification_outcomes(1).unwrap(),
+            vec![NotificationOutcome {
+                duration: Duration::ZERO,
+                error: None,
+                ..failed.clone()
+            }]
         );
+        data_store
+            .finish_notification_handling(failed_id, &failed, an_hour_ago)
+            .unwrap();
 
-        // Disabling the address.
+        let succeeded_id = match data_store
+            .start_notification_handling("hash", "PaymentReceived", now, window.clone())
+            .unwrap()
+        {
+            NotificationHandlingStart::Started { log_id } => log_id,
+            start => panic!("Unexpected start {start:?}"),
+        };
+        let succeeded = NotificationOutcome {
+            received_at: now,
+            notification: Some(Notification::OnchainPaymentSwapOutUpdated {
+                amount_sat: 1_000,
+                payment_hash: "payment hash".to_string(),
+                status: ReverseSwapStatus::CompletedSeen,
+            }),
+            error: None,
+            ..failed.clone()
+        };
         data_store
-            .update_lightning_address("satoshi@lipa.swiss", EnableStatus::FeatureDisabled)
+            .finish_notification_handling(succeeded_id, &succeeded, an_hour_ago)
             .unwrap();
-        let addresses = data_store.retrieve_lightning_addresses().unwrap();
         assert_eq!(
-            addresses,
-            vec![(
-                "satoshi@lipa.swiss".to_string(),
-                EnableStatus::FeatureDisabled
-            )]
+            data_store
+                .start_notification_handling("hash", "PaymentReceived", now, window.clone())
+                .unwrap(),
+            NotificationHandlingStart::AlreadyHandled(succeeded.notification.clone().unwrap())
         );
 
-        // Storing the same address enables it back.
+        let out_of_range = NotificationOutcome {
+            payload_hash: "other hash".to_string(),
+            payload_kind: "AddressTxsConfirmed".to_string(),
+            notification: Some(Notification::OnchainDepositOutOfRange {
+                address: "address".to_string(),
+                amount_sat: 500,
+                issue: SwapDepositIssue::BelowMinimum,
+            }),
+            ..succeeded.clone()
+        };
+        let out_of_range_id = match data_store
+            .start_notification_handling("other hash", "AddressTxsConfirmed", now, window)
+            .unwrap()
+        {
+            NotificationHandlingStart::Started { log_id } => log_id,
+            start => panic!("Unexpected start {start:?}"),
+        };
         data_store
-            .store_lightning_address("satoshi@lipa.swiss")
+            .finish_notification_handling(out_of_range_id, &out_of_range, an_hour_ago)
             .unwrap();
-        let addresses = data_store.retrieve_lightning_addresses().unwrap();
         assert_eq!(
-            addresses,
-            vec![("satoshi@lipa.swiss".to_string(), EnableStatus::Enabled)]
+            data_store.retrieve_notification_outcomes(10).unwrap(),
+            vec![out_of_range.clone(), succeeded.clone(), failed]
+        );
+        assert_eq!(
+            data_store.retrieve_notification_outcomes(1).unwrap(),
+            vec![out_of_range.clone()]
         );
 
-        // Storing another address.
+        // Outcomes received before the retention cutoff are removed
+        let duplicate = NotificationOutcome {
+            is_duplicate: true,
+            ..out_of_range.clone()
+        };
         data_store
-            .store_lightning_address("finney@lipa.swiss")
+            .store_notification_outcome(&duplicate, now)
             .unwrap();
-        let addresses = data_store.retrieve_lightning_addresses().unwrap();
         assert_eq!(
-            addresses,
-            vec![
-                ("satoshi@lipa.swiss".to_string(), EnableStatus::Enabled),
-                ("finney@lipa.swiss".to_string(), EnableStatus::Enabled),
-            ]
+            data_store.retrieve_notification_outcomes(10).unwrap(),
+            vec![duplicate, out_of_range, succeeded]
         );
     }
 
     #[test]
-    fn test_storing_channel_close_hidden_amount() {
-        let db_name = String::from("hidden_channel_closes.db3");
+    fn test_max_lsp_fee() {
+        let db_name = String::from("max_lsp_fee.db3");
+        reset_db(&db_name);
+        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
+
+        assert_eq!(data_store.retrieve_max_lsp_fee().unwrap(), None);
+
+        let absolute = MaxLspFee {
+            max_fee_sat: Some(5_000),
+            max_fee_permyriad: None,
+        };
+        data_store.append_max_lsp_fee(Some(&absolute)).unwrap();
+        assert_eq!(data_store.retrieve_max_lsp_fee().unwrap(), Some(absolute));
+
+        let both = MaxLspFee {
+            max_fee_sat: Some(5_000),
+            max_fee_permyriad: Some(200),
+        };
+        data_store.append_max_lsp_fee(Some(&both)).unwrap();
+        assert_eq!(data_store.retrieve_max_lsp_fee().unwrap(), Some(both));
+
+        data_store.append_max_lsp_fee(None).unwrap();
+        assert_eq!(data_store.retrieve_max_lsp_fee().unwrap(), None);
+    }
+
+    #[test]
+    fn test_known_channels() {
+        let db_name = String::from("known_channels.db3");
         reset_db(&db_name);
         let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
 
+        assert!(data_store.retrieve_known_channels().unwrap().is_empty());
+
+        let channel = KnownChannel {
+            funding_txid: "funding txid".to_string(),
+            channel_id: None,
+            state: ChannelState::Opening,
+            closing_for_lsp_migration: false,
+        };
+        data_store.store_known_channel(&channel).unwrap();
         assert_eq!(
-            data_store
-                .retrieve_hidden_channel_close_onchain_funds_amount_sat()
-                .unwrap(),
-            None
+            data_store.retrieve_known_channels().unwrap(),
+            vec![channel.clone()]
         );
 
-        data_store
-            .store_hidden_channel_close_onchain_funds_amount_sat(50)
-            .unwrap();
+        let channel = KnownChannel {
+            channel_id: Some("channel id".to_string()),
+            state: ChannelState::Closing,
+            closing_for_lsp_migration: true,
+            ..channel
+        };
+        data_store.store_known_channel(&channel).unwrap();
         assert_eq!(
-            data_store
-                .retrieve_hidden_channel_close_onchain_funds_amount_sat()
-                .unwrap(),
-            Some(50)
+            data_store.retrieve_known_channels().unwrap(),
+            vec![channel.clone()]
         );
 
         data_store
-            .store_hidden_channel_close_onchain_funds_amount_sat(2000)
+            .set_closing_for_lsp_migration("funding txid", false)
             .unwrap();
         assert_eq!(
-            data_store
-                .retrieve_hidden_channel_close_onchain_funds_amount_sat()
-                .unwrap(),
-            Some(2000)
+            data_store.retrieve_known_channels().unwrap(),
+            vec![KnownChannel {
+                closing_for_lsp_migration: false,
+                ..channel
+            }]
         );
     }
 
     #[test]
-    fn test_storing_hidden_failed_swap() {
-        let db_name = String::from("hidden_failed_swaps.db3");
+    fn test_lsp_fee_offers() {
+        let db_name = String::from("lsp_fee_offers.db3");
         reset_db(&db_name);
         let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
 
-        assert!(data_store
-            .retrieve_hidden_unresolved_failed_swaps()
-            .unwrap()
-            .is_empty());
-
-        data_store
-            .store_hidden_unresolved_failed_swap("address1")
-            .unwrap();
         assert_eq!(
             data_store
-                .retrieve_hidden_unresolved_failed_swaps()
+                .retrieve_lsp_fee_offer(LspFeeOfferKind::Cheaper)
                 .unwrap(),
-            vec!["address1".to_string()]
+            None
         );
 
+        let offer = |min_msat, valid_until: &str| OpeningFeeParams {
+            valid_until: valid_until.to_string(),
+            max_idle_time: 4_000,
+            max_client_to_self_delay: 2_016,
+            promise: "promise".to_string(),
+            ..fee_params(min_msat, 4_000)
+        };
+        let cheaper = offer(2_000_000, "2030-01-01T00:10:00Z");
+        let longer_valid = offer(2_500_000, "2030-01-03T00:00:00Z");
         data_store
-            .store_hidden_unresolved_failed_swap("address2")
+            .store_lsp_fee_offer(LspFeeOfferKind::Cheaper, &cheaper)
+            .unwrap();
+        data_store
+            .store_lsp_fee_offer(LspFeeOfferKind::LongerValid, &longer_valid)
             .unwrap();
         assert_eq!(
             data_store
-                .retrieve_hidden_unresolved_failed_swaps()
+                .retrieve_lsp_fee_offer(LspFeeOfferKind::Cheaper)
                 .unwrap(),
-            vec!["address1".to_string(), "address2".to_string()]
+            Some(cheaper)
         );
-    }
-
-    #[test]
-    fn test_storing_fiat_currency() {
-        let db_name = String::from("fiat_currency.db3");
-        reset_db(&db_name);
-        let mut data_store = DataStore::new(&format!("{TEST_DB_PATH}/{db_name}")).unwrap();
-
-        assert!(data_store
-            .retrieve_last_set_fiat_currency()
-            .unwrap()
-            .is_none());
 
-        data_store.store_selected_fiat_currency("EUR").unwrap();
+        let newer_cheaper = offer(1_000_000, "2030-01-01T00:20:00Z");
+        data_store
+            .store_lsp_fee_offer(LspFeeOfferKind::Cheaper, &newer_cheaper)
+            .unwrap();
         assert_eq!(
             data_store
-                .retrieve_last_set_fiat_currency()
-                .unwrap()
+                .retrieve_lsp_fee_offer(LspFeeOfferKind::Cheaper)
                 .unwrap(),
-            "EUR"
+            Some(newer_cheaper)
         );
-
-        data_store.store_selected_fiat_currency("CHF").unwrap();
         assert_eq!(
             data_store
-                .retrieve_last_set_fiat_currency()
-                .unwrap()
+                .retrieve_lsp_fee_offer(LspFeeOfferKind::LongerValid)
                 .unwrap(),
-            "CHF"
+            Some(longer_valid)
         );
     }
-
-    fn reset_db(db_name: &str) {
-        let _ = fs::create_dir(TEST_DB_PATH);
-        let _ = fs::remove_file(format!("{TEST_DB_PATH}/{db_name}"));
-    }
 }
@@ -1,6 +1,6 @@
 use crate::{invalid_input, permanent_failure, runtime_error};
 
-use breez_sdk_core::error::SendPaymentError;
+use breez_sdk_core::error::{ReceiveOnchainError, SendPaymentError};
 use std::fmt::{Display, Formatter};
 
 /// A code that specifies the RuntimeError that occurred
@@ -16,6 +16,9 @@ pub enum RuntimeErrorCode {
     BackupServiceUnavailable,
     /// No backup was found for the provided mnemonic.
     BackupNotFound,
+    /// The fee charged by the LSP for opening a channel exceeds the limit set through
+    /// [`Config::set_max_lsp_fee`](crate::Config::set_max_lsp_fee).
+    LspFeeAboveLimit,
 
     // Breez runtime errors
     /// Information about the remote node isn't cached and couldn't be accessed. Could be a network error.
@@ -195,12 +198,48 @@ pub fn to_mnemonic_error(e: bip39::Error) -> MnemonicError {
     }
 }
 
+/// An error that occurred while creating a swap address.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SwapError {
+    #[error("Generic: {err}")]
+    Generic { err: String },
+    #[error("ServiceConnectivity: {err}")]
+    ServiceConnectivity { err: String },
+    /// Another swap is already in progress.
+    #[error("SwapInProgress: {err}")]
+    SwapInProgress { err: String },
+    /// The fee terms of the LSP can't satisfy the limit set through
+    /// [`Config::set_max_lsp_fee`](crate::Config::set_max_lsp_fee) for any amount.
+    #[error("LspFeeAboveLimit: {err}")]
+    LspFeeAboveLimit { err: String },
+}
+
+impl From<ReceiveOnchainError> for SwapError {
+    fn from(error: ReceiveOnchainError) -> Self {
+        match error {
+            ReceiveOnchainError::Generic { err } => SwapError::Generic { err },
+            ReceiveOnchainError::ServiceConnectivity { err } => {
+                SwapError::ServiceConnectivity { err }
+            }
+            ReceiveOnchainError::SwapInProgress { err } => SwapError::SwapInProgress { err },
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, thiserror::Error)]
 pub enum SimpleError {
     #[error("SimpleError: {msg}")]
     Simple { msg: String },
 }
 
+impl From<uniffi::UnexpectedUniFFICallbackError> for SimpleError {
+    fn from(error: uniffi::UnexpectedUniFFICallbackError) -> Self {
+        SimpleError::Simple {
+            msg: format!("Unexpected callback error: {}", error.reason),
+        }
+    }
+}
+
 pub(crate) fn map_send_payment_error(err: SendPaymentError) -> PayError {
     match err {
         SendPaymentError::AlreadyPaid => {
@@ -303,6 +342,9 @@ pub enum NotificationHandlingErrorCode {
     /// The notification payload implied the existence of an incoming payment, but it was not
     /// received in time. Starting the app might help complete the payment.
     ExpectedPaymentNotReceived,
+    /// The notification payload implied the failure of an outgoing payment, but the payment
+    /// either succeeded or its failure was not observed in time.
+    ExpectedPaymentFailureNotObserved,
     /// An inbound payment was rejected as it required opening a new channel.
     InsufficientInboundLiquidity,
     /// A request to one of lipa's services failed.
@@ -310,6 +352,8 @@ pub enum NotificationHandlingErrorCode {
     /// The notification payload is disabled in the provided
     /// [`NotificationToggles`](crate::notification_handling::NotificationToggles).
     NotificationDisabledInNotificationToggles,
+    /// The same notification payload is already being handled by an earlier delivery.
+    NotificationAlreadyBeingHandled,
 }
 
 impl Display for NotificationHandlingErrorCode {
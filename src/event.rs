@@ -1,21 +1,99 @@
+use crate::data_store::DataStore;
+use crate::locker::Locker;
+use crate::reverse_swap::reached_stages;
+use crate::swap::deposit_issue;
+use crate::util::LogIgnoreError;
 use crate::{analytics::AnalyticsInterceptor, EventsCallback};
 
-use breez_sdk_core::{BreezEvent, EventListener, PaymentDetails, ReverseSwapStatus, SwapStatus};
-use std::sync::Arc;
+use breez_sdk_core::{
+    BreezEvent, EventListener, PaymentDetails, ReverseSwapInfo, ReverseSwapStatus, SwapInfo,
+    SwapStatus,
+};
+use log::{warn, Level};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 pub(crate) struct LipaEventListener {
     events_callback: Arc<Box<dyn EventsCallback>>,
     analytics_interceptor: Arc<AnalyticsInterceptor>,
+    data_store: Arc<Mutex<DataStore>>,
+    /// The height of the last block announced by the node, 0 if none was announced yet.
+    block_height: AtomicU32,
+    /// Out-of-range swap deposits already reported, as (swap address, deposited sats).
+    reported_out_of_range_deposits: Mutex<HashSet<(String, u64)>>,
+    /// Reverse swaps whose claim tx was seen unconfirmed since the listener was started.
+    unconfirmed_claims: Mutex<HashSet<String>>,
 }
 
 impl LipaEventListener {
     pub fn new(
         events_callback: Arc<Box<dyn EventsCallback>>,
         analytics_interceptor: Arc<AnalyticsInterceptor>,
+        data_store: Arc<Mutex<DataStore>>,
     ) -> Self {
         Self {
             events_callback,
             analytics_interceptor,
+            data_store,
+            block_height: AtomicU32::new(0),
+            reported_out_of_range_deposits: Mutex::new(HashSet::new()),
+            unconfirmed_claims: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn report_out_of_range_deposit(&self, swap: &SwapInfo) {
+        if !matches!(
+            swap.status,
+            SwapStatus::WaitingConfirmation | SwapStatus::Redeemable
+        ) {
+            return;
+        }
+        if let Some(issue) = deposit_issue(swap) {
+            let deposited_sat = swap.confirmed_sats + swap.unconfirmed_sats;
+            let newly_seen = self
+                .reported_out_of_range_deposits
+                .lock_unwrap()
+                .insert((swap.bitcoin_address.clone(), deposited_sat));
+            if newly_seen {
+                warn!(
+                    "Swap address {} received {deposited_sat} sats which is out of the accepted range: {issue:?}",
+                    swap.bitcoin_address
+                );
+                self.events_callback
+                    .swap_deposit_out_of_range(swap.bitcoin_address.clone(), issue);
+            }
+        }
+    }
+
+    fn record_reverse_swap_progress(&self, reverse_swap: &ReverseSwapInfo) {
+        // The claim tx confirmation is checked on every new block, so if the claim tx was seen
+        // unconfirmed before, it got confirmed in the last block. Otherwise its height is unknown.
+        let claim_confirmed_at_height = match reverse_swap.status {
+            ReverseSwapStatus::CompletedSeen => {
+                self.unconfirmed_claims
+                    .lock_unwrap()
+                    .insert(reverse_swap.id.clone());
+                None
+            }
+            ReverseSwapStatus::CompletedConfirmed => {
+                let was_unconfirmed = self
+                    .unconfirmed_claims
+                    .lock_unwrap()
+                    .remove(&reverse_swap.id);
+                match self.block_height.load(Ordering::Relaxed) {
+                    height if was_unconfirmed && height > 0 => Some(height),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let mut data_store = self.data_store.lock_unwrap();
+        for entry in reached_stages(reverse_swap, claim_confirmed_at_height, SystemTime::now()) {
+            data_store
+                .store_reverse_swap_stage(&reverse_swap.id, &entry)
+                .log_ignore_error(Level::Error, "Failed to store reverse swap stage");
         }
     }
 }
@@ -24,7 +102,9 @@ impl EventListener for LipaEventListener {
     fn on_event(&self, e: BreezEvent) {
         report_event_for_analytics(&e, &self.analytics_interceptor);
         match e {
-            BreezEvent::NewBlock { .. } => {}
+            BreezEvent::NewBlock { block } => {
+                self.block_height.store(block, Ordering::Relaxed);
+            }
             BreezEvent::InvoicePaid { details } => {
                 self.events_callback.payment_received(details.payment_hash)
             }
@@ -46,23 +126,27 @@ impl EventListener for LipaEventListener {
             BreezEvent::BackupSucceeded => {}
             BreezEvent::BackupFailed { .. } => {}
             BreezEvent::SwapUpdated { details } => {
+                self.report_out_of_range_deposit(&details);
                 if details.status == SwapStatus::Completed {
                     self.events_callback
                         .swap_received(hex::encode(details.payment_hash));
                 }
             }
-            BreezEvent::ReverseSwapUpdated { details } => match details.status {
-                ReverseSwapStatus::CompletedSeen => {
-                    self.events_callback.reverse_swap_sent(details.id);
+            BreezEvent::ReverseSwapUpdated { details } => {
+                self.record_reverse_swap_progress(&details);
+                match details.status {
+                    ReverseSwapStatus::CompletedSeen => {
+                        self.events_callback.reverse_swap_sent(details.id);
+                    }
+                    ReverseSwapStatus::CompletedConfirmed => {
+                        self.events_callback.reverse_swap_settled(details.id);
+                    }
+                    ReverseSwapStatus::Cancelled => {
+                        self.events_callback.reverse_swap_cancelled(details.id);
+                    }
+                    _ => {}
                 }
-                ReverseSwapStatus::CompletedConfirmed => {
-                    self.events_callback.reverse_swap_settled(details.id);
-                }
-                ReverseSwapStatus::Cancelled => {
-                    self.events_callback.reverse_swap_cancelled(details.id);
-                }
-                _ => {}
-            },
+            }
         }
     }
 }
@@ -0,0 +1,198 @@
+use crate::data_store::DataStore;
+use crate::locker::Locker;
+use crate::{EventsCallback, ExchangeRate};
+
+use log::{debug, error};
+use num_enum::TryFromPrimitive;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// How far the price has to move back beyond the threshold of a triggered alert before it can
+/// trigger again. Prevents alerts from firing repeatedly while the price hovers around the threshold.
+const ALERT_HYSTERESIS_PERMYRIAD: u64 = 100;
+
+const MINOR_UNITS_PER_BITCOIN_PER_SAT_RATE: u64 = 100_000_000 * 100;
+
+/// The direction in which the price of bitcoin has to cross the threshold of an [`ExchangeRateAlert`].
+#[derive(PartialEq, Eq, Debug, TryFromPrimitive, Clone, Copy)]
+#[repr(u8)]
+pub enum ExchangeRateAlertDirection {
+    /// Triggers when the price rises to or above the threshold.
+    Above,
+    /// Triggers when the price falls to or below the threshold.
+    Below,
+}
+
+/// A price alert added through [`Config::add_exchange_rate_alert`](crate::Config::add_exchange_rate_alert).
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ExchangeRateAlert {
+    pub id: u64,
+    pub currency_code: String,
+    /// Price of one bitcoin denominated in the currency's minor units.
+    pub threshold_minor_units: u64,
+    pub direction: ExchangeRateAlertDirection,
+    /// Whether the alert triggers once the threshold is crossed. An alert is disarmed when it
+    /// triggers and is re-armed once the price has moved back beyond the threshold by 1%.
+    pub armed: bool,
+    pub last_triggered_at: Option<SystemTime>,
+}
+
+#[derive(PartialEq, Debug)]
+pub(crate) enum AlertTransition {
+    Trigger,
+    Rearm,
+}
+
+/// Price of one bitcoin in minor units of the currency of the exchange rate.
+pub(crate) fn bitcoin_price_minor_units(rate: &ExchangeRate) -> Option<u64> {
+    MINOR_UNITS_PER_BITCOIN_PER_SAT_RATE.checked_div(rate.rate as u64)
+}
+
+pub(crate) fn evaluate_alert(
+    alert: &ExchangeRateAlert,
+    price_minor_units: u64,
+) -> Option<AlertTransition> {
+    let threshold = alert.threshold_minor_units;
+    let margin = threshold * ALERT_HYSTERESIS_PERMYRIAD / 10_000;
+    match (alert.direction, alert.armed) {
+        (ExchangeRateAlertDirection::Above, true) if price_minor_units >= threshold => {
+            Some(AlertTransition::Trigger)
+        }
+        (ExchangeRateAlertDirection::Above, false)
+            if price_minor_units < threshold.saturating_sub(margin) =>
+        {
+            Some(AlertTransition::Rearm)
+        }
+        (ExchangeRateAlertDirection::Below, true) if price_minor_units <= threshold => {
+            Some(AlertTransition::Trigger)
+        }
+        (ExchangeRateAlertDirection::Below, false) if price_minor_units > threshold + margin => {
+            Some(AlertTransition::Rearm)
+        }
+        _ => None,
+    }
+}
+
+/// Evaluates all stored alerts against the freshly fetched exchange rates and notifies about
+/// triggered ones through [`EventsCallback::exchange_rate_alert_triggered`].
+pub(crate) fn evaluate_exchange_rate_alerts(
+    data_store: &Arc<Mutex<DataStore>>,
+    rates: &[ExchangeRate],
+    events_callback: &dyn EventsCallback,
+) {
+    let alerts = match data_store.lock_unwrap().retrieve_exchange_rate_alerts() {
+        Ok(alerts) => alerts,
+        Err(e) => {
+            error!("Failed to retrieve exchange rate alerts: {e}");
+            return;
+        }
+    };
+
+    for alert in alerts {
+        let rate = match rates
+            .iter()
+            .find(|r| r.currency_code == alert.currency_code)
+        {
+            Some(rate) => rate,
+            None => continue,
+        };
+        let price = match bitcoin_price_minor_units(rate) {
+            Some(price) => price,
+            None => continue,
+        };
+        match evaluate_alert(&alert, price) {
+            Some(AlertTransition::Trigger) => {
+                debug!("Exchange rate alert {} triggered", alert.id);
+                let triggered_at = SystemTime::now();
+                if let Err(e) = data_store.lock_unwrap().update_exchange_rate_alert_state(
+                    alert.id,
+                    false,
+                    Some(triggered_at),
+                ) {
+                    error!("Failed to disarm exchange rate alert: {e}");
+                    continue;
+                }
+                events_callback.exchange_rate_alert_triggered(
+                    ExchangeRateAlert {
+                        armed: false,
+                        last_triggered_at: Some(triggered_at),
+                        ..alert
+                    },
+                    rate.clone(),
+                );
+            }
+            Some(AlertTransition::Rearm) => {
+                debug!("Exchange rate alert {} re-armed", alert.id);
+                if let Err(e) = data_store.lock_unwrap().update_exchange_rate_alert_state(
+                    alert.id,
+                    true,
+                    alert.last_triggered_at,
+                ) {
+                    error!("Failed to re-arm exchange rate alert: {e}");
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(direction: ExchangeRateAlertDirection, armed: bool) -> ExchangeRateAlert {
+        ExchangeRateAlert {
+            id: 1,
+            currency_code: "CHF".to_string(),
+            threshold_minor_units: 10_000_000,
+            direction,
+            armed,
+            last_triggered_at: None,
+        }
+    }
+
+    #[test]
+    fn test_evaluate_alert() {
+        let above = alert(ExchangeRateAlertDirection::Above, true);
+        assert_eq!(evaluate_alert(&above, 9_999_999), None);
+        assert_eq!(
+            evaluate_alert(&above, 10_000_000),
+            Some(AlertTransition::Trigger)
+        );
+
+        let above = alert(ExchangeRateAlertDirection::Above, false);
+        assert_eq!(evaluate_alert(&above, 10_500_000), None);
+        assert_eq!(evaluate_alert(&above, 9_950_000), None);
+        assert_eq!(
+            evaluate_alert(&above, 9_899_999),
+            Some(AlertTransition::Rearm)
+        );
+
+        let below = alert(ExchangeRateAlertDirection::Below, true);
+        assert_eq!(evaluate_alert(&below, 10_000_001), None);
+        assert_eq!(
+            evaluate_alert(&below, 10_000_000),
+            Some(AlertTransition::Trigger)
+        );
+
+        let below = alert(ExchangeRateAlertDirection::Below, false);
+        assert_eq!(evaluate_alert(&below, 10_050_000), None);
+        assert_eq!(
+            evaluate_alert(&below, 10_100_001),
+            Some(AlertTransition::Rearm)
+        );
+    }
+
+    #[test]
+    fn test_bitcoin_price_minor_units() {
+        let rate = |rate| ExchangeRate {
+            currency_code: "CHF".to_string(),
+            rate,
+            updated_at: SystemTime::now(),
+            source: String::new(),
+        };
+        // 1000 sats per franc -> 100'000 francs per bitcoin
+        assert_eq!(bitcoin_price_minor_units(&rate(1_000)), Some(10_000_000));
+        assert_eq!(bitcoin_price_minor_units(&rate(0)), None);
+    }
+}
@@ -1,7 +1,16 @@
-use crate::errors::SimpleError;
+use crate::errors::{Result, SimpleError};
+use crate::locker::Locker;
 use honeybadger::Auth;
-use std::sync::Arc;
-use std::time::SystemTime;
+use log::warn;
+use perro::{ensure, invalid_input};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Name of the exchange rate provider run by lipa. It's always registered.
+pub(crate) const LIPA_EXCHANGE_RATE_SOURCE: &str = "lipa";
+/// Source of the exchange rates at which Pocket offers are exchanged.
+pub(crate) const POCKET_EXCHANGE_RATE_SOURCE: &str = "pocket";
 
 /// Exchange rate as sats per major unit accompanied by the specific currency code and time the rate was updated at.
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -9,10 +18,14 @@ pub struct ExchangeRate {
     pub currency_code: String,
     pub rate: u32,
     pub updated_at: SystemTime,
+    /// Name of the provider the rate was obtained from (e.g. "lipa").
+    pub source: String,
 }
 
+/// A source of exchange rates that can be registered through
+/// [`Config::register_exchange_rate_provider`](crate::Config::register_exchange_rate_provider).
 pub trait ExchangeRateProvider: Send + Sync {
-    fn query_all_exchange_rates(&self) -> Result<Vec<ExchangeRate>, SimpleError>;
+    fn query_all_exchange_rates(&self) -> std::result::Result<Vec<ExchangeRate>, SimpleError>;
 }
 
 pub(crate) struct ExchangeRateProviderImpl {
@@ -27,7 +40,7 @@ impl ExchangeRateProviderImpl {
 }
 
 impl ExchangeRateProvider for ExchangeRateProviderImpl {
-    fn query_all_exchange_rates(&self) -> Result<Vec<ExchangeRate>, SimpleError> {
+    fn query_all_exchange_rates(&self) -> std::result::Result<Vec<ExchangeRate>, SimpleError> {
         Ok(self
             .provider
             .query_all_exchange_rates()
@@ -39,7 +52,196 @@ impl ExchangeRateProvider for ExchangeRateProviderImpl {
                 currency_code: r.currency_code,
                 rate: r.sats_per_unit,
                 updated_at: r.updated_at,
+                source: LIPA_EXCHANGE_RATE_SOURCE.to_string(),
             })
             .collect())
     }
 }
+
+/// Queries the registered exchange rate providers in the configured order, falling back to the
+/// next provider if one fails or only provides stale exchange rates.
+pub(crate) struct ExchangeRateProviderChain {
+    providers: Mutex<Vec<(String, Arc<dyn ExchangeRateProvider>)>>,
+    chain: Mutex<Vec<String>>,
+    staleness_threshold: Mutex<Option<Duration>>,
+}
+
+impl ExchangeRateProviderChain {
+    pub fn new(lipa_provider: Box<dyn ExchangeRateProvider>) -> Self {
+        Self {
+            providers: Mutex::new(vec![(
+                LIPA_EXCHANGE_RATE_SOURCE.to_string(),
+                Arc::from(lipa_provider),
+            )]),
+            chain: Mutex::new(vec![LIPA_EXCHANGE_RATE_SOURCE.to_string()]),
+            staleness_threshold: Mutex::new(None),
+        }
+    }
+
+    pub fn register(&self, name: String, provider: Box<dyn ExchangeRateProvider>) -> Result<()> {
+        ensure!(
+            name != LIPA_EXCHANGE_RATE_SOURCE,
+            invalid_input(format!("The name \"{name}\" is reserved"))
+        );
+        let mut providers = self.providers.lock_unwrap();
+        providers.retain(|(n, _)| *n != name);
+        providers.push((name, Arc::from(provider)));
+        Ok(())
+    }
+
+    pub fn set_chain(
+        &self,
+        chain: Vec<String>,
+        staleness_threshold: Option<Duration>,
+    ) -> Result<()> {
+        ensure!(
+            !chain.is_empty(),
+            invalid_input("At least one exchange rate provider is required")
+        );
+        ensure!(
+            chain.iter().collect::<HashSet<_>>().len() == chain.len(),
+            invalid_input("Exchange rate providers contain duplicates")
+        );
+        let providers = self.providers.lock_unwrap();
+        if let Some(name) = chain
+            .iter()
+            .find(|name| !providers.iter().any(|(n, _)| n == *name))
+        {
+            invalid_input!("No exchange rate provider registered under the name \"{name}\"");
+        }
+        *self.chain.lock_unwrap() = chain;
+        *self.staleness_threshold.lock_unwrap() = staleness_threshold;
+        Ok(())
+    }
+
+    pub fn get_chain(&self) -> Vec<String> {
+        self.chain.lock_unwrap().clone()
+    }
+
+    fn is_stale(&self, rates: &[ExchangeRate]) -> bool {
+        let staleness_threshold = match *self.staleness_threshold.lock_unwrap() {
+            Some(staleness_threshold) => staleness_threshold,
+            None => return false,
+        };
+        let now = SystemTime::now();
+        rates
+            .iter()
+            .any(|r| is_older_than(r, staleness_threshold, now))
+    }
+}
+
+impl ExchangeRateProvider for ExchangeRateProviderChain {
+    fn query_all_exchange_rates(&self) -> std::result::Result<Vec<ExchangeRate>, SimpleError> {
+        let chain = self.get_chain();
+        for name in chain {
+            let provider = self
+                .providers
+                .lock_unwrap()
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, p)| Arc::clone(p));
+            let provider = match provider {
+                Some(provider) => provider,
+                None => continue,
+            };
+            match provider.query_all_exchange_rates() {
+                Ok(rates) if rates.is_empty() => {
+                    warn!("Exchange rate provider \"{name}\" provided no exchange rates")
+                }
+                Ok(rates) if self.is_stale(&rates) => {
+                    warn!("Exchange rate provider \"{name}\" provided stale exchange rates")
+                }
+                Ok(rates) => {
+                    return Ok(rates
+                        .into_iter()
+                        .map(|r| ExchangeRate {
+                            source: name.clone(),
+                            ..r
+                        })
+                        .collect())
+                }
+                Err(e) => warn!("Exchange rate provider \"{name}\" failed: {e}"),
+            }
+        }
+        Err(SimpleError::Simple {
+            msg: "None of the exchange rate providers provided up to date exchange rates"
+                .to_string(),
+        })
+    }
+}
+
+/// Whether the exchange rate was updated more than `age` before `now`.
+pub(crate) fn is_older_than(rate: &ExchangeRate, age: Duration, now: SystemTime) -> bool {
+    now.duration_since(rate.updated_at)
+        .map(|rate_age| rate_age > age)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StaticProvider {
+        rates: Option<Vec<ExchangeRate>>,
+    }
+
+    impl ExchangeRateProvider for StaticProvider {
+        fn query_all_exchange_rates(&self) -> std::result::Result<Vec<ExchangeRate>, SimpleError> {
+            self.rates.clone().ok_or(SimpleError::Simple {
+                msg: "Unavailable".to_string(),
+            })
+        }
+    }
+
+    fn provider(rates: Option<Vec<ExchangeRate>>) -> Box<dyn ExchangeRateProvider> {
+        Box::new(StaticProvider { rates })
+    }
+
+    fn rate(age: Duration) -> ExchangeRate {
+        ExchangeRate {
+            currency_code: "EUR".to_string(),
+            rate: 4000,
+            updated_at: SystemTime::now() - age,
+            source: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_fallback_chain() {
+        let chain = ExchangeRateProviderChain::new(provider(None));
+        assert!(chain.query_all_exchange_rates().is_err());
+
+        chain
+            .register(
+                "stale".to_string(),
+                provider(Some(vec![rate(Duration::from_secs(3600))])),
+            )
+            .unwrap();
+        chain
+            .register(
+                "fresh".to_string(),
+                provider(Some(vec![rate(Duration::ZERO)])),
+            )
+            .unwrap();
+        assert!(chain
+            .register(LIPA_EXCHANGE_RATE_SOURCE.to_string(), provider(None))
+            .is_err());
+        assert!(chain.set_chain(vec!["unknown".to_string()], None).is_err());
+        assert!(chain.set_chain(Vec::new(), None).is_err());
+
+        let names = vec![
+            LIPA_EXCHANGE_RATE_SOURCE.to_string(),
+            "stale".to_string(),
+            "fresh".to_string(),
+        ];
+        chain.set_chain(names.clone(), None).unwrap();
+        let rates = chain.query_all_exchange_rates().unwrap();
+        assert_eq!(rates[0].source, "stale");
+
+        chain
+            .set_chain(names, Some(Duration::from_secs(600)))
+            .unwrap();
+        let rates = chain.query_all_exchange_rates().unwrap();
+        assert_eq!(rates[0].source, "fresh");
+    }
+}
@@ -174,7 +174,7 @@ impl FiatTopup {
             .offer_manager
             .query_uncompleted_topups()
             .map_runtime_error_to(RuntimeErrorCode::OfferServiceUnavailable)?;
-        let rate = self.support.get_exchange_rate();
+        let rate = self.support.get_display_rates();
 
         let list_payments_request = ListPaymentsRequest {
             filters: Some(vec![PaymentTypeFilter::Received]),
@@ -237,7 +237,7 @@ impl FiatTopup {
             permanent_failure("LNURLw provides more")
         );
 
-        let exchange_rate = self.support.get_exchange_rate();
+        let exchange_rate = self.support.get_display_rates();
 
         Ok((offer.amount.to_msats() - max_withdrawable_msats)
             .as_msats()
@@ -294,7 +294,7 @@ impl FiatTopup {
                             .get_cheaper_lsp_fee()?
                     };
                     let lsp_fee_calculation_result = self.support.calculate_lsp_fee_for_amount(
-                        payment_amount_msat.as_msats().to_amount_up(&None).sats,
+                        payment_amount_msat.as_msats().sats_round_up().sats,
                         lsp_fee_params,
                     )?;
                     Some(lsp_fee_calculation_result.lsp_fee.to_msats())
@@ -471,6 +471,7 @@ mod tests {
                     currency_code: "".to_string(),
                     rate: 0,
                     updated_at: SystemTime::now(),
+                    source: "lipa".to_string(),
                 },
                 topup_value_minor_units: 0,
                 topup_value_sats: Some(0),
@@ -497,6 +498,7 @@ mod tests {
                     currency_code: "".to_string(),
                     rate: 0,
                     updated_at: SystemTime::now(),
+                    source: "lipa".to_string(),
                 },
                 topup_value_minor_units: 0,
                 topup_value_sats: Some(0),
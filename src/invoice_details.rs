@@ -1,7 +1,6 @@
-use crate::amount::{Amount, AsSats, ToAmount};
+use crate::amount::{Amount, AsSats, DisplayRates, ToAmount};
 
 use crate::util::unix_timestamp_to_system_time;
-use crate::ExchangeRate;
 use breez_sdk_core::LNInvoice;
 use std::time::{Duration, SystemTime};
 
@@ -30,10 +29,7 @@ pub struct InvoiceDetails {
 }
 
 impl InvoiceDetails {
-    pub(crate) fn from_ln_invoice(
-        ln_invoice: LNInvoice,
-        exchange_rate: &Option<ExchangeRate>,
-    ) -> Self {
+    pub(crate) fn from_ln_invoice(ln_invoice: LNInvoice, exchange_rate: &DisplayRates) -> Self {
         InvoiceDetails {
             invoice: ln_invoice.bolt11,
             amount: ln_invoice
@@ -20,11 +20,14 @@ mod analytics;
 mod async_runtime;
 mod auth;
 mod backup;
+mod balance_history;
 mod callbacks;
+mod channel;
 mod config;
 mod data_store;
 mod errors;
 mod event;
+mod exchange_rate_alert;
 mod exchange_rate_provider;
 mod fiat_topup;
 mod invoice_details;
@@ -34,9 +37,12 @@ mod lightning_address;
 mod limits;
 mod locker;
 mod logger;
+mod lsp;
 mod migrations;
 mod node_config;
 mod notification_handling;
+mod notification_log;
+mod notification_text;
 mod offer;
 mod onchain;
 mod payment;
@@ -54,35 +60,48 @@ mod util;
 
 pub use crate::activity::{Activity, ChannelCloseInfo, ChannelCloseState, ListActivitiesResponse};
 pub use crate::amount::{Amount, FiatValue};
-use crate::amount::{AsSats, Msats, Permyriad, ToAmount};
+use crate::amount::{AsSats, DisplayRates, Msats, Permyriad, ToAmount};
 use crate::analytics::{derive_analytics_keys, AnalyticsInterceptor};
 pub use crate::analytics::{AnalyticsConfig, InvoiceCreationMetadata, PaymentMetadata};
 use crate::async_runtime::AsyncRuntime;
 use crate::auth::{build_async_auth, build_auth};
 use crate::backup::BackupManager;
+pub use crate::balance_history::BalanceHistoryPoint;
 pub use crate::callbacks::EventsCallback;
+pub use crate::channel::{ChannelCloseReason, ChannelInfo, ChannelState};
 pub use crate::errors::{
     DecodeDataError, Error as LnError, LnUrlPayError, LnUrlPayErrorCode, LnUrlPayResult,
     MnemonicError, NotificationHandlingError, NotificationHandlingErrorCode, ParseError,
     ParsePhoneNumberError, ParsePhoneNumberPrefixError, PayError, PayErrorCode, PayResult, Result,
-    RuntimeErrorCode, SimpleError, UnsupportedDataType,
+    RuntimeErrorCode, SimpleError, SwapError, UnsupportedDataType,
 };
 use crate::errors::{LnUrlWithdrawError, LnUrlWithdrawErrorCode, LnUrlWithdrawResult};
 use crate::event::LipaEventListener;
-pub use crate::exchange_rate_provider::ExchangeRate;
-use crate::exchange_rate_provider::ExchangeRateProviderImpl;
+pub use crate::exchange_rate_alert::{ExchangeRateAlert, ExchangeRateAlertDirection};
+pub use crate::exchange_rate_provider::{ExchangeRate, ExchangeRateProvider};
+use crate::exchange_rate_provider::{ExchangeRateProviderChain, ExchangeRateProviderImpl};
 pub use crate::invoice_details::InvoiceDetails;
 use crate::key_derivation::derive_persistence_encryption_key;
 pub use crate::lightning::bolt11::Bolt11;
 pub use crate::lightning::lnurl::{LnUrlPayDetails, LnUrlWithdrawDetails, Lnurl};
+pub use crate::lightning::outbox::{Outbox, QueuedPaymentInfo};
+pub use crate::lightning::receive_advice::{
+    ReceiveAdvice, ReceiveFeeEstimate, ReceiveRecommendation,
+};
 pub use crate::lightning::receive_limits::{LiquidityLimit, ReceiveAmountLimits};
 pub use crate::limits::PaymentAmountLimits;
 use crate::locker::Locker;
+pub use crate::lsp::{Lsp, LspInfo, LspSwitchInfo, MaxLspFee};
 pub use crate::node_config::{
-    BreezSdkConfig, LightningNodeConfig, MaxRoutingFeeConfig, ReceiveLimitsConfig,
-    RemoteServicesConfig, TzConfig, TzTime,
+    BreezSdkConfig, ExchangeRateStalenessConfig, LightningNodeConfig, MaxRoutingFeeConfig,
+    ReceiveLimitsConfig, RemoteServicesConfig, TzConfig, TzTime,
+};
+pub use crate::notification_handling::{
+    handle_notification, handle_notification_localized, LocalizedNotification, Notification,
+    NotificationToggles,
 };
-pub use crate::notification_handling::{handle_notification, Notification, NotificationToggles};
+pub use crate::notification_log::NotificationOutcome;
+pub use crate::notification_text::NotificationText;
 pub use crate::offer::{Offer, OfferInfo, OfferStatus};
 pub use crate::payment::{
     IncomingPaymentInfo, OutgoingPaymentInfo, PaymentInfo, PaymentState, Recipient,
@@ -90,10 +109,11 @@ pub use crate::payment::{
 use crate::phone_number::PhoneNumberPrefixParser;
 pub use crate::phone_number::{PhoneNumber, PhoneNumberRecipient};
 pub use crate::recovery::recover_lightning_node;
-pub use crate::reverse_swap::ReverseSwapInfo;
+pub use crate::reverse_swap::{ReverseSwapInfo, ReverseSwapStage, ReverseSwapTimelineEvent};
 pub use crate::secret::{generate_secret, mnemonic_to_secret, words_by_prefix, Secret};
 pub use crate::swap::{
-    FailedSwapInfo, ResolveFailedSwapInfo, SwapAddressInfo, SwapInfo, SwapToLightningFees,
+    FailedSwapInfo, OutOfRangeSwapDeposit, ResolveFailedSwapInfo, SwapAddressInfo,
+    SwapDepositIssue, SwapDetails, SwapInfo, SwapState, SwapToLightningFees,
 };
 use crate::symmetric_encryption::deterministic_encrypt;
 use crate::task_manager::TaskManager;
@@ -111,11 +131,13 @@ pub use crate::lightning_address::LightningAddress;
 pub use crate::onchain::channel_closes::{ChannelClose, SweepChannelCloseInfo};
 pub use crate::onchain::reverse_swap::ReverseSwap;
 pub use crate::onchain::swap::{Swap, SweepFailedSwapInfo};
-pub use crate::onchain::Onchain;
+pub use crate::onchain::{
+    AutoResolutionPolicy, AutoResolutionStrategy, FeePriority, Onchain, OnchainFeeRates,
+    ResolvedFundsSource, StuckTransactionInfo,
+};
 use crate::support::Support;
-pub use breez_sdk_core::error::ReceiveOnchainError as SwapError;
 pub use breez_sdk_core::error::RedeemOnchainError as SweepError;
-use breez_sdk_core::error::{ReceiveOnchainError, RedeemOnchainError};
+use breez_sdk_core::error::RedeemOnchainError;
 pub use breez_sdk_core::HealthCheckStatus as BreezHealthCheckStatus;
 pub use breez_sdk_core::ReverseSwapStatus;
 use breez_sdk_core::{
@@ -141,6 +163,7 @@ use perro::{
 use squirrel::RemoteBackupClient;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 use std::{env, fs};
 
 const LOGS_DIR: &str = "logs";
@@ -166,6 +189,9 @@ pub struct LspFee {
     pub channel_minimum_fee: Amount,
     /// Parts per myriad (aka basis points) -> 100 is 1%
     pub channel_fee_permyriad: u64,
+    /// Until when the LSP honors this fee. The fee is refreshed in the background before it
+    /// expires.
+    pub valid_until: SystemTime,
 }
 
 /// The type returned by [`LightningNode::calculate_lsp_fee`].
@@ -243,6 +269,7 @@ pub struct SweepInfo {
 pub(crate) struct UserPreferences {
     fiat_currency: String,
     timezone_config: TzConfig,
+    secondary_fiat_currencies: Vec<String>,
 }
 
 /// Decoded data that can be obtained using [`LightningNode::decode_data`].
@@ -294,6 +321,30 @@ pub struct ClearWalletInfo {
     pub onchain_fee: Amount,
     /// Estimate for the fee paid to the swap service.
     pub swap_fee: Amount,
+    /// The currently recommended fee rates for each [`FeePriority`].
+    pub fee_rates: OnchainFeeRates,
+    prepare_response: PrepareOnchainPaymentResponse,
+}
+
+/// Information about an on-chain send of an arbitrary amount as returned by
+/// [`ReverseSwap::prepare_send`].
+pub struct OnchainSendInfo {
+    /// The amount that will be received on the destination address.
+    pub amount: Amount,
+    /// The amount that will be deducted from the LN channels, including all fees.
+    pub sender_amount: Amount,
+    /// Total fee estimate. Can differ from the fees that are charged when the send happens.
+    pub total_estimated_fees: Amount,
+    /// Estimate for the on-chain fee of the lockup tx.
+    pub lockup_fee: Amount,
+    /// Estimate for the on-chain fee of the claim tx.
+    pub claim_fee: Amount,
+    /// Estimate for the fee paid to the swap service.
+    pub swap_fee: Amount,
+    /// The on-chain address the funds will be sent to.
+    pub destination: BitcoinAddressData,
+    /// The currently recommended fee rates for each [`FeePriority`].
+    pub fee_rates: OnchainFeeRates,
     prepare_response: PrepareOnchainPaymentResponse,
 }
 
@@ -326,6 +377,8 @@ pub struct LightningNode {
     lightning_address: Arc<LightningAddress>,
     phone_number: Arc<PhoneNumber>,
     util: Arc<Util>,
+    lsp: Arc<Lsp>,
+    support: Arc<Support>,
 }
 
 /// Contains the fee information for the options to resolve funds that have moved on-chain.
@@ -345,13 +398,37 @@ pub struct OnchainResolvingFees {
     /// It should be provided when swapping funds back to lightning or when sweeping funds
     /// to on-chain to ensure the same fee rate is used.
     pub sats_per_vbyte: u32,
+    /// The currently recommended fee rates for each [`FeePriority`].
+    pub fee_rates: OnchainFeeRates,
 }
 
 #[allow(clippy::large_enum_variant)]
 pub enum ActionRequiredItem {
-    UncompletedOffer { offer: OfferInfo },
-    UnresolvedFailedSwap { failed_swap: FailedSwapInfo },
-    ChannelClosesFundsAvailable { available_funds: Amount },
+    UncompletedOffer {
+        offer: OfferInfo,
+    },
+    UnresolvedFailedSwap {
+        failed_swap: FailedSwapInfo,
+    },
+    ChannelClosesFundsAvailable {
+        available_funds: Amount,
+    },
+    /// A payment was received to an invoice that had previously been cancelled using
+    /// [`Bolt11::cancel`].
+    CancelledInvoicePaid {
+        incoming_payment_info: IncomingPaymentInfo,
+    },
+    /// A failed swap refund hasn't confirmed for a long time. Its fee can be bumped using
+    /// [`Onchain::bump_stuck_transaction_fee`].
+    StuckTransaction {
+        stuck_transaction: StuckTransactionInfo,
+    },
+    /// Funds outside the accepted range were sent to a swap address and won't be swapped into
+    /// LN. Once the deposit confirms, the funds can be recovered through
+    /// [`ActionRequiredItem::UnresolvedFailedSwap`].
+    SwapDepositOutOfRange {
+        deposit: OutOfRangeSwapDeposit,
+    },
 }
 
 impl From<OfferInfo> for ActionRequiredItem {
@@ -416,12 +493,14 @@ impl LightningNode {
             }
             Some(c) => c,
         };
+        let secondary_fiat_currencies = data_store.retrieve_secondary_fiat_currencies()?;
 
         let data_store = Arc::new(Mutex::new(data_store));
 
         let user_preferences = Arc::new(Mutex::new(UserPreferences {
             fiat_currency,
             timezone_config: node_config.timezone_config.clone(),
+            secondary_fiat_currencies,
         }));
 
         let analytics_client = AnalyticsClient::new(
@@ -442,6 +521,7 @@ impl LightningNode {
         let event_listener = Box::new(LipaEventListener::new(
             Arc::clone(&events_callback),
             Arc::clone(&analytics_interceptor),
+            Arc::clone(&data_store),
         ));
 
         let sdk = rt.handle().block_on(async {
@@ -471,10 +551,12 @@ impl LightningNode {
             Ok(sdk)
         })?;
 
-        let exchange_rate_provider = Box::new(ExchangeRateProviderImpl::new(
-            node_config.remote_services_config.backend_url.clone(),
-            Arc::clone(&auth),
-        ));
+        let exchange_rate_providers = Arc::new(ExchangeRateProviderChain::new(Box::new(
+            ExchangeRateProviderImpl::new(
+                node_config.remote_services_config.backend_url.clone(),
+                Arc::clone(&auth),
+            ),
+        )));
 
         let offer_manager = Arc::new(OfferManager::new(
             node_config.remote_services_config.backend_url.clone(),
@@ -490,14 +572,15 @@ impl LightningNode {
 
         let task_manager = Arc::new(Mutex::new(TaskManager::new(
             rt.handle(),
-            exchange_rate_provider,
+            Arc::clone(&exchange_rate_providers) as Arc<dyn ExchangeRateProvider>,
+            node_config.exchange_rate_staleness_config.clone(),
             Arc::clone(&data_store),
+            Arc::clone(&user_preferences),
             Arc::clone(&sdk),
             backup_manager,
             events_callback,
             node_config.breez_sdk_config.breez_sdk_api_key.clone(),
         )?));
-        task_manager.lock_unwrap().foreground();
 
         register_webhook_url(&rt, &sdk, &auth, &node_config)?;
 
@@ -514,6 +597,7 @@ impl LightningNode {
             rt: Arc::clone(&rt),
             data_store: Arc::clone(&data_store),
             task_manager: Arc::clone(&task_manager),
+            exchange_rate_providers,
             allowed_countries_country_iso_3166_1_alpha_2: node_config
                 .phone_number_allowed_countries_iso_3166_1_alpha_2
                 .clone(),
@@ -535,9 +619,15 @@ impl LightningNode {
         ));
 
         let onchain = Arc::new(Onchain::new(Arc::clone(&support)));
+        {
+            let mut task_manager = task_manager.lock_unwrap();
+            task_manager.set_onchain(&onchain);
+            task_manager.foreground();
+        }
 
         let actions_required = Arc::new(ActionsRequired::new(
             Arc::clone(&support),
+            Arc::clone(&activities),
             Arc::clone(&fiat_topup),
             Arc::clone(&onchain),
         ));
@@ -548,6 +638,8 @@ impl LightningNode {
 
         let util = Arc::new(Util::new(Arc::clone(&support)));
 
+        let lsp = Arc::new(Lsp::new(Arc::clone(&support)));
+
         Ok(LightningNode {
             sdk,
             auth,
@@ -562,6 +654,8 @@ impl LightningNode {
             lightning_address,
             phone_number,
             util,
+            lsp,
+            support,
         })
     }
 
@@ -601,6 +695,10 @@ impl LightningNode {
         Arc::clone(&self.util)
     }
 
+    pub fn lsp(&self) -> Arc<Lsp> {
+        Arc::clone(&self.lsp)
+    }
+
     /// Request some basic info about the node
     ///
     /// Requires network: **no**
@@ -1203,13 +1301,16 @@ impl LightningNode {
     ) -> std::result::Result<SweepInfo, RedeemOnchainError> {
         self.onchain
             .channel_close()
-            .prepare_sweep(BitcoinAddressData {
-                address,
-                network: Network::Bitcoin,
-                amount_sat: None,
-                label: None,
-                message: None,
-            })
+            .prepare_sweep(
+                BitcoinAddressData {
+                    address,
+                    network: Network::Bitcoin,
+                    amount_sat: None,
+                    label: None,
+                    message: None,
+                },
+                FeePriority::HalfHour,
+            )
             .map(SweepInfo::from)
     }
 
@@ -1224,7 +1325,9 @@ impl LightningNode {
     /// Requires network: **yes**
     #[deprecated = "onchain().channel_close().sweep() should be used instead"]
     pub fn sweep_funds_from_channel_closes(&self, sweep_info: SweepInfo) -> Result<String> {
-        self.onchain.channel_close().sweep(sweep_info.into())
+        self.onchain
+            .channel_close()
+            .sweep_to_address(sweep_info.address, sweep_info.onchain_fee_rate)
     }
 
     /// Generates a Bitcoin on-chain address that can be used to topup the local LN wallet from an
@@ -1244,7 +1347,7 @@ impl LightningNode {
     pub fn generate_swap_address(
         &self,
         _lsp_fee_params: Option<OpeningFeeParams>,
-    ) -> std::result::Result<SwapAddressInfo, ReceiveOnchainError> {
+    ) -> std::result::Result<SwapAddressInfo, SwapError> {
         self.onchain.swap().create()
     }
 
@@ -1254,6 +1357,7 @@ impl LightningNode {
     /// Requires network: **yes**
     #[deprecated = "actions_required().list() should be used instead"]
     pub fn get_unresolved_failed_swaps(&self) -> Result<Vec<FailedSwapInfo>> {
+        let rates = self.support.get_display_rates();
         Ok(self
             .rt
             .handle()
@@ -1266,10 +1370,7 @@ impl LightningNode {
             .filter(|s| s.refund_tx_ids.is_empty())
             .map(|s| FailedSwapInfo {
                 address: s.bitcoin_address,
-                amount: s
-                    .confirmed_sats
-                    .as_sats()
-                    .to_amount_down(&self.get_exchange_rate()),
+                amount: s.confirmed_sats.as_sats().to_amount_down(&rates),
                 created_at: unix_timestamp_to_system_time(s.created_at as u64),
             })
             .collect())
@@ -1289,7 +1390,7 @@ impl LightningNode {
     ) -> Result<Option<OnchainResolvingFees>> {
         self.onchain
             .swap()
-            .determine_resolving_fees(failed_swap_info)
+            .determine_resolving_fees(failed_swap_info, FeePriority::HalfHour)
     }
 
     /// Prepares the resolution of a failed swap in order to know how much will be recovered and how much
@@ -1319,6 +1420,7 @@ impl LightningNode {
                     label: None,
                     message: None,
                 },
+                FeePriority::HalfHour,
             )
             .map(ResolveFailedSwapInfo::from)
     }
@@ -1341,7 +1443,11 @@ impl LightningNode {
         &self,
         resolve_failed_swap_info: ResolveFailedSwapInfo,
     ) -> Result<String> {
-        self.onchain.swap().sweep(resolve_failed_swap_info.into())
+        self.onchain.swap().refund(
+            resolve_failed_swap_info.swap_address,
+            resolve_failed_swap_info.to_address,
+            resolve_failed_swap_info.onchain_fee_rate,
+        )
     }
 
     /// Automatically swaps failed swap funds back to lightning.
@@ -1379,7 +1485,9 @@ impl LightningNode {
     /// Requires network: **yes**
     #[deprecated = "onchain().channel_close().determine_resolving_fees() should be used instead"]
     pub fn get_channel_close_resolving_fees(&self) -> Result<Option<OnchainResolvingFees>> {
-        self.onchain.channel_close().determine_resolving_fees()
+        self.onchain
+            .channel_close()
+            .determine_resolving_fees(FeePriority::HalfHour)
     }
 
     /// Automatically swaps on-chain funds back to lightning.
@@ -1455,7 +1563,9 @@ impl LightningNode {
     /// Requires network: **yes**
     #[deprecated = "onchain().reverse_swap().prepare_clear_wallet() should be used instead"]
     pub fn prepare_clear_wallet(&self) -> Result<ClearWalletInfo> {
-        self.onchain.reverse_swap().prepare_clear_wallet()
+        self.onchain
+            .reverse_swap()
+            .prepare_clear_wallet(FeePriority::HalfHour)
     }
 
     /// Starts a reverse swap that sends all funds in LN channels to the provided on-chain address.
@@ -1563,14 +1673,7 @@ impl LightningNode {
     // Only meant for example CLI use
     #[doc(hidden)]
     pub fn close_all_channels_with_current_lsp(&self) -> Result<()> {
-        self.rt
-            .handle()
-            .block_on(self.sdk.close_lsp_channels())
-            .map_to_runtime_error(
-                RuntimeErrorCode::NodeUnavailable,
-                "Failed to close channels",
-            )?;
-        Ok(())
+        self.lsp.close_channels_with_current_lsp()
     }
 }
 
@@ -1684,7 +1787,7 @@ pub(crate) fn enable_backtrace() {
     env::set_var("RUST_BACKTRACE", "1");
 }
 
-fn fill_payout_fee(offer: Offer, requested_amount: Msats, rate: &Option<ExchangeRate>) -> Offer {
+fn fill_payout_fee(offer: Offer, requested_amount: Msats, rate: &DisplayRates) -> Offer {
     let lightning_payout_fee = offer.topup_value_sats.map(|v| {
         (v.as_sats().msats - requested_amount.msats)
             .as_msats()
@@ -1,14 +1,14 @@
 use crate::amount::AsSats;
 use crate::errors::map_send_payment_error;
 use crate::locker::Locker;
-use crate::support::Support;
+use crate::support::{lsp_fee_msat, Support};
 use crate::{
     InvoiceCreationMetadata, InvoiceDetails, PayErrorCode, PayResult, PaymentMetadata,
     RuntimeErrorCode,
 };
 use breez_sdk_core::error::SendPaymentError;
-use breez_sdk_core::{OpeningFeeParams, SendPaymentRequest};
-use perro::{ensure, runtime_error, MapToError};
+use breez_sdk_core::{OpeningFeeParams, PaymentStatus, SendPaymentRequest};
+use perro::{ensure, invalid_input, runtime_error, MapToError};
 use std::sync::Arc;
 
 pub struct Bolt11 {
@@ -27,11 +27,16 @@ impl Bolt11 {
     ///   payment (sender will have to pay fees on top of that amount)
     /// * `lsp_fee_params` - the params that will be used to determine the lsp fee.
     ///    Can be obtained from [`Lightning::calculate_lsp_fee_for_amount`](crate::Lightning::calculate_lsp_fee_for_amount)
-    ///    to guarantee predicted fees are the ones charged.
+    ///    to guarantee predicted fees are the ones charged. If not provided, the cheaper of the
+    ///    cached LSP fee offers is used.
     /// * `description` - a description to be embedded into the created invoice
     /// * `metadata` - additional data about the invoice creation used for analytics purposes,
     ///    used to improve the user experience
     ///
+    /// Fails with [`RuntimeErrorCode::LspFeeAboveLimit`] if a new channel is needed and its fee
+    /// exceeds the limit set through [`Config::set_max_lsp_fee`](crate::Config::set_max_lsp_fee).
+    /// The fee is checked before the invoice is created.
+    ///
     /// Requires network: **yes**
     pub fn create(
         &self,
@@ -40,6 +45,31 @@ impl Bolt11 {
         description: String,
         metadata: InvoiceCreationMetadata,
     ) -> crate::Result<InvoiceDetails> {
+        let max_receivable_sat = self
+            .support
+            .get_node_info()?
+            .channels_info
+            .max_receivable_single_payment
+            .sats;
+        // Fee params are only needed (and the fee only checked) if a new channel is needed
+        let lsp_fee_params = if amount_sat > max_receivable_sat {
+            let lsp_fee_params = match lsp_fee_params {
+                Some(lsp_fee_params) => lsp_fee_params,
+                None => self
+                    .support
+                    .task_manager
+                    .lock_unwrap()
+                    .get_cheaper_lsp_fee()?,
+            };
+            self.support.ensure_lsp_fee_within_limit(
+                lsp_fee_msat(amount_sat, &lsp_fee_params),
+                amount_sat.as_sats().msats,
+            )?;
+            Some(lsp_fee_params)
+        } else {
+            lsp_fee_params
+        };
+
         let response = self
             .support
             .rt
@@ -61,6 +91,12 @@ impl Bolt11 {
                 RuntimeErrorCode::NodeUnavailable,
                 "Failed to create an invoice",
             )?;
+        // The node's inbound liquidity might have changed since the fee was checked, requiring a
+        // channel after all.
+        self.support.ensure_lsp_fee_within_limit(
+            response.opening_fee_msat.unwrap_or_default(),
+            amount_sat.as_sats().msats,
+        )?;
 
         self.support
             .store_payment_info(&response.ln_invoice.payment_hash, None);
@@ -82,10 +118,48 @@ impl Bolt11 {
         );
         Ok(InvoiceDetails::from_ln_invoice(
             response.ln_invoice,
-            &self.support.get_exchange_rate(),
+            &self.support.get_display_rates(),
         ))
     }
 
+    /// Cancel a bolt11 invoice previously created with [`Bolt11::create`].
+    ///
+    /// The invoice will no longer be listed as pending and its [`PaymentState`](crate::PaymentState)
+    /// becomes [`PaymentState::Cancelled`](crate::PaymentState::Cancelled).
+    /// The node can't reject payments to the invoice, so if one still arrives after cancellation,
+    /// an [`ActionRequiredItem::CancelledInvoicePaid`](crate::ActionRequiredItem::CancelledInvoicePaid)
+    /// is returned by [`ActionsRequired::list`](crate::ActionsRequired::list).
+    ///
+    /// Parameters:
+    /// * `payment_hash` - hex representation of the payment hash of the invoice
+    ///
+    /// Requires network: **no**
+    pub fn cancel(&self, payment_hash: String) -> crate::Result<()> {
+        let payment = self
+            .support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.payment_by_hash(payment_hash.clone()))
+            .map_to_runtime_error(
+                RuntimeErrorCode::NodeUnavailable,
+                "Failed to get payment by hash",
+            )?;
+        ensure!(
+            payment.map(|p| p.status) != Some(PaymentStatus::Complete),
+            invalid_input("The invoice has already been paid")
+        );
+
+        let cancelled = self
+            .support
+            .data_store
+            .lock_unwrap()
+            .cancel_created_invoice(&payment_hash)?;
+        if !cancelled {
+            invalid_input!("No uncancelled invoice with provided hash was found");
+        }
+        Ok(())
+    }
+
     /// Start an attempt to pay an invoice. Can immediately fail, meaning that the payment couldn't be started.
     /// If successful, it doesn't mean that the payment itself was successful (funds received by the payee).
     /// After this method returns, the consumer of this library will learn about a successful/failed payment through the
@@ -1,9 +1,9 @@
-use crate::amount::{AsSats, ToAmount};
+use crate::amount::{AsSats, DisplayRates, ToAmount};
 use crate::errors::{
     map_lnurl_pay_error, map_lnurl_withdraw_error, LnUrlWithdrawErrorCode, LnUrlWithdrawResult,
 };
 use crate::support::Support;
-use crate::{Amount, DecodeDataError, ExchangeRate, LnUrlPayErrorCode, LnUrlPayResult};
+use crate::{Amount, DecodeDataError, LnUrlPayErrorCode, LnUrlPayResult};
 use breez_sdk_core::{
     LnUrlPayRequest, LnUrlPayRequestData, LnUrlWithdrawRequest, LnUrlWithdrawRequestData,
     MetadataItem,
@@ -144,7 +144,7 @@ pub struct LnUrlPayDetails {
 impl LnUrlPayDetails {
     pub(crate) fn from_lnurl_pay_request_data(
         request_data: LnUrlPayRequestData,
-        exchange_rate: &Option<ExchangeRate>,
+        exchange_rate: &DisplayRates,
     ) -> Result<Self, DecodeDataError> {
         let (short_description, long_description) = parse_metadata(&request_data.metadata_str)
             .map_err(|msg| DecodeDataError::LnUrlError { msg })?;
@@ -177,7 +177,7 @@ pub struct LnUrlWithdrawDetails {
 impl LnUrlWithdrawDetails {
     pub(crate) fn from_lnurl_withdraw_request_data(
         request_data: LnUrlWithdrawRequestData,
-        exchange_rate: &Option<ExchangeRate>,
+        exchange_rate: &DisplayRates,
     ) -> Self {
         Self {
             min_withdrawable: request_data
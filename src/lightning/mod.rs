@@ -1,21 +1,30 @@
 pub mod bolt11;
 pub mod lnurl;
+pub mod outbox;
+pub mod receive_advice;
 pub mod receive_limits;
 
-use crate::amount::{AsSats, Permyriad, ToAmount};
+use crate::amount::{AsSats, DisplayRates, Permyriad, ToAmount};
 use crate::errors::Result;
 use crate::lightning::bolt11::Bolt11;
 use crate::lightning::lnurl::Lnurl;
+use crate::lightning::outbox::Outbox;
+use crate::lightning::receive_advice::{advise, ReceiveAdvice};
 use crate::lightning::receive_limits::ReceiveAmountLimits;
 use crate::locker::Locker;
-use crate::support::Support;
+use crate::support::{to_lsp_fee, Support};
 use crate::{
-    CalculateLspFeeResponseV2, ExchangeRate, LspFee, MaxRoutingFeeConfig, MaxRoutingFeeMode,
-    RuntimeErrorCode,
+    CalculateLspFeeResponseV2, LspFee, MaxRoutingFeeConfig, MaxRoutingFeeMode, RuntimeErrorCode,
 };
-use breez_sdk_core::OpeningFeeParams;
-use perro::MapToError;
+use breez_sdk_core::{ListPaymentsRequest, OpeningFeeParams, PaymentStatus, PaymentTypeFilter};
+use perro::{ensure, invalid_input, MapToError};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How many recent incoming payments [`Lightning::advise_receive`] considers.
+const RECEIVE_ADVICE_HISTORY_SIZE: u32 = 10;
+/// How far back [`Lightning::advise_receive`] looks for incoming payments.
+const RECEIVE_ADVICE_HISTORY_PERIOD: Duration = Duration::from_secs(30 * 24 * 60 * 60);
 
 /// Payment affordability returned by [`Lightning::determine_payment_affordability`].
 #[derive(Debug)]
@@ -32,6 +41,7 @@ pub enum PaymentAffordability {
 pub struct Lightning {
     bolt11: Arc<Bolt11>,
     lnurl: Arc<Lnurl>,
+    outbox: Arc<Outbox>,
     support: Arc<Support>,
 }
 
@@ -40,9 +50,11 @@ impl Lightning {
     pub(crate) fn new(support: Arc<Support>) -> Self {
         let bolt11 = Arc::new(Bolt11::new(Arc::clone(&support)));
         let lnurl = Arc::new(Lnurl::new(Arc::clone(&support)));
+        let outbox = Arc::new(Outbox::new(Arc::clone(&support)));
         Self {
             bolt11,
             lnurl,
+            outbox,
             support,
         }
     }
@@ -55,6 +67,10 @@ impl Lightning {
         Arc::clone(&self.lnurl)
     }
 
+    pub fn outbox(&self) -> Arc<Outbox> {
+        Arc::clone(&self.outbox)
+    }
+
     /// Determine the max routing fee mode that will be employed to restrict the fees for paying a
     /// given amount in sats.
     ///
@@ -63,7 +79,7 @@ impl Lightning {
         get_payment_max_routing_fee_mode(
             &self.support.node_config.max_routing_fee_config,
             amount_sat,
-            &self.support.get_exchange_rate(),
+            &self.support.get_display_rates(),
         )
     }
 
@@ -123,7 +139,7 @@ impl Lightning {
         Ok(ReceiveAmountLimits::calculate(
             max_inbound_amount.sats,
             lsp_min_fee_amount.sats,
-            &self.support.get_exchange_rate(),
+            &self.support.get_display_rates(),
             &self.support.node_config.receive_limits_config,
         ))
     }
@@ -149,18 +165,86 @@ impl Lightning {
             .calculate_lsp_fee_for_amount(amount_sat, self.get_lsp_fee_params()?)
     }
 
+    /// Advises on whether to accept a channel open now, and at what size, to minimize the total
+    /// LSP fees paid for a series of upcoming receives.
+    ///
+    /// Parameters:
+    /// * `expected_amounts_sat` - the amounts expected to be received next, in order. If empty,
+    ///   the amounts of the last 10 incoming payments of the past 30 days are used instead.
+    ///
+    /// Requires network: **no**
+    pub fn advise_receive(&self, expected_amounts_sat: Vec<u64>) -> Result<ReceiveAdvice> {
+        let expected_amounts_sat = if expected_amounts_sat.is_empty() {
+            self.recent_incoming_amounts_sat()?
+        } else {
+            expected_amounts_sat
+        };
+        ensure!(
+            !expected_amounts_sat.is_empty(),
+            invalid_input("No expected amounts provided and no recent incoming payments")
+        );
+
+        let inbound_capacity_sat = self
+            .support
+            .get_node_info()?
+            .channels_info
+            .max_receivable_single_payment
+            .sats;
+        let (cheaper_fee_params, longer_valid_fee_params) = {
+            let task_manager = self.support.task_manager.lock_unwrap();
+            (
+                task_manager.get_cheaper_lsp_fee()?,
+                task_manager.get_longer_valid_lsp_fee()?,
+            )
+        };
+        Ok(advise(
+            inbound_capacity_sat,
+            &expected_amounts_sat,
+            &cheaper_fee_params,
+            &longer_valid_fee_params,
+            &self.support.get_display_rates(),
+        ))
+    }
+
+    fn recent_incoming_amounts_sat(&self) -> Result<Vec<u64>> {
+        let from_timestamp = (SystemTime::now() - RECEIVE_ADVICE_HISTORY_PERIOD)
+            .duration_since(UNIX_EPOCH)
+            .map_to_permanent_failure("System time is before the unix epoch")?
+            .as_secs();
+        let payments = self
+            .support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.list_payments(ListPaymentsRequest {
+                filters: Some(vec![PaymentTypeFilter::Received]),
+                metadata_filters: None,
+                from_timestamp: Some(from_timestamp as i64),
+                to_timestamp: None,
+                include_failures: Some(false),
+                limit: Some(RECEIVE_ADVICE_HISTORY_SIZE),
+                offset: None,
+            }))
+            .map_to_runtime_error(
+                RuntimeErrorCode::NodeUnavailable,
+                "Failed to list recent incoming payments",
+            )?;
+        // Payments are listed most recent first.
+        Ok(payments
+            .into_iter()
+            .rev()
+            .filter(|p| p.status == PaymentStatus::Complete)
+            .map(|p| p.amount_msat / 1_000)
+            .collect())
+    }
+
     /// When *receiving* payments, a new channel MAY be required. A fee will be charged to the user.
     /// This does NOT impact *sending* payments.
     /// Get information about the fee charged by the LSP for opening new channels
     ///
     /// Requires network: **no**
     pub fn get_lsp_fee(&self) -> Result<LspFee> {
-        let exchange_rate = self.support.get_exchange_rate();
-        let lsp_fee = self.get_lsp_fee_params()?;
-        Ok(LspFee {
-            channel_minimum_fee: lsp_fee.min_msat.as_msats().to_amount_up(&exchange_rate),
-            channel_fee_permyriad: lsp_fee.proportional as u64 / 100,
-        })
+        let exchange_rate = self.support.get_display_rates();
+        Ok(to_lsp_fee(&self.get_lsp_fee_params()?, &exchange_rate))
     }
 
     pub(crate) fn get_lsp_fee_params(&self) -> Result<OpeningFeeParams> {
@@ -174,7 +258,7 @@ impl Lightning {
 fn get_payment_max_routing_fee_mode(
     config: &MaxRoutingFeeConfig,
     amount_sat: u64,
-    exchange_rate: &Option<ExchangeRate>,
+    exchange_rate: &DisplayRates,
 ) -> MaxRoutingFeeMode {
     let max_fee_permyriad = Permyriad(config.max_routing_fee_permyriad);
     let relative_fee = max_fee_permyriad.of(&amount_sat.as_sats());
@@ -194,7 +278,7 @@ fn get_payment_max_routing_fee_mode(
 
 #[cfg(test)]
 mod tests {
-    use crate::amount::{Permyriad, Sats};
+    use crate::amount::{DisplayRates, Permyriad, Sats};
     use crate::lightning::get_payment_max_routing_fee_mode;
     use crate::{MaxRoutingFeeConfig, MaxRoutingFeeMode};
 
@@ -209,7 +293,7 @@ mod tests {
                 max_routing_fee_exempt_fee_sats: EXEMPT_FEE.sats,
             },
             EXEMPT_FEE.msats / ((MAX_FEE_PERMYRIAD.0 as u64) / 10) - 1,
-            &None,
+            &DisplayRates::default(),
         );
 
         match max_routing_mode {
@@ -230,7 +314,7 @@ mod tests {
                 max_routing_fee_exempt_fee_sats: EXEMPT_FEE.sats,
             },
             EXEMPT_FEE.msats / ((MAX_FEE_PERMYRIAD.0 as u64) / 10),
-            &None,
+            &DisplayRates::default(),
         );
 
         match max_routing_mode {
@@ -0,0 +1,561 @@
+use crate::amount::{AsSats, DisplayRates, ToAmount};
+use crate::data_store::{DataStore, OutboxItem, OutboxItemStatus, OutboxPayment};
+use crate::errors::{map_send_payment_error, Result};
+use crate::locker::Locker;
+use crate::node_config::WithTimezone;
+use crate::support::Support;
+use crate::{
+    Amount, EventsCallback, ExchangeRate, InvoiceDetails, PayErrorCode, Recipient,
+    RuntimeErrorCode, TzConfig, TzTime, UserPreferences,
+};
+use breez_sdk_core::lnurl::pay::LnUrlPayResult;
+use breez_sdk_core::{
+    parse_invoice, BreezServices, ListPaymentsRequest, LnUrlPayError, LnUrlPayRequest,
+    LnUrlPayRequestData, Payment, PaymentDetails, PaymentStatus, PaymentTypeFilter,
+    SendPaymentRequest,
+};
+use log::{debug, error, info, warn};
+use perro::{ensure, invalid_input, MapToError, OptionToError};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Information about a payment waiting in the outbox to be sent.
+#[derive(PartialEq, Debug)]
+pub struct QueuedPaymentInfo {
+    /// Id of the queued payment. Can be used to cancel it using [`Outbox::cancel`].
+    pub id: u64,
+    /// The amount that will be paid, excluding routing fees.
+    pub amount: Amount,
+    /// Information about the payment's recipient.
+    pub recipient: Recipient,
+    /// Details of the invoice to be paid. Only present for BOLT11 payments.
+    pub invoice_details: Option<InvoiceDetails>,
+    /// Comment to be sent to the recipient. Only present for LNURL-pay payments.
+    pub comment_for_recipient: Option<String>,
+    pub queued_at: TzTime,
+    /// The moment the payment will be dropped if it couldn't be sent by then.
+    pub expires_at: TzTime,
+}
+
+/// Persistent queue of outgoing payments that couldn't be sent because the node was unavailable.
+///
+/// Queued payments are sent automatically once the node becomes available again. Until then,
+/// they are listed as [`Activity::QueuedPayment`](crate::Activity::QueuedPayment) among the
+/// pending activities.
+pub struct Outbox {
+    support: Arc<Support>,
+}
+
+impl Outbox {
+    pub(crate) fn new(support: Arc<Support>) -> Self {
+        Self { support }
+    }
+
+    /// Queue a BOLT11 payment to be sent once the node is available.
+    ///
+    /// Meant to be used when [`Bolt11::pay`](crate::Bolt11::pay) failed with
+    /// [`PayErrorCode::NodeUnavailable`](crate::PayErrorCode::NodeUnavailable).
+    ///
+    /// Parameters:
+    /// * `invoice_details` - details of an invoice decode by [`LightningNode::decode_data`](crate::LightningNode::decode_data)
+    /// * `amount_sat` - amount in sats to be paid. Required if, and only if, the invoice doesn't
+    ///   specify an amount.
+    /// * `expires_at` - the moment after which the payment should no longer be attempted.
+    ///   Defaults to the expiry of the invoice and can't be later than it.
+    ///
+    /// Returns the id of the queued payment.
+    ///
+    /// Requires network: **no**
+    pub fn queue_bolt11(
+        &self,
+        invoice_details: InvoiceDetails,
+        amount_sat: Option<u64>,
+        expires_at: Option<SystemTime>,
+    ) -> Result<u64> {
+        ensure!(
+            invoice_details.amount.is_none() == amount_sat.is_some(),
+            invalid_input("An amount must be provided if and only if the invoice has no amount")
+        );
+        let expires_at = expires_at
+            .unwrap_or(invoice_details.expiry_timestamp)
+            .min(invoice_details.expiry_timestamp);
+        ensure!(
+            expires_at > SystemTime::now(),
+            invalid_input("The payment would already be expired")
+        );
+
+        let id = self.support.data_store.lock_unwrap().store_outbox_payment(
+            &OutboxPayment::Bolt11 {
+                invoice: invoice_details.invoice,
+                amount_sat,
+            },
+            expires_at,
+        )?;
+        info!("Queued BOLT11 payment with id {id}");
+        Ok(id)
+    }
+
+    /// Queue an LNURL-pay payment to be sent once the node is available.
+    ///
+    /// Meant to be used when [`Lnurl::pay`](crate::Lnurl::pay) failed with
+    /// [`LnUrlPayErrorCode::ServiceConnectivity`](crate::LnUrlPayErrorCode::ServiceConnectivity).
+    ///
+    /// Parameters:
+    /// * `lnurl_pay_request_data` - LNURL-pay request data as obtained from
+    ///     [`LightningNode::decode_data`](crate::LightningNode::decode_data)
+    /// * `amount_sat` - amount to be paid
+    /// * `comment` - optional comment to be sent to payee (`max_comment_length` in
+    ///     [`LnUrlPayDetails`](crate::LnUrlPayDetails) must be respected)
+    /// * `expires_at` - the moment after which the payment should no longer be attempted
+    ///
+    /// Returns the id of the queued payment.
+    ///
+    /// Requires network: **no**
+    pub fn queue_lnurl_pay(
+        &self,
+        lnurl_pay_request_data: LnUrlPayRequestData,
+        amount_sat: u64,
+        comment: Option<String>,
+        expires_at: SystemTime,
+    ) -> Result<u64> {
+        let comment_allowed = lnurl_pay_request_data.comment_allowed;
+        ensure!(
+            !matches!(comment, Some(ref comment) if comment.len() > comment_allowed as usize),
+            invalid_input(format!(
+                "The provided comment is longer than the allowed {comment_allowed} characters"
+            ))
+        );
+        let amount_msat = amount_sat.as_sats().msats;
+        ensure!(
+            (lnurl_pay_request_data.min_sendable..=lnurl_pay_request_data.max_sendable)
+                .contains(&amount_msat),
+            invalid_input("The amount is outside of the range accepted by the LNURL-pay service")
+        );
+        ensure!(
+            expires_at > SystemTime::now(),
+            invalid_input("The payment would already be expired")
+        );
+
+        let request_data = serde_json::to_string(&lnurl_pay_request_data)
+            .map_to_permanent_failure("Failed to serialize LNURL-pay request data")?;
+        let id = self.support.data_store.lock_unwrap().store_outbox_payment(
+            &OutboxPayment::LnUrlPay {
+                request_data,
+                amount_sat,
+                comment,
+            },
+            expires_at,
+        )?;
+        info!("Queued LNURL-pay payment with id {id}");
+        Ok(id)
+    }
+
+    /// Cancel a queued payment. Only possible as long as no attempt to send it has been started.
+    ///
+    /// Parameters:
+    /// * `id` - the id of the queued payment
+    ///
+    /// Requires network: **no**
+    pub fn cancel(&self, id: u64) -> Result<()> {
+        let cancelled = self
+            .support
+            .data_store
+            .lock_unwrap()
+            .update_outbox_payment_status(
+                id,
+                OutboxItemStatus::Queued,
+                OutboxItemStatus::Cancelled,
+                None,
+            )?;
+        ensure!(
+            cancelled,
+            invalid_input("No queued payment with provided id was found")
+        );
+        info!("Cancelled queued payment with id {id}");
+        Ok(())
+    }
+
+    /// List all payments waiting in the outbox.
+    ///
+    /// Requires network: **no**
+    pub fn list(&self) -> Result<Vec<QueuedPaymentInfo>> {
+        list_queued_payments(&self.support)
+    }
+}
+
+pub(crate) fn list_queued_payments(support: &Support) -> Result<Vec<QueuedPaymentInfo>> {
+    let tz_config = support
+        .user_preferences
+        .lock_unwrap()
+        .timezone_config
+        .clone();
+    let exchange_rate = support.get_display_rates();
+    let items = support
+        .data_store
+        .lock_unwrap()
+        .retrieve_pending_outbox_payments()?;
+    items
+        .into_iter()
+        .map(|i| {
+            queued_payment_info_from_outbox_item(
+                i,
+                &exchange_rate,
+                tz_config.clone(),
+                &support
+                    .node_config
+                    .remote_services_config
+                    .lipa_lightning_domain,
+            )
+        })
+        .collect()
+}
+
+fn queued_payment_info_from_outbox_item(
+    item: OutboxItem,
+    exchange_rate: &DisplayRates,
+    tz_config: TzConfig,
+    lipa_lightning_domain: &str,
+) -> Result<QueuedPaymentInfo> {
+    let (amount, recipient, invoice_details, comment_for_recipient) = match item.payment {
+        OutboxPayment::Bolt11 {
+            invoice,
+            amount_sat,
+        } => {
+            let invoice = parse_invoice(&invoice).map_to_permanent_failure(format!(
+                "Invalid invoice obtained from local db: {invoice}"
+            ))?;
+            let invoice_details = InvoiceDetails::from_ln_invoice(invoice, exchange_rate);
+            let amount = match amount_sat {
+                Some(amount_sat) => amount_sat.as_sats().to_amount_up(exchange_rate),
+                None => invoice_details.amount.clone().ok_or_permanent_failure(
+                    "Queued payment of an invoice without amount has no amount",
+                )?,
+            };
+            (amount, Recipient::Unknown, Some(invoice_details), None)
+        }
+        OutboxPayment::LnUrlPay {
+            request_data,
+            amount_sat,
+            comment,
+        } => {
+            let request_data = serde_json::from_str::<LnUrlPayRequestData>(&request_data)
+                .map_to_permanent_failure(
+                    "Invalid LNURL-pay request data obtained from local db",
+                )?;
+            let recipient = match request_data.ln_address {
+                Some(ref address) => {
+                    Recipient::from_lightning_address(address, lipa_lightning_domain)
+                }
+                None => Recipient::LnUrlPayDomain {
+                    domain: request_data.domain,
+                },
+            };
+            (
+                amount_sat.as_sats().to_amount_up(exchange_rate),
+                recipient,
+                None,
+                comment,
+            )
+        }
+    };
+
+    Ok(QueuedPaymentInfo {
+        id: item.id,
+        amount,
+        recipient,
+        invoice_details,
+        comment_for_recipient,
+        queued_at: item.queued_at.with_timezone(tz_config.clone()),
+        expires_at: item.expires_at.with_timezone(tz_config),
+    })
+}
+
+enum SendError {
+    /// The node or service couldn't be reached, the payment should be retried later.
+    Unavailable(String),
+    Failed(String),
+}
+
+/// Attempts to send all queued payments of the outbox.
+///
+/// Payments that can't be sent because the node is still unavailable are kept queued.
+/// Expired payments and payments that failed for any other reason are dropped from the outbox
+/// and reported through [`EventsCallback::queued_payment_failed`].
+///
+/// The payments in `interrupted_payment_ids` were being sent when the app was stopped. They are
+/// first resolved based on what the node knows about them.
+pub(crate) async fn flush_outbox(
+    sdk: &BreezServices,
+    data_store: &Mutex<DataStore>,
+    user_preferences: &Mutex<UserPreferences>,
+    exchange_rates: &Mutex<Vec<ExchangeRate>>,
+    events_callback: &dyn EventsCallback,
+    interrupted_payment_ids: &Mutex<Vec<u64>>,
+) -> Result<()> {
+    let items = data_store
+        .lock_unwrap()
+        .retrieve_pending_outbox_payments()?;
+    if items.is_empty() {
+        return Ok(());
+    }
+    if let Err(e) = sdk.node_info() {
+        debug!("Node is still unavailable, not flushing the outbox: {e}");
+        return Ok(());
+    }
+
+    for item in items {
+        let id = item.id;
+        if interrupted_payment_ids.lock_unwrap().contains(&id) {
+            match resolve_interrupted_payment(sdk, data_store, events_callback, item).await {
+                Ok(()) => interrupted_payment_ids.lock_unwrap().retain(|i| *i != id),
+                Err(e) => error!("Failed to resolve interrupted queued payment with id {id}: {e}"),
+            }
+            continue;
+        }
+        if item.expires_at <= SystemTime::now() {
+            if data_store.lock_unwrap().update_outbox_payment_status(
+                id,
+                OutboxItemStatus::Queued,
+                OutboxItemStatus::Expired,
+                None,
+            )? {
+                info!("Queued payment with id {id} expired");
+                events_callback.queued_payment_failed(id);
+            }
+            continue;
+        }
+        // Claiming the payment makes sure it hasn't been cancelled in the meantime.
+        if !data_store.lock_unwrap().update_outbox_payment_status(
+            id,
+            OutboxItemStatus::Queued,
+            OutboxItemStatus::Sending,
+            None,
+        )? {
+            continue;
+        }
+
+        let result = match item.payment {
+            OutboxPayment::Bolt11 {
+                invoice,
+                amount_sat,
+            } => send_bolt11(sdk, invoice, amount_sat).await,
+            OutboxPayment::LnUrlPay {
+                request_data,
+                amount_sat,
+                comment,
+            } => send_lnurl_pay(sdk, request_data, amount_sat, comment).await,
+        };
+
+        let (status, payment_hash) = match result {
+            Ok(payment_hash) => {
+                info!("Sent queued payment with id {id}");
+                let user_preferences = user_preferences.lock_unwrap().clone();
+                let exchange_rates = exchange_rates.lock_unwrap().clone();
+                if let Err(e) = data_store.lock_unwrap().store_payment_info(
+                    &payment_hash,
+                    user_preferences,
+                    exchange_rates,
+                    None,
+                    None,
+                    None,
+                ) {
+                    error!("Failed to persist payment info: {e}");
+                }
+                (OutboxItemStatus::Sent, Some(payment_hash))
+            }
+            Err(SendError::Unavailable(e)) => {
+                warn!("Queued payment with id {id} couldn't be sent, will retry: {e}");
+                (OutboxItemStatus::Queued, None)
+            }
+            Err(SendError::Failed(e)) => {
+                warn!("Queued payment with id {id} failed: {e}");
+                events_callback.queued_payment_failed(id);
+                (OutboxItemStatus::Failed, None)
+            }
+        };
+        data_store.lock_unwrap().update_outbox_payment_status(
+            id,
+            OutboxItemStatus::Sending,
+            status,
+            payment_hash.as_deref(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Resolves a queued payment whose send attempt was interrupted by the app being stopped.
+///
+/// If the node knows the payment, it was sent and is tracked like any other payment unless it
+/// failed. Otherwise the send attempt never started and the payment is queued again.
+async fn resolve_interrupted_payment(
+    sdk: &BreezServices,
+    data_store: &Mutex<DataStore>,
+    events_callback: &dyn EventsCallback,
+    item: OutboxItem,
+) -> Result<()> {
+    let id = item.id;
+    let payment = find_sent_payment(sdk, &item).await?;
+    let (status, payment_hash) = match payment {
+        Some(payment) => {
+            let payment_hash = match payment.details {
+                PaymentDetails::Ln { data } => Some(data.payment_hash),
+                _ => None,
+            };
+            match payment.status {
+                PaymentStatus::Pending | PaymentStatus::Complete => {
+                    info!(
+                        "Interrupted queued payment with id {id} was sent, status: {:?}",
+                        payment.status
+                    );
+                    (OutboxItemStatus::Sent, payment_hash)
+                }
+                PaymentStatus::Failed => {
+                    warn!("Interrupted queued payment with id {id} failed");
+                    (OutboxItemStatus::Failed, payment_hash)
+                }
+            }
+        }
+        None if item.expires_at <= SystemTime::now() => {
+            info!("Interrupted queued payment with id {id} expired");
+            (OutboxItemStatus::Expired, None)
+        }
+        None => {
+            info!("Interrupted queued payment with id {id} wasn't sent, queueing it again");
+            (OutboxItemStatus::Queued, None)
+        }
+    };
+    let updated = data_store.lock_unwrap().update_outbox_payment_status(
+        id,
+        OutboxItemStatus::Sending,
+        status,
+        payment_hash.as_deref(),
+    )?;
+    if updated && matches!(status, OutboxItemStatus::Failed | OutboxItemStatus::Expired) {
+        events_callback.queued_payment_failed(id);
+    }
+    Ok(())
+}
+
+/// Looks up the payment the node made for a queued payment, if any.
+///
+/// BOLT11 payments are found by their payment hash. The invoice of an LNURL-pay payment isn't
+/// known, so it's recognized by the amount and the description hash committing to the metadata of
+/// the LNURL-pay request.
+async fn find_sent_payment(sdk: &BreezServices, item: &OutboxItem) -> Result<Option<Payment>> {
+    match &item.payment {
+        OutboxPayment::Bolt11 { invoice, .. } => {
+            let payment_hash = parse_invoice(invoice)
+                .map_to_permanent_failure(format!(
+                    "Invalid invoice obtained from local db: {invoice}"
+                ))?
+                .payment_hash;
+            sdk.payment_by_hash(payment_hash)
+                .await
+                .map_to_runtime_error(
+                    RuntimeErrorCode::NodeUnavailable,
+                    "Failed to get payment by hash",
+                )
+        }
+        OutboxPayment::LnUrlPay {
+            request_data,
+            amount_sat,
+            ..
+        } => {
+            let request_data = serde_json::from_str::<LnUrlPayRequestData>(request_data)
+                .map_to_permanent_failure(
+                    "Invalid LNURL-pay request data obtained from local db",
+                )?;
+            let description_hash = hex::encode(Sha256::digest(request_data.metadata_str));
+            let from_timestamp = item
+                .queued_at
+                .duration_since(UNIX_EPOCH)
+                .map_to_permanent_failure("Queued payment has a time before the unix epoch")?
+                .as_secs() as i64;
+            let payments = sdk
+                .list_payments(ListPaymentsRequest {
+                    filters: Some(vec![PaymentTypeFilter::Sent]),
+                    metadata_filters: None,
+                    from_timestamp: Some(from_timestamp),
+                    to_timestamp: None,
+                    include_failures: Some(true),
+                    limit: None,
+                    offset: None,
+                })
+                .await
+                .map_to_runtime_error(
+                    RuntimeErrorCode::NodeUnavailable,
+                    "Failed to list payments",
+                )?;
+            Ok(payments.into_iter().find(|p| {
+                let PaymentDetails::Ln { data } = &p.details else {
+                    return false;
+                };
+                p.amount_msat == amount_sat.as_sats().msats
+                    && parse_invoice(&data.bolt11).is_ok_and(|invoice| {
+                        invoice.description_hash.as_deref() == Some(description_hash.as_str())
+                    })
+            }))
+        }
+    }
+}
+
+async fn send_bolt11(
+    sdk: &BreezServices,
+    invoice: String,
+    amount_sat: Option<u64>,
+) -> std::result::Result<String, SendError> {
+    let payment_hash = parse_invoice(&invoice)
+        .map_err(|e| SendError::Failed(format!("Invalid invoice: {e}")))?
+        .payment_hash;
+    sdk.send_payment(SendPaymentRequest {
+        bolt11: invoice,
+        use_trampoline: true,
+        amount_msat: amount_sat.map(|a| a.as_sats().msats),
+        label: None,
+    })
+    .await
+    .map_err(|e| match map_send_payment_error(e) {
+        e @ perro::Error::RuntimeError {
+            code: PayErrorCode::NodeUnavailable,
+            ..
+        } => SendError::Unavailable(e.to_string()),
+        e => SendError::Failed(e.to_string()),
+    })?;
+    Ok(payment_hash)
+}
+
+async fn send_lnurl_pay(
+    sdk: &BreezServices,
+    request_data: String,
+    amount_sat: u64,
+    comment: Option<String>,
+) -> std::result::Result<String, SendError> {
+    let request_data = serde_json::from_str::<LnUrlPayRequestData>(&request_data)
+        .map_err(|e| SendError::Failed(format!("Invalid LNURL-pay request data: {e}")))?;
+    match sdk
+        .lnurl_pay(LnUrlPayRequest {
+            data: request_data,
+            amount_msat: amount_sat.as_sats().msats,
+            use_trampoline: true,
+            comment,
+            payment_label: None,
+            validate_success_action_url: Some(false),
+        })
+        .await
+    {
+        Ok(LnUrlPayResult::EndpointSuccess { data }) => Ok(data.payment.id),
+        Ok(LnUrlPayResult::EndpointError { data }) => Err(SendError::Failed(format!(
+            "LNURL server returned error: {}",
+            data.reason
+        ))),
+        Ok(LnUrlPayResult::PayError { data }) => Err(SendError::Failed(format!(
+            "Paying invoice for LNURL pay failed: {}",
+            data.reason
+        ))),
+        Err(e @ LnUrlPayError::ServiceConnectivity { .. }) => {
+            Err(SendError::Unavailable(e.to_string()))
+        }
+        Err(e) => Err(SendError::Failed(e.to_string())),
+    }
+}
@@ -0,0 +1,189 @@
+use crate::amount::{AsSats, DisplayRates, ToAmount};
+use crate::support::{lsp_fee_msat, to_lsp_fee};
+use crate::{Amount, LspFee};
+use breez_sdk_core::OpeningFeeParams;
+
+/// What to do about inbound liquidity ahead of the expected receives, as returned by
+/// [`Lightning::advise_receive`](crate::Lightning::advise_receive).
+#[derive(Debug, PartialEq)]
+pub enum ReceiveRecommendation {
+    /// The current inbound capacity covers all expected receives.
+    NoChannelNeeded,
+    /// Accept a channel open now by receiving at least `channel_size` in a single payment, so
+    /// the channel opening fee is only paid once instead of for several of the expected receives.
+    OpenChannelNow { channel_size: Amount },
+    /// Let channels be opened on demand when a payment exceeds the inbound capacity.
+    OpenOnDemand,
+}
+
+/// The channel opening fees for the expected receives under one of the LSP fee offers.
+pub struct ReceiveFeeEstimate {
+    pub lsp_fee: LspFee,
+    /// The total fees if channels are opened on demand.
+    pub on_demand_fees: Amount,
+    /// The fees if a single channel covering all expected receives is opened now.
+    pub open_now_fees: Amount,
+}
+
+/// The advice returned by [`Lightning::advise_receive`](crate::Lightning::advise_receive).
+pub struct ReceiveAdvice {
+    /// The recommendation, based on the cheaper LSP fee offer.
+    pub recommendation: ReceiveRecommendation,
+    /// The receives the advice is based on.
+    pub expected_amounts: Vec<Amount>,
+    /// The maximum amount currently receivable in a single payment without a channel open.
+    pub inbound_capacity: Amount,
+    /// The fees with the cheaper LSP fee offer, which is the one used for invoices.
+    pub cheaper_offer: ReceiveFeeEstimate,
+    /// The fees with the LSP fee offer that is valid for longer, which is the one used for swaps.
+    pub longer_valid_offer: ReceiveFeeEstimate,
+}
+
+struct FeeSimulation {
+    on_demand_fee_msat: u64,
+    open_now_fee_msat: u64,
+    shortfall_sat: u64,
+}
+
+/// Simulates receiving `expected_amounts_sat` in order. A payment that doesn't fit into the
+/// remaining inbound capacity is assumed to open a new channel that only fits the payment itself.
+fn simulate_fees(
+    inbound_capacity_sat: u64,
+    expected_amounts_sat: &[u64],
+    fee_params: &OpeningFeeParams,
+) -> FeeSimulation {
+    let mut remaining_inbound_sat = inbound_capacity_sat;
+    let mut on_demand_fee_msat = 0;
+    for amount_sat in expected_amounts_sat {
+        if *amount_sat <= remaining_inbound_sat {
+            remaining_inbound_sat -= amount_sat;
+        } else {
+            on_demand_fee_msat += lsp_fee_msat(*amount_sat, fee_params);
+        }
+    }
+
+    let total_sat: u64 = expected_amounts_sat.iter().sum();
+    let shortfall_sat = total_sat.saturating_sub(inbound_capacity_sat);
+    let open_now_fee_msat = if shortfall_sat > 0 {
+        lsp_fee_msat(shortfall_sat, fee_params)
+    } else {
+        0
+    };
+
+    FeeSimulation {
+        on_demand_fee_msat,
+        open_now_fee_msat,
+        shortfall_sat,
+    }
+}
+
+fn to_estimate(
+    simulation: &FeeSimulation,
+    fee_params: &OpeningFeeParams,
+    rate: &DisplayRates,
+) -> ReceiveFeeEstimate {
+    ReceiveFeeEstimate {
+        lsp_fee: to_lsp_fee(fee_params, rate),
+        on_demand_fees: simulation.on_demand_fee_msat.as_msats().to_amount_up(rate),
+        open_now_fees: simulation.open_now_fee_msat.as_msats().to_amount_up(rate),
+    }
+}
+
+pub(crate) fn advise(
+    inbound_capacity_sat: u64,
+    expected_amounts_sat: &[u64],
+    cheaper_fee_params: &OpeningFeeParams,
+    longer_valid_fee_params: &OpeningFeeParams,
+    rate: &DisplayRates,
+) -> ReceiveAdvice {
+    let cheaper = simulate_fees(
+        inbound_capacity_sat,
+        expected_amounts_sat,
+        cheaper_fee_params,
+    );
+    let longer_valid = simulate_fees(
+        inbound_capacity_sat,
+        expected_amounts_sat,
+        longer_valid_fee_params,
+    );
+
+    let recommendation = if cheaper.on_demand_fee_msat == 0 && cheaper.shortfall_sat == 0 {
+        ReceiveRecommendation::NoChannelNeeded
+    } else if cheaper.shortfall_sat > 0 && cheaper.open_now_fee_msat < cheaper.on_demand_fee_msat {
+        ReceiveRecommendation::OpenChannelNow {
+            channel_size: cheaper.shortfall_sat.as_sats().to_amount_up(rate),
+        }
+    } else {
+        ReceiveRecommendation::OpenOnDemand
+    };
+
+    ReceiveAdvice {
+        recommendation,
+        expected_amounts: expected_amounts_sat
+            .iter()
+            .copied()
+            .map(|a| a.as_sats().to_amount_down(rate))
+            .collect(),
+        inbound_capacity: inbound_capacity_sat.as_sats().to_amount_down(rate),
+        cheaper_offer: to_estimate(&cheaper, cheaper_fee_params, rate),
+        longer_valid_offer: to_estimate(&longer_valid, longer_valid_fee_params, rate),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::fee_params;
+
+    #[test]
+    fn test_advise_no_channel_needed() {
+        let params = fee_params(2_000_000, 4_000);
+        let advice = advise(
+            100_000,
+            &[30_000, 70_000],
+            &params,
+            &params,
+            &DisplayRates::default(),
+        );
+        assert_eq!(
+            advice.recommendation,
+            ReceiveRecommendation::NoChannelNeeded
+        );
+        assert_eq!(advice.cheaper_offer.on_demand_fees.sats, 0);
+        assert_eq!(advice.cheaper_offer.open_now_fees.sats, 0);
+    }
+
+    #[test]
+    fn test_advise_open_channel_now() {
+        // Each of the three receives would pay the minimum fee of 2,000 sats on its own.
+        let cheaper = fee_params(2_000_000, 4_000);
+        let longer_valid = fee_params(2_500_000, 5_000);
+        let advice = advise(
+            10_000,
+            &[20_000, 20_000, 20_000],
+            &cheaper,
+            &longer_valid,
+            &DisplayRates::default(),
+        );
+        assert_eq!(
+            advice.recommendation,
+            ReceiveRecommendation::OpenChannelNow {
+                channel_size: 50_000_u64.as_sats().to_amount_up(&DisplayRates::default())
+            }
+        );
+        assert_eq!(advice.cheaper_offer.on_demand_fees.sats, 6_000);
+        assert_eq!(advice.cheaper_offer.open_now_fees.sats, 2_000);
+        assert_eq!(advice.longer_valid_offer.on_demand_fees.sats, 7_500);
+        assert_eq!(advice.longer_valid_offer.open_now_fees.sats, 2_500);
+    }
+
+    #[test]
+    fn test_advise_open_on_demand() {
+        // A single large receive pays the proportional fee either way.
+        let params = fee_params(2_000_000, 4_000);
+        let advice = advise(0, &[1_000_000], &params, &params, &DisplayRates::default());
+        assert_eq!(advice.recommendation, ReceiveRecommendation::OpenOnDemand);
+        assert_eq!(advice.cheaper_offer.on_demand_fees.sats, 4_000);
+        assert_eq!(advice.cheaper_offer.open_now_fees.sats, 4_000);
+    }
+}
@@ -1,6 +1,6 @@
-use crate::amount::{AsSats, ToAmount};
+use crate::amount::{AsSats, DisplayRates, ToAmount};
 use crate::node_config::ReceiveLimitsConfig;
-use crate::{Amount, ExchangeRate};
+use crate::Amount;
 
 /// Information on the limits imposed on the next receiving payment
 pub struct ReceiveAmountLimits {
@@ -23,7 +23,7 @@ impl ReceiveAmountLimits {
     pub fn calculate(
         inbound_capacity_sat: u64,
         lsp_min_fee_sat: u64,
-        exchange_rate: &Option<ExchangeRate>,
+        exchange_rate: &DisplayRates,
         receive_limits_config: &ReceiveLimitsConfig,
     ) -> Self {
         let min_receive_sat = (lsp_min_fee_sat as f64
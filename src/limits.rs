@@ -28,7 +28,7 @@ impl PaymentAmountLimits {
         ReceiveAmountLimits::calculate(
             inbound_capacity_sat,
             lsp_min_fee_sat,
-            exchange_rate,
+            &exchange_rate.clone().into(),
             receive_limits_config,
         )
         .into()
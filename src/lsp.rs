@@ -0,0 +1,405 @@
+use crate::amount::{AsSats, DisplayRates, ToAmount};
+use crate::channel::fetch_channels;
+use crate::data_store::KnownChannel;
+use crate::errors::Result;
+use crate::locker::Locker;
+use crate::support::Support;
+use crate::{Amount, RuntimeErrorCode};
+use breez_sdk_core::{LspInformation, OpeningFeeParams};
+use log::info;
+use perro::{ensure, invalid_input, MapToError, OptionToError};
+use std::sync::Arc;
+
+/// A Lightning Service Provider (LSP) the node can use, as listed by [`Lsp::list`].
+///
+/// The SDK doesn't publish the uptime or the channel size limits of LSPs. The health of the Breez
+/// services, which all LSPs depend on, can be queried using
+/// [`Util::query_health_status`](crate::Util::query_health_status).
+pub struct LspInfo {
+    pub id: String,
+    pub name: String,
+    pub pubkey: String,
+    pub host: String,
+    /// Whether this is the LSP the node currently uses.
+    pub is_current: bool,
+    /// The fee offers for opening channels, cheapest first.
+    pub opening_fee_params_list: Vec<OpeningFeeParams>,
+    /// The base fee charged for routing a payment through the LSP.
+    pub routing_base_fee: Amount,
+    /// The proportional fee charged for routing a payment through the LSP, in parts per million.
+    pub routing_fee_ppm: u64,
+    /// The smallest payment that can be routed through the LSP.
+    pub min_htlc: Amount,
+}
+
+/// Information about switching to another LSP, as returned by [`Lsp::prepare_switch`].
+pub struct LspSwitchInfo {
+    /// The id of the LSP currently used, if any.
+    pub from_lsp_id: Option<String>,
+    pub to_lsp: LspInfo,
+    /// The funds in channels with the current LSP. They move on-chain once the channels are
+    /// closed and can then be swapped back to lightning using
+    /// [`ChannelClose::swap`](crate::ChannelClose::swap), which opens a channel with the new LSP.
+    pub funds_moved_onchain: Amount,
+}
+
+/// A user-set limit on the fees charged by the LSP for opening channels, set through
+/// [`Config::set_max_lsp_fee`](crate::Config::set_max_lsp_fee). Receiving is refused if the fee
+/// exceeds any of the set limits.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MaxLspFee {
+    /// The maximum fee in sats.
+    pub max_fee_sat: Option<u64>,
+    /// The maximum fee in parts per 10,000 of the received amount. For example, `100` only
+    /// accepts fees of at most 1% of the amount.
+    pub max_fee_permyriad: Option<u32>,
+}
+
+impl MaxLspFee {
+    pub(crate) fn accepts_fee(&self, fee_msat: u64, amount_msat: u64) -> bool {
+        self.max_fee_sat
+            .is_none_or(|max_fee_sat| fee_msat <= max_fee_sat.saturating_mul(1_000))
+            && self.max_fee_permyriad.is_none_or(|max_fee_permyriad| {
+                fee_msat.saturating_mul(10_000)
+                    <= amount_msat.saturating_mul(max_fee_permyriad as u64)
+            })
+    }
+
+    /// Whether at least some amounts can be received under the given fee terms without exceeding
+    /// the limit.
+    pub(crate) fn accepts_fee_params(&self, fee_params: &OpeningFeeParams) -> bool {
+        self.max_fee_sat
+            .is_none_or(|max_fee_sat| fee_params.min_msat <= max_fee_sat.saturating_mul(1_000))
+            && self.max_fee_permyriad.is_none_or(|max_fee_permyriad| {
+                fee_params.proportional as u64 <= max_fee_permyriad as u64 * 100
+            })
+    }
+}
+
+pub struct Lsp {
+    support: Arc<Support>,
+}
+
+impl Lsp {
+    pub(crate) fn new(support: Arc<Support>) -> Self {
+        Self { support }
+    }
+
+    /// Lists the LSPs available to the node, including the one currently used.
+    ///
+    /// Requires network: **yes**
+    pub fn list(&self) -> Result<Vec<LspInfo>> {
+        let current_lsp_id = self.current_lsp_id()?;
+        let lsps = self
+            .support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.list_lsps())
+            .map_to_runtime_error(
+                RuntimeErrorCode::LspServiceUnavailable,
+                "Failed to list LSPs",
+            )?;
+        let rate = self.support.get_display_rates();
+        Ok(lsps
+            .into_iter()
+            .map(|lsp| to_lsp_info(lsp, current_lsp_id.as_deref(), &rate))
+            .collect())
+    }
+
+    /// Prepares switching to another LSP.
+    ///
+    /// Parameters:
+    /// * `lsp_id` - the id of the LSP to switch to, as listed by [`Lsp::list`]
+    ///
+    /// Requires network: **yes**
+    pub fn prepare_switch(&self, lsp_id: String) -> Result<LspSwitchInfo> {
+        let to_lsp = select_switch_target(self.list()?, &lsp_id)?;
+
+        Ok(LspSwitchInfo {
+            from_lsp_id: self.current_lsp_id()?,
+            to_lsp,
+            funds_moved_onchain: self.support.get_node_info()?.channels_info.local_balance,
+        })
+    }
+
+    /// Switches to another LSP. Channels with the current LSP are closed cooperatively, moving
+    /// their funds on-chain. They then show up as
+    /// [`ActionRequiredItem::ChannelClosesFundsAvailable`](crate::ActionRequiredItem::ChannelClosesFundsAvailable)
+    /// and can be resolved through [`ChannelClose`](crate::ChannelClose).
+    ///
+    /// The switch is validated again and the new LSP is checked to be reachable before any
+    /// channel is closed. If connecting to the new LSP fails after the channels were closed, the
+    /// returned error says so. The funds are then on-chain and the switch can be retried.
+    ///
+    /// Parameters:
+    /// * `switch_info` - obtained using [`Lsp::prepare_switch`]
+    ///
+    /// Requires network: **yes**
+    pub fn switch(&self, switch_info: LspSwitchInfo) -> Result<()> {
+        let to_lsp = select_switch_target(self.list()?, &switch_info.to_lsp.id)?;
+        ensure!(
+            self.current_lsp_id()? == switch_info.from_lsp_id,
+            invalid_input("The current LSP changed, the switch has to be prepared again")
+        );
+        self.support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.fetch_lsp_info(to_lsp.id.clone()))
+            .map_to_runtime_error(
+                RuntimeErrorCode::LspServiceUnavailable,
+                "The LSP to switch to isn't reachable",
+            )?
+            .ok_or_runtime_error(
+                RuntimeErrorCode::LspServiceUnavailable,
+                "The LSP to switch to isn't reachable",
+            )?;
+
+        let has_channels = self
+            .support
+            .get_node_info()?
+            .channels_info
+            .local_balance
+            .sats
+            > 0;
+        if has_channels {
+            // Channels are marked before closing them, so a sync running in between reports
+            // their closes as migrations. The marks are removed again if closing fails.
+            let marked_funding_txids = self.mark_channels_for_lsp_migration()?;
+            if let Err(e) = self.close_channels_with_current_lsp() {
+                self.unmark_channels_for_lsp_migration(&marked_funding_txids)?;
+                return Err(e);
+            }
+        }
+        self.support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.connect_lsp(to_lsp.id.clone()))
+            .map_to_runtime_error(
+                RuntimeErrorCode::LspServiceUnavailable,
+                if has_channels {
+                    "Failed to connect to the new LSP. The channels with the previous LSP were \
+                    closed and their funds are now on-chain"
+                } else {
+                    "Failed to connect to the new LSP"
+                },
+            )?;
+        info!(
+            "Switched LSP from {:?} to {}",
+            switch_info.from_lsp_id, to_lsp.id
+        );
+        self.support
+            .task_manager
+            .lock_unwrap()
+            .request_lsp_fee_update();
+        Ok(())
+    }
+
+    pub(crate) fn close_channels_with_current_lsp(&self) -> Result<()> {
+        self.support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.close_lsp_channels())
+            .map_to_runtime_error(
+                RuntimeErrorCode::NodeUnavailable,
+                "Failed to close channels",
+            )?;
+        Ok(())
+    }
+
+    /// Records the open channels as closing for switching LSPs, so their closes are reported with
+    /// [`ChannelCloseReason::LspMigration`](crate::ChannelCloseReason::LspMigration).
+    ///
+    /// Returns the funding txids of the marked channels.
+    fn mark_channels_for_lsp_migration(&self) -> Result<Vec<String>> {
+        let channels = self
+            .support
+            .rt
+            .handle()
+            .block_on(fetch_channels(&self.support.sdk, &DisplayRates::default()))?;
+        let mut data_store = self.support.data_store.lock_unwrap();
+        let mut marked_funding_txids = Vec::new();
+        for channel in channels.into_iter().filter(|c| c.state.is_open()) {
+            data_store.store_known_channel(&KnownChannel {
+                funding_txid: channel.funding_txid.clone(),
+                channel_id: channel.channel_id,
+                state: channel.state,
+                closing_for_lsp_migration: true,
+            })?;
+            marked_funding_txids.push(channel.funding_txid);
+        }
+        Ok(marked_funding_txids)
+    }
+
+    fn unmark_channels_for_lsp_migration(&self, funding_txids: &[String]) -> Result<()> {
+        let mut data_store = self.support.data_store.lock_unwrap();
+        for funding_txid in funding_txids {
+            data_store.set_closing_for_lsp_migration(funding_txid, false)?;
+        }
+        Ok(())
+    }
+
+    fn current_lsp_id(&self) -> Result<Option<String>> {
+        self.support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.lsp_id())
+            .map_to_runtime_error(
+                RuntimeErrorCode::NodeUnavailable,
+                "Failed to get the current LSP",
+            )
+    }
+}
+
+/// Picks the LSP to switch to out of the listed ones, ensuring a switch to it is possible.
+fn select_switch_target(lsps: Vec<LspInfo>, lsp_id: &str) -> Result<LspInfo> {
+    let to_lsp = lsps
+        .into_iter()
+        .find(|lsp| lsp.id == lsp_id)
+        .ok_or_else(|| invalid_input(format!("Unknown LSP {lsp_id}")))?;
+    ensure!(
+        !to_lsp.is_current,
+        invalid_input("The LSP is already in use")
+    );
+    ensure!(
+        !to_lsp.opening_fee_params_list.is_empty(),
+        invalid_input("The LSP doesn't offer to open channels")
+    );
+    Ok(to_lsp)
+}
+
+fn to_lsp_info(lsp: LspInformation, current_lsp_id: Option<&str>, rate: &DisplayRates) -> LspInfo {
+    let mut opening_fee_params_list = lsp.opening_fee_params_list.values;
+    opening_fee_params_list.sort_by_key(|p| (p.min_msat, p.proportional));
+    LspInfo {
+        is_current: current_lsp_id == Some(lsp.id.as_str()),
+        id: lsp.id,
+        name: lsp.name,
+        pubkey: lsp.pubkey,
+        host: lsp.host,
+        opening_fee_params_list,
+        routing_base_fee: (lsp.base_fee_msat.max(0) as u64)
+            .as_msats()
+            .to_amount_up(rate),
+        routing_fee_ppm: (lsp.fee_rate * 1_000_000_f64) as u64,
+        min_htlc: (lsp.min_htlc_msat.max(0) as u64)
+            .as_msats()
+            .to_amount_up(rate),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::fee_params;
+    use breez_sdk_core::OpeningFeeParamsMenu;
+
+    fn lsp_information(id: &str, opening_fee_params: Vec<OpeningFeeParams>) -> LspInformation {
+        LspInformation {
+            id: id.to_string(),
+            name: format!("{id} name"),
+            widget_url: String::new(),
+            pubkey: format!("{id} pubkey"),
+            host: format!("{id} host"),
+            base_fee_msat: 1_000,
+            fee_rate: 0.5,
+            time_lock_delta: 144,
+            min_htlc_msat: 600,
+            lsp_pubkey: Vec::new(),
+            opening_fee_params_list: OpeningFeeParamsMenu {
+                values: opening_fee_params,
+            },
+        }
+    }
+
+    #[test]
+    fn test_to_lsp_info() {
+        let lsp = lsp_information(
+            "lsp",
+            vec![fee_params(5_000_000, 10_000), fee_params(2_000_000, 20_000)],
+        );
+
+        let info = to_lsp_info(lsp.clone(), Some("lsp"), &DisplayRates::default());
+        assert!(info.is_current);
+        assert_eq!(info.id, "lsp");
+        assert_eq!(info.name, "lsp name");
+        assert_eq!(info.pubkey, "lsp pubkey");
+        assert_eq!(info.host, "lsp host");
+        assert_eq!(
+            info.opening_fee_params_list,
+            vec![fee_params(2_000_000, 20_000), fee_params(5_000_000, 10_000)]
+        );
+        assert_eq!(info.routing_base_fee.sats, 1);
+        assert_eq!(info.routing_fee_ppm, 500_000);
+        assert_eq!(info.min_htlc.sats, 1);
+
+        assert!(!to_lsp_info(lsp.clone(), Some("other"), &DisplayRates::default()).is_current);
+        assert!(!to_lsp_info(lsp, None, &DisplayRates::default()).is_current);
+    }
+
+    #[test]
+    fn test_select_switch_target() {
+        let rate = DisplayRates::default();
+        let lsps = || {
+            vec![
+                to_lsp_info(
+                    lsp_information("current", vec![fee_params(2_000_000, 10_000)]),
+                    Some("current"),
+                    &rate,
+                ),
+                to_lsp_info(
+                    lsp_information("other", vec![fee_params(2_000_000, 10_000)]),
+                    Some("current"),
+                    &rate,
+                ),
+                to_lsp_info(
+                    lsp_information("no fees", Vec::new()),
+                    Some("current"),
+                    &rate,
+                ),
+            ]
+        };
+
+        assert_eq!(select_switch_target(lsps(), "other").unwrap().id, "other");
+        assert!(select_switch_target(lsps(), "unknown").is_err());
+        assert!(select_switch_target(lsps(), "current").is_err());
+        assert!(select_switch_target(lsps(), "no fees").is_err());
+    }
+
+    #[test]
+    fn test_max_lsp_fee_accepts_fee() {
+        let absolute = MaxLspFee {
+            max_fee_sat: Some(2_000),
+            max_fee_permyriad: None,
+        };
+        assert!(absolute.accepts_fee(2_000_000, 10_000_000));
+        assert!(!absolute.accepts_fee(2_000_001, 1_000_000_000));
+
+        let relative = MaxLspFee {
+            max_fee_sat: None,
+            max_fee_permyriad: Some(100),
+        };
+        assert!(relative.accepts_fee(100_000, 10_000_000));
+        assert!(!relative.accepts_fee(100_001, 10_000_000));
+
+        let both = MaxLspFee {
+            max_fee_sat: Some(2_000),
+            max_fee_permyriad: Some(100),
+        };
+        assert!(both.accepts_fee(2_000_000, 200_000_000));
+        assert!(!both.accepts_fee(2_000_000, 100_000_000));
+        assert!(!both.accepts_fee(3_000_000, 300_000_000));
+    }
+
+    #[test]
+    fn test_max_lsp_fee_accepts_fee_params() {
+        let fee_params = fee_params(2_000_000, 10_000);
+        let max_lsp_fee = |max_fee_sat, max_fee_permyriad| MaxLspFee {
+            max_fee_sat,
+            max_fee_permyriad,
+        };
+
+        assert!(max_lsp_fee(Some(2_000), Some(100)).accepts_fee_params(&fee_params));
+        assert!(!max_lsp_fee(Some(1_999), None).accepts_fee_params(&fee_params));
+        assert!(!max_lsp_fee(None, Some(99)).accepts_fee_params(&fee_params));
+    }
+}
@@ -184,6 +184,139 @@ const MIGRATION_19_PAYMENT_OPTIONAL_FIELDS: &str = "
     ALTER TABLE payments_new RENAME TO payments;
 ";
 
+const MIGRATION_20_CANCELLED_INVOICES: &str = "
+    ALTER TABLE created_invoices ADD COLUMN cancelled_at INTEGER DEFAULT NULL;
+    ALTER TABLE created_invoices ADD COLUMN cancelled_payment_dismissed INTEGER NOT NULL DEFAULT 0;
+";
+
+const MIGRATION_21_OUTBOX: &str = "
+    CREATE TABLE outbox (
+        id INTEGER NOT NULL PRIMARY KEY,
+        invoice TEXT NULL,
+        lnurl_pay_request_data TEXT NULL,
+        amount_sat INTEGER NULL,
+        comment TEXT NULL,
+        status INTEGER NOT NULL DEFAULT 0,
+        payment_hash TEXT NULL,
+        queued_at INTEGER NOT NULL,
+        expires_at INTEGER NOT NULL
+    );
+";
+
+const MIGRATION_22_SECONDARY_FIAT_CURRENCIES: &str = "
+    CREATE TABLE secondary_fiat_currencies (
+        position INTEGER NOT NULL PRIMARY KEY,
+        fiat_currency TEXT NOT NULL
+    );
+";
+
+const MIGRATION_23_BALANCE_CHANGES: &str = "
+    CREATE TABLE balance_changes (
+        payment_hash TEXT NOT NULL PRIMARY KEY,
+        changed_at INTEGER NOT NULL,
+        balance_change_msat INTEGER NOT NULL,
+        pending INTEGER NOT NULL
+    );
+    CREATE INDEX balance_changes_changed_at ON balance_changes (changed_at);
+";
+
+const MIGRATION_24_EXCHANGE_RATE_SOURCE: &str = "
+    ALTER TABLE exchange_rates ADD COLUMN source TEXT NOT NULL DEFAULT 'lipa';
+    ALTER TABLE exchange_rates_history ADD COLUMN source TEXT NOT NULL DEFAULT 'lipa';
+";
+
+const MIGRATION_25_EXCHANGE_RATE_ALERTS: &str = "
+    CREATE TABLE exchange_rate_alerts (
+        id INTEGER NOT NULL PRIMARY KEY,
+        currency_code TEXT NOT NULL,
+        threshold_minor_units INTEGER NOT NULL,
+        direction INTEGER NOT NULL,
+        armed INTEGER NOT NULL DEFAULT 1,
+        last_triggered_at INTEGER NULL
+    );
+";
+
+const MIGRATION_26_SWEEP_TRANSACTIONS: &str = "
+    CREATE TABLE sweep_transactions (
+        txid TEXT NOT NULL PRIMARY KEY,
+        swap_address TEXT NOT NULL,
+        to_address TEXT NOT NULL,
+        sat_per_vbyte INTEGER NOT NULL,
+        broadcast_at INTEGER NOT NULL,
+        confirmed INTEGER NOT NULL DEFAULT 0,
+        replaced_by TEXT NULL
+    );
+";
+
+const MIGRATION_27_REVERSE_SWAP_TIMELINE: &str = "
+    CREATE TABLE reverse_swap_timeline (
+        reverse_swap_id TEXT NOT NULL,
+        stage INTEGER NOT NULL,
+        txid TEXT NULL,
+        block_height INTEGER NULL,
+        occurred_at INTEGER NULL,
+        PRIMARY KEY (reverse_swap_id, stage)
+    );
+";
+
+const MIGRATION_28_AUTO_RESOLUTION_POLICY: &str = "
+    CREATE TABLE auto_resolution_policy (
+        id INTEGER NOT NULL PRIMARY KEY,
+        enabled INTEGER NOT NULL,
+        sweep_address TEXT NULL,
+        max_fee_permyriad INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+";
+
+const MIGRATION_29_KNOWN_CHANNELS: &str = "
+    CREATE TABLE known_channels (
+        funding_txid TEXT NOT NULL PRIMARY KEY,
+        channel_id TEXT NULL,
+        state INTEGER NOT NULL,
+        closing_for_lsp_migration INTEGER NOT NULL DEFAULT 0
+    );
+";
+
+const MIGRATION_30_LSP_FEE_OFFERS: &str = "
+    CREATE TABLE lsp_fee_offers (
+        kind INTEGER NOT NULL PRIMARY KEY,
+        min_msat INTEGER NOT NULL,
+        proportional INTEGER NOT NULL,
+        valid_until TEXT NOT NULL,
+        max_idle_time INTEGER NOT NULL,
+        max_client_to_self_delay INTEGER NOT NULL,
+        promise TEXT NOT NULL
+    );
+";
+
+const MIGRATION_31_MAX_LSP_FEE: &str = "
+    CREATE TABLE max_lsp_fee (
+        id INTEGER NOT NULL PRIMARY KEY,
+        max_fee_sat INTEGER NULL,
+        max_fee_permyriad INTEGER NULL,
+        updated_at INTEGER NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+";
+
+const MIGRATION_32_NOTIFICATION_LOG: &str = "
+    CREATE TABLE notification_log (
+        id INTEGER NOT NULL PRIMARY KEY,
+        payload_hash TEXT NOT NULL,
+        payload_kind TEXT NOT NULL,
+        received_at INTEGER NOT NULL,
+        duration_ms INTEGER NOT NULL,
+        is_duplicate INTEGER NOT NULL,
+        notification_kind INTEGER NULL,
+        amount_sat INTEGER NULL,
+        payment_hash TEXT NULL,
+        address TEXT NULL,
+        detail INTEGER NULL,
+        error TEXT NULL
+    );
+    CREATE INDEX notification_log_payload_hash ON notification_log (payload_hash);
+";
+
 pub(crate) fn migrate(conn: &mut Connection) -> Result<()> {
     migrations()
         .to_latest(conn)
@@ -211,6 +344,19 @@ fn migrations() -> Migrations<'static> {
         M::up(MIGRATION_17_HIDDEN_FAILED_SWAPS),
         M::up(MIGRATION_18_FIAT_CURRENCY),
         M::up(MIGRATION_19_PAYMENT_OPTIONAL_FIELDS),
+        M::up(MIGRATION_20_CANCELLED_INVOICES),
+        M::up(MIGRATION_21_OUTBOX),
+        M::up(MIGRATION_22_SECONDARY_FIAT_CURRENCIES),
+        M::up(MIGRATION_23_BALANCE_CHANGES),
+        M::up(MIGRATION_24_EXCHANGE_RATE_SOURCE),
+        M::up(MIGRATION_25_EXCHANGE_RATE_ALERTS),
+        M::up(MIGRATION_26_SWEEP_TRANSACTIONS),
+        M::up(MIGRATION_27_REVERSE_SWAP_TIMELINE),
+        M::up(MIGRATION_28_AUTO_RESOLUTION_POLICY),
+        M::up(MIGRATION_29_KNOWN_CHANNELS),
+        M::up(MIGRATION_30_LSP_FEE_OFFERS),
+        M::up(MIGRATION_31_MAX_LSP_FEE),
+        M::up(MIGRATION_32_NOTIFICATION_LOG),
     ])
 }
 
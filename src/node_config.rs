@@ -1,4 +1,4 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 /// An object that holds all configuration needed to start a LightningNode instance.
 #[derive(Debug, Clone)]
@@ -29,6 +29,10 @@ pub struct LightningNodeConfig {
     pub max_routing_fee_config: MaxRoutingFeeConfig,
     pub receive_limits_config: ReceiveLimitsConfig,
     pub topup_referral_code_max_length: u32,
+    pub exchange_rate_staleness_config: ExchangeRateStalenessConfig,
+    /// Failed swap refunds still unconfirmed this long after being broadcast are reported as
+    /// [`ActionRequiredItem::StuckTransaction`](crate::ActionRequiredItem::StuckTransaction).
+    pub stuck_transaction_threshold: Duration,
 }
 
 #[derive(Debug, Clone)]
@@ -66,6 +70,16 @@ pub struct ReceiveLimitsConfig {
     pub min_receive_channel_open_fee_multiplier: f64,
 }
 
+#[derive(Debug, Clone)]
+pub struct ExchangeRateStalenessConfig {
+    /// Exchange rates older than this are considered stale. Fiat values computed using them are
+    /// flagged through [`FiatValue::is_stale`](crate::FiatValue::is_stale).
+    pub stale_after: Duration,
+    /// Exchange rates older than this are no longer used, so amounts come without fiat values.
+    /// If not set, exchange rates are used regardless of their age.
+    pub discard_after: Option<Duration>,
+}
+
 /// An object that holds timezone configuration values necessary for 3L to do timestamp annotation. These values get tied
 /// together with every timestamp persisted in the local payment database.
 #[derive(Clone, Debug, Default, PartialEq)]
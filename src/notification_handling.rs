@@ -1,33 +1,47 @@
-use crate::amount::AsSats;
+use crate::amount::{AsSats, DisplayRates, ToAmount};
 use crate::analytics::{derive_analytics_keys, AnalyticsInterceptor};
 use crate::async_runtime::AsyncRuntime;
 use crate::auth::{build_async_auth, build_auth};
-use crate::data_store::DataStore;
+use crate::backup::BackupManager;
+use crate::data_store::{DataStore, DeduplicationWindow, NotificationHandlingStart};
 use crate::errors::{NotificationHandlingErrorCode, NotificationHandlingResult};
 use crate::event::report_event_for_analytics;
-use crate::exchange_rate_provider::{ExchangeRateProvider, ExchangeRateProviderImpl};
+use crate::exchange_rate_provider::{
+    is_older_than, ExchangeRateProvider, ExchangeRateProviderImpl,
+};
+use crate::key_derivation::derive_persistence_encryption_key;
 use crate::logger::init_logger_once;
+use crate::notification_log::{
+    hash_payload, NotificationOutcome, DEDUPLICATION_WINDOW, RETENTION_PERIOD,
+};
+use crate::notification_text::{notification_text, Language, NotificationText};
+use crate::swap::deposit_issue;
 use crate::util::LogIgnoreError;
 use crate::{
     enable_backtrace, register_webhook_url, sanitize_input, start_sdk, EnableStatus,
-    LightningNodeConfig, RuntimeErrorCode, UserPreferences, DB_FILENAME, LOGS_DIR,
+    LightningNodeConfig, RuntimeErrorCode, SwapDepositIssue, UserPreferences, DB_FILENAME,
+    LOGS_DIR,
 };
 use breez_sdk_core::{
-    BreezEvent, BreezServices, EventListener, OpenChannelFeeRequest, Payment, PaymentStatus,
-    ReceivePaymentRequest, SwapInfo,
+    BreezEvent, BreezServices, EventListener, ListPaymentsRequest, OpenChannelFeeRequest, Payment,
+    PaymentDetails, PaymentStatus, PaymentTypeFilter, ReceivePaymentRequest, ReverseSwapInfo,
+    ReverseSwapStatus, SwapInfo,
 };
-use log::{debug, Level};
+use crow::LanguageCode;
+use log::{debug, info, warn, Level};
 use parrot::AnalyticsClient;
 use perro::{ensure, invalid_input, permanent_failure, runtime_error, MapToError, ResultTrait};
 use pigeon::submit_lnurl_pay_invoice;
 use serde::Deserialize;
+use squirrel::RemoteBackupClient;
 use std::path::Path;
+use std::str::FromStr;
 use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
 use std::sync::{mpsc, Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// A notification to be displayed to the user.
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Notification {
     /// The notification that a previously issued bolt11 invoice was paid.
     /// The `amount_sat` of the payment is provided.
@@ -51,12 +65,63 @@ pub enum Notification {
         payment_hash: String,
     },
     /// The notification that an on-chain pay transaction has been broadcast successfully.
-    // TODO: Return `payment_hash` and `amount_sat`. Requires changes by the Breez SDK https://github.com/breez/breez-sdk-greenlight/issues/1159
-    OnchainPaymentSwappedOut {},
+    /// The `amount_sat` sent on-chain is provided. It and the `payment_hash` are missing if the
+    /// on-chain payment couldn't be looked up after the funds were claimed.
+    ///
+    /// The `payment_hash` can be used to directly open the associated
+    /// [`Activity`](crate::Activity) using
+    /// [`LightningNode::get_activity`](crate::LightningNode::get_activity).
+    OnchainPaymentSwappedOut {
+        amount_sat: Option<u64>,
+        payment_hash: Option<String>,
+    },
+    /// The notification that the status of an on-chain payment changed without requiring any
+    /// action. The `amount_sat` to be sent on-chain and the new `status` are provided.
+    ///
+    /// The `payment_hash` can be used to directly open the associated
+    /// [`Activity`](crate::Activity) using
+    /// [`LightningNode::get_activity`](crate::LightningNode::get_activity).
+    OnchainPaymentSwapOutUpdated {
+        amount_sat: u64,
+        payment_hash: String,
+        status: ReverseSwapStatus,
+    },
+    /// The notification that funds outside the range accepted by the swap service were sent to
+    /// a swap address. They won't be swapped into LN and have to be recovered by resolving the
+    /// resulting failed swap.
+    /// The total `amount_sat` sent to the `address` is provided.
+    OnchainDepositOutOfRange {
+        address: String,
+        amount_sat: u64,
+        issue: SwapDepositIssue,
+    },
     /// The notification that an invoice was created and submitted for payment as part of an
     /// incoming LNURL payment.
     /// The `amount_sat` of the created invoice is provided.
     LnurlInvoiceCreated { amount_sat: u64 },
+    /// The notification that an LNURL-withdraw was paid out to the node.
+    /// The `amount_sat` of the payment is provided.
+    ///
+    /// The `payment_hash` can be used to directly open the associated
+    /// [`IncomingPaymentInfo`](crate::IncomingPaymentInfo) using
+    /// [`LightningNode::get_incoming_payment`](crate::LightningNode::get_incoming_payment).
+    LnurlWithdrawCompleted {
+        amount_sat: u64,
+        payment_hash: String,
+    },
+    /// The notification that an outgoing payment failed.
+    /// The `amount_sat` of the payment is provided.
+    ///
+    /// The `payment_hash` can be used to directly open the associated
+    /// [`OutgoingPaymentInfo`](crate::OutgoingPaymentInfo) using
+    /// [`LightningNode::get_outgoing_payment`](crate::LightningNode::get_outgoing_payment).
+    OutgoingPaymentFailed {
+        amount_sat: u64,
+        payment_hash: String,
+    },
+    /// The notification that local data which hadn't been backed up yet was backed up to remote
+    /// storage.
+    LocalDataBackedUp {},
 }
 
 /// A configuration struct used to enable/disable processing of different payloads in [`handle_notification`].
@@ -64,6 +129,10 @@ pub struct NotificationToggles {
     pub payment_received_is_enabled: bool,
     pub address_txs_confirmed_is_enabled: bool,
     pub lnurl_pay_request_is_enabled: bool,
+    pub reverse_swap_status_update_is_enabled: bool,
+    pub payment_failed_is_enabled: bool,
+    pub lnurl_withdraw_completed_is_enabled: bool,
+    pub backup_reminder_is_enabled: bool,
 }
 
 /// Handles a notification.
@@ -71,8 +140,21 @@ pub struct NotificationToggles {
 /// Notifications are used to wake up the node in order to process some request. Currently supported
 /// requests are:
 /// * Receive a payment from a previously issued bolt11 invoice.
-/// * Receive a payment from a confirmed swap.
+/// * Receive a payment from a confirmed swap, or report a swap deposit outside the accepted range.
 /// * Issue an invoice in order to receive an LNURL payment.
+/// * Report a status update of an on-chain payment, claiming its funds if needed.
+/// * Report the failure of an outgoing payment.
+/// * Report the completion of an LNURL-withdraw.
+/// * Back up local data that hasn't been backed up yet.
+///
+/// Every handled notification is recorded and can be listed using
+/// [`Util::list_notification_outcomes`](crate::Util::list_notification_outcomes). Payloads that
+/// identify a single event, i.e. a payment hash or an LNURL request, aren't handled twice: if the
+/// same payload is delivered again within an hour of being handled successfully, the previous
+/// result is returned, and while it's still being handled, the redelivery fails with
+/// [`NotificationHandlingErrorCode::NotificationAlreadyBeingHandled`].
+///
+/// To also get the text to display to the user, use [`handle_notification_localized`].
 ///
 /// Requires network: **yes**
 pub fn handle_notification(
@@ -100,57 +182,231 @@ pub fn handle_notification(
         }
     };
 
-    match payload {
-        Payload::PaymentReceived { .. } => ensure!(
-            notification_toggles.payment_received_is_enabled,
-            runtime_error(
-                NotificationHandlingErrorCode::NotificationDisabledInNotificationToggles,
-                "PaymentReceived notification dismissed due to disabled setting in NotificationToggles"
-            )
-        ),
-        Payload::AddressTxsConfirmed { .. } => ensure!(
-            notification_toggles.address_txs_confirmed_is_enabled,
-            runtime_error(
-                NotificationHandlingErrorCode::NotificationDisabledInNotificationToggles,
-                "AddressTxsConfirmed notification dismissed due to disabled setting in NotificationToggles"
-            )
-        ),
-        Payload::LnurlPayRequest { .. } => ensure!(
-            notification_toggles.lnurl_pay_request_is_enabled,
-            runtime_error(
-                NotificationHandlingErrorCode::NotificationDisabledInNotificationToggles,
-                "LnurlPayRequest notification dismissed due to disabled setting in NotificationToggles"
+    ensure!(
+        payload.is_enabled(&notification_toggles),
+        runtime_error(
+            NotificationHandlingErrorCode::NotificationDisabledInNotificationToggles,
+            format!(
+                "{} notification dismissed due to disabled setting in NotificationToggles",
+                payload.name()
             )
-        ),
-    }
+        )
+    );
 
-    let rt = AsyncRuntime::new()
+    let received_at = SystemTime::now();
+    let payload_hash = hash_payload(&notification_payload);
+    let db_path = format!("{}/{DB_FILENAME}", config.local_persistence_path);
+    let mut data_store = DataStore::new(&db_path)
         .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)?;
+    let payload_kind = payload.name().to_string();
 
-    let (tx, rx) = mpsc::channel();
-    let analytics_interceptor = build_analytics_interceptor(&config, &rt)?;
-    let event_listener = Box::new(NotificationHandlerEventListener::new(
-        tx,
-        analytics_interceptor,
-    ));
-    let sdk = rt
-        .handle()
-        .block_on(start_sdk(&config, event_listener))
+    // Push providers may redeliver notifications, which must not be handled again. A handler
+    // that got killed never completes its log entry, so only entries within this call's timeout
+    // count as in progress.
+    let deduplication = payload.identifies_event().then(|| DeduplicationWindow {
+        handled_since: received_at - DEDUPLICATION_WINDOW,
+        started_since: received_at.checked_sub(timeout).unwrap_or(UNIX_EPOCH),
+    });
+    let log_id = match data_store
+        .start_notification_handling(&payload_hash, &payload_kind, received_at, deduplication)
+        .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)?
+    {
+        NotificationHandlingStart::Started { log_id } => log_id,
+        NotificationHandlingStart::AlreadyHandled(notification) => {
+            info!("Notification was already handled, returning the previous result");
+            let result = Ok(notification);
+            record_duplicate(
+                &mut data_store,
+                payload_hash,
+                payload_kind,
+                received_at,
+                &result,
+            );
+            return result;
+        }
+        NotificationHandlingStart::InProgress => {
+            info!("Notification is already being handled");
+            let result = Err(runtime_error(
+                NotificationHandlingErrorCode::NotificationAlreadyBeingHandled,
+                "The same notification is already being handled",
+            ));
+            record_duplicate(
+                &mut data_store,
+                payload_hash,
+                payload_kind,
+                received_at,
+                &result,
+            );
+            return result;
+        }
+    };
+
+    let result = handle_payload(config, payload, timeout_instant);
+
+    data_store
+        .finish_notification_handling(
+            log_id,
+            &NotificationOutcome {
+                payload_hash,
+                payload_kind,
+                received_at,
+                duration: received_at.elapsed().unwrap_or_default(),
+                is_duplicate: false,
+                notification: result.as_ref().ok().cloned(),
+                error: result.as_ref().err().map(|e| e.to_string()),
+            },
+            received_at - RETENTION_PERIOD,
+        )
+        .log_ignore_error(Level::Warn, "Failed to record notification outcome");
+    result
+}
+
+fn record_duplicate(
+    data_store: &mut DataStore,
+    payload_hash: String,
+    payload_kind: String,
+    received_at: SystemTime,
+    result: &NotificationHandlingResult<Notification>,
+) {
+    data_store
+        .store_notification_outcome(
+            &NotificationOutcome {
+                payload_hash,
+                payload_kind,
+                received_at,
+                duration: received_at.elapsed().unwrap_or_default(),
+                is_duplicate: true,
+                notification: result.as_ref().ok().cloned(),
+                error: result.as_ref().err().map(|e| e.to_string()),
+            },
+            received_at - RETENTION_PERIOD,
+        )
+        .log_ignore_error(Level::Warn, "Failed to record notification outcome");
+}
+
+/// A [`Notification`] accompanied by user-facing text in the requested language.
+pub struct LocalizedNotification {
+    pub notification: Notification,
+    /// The title and body to display for the notification. Not present for notifications that
+    /// aren't meant to be shown to the user, such as [`Notification::LocalDataBackedUp`].
+    pub text: Option<NotificationText>,
+}
+
+/// Handles a notification like [`handle_notification`], additionally providing a title and body
+/// to be displayed to the user.
+///
+/// Parameters:
+/// * `language_iso_639_1` - the language of the text as an ISO 639-1 code. Currently English and
+///   German are supported, other languages fall back to English.
+///
+/// Amounts are presented in sats and in the user's selected fiat currency, using the last known
+/// exchange rate.
+///
+/// Requires network: **yes**
+pub fn handle_notification_localized(
+    config: LightningNodeConfig,
+    notification_payload: String,
+    notification_toggles: NotificationToggles,
+    timeout: Duration,
+    language_iso_639_1: String,
+) -> NotificationHandlingResult<LocalizedNotification> {
+    LanguageCode::from_str(&language_iso_639_1.to_lowercase())
+        .map_to_invalid_input("Invalid language code")?;
+    let language = Language::from_iso_639_1(&language_iso_639_1);
+
+    let db_path = format!("{}/{DB_FILENAME}", config.local_persistence_path);
+    let discard_rates_after = config.exchange_rate_staleness_config.discard_after;
+    let notification =
+        handle_notification(config, notification_payload, notification_toggles, timeout)?;
+
+    let rates = get_display_rates(&db_path, discard_rates_after).unwrap_or_else(|e| {
+        warn!("Failed to get exchange rates for notification text: {e}");
+        DisplayRates::default()
+    });
+    let text = notification_text(&notification, language, |amount_sat| {
+        amount_sat.as_sats().to_amount_down(&rates)
+    });
+
+    Ok(LocalizedNotification { notification, text })
+}
+
+fn get_display_rates(
+    db_path: &str,
+    discard_after: Option<Duration>,
+) -> NotificationHandlingResult<DisplayRates> {
+    let data_store = DataStore::new(db_path)
+        .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)?;
+    let fiat_currency = match data_store
+        .retrieve_last_set_fiat_currency()
+        .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)?
+    {
+        Some(fiat_currency) => fiat_currency,
+        None => return Ok(DisplayRates::default()),
+    };
+    let mut rates = data_store
+        .get_all_exchange_rates()
+        .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)?;
+    if let Some(discard_after) = discard_after {
+        let now = SystemTime::now();
+        rates.retain(|r| !is_older_than(r, discard_after, now));
+    }
+    Ok(DisplayRates::select(&rates, &fiat_currency, &[]))
+}
+
+fn handle_payload(
+    config: LightningNodeConfig,
+    payload: Payload,
+    timeout_instant: Instant,
+) -> NotificationHandlingResult<Notification> {
+    let rt = AsyncRuntime::new()
         .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)?;
 
     match payload {
         Payload::PaymentReceived { payment_hash } => {
+            let (sdk, rx) = start_notification_sdk(&rt, &config)?;
             handle_payment_received_notification(rt, sdk, rx, payment_hash, timeout_instant)
         }
         Payload::AddressTxsConfirmed { address } => {
+            let (sdk, rx) = start_notification_sdk(&rt, &config)?;
             handle_address_txs_confirmed_notification(rt, sdk, rx, address, timeout_instant)
         }
         Payload::LnurlPayRequest { data } => {
+            let (sdk, _) = start_notification_sdk(&rt, &config)?;
             handle_lnurl_pay_request_notification(rt, sdk, config, data)
         }
+        Payload::ReverseSwapStatusUpdate { id, lockup_address } => {
+            let (sdk, _) = start_notification_sdk(&rt, &config)?;
+            handle_reverse_swap_status_update_notification(rt, sdk, id, lockup_address)
+        }
+        Payload::PaymentFailed { payment_hash } => {
+            let (sdk, rx) = start_notification_sdk(&rt, &config)?;
+            handle_payment_failed_notification(rt, sdk, rx, payment_hash, timeout_instant)
+        }
+        Payload::LnurlWithdrawCompleted { payment_hash } => {
+            let (sdk, rx) = start_notification_sdk(&rt, &config)?;
+            handle_lnurl_withdraw_completed_notification(rt, sdk, rx, payment_hash, timeout_instant)
+        }
+        Payload::BackupReminder => handle_backup_reminder_notification(rt, config),
     }
 }
 
+fn start_notification_sdk(
+    rt: &AsyncRuntime,
+    config: &LightningNodeConfig,
+) -> NotificationHandlingResult<(Arc<BreezServices>, Receiver<BreezEvent>)> {
+    let (tx, rx) = mpsc::channel();
+    let analytics_interceptor = build_analytics_interceptor(config, rt)?;
+    let event_listener = Box::new(NotificationHandlerEventListener::new(
+        tx,
+        analytics_interceptor,
+    ));
+    let sdk = rt
+        .handle()
+        .block_on(start_sdk(config, event_listener))
+        .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)?;
+    Ok((sdk, rx))
+}
+
 fn build_analytics_interceptor(
     config: &LightningNodeConfig,
     rt: &AsyncRuntime,
@@ -168,6 +424,7 @@ fn build_analytics_interceptor(
     let user_preferences = Arc::new(Mutex::new(UserPreferences {
         fiat_currency,
         timezone_config: config.timezone_config.clone(),
+        secondary_fiat_currencies: Vec::new(),
     }));
 
     let strong_typed_seed = get_strong_typed_seed(config)?;
@@ -228,6 +485,10 @@ fn handle_swap_notification(
         )
     );
 
+    if let Some(notification) = to_out_of_range_notification(&in_progress_swap) {
+        return Ok(notification);
+    }
+
     rt.handle()
         .block_on(sdk.redeem_swap(address.clone()))
         .map_to_runtime_error(
@@ -248,16 +509,109 @@ fn handle_reverse_swap_notification(
     sdk: Arc<BreezServices>,
     address: String,
 ) -> NotificationHandlingResult<Notification> {
-    debug!("Trying to claim reverse swap with lock address: {address}");
+    claim_reverse_swap(&rt, &sdk, address)?;
+
+    // Only a single reverse swap can be in progress at a time. Looking it up is best effort, the
+    // funds have been claimed at this point.
+    let reverse_swap = find_reverse_swap(&rt, &sdk, |r| {
+        matches!(
+            r.status,
+            ReverseSwapStatus::Initial
+                | ReverseSwapStatus::InProgress
+                | ReverseSwapStatus::CompletedSeen
+        )
+    })
+    .unwrap_or_else(|e| {
+        warn!("Failed to look up the claimed on-chain payment: {e}");
+        None
+    });
+
+    Ok(Notification::OnchainPaymentSwappedOut {
+        amount_sat: reverse_swap.as_ref().map(|(_, r)| r.onchain_amount_sat),
+        payment_hash: reverse_swap.map(|(payment_hash, _)| payment_hash),
+    })
+}
+
+fn handle_reverse_swap_status_update_notification(
+    rt: AsyncRuntime,
+    sdk: Arc<BreezServices>,
+    id: String,
+    lockup_address: Option<String>,
+) -> NotificationHandlingResult<Notification> {
+    let (payment_hash, reverse_swap) =
+        find_reverse_swap(&rt, &sdk, |r| r.id == id)?.ok_or_else(|| {
+            runtime_error(
+                NotificationHandlingErrorCode::InProgressSwapNotFound,
+                format!("Received a status update for unknown reverse swap {id}"),
+            )
+        })?;
+
+    // The funds can be claimed once the swap provider has locked them up
+    if let Some(lockup_address) = lockup_address {
+        if reverse_swap.status == ReverseSwapStatus::InProgress {
+            claim_reverse_swap(&rt, &sdk, lockup_address)?;
+            return Ok(Notification::OnchainPaymentSwappedOut {
+                amount_sat: Some(reverse_swap.onchain_amount_sat),
+                payment_hash: Some(payment_hash),
+            });
+        }
+    }
+
+    Ok(Notification::OnchainPaymentSwapOutUpdated {
+        amount_sat: reverse_swap.onchain_amount_sat,
+        payment_hash,
+        status: reverse_swap.status,
+    })
+}
+
+fn claim_reverse_swap(
+    rt: &AsyncRuntime,
+    sdk: &Arc<BreezServices>,
+    lockup_address: String,
+) -> NotificationHandlingResult<()> {
+    debug!("Trying to claim reverse swap with lock address: {lockup_address}");
 
     rt.handle()
-        .block_on(sdk.claim_reverse_swap(address))
+        .block_on(sdk.claim_reverse_swap(lockup_address))
         .map_to_runtime_error(
             NotificationHandlingErrorCode::NodeUnavailable,
             "Failed to claim reverse swap",
-        )?;
+        )
+}
+
+/// Finds the reverse swap matching the predicate, returning it along with the payment hash of the
+/// associated outgoing payment.
+fn find_reverse_swap<P>(
+    rt: &AsyncRuntime,
+    sdk: &Arc<BreezServices>,
+    predicate: P,
+) -> NotificationHandlingResult<Option<(String, ReverseSwapInfo)>>
+where
+    P: Fn(&ReverseSwapInfo) -> bool,
+{
+    let payments = rt
+        .handle()
+        .block_on(sdk.list_payments(ListPaymentsRequest {
+            filters: Some(vec![PaymentTypeFilter::Sent]),
+            metadata_filters: None,
+            from_timestamp: None,
+            to_timestamp: None,
+            include_failures: Some(false),
+            limit: None,
+            offset: None,
+        }))
+        .map_to_runtime_error(RuntimeErrorCode::NodeUnavailable, "Failed to list payments")
+        .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)?;
 
-    Ok(Notification::OnchainPaymentSwappedOut {})
+    Ok(payments
+        .into_iter()
+        .find_map(|payment| match payment.details {
+            PaymentDetails::Ln { data } => data
+                .reverse_swap_info
+                .filter(&predicate)
+                .map(|reverse_swap| (data.payment_hash, reverse_swap)),
+            _ => None,
+        }))
 }
 
 fn handle_address_txs_confirmed_notification(
@@ -286,9 +640,32 @@ fn handle_address_txs_confirmed_notification(
         );
     }
 
+    if let Some(notification) = rt
+        .handle()
+        .block_on(sdk.list_refundables())
+        .map_to_runtime_error(
+            RuntimeErrorCode::NodeUnavailable,
+            "Failed to list refundable swaps",
+        )
+        .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)?
+        .iter()
+        .filter(|s| s.bitcoin_address == address)
+        .find_map(to_out_of_range_notification)
+    {
+        return Ok(notification);
+    }
+
     handle_reverse_swap_notification(rt, sdk, address)
 }
 
+fn to_out_of_range_notification(swap: &SwapInfo) -> Option<Notification> {
+    deposit_issue(swap).map(|issue| Notification::OnchainDepositOutOfRange {
+        address: swap.bitcoin_address.clone(),
+        amount_sat: swap.confirmed_sats + swap.unconfirmed_sats,
+        issue,
+    })
+}
+
 fn handle_lnurl_pay_request_notification(
     rt: AsyncRuntime,
     sdk: Arc<BreezServices>,
@@ -403,6 +780,7 @@ fn handle_lnurl_pay_request_notification(
     let user_preferences = UserPreferences {
         fiat_currency,
         timezone_config: config.timezone_config.clone(),
+        secondary_fiat_currencies: Vec::new(),
     };
     let exchange_rate_provider = ExchangeRateProviderImpl::new(
         config.remote_services_config.backend_url.clone(),
@@ -449,6 +827,99 @@ fn handle_lnurl_pay_request_notification(
     })
 }
 
+fn handle_lnurl_withdraw_completed_notification(
+    rt: AsyncRuntime,
+    sdk: Arc<BreezServices>,
+    event_receiver: Receiver<BreezEvent>,
+    payment_hash: String,
+    timeout_instant: Instant,
+) -> NotificationHandlingResult<Notification> {
+    let payment = wait_for_payment(rt, sdk, event_receiver, &payment_hash, timeout_instant)?;
+    Ok(Notification::LnurlWithdrawCompleted {
+        amount_sat: payment.amount_msat / 1000,
+        payment_hash,
+    })
+}
+
+fn handle_payment_failed_notification(
+    rt: AsyncRuntime,
+    sdk: Arc<BreezServices>,
+    event_receiver: Receiver<BreezEvent>,
+    payment_hash: String,
+    timeout_instant: Instant,
+) -> NotificationHandlingResult<Notification> {
+    while Instant::now() < timeout_instant {
+        debug!("Checking for the failed payment...");
+        match get_payment(&rt, &sdk, &payment_hash)? {
+            Some(payment) if payment.status == PaymentStatus::Failed => {
+                debug!("Checking for the failed payment... Found");
+                return Ok(Notification::OutgoingPaymentFailed {
+                    amount_sat: payment.amount_msat / 1000,
+                    payment_hash,
+                });
+            }
+            Some(payment) if payment.status == PaymentStatus::Complete => {
+                runtime_error!(
+                    NotificationHandlingErrorCode::ExpectedPaymentFailureNotObserved,
+                    "Expected outgoing payment with hash {payment_hash} to fail but it succeeded"
+                )
+            }
+            _ => debug!("Checking for the failed payment... None"),
+        }
+
+        // Wait for the SDK to process payment updates before checking again
+        match event_receiver.recv_timeout(Duration::from_secs(2)) {
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                permanent_failure!("The SDK stopped running unexpectedly");
+            }
+        }
+    }
+
+    runtime_error!(
+        NotificationHandlingErrorCode::ExpectedPaymentFailureNotObserved,
+        "Expected outgoing payment with hash {payment_hash} to fail but no failure was observed"
+    )
+}
+
+fn handle_backup_reminder_notification(
+    rt: AsyncRuntime,
+    config: LightningNodeConfig,
+) -> NotificationHandlingResult<Notification> {
+    let db_path = format!("{}/{DB_FILENAME}", config.local_persistence_path);
+    DataStore::new(&db_path)
+        .and_then(|mut data_store| data_store.backup_db())
+        .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)?;
+
+    let strong_typed_seed = get_strong_typed_seed(&config)?;
+    let async_auth = build_async_auth(
+        &strong_typed_seed,
+        &config.remote_services_config.backend_url,
+    )
+    .map_to_runtime_error(
+        NotificationHandlingErrorCode::LipaServiceUnavailable,
+        "Failed to authenticate against backend",
+    )?;
+    let persistence_encryption_key = derive_persistence_encryption_key(&strong_typed_seed)
+        .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)?;
+    let backup_manager = BackupManager::new(
+        RemoteBackupClient::new(
+            config.remote_services_config.backend_url.clone(),
+            Arc::new(async_auth),
+        ),
+        db_path,
+        persistence_encryption_key,
+    );
+    rt.handle()
+        .block_on(backup_manager.backup())
+        .map_to_runtime_error(
+            NotificationHandlingErrorCode::LipaServiceUnavailable,
+            "Failed to back up local data to remote storage",
+        )?;
+
+    Ok(Notification::LocalDataBackedUp {})
+}
+
 fn report_insuficcient_inbound_liquidity(
     rt: AsyncRuntime,
     backend_url: &str,
@@ -469,25 +940,26 @@ fn report_insuficcient_inbound_liquidity(
         .map_runtime_error_to(NotificationHandlingErrorCode::LipaServiceUnavailable)
 }
 
-fn get_confirmed_payment(
+fn get_payment(
     rt: &AsyncRuntime,
     sdk: &Arc<BreezServices>,
     payment_hash: &str,
 ) -> NotificationHandlingResult<Option<Payment>> {
-    let payment = rt
-        .handle()
+    rt.handle()
         .block_on(sdk.payment_by_hash(payment_hash.to_string()))
         .map_to_runtime_error(
             RuntimeErrorCode::NodeUnavailable,
             "Failed to get payment by hash",
         )
-        .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)?;
-    if let Some(payment) = payment {
-        if payment.status == PaymentStatus::Complete {
-            return Ok(Some(payment));
-        }
-    }
-    Ok(None)
+        .map_runtime_error_using(NotificationHandlingErrorCode::from_runtime_error)
+}
+
+fn get_confirmed_payment(
+    rt: &AsyncRuntime,
+    sdk: &Arc<BreezServices>,
+    payment_hash: &str,
+) -> NotificationHandlingResult<Option<Payment>> {
+    Ok(get_payment(rt, sdk, payment_hash)?.filter(|p| p.status == PaymentStatus::Complete))
 }
 
 fn wait_for_payment(
@@ -571,6 +1043,58 @@ enum Payload {
         #[serde(flatten)]
         data: LnurlPayRequestData,
     },
+    ReverseSwapStatusUpdate {
+        id: String,
+        lockup_address: Option<String>,
+    },
+    PaymentFailed {
+        payment_hash: String,
+    },
+    LnurlWithdrawCompleted {
+        payment_hash: String,
+    },
+    BackupReminder,
+}
+
+impl Payload {
+    fn name(&self) -> &'static str {
+        match self {
+            Payload::PaymentReceived { .. } => "PaymentReceived",
+            Payload::AddressTxsConfirmed { .. } => "AddressTxsConfirmed",
+            Payload::LnurlPayRequest { .. } => "LnurlPayRequest",
+            Payload::ReverseSwapStatusUpdate { .. } => "ReverseSwapStatusUpdate",
+            Payload::PaymentFailed { .. } => "PaymentFailed",
+            Payload::LnurlWithdrawCompleted { .. } => "LnurlWithdrawCompleted",
+            Payload::BackupReminder => "BackupReminder",
+        }
+    }
+
+    /// Whether the payload identifies a single event, so redeliveries can be recognized by the
+    /// payload alone. Other payloads may legitimately repeat, e.g. for a further transaction to
+    /// the same address.
+    fn identifies_event(&self) -> bool {
+        matches!(
+            self,
+            Payload::PaymentReceived { .. }
+                | Payload::LnurlPayRequest { .. }
+                | Payload::PaymentFailed { .. }
+                | Payload::LnurlWithdrawCompleted { .. }
+        )
+    }
+
+    fn is_enabled(&self, toggles: &NotificationToggles) -> bool {
+        match self {
+            Payload::PaymentReceived { .. } => toggles.payment_received_is_enabled,
+            Payload::AddressTxsConfirmed { .. } => toggles.address_txs_confirmed_is_enabled,
+            Payload::LnurlPayRequest { .. } => toggles.lnurl_pay_request_is_enabled,
+            Payload::ReverseSwapStatusUpdate { .. } => {
+                toggles.reverse_swap_status_update_is_enabled
+            }
+            Payload::PaymentFailed { .. } => toggles.payment_failed_is_enabled,
+            Payload::LnurlWithdrawCompleted { .. } => toggles.lnurl_withdraw_completed_is_enabled,
+            Payload::BackupReminder => toggles.backup_reminder_is_enabled,
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -640,6 +1164,32 @@ mod tests {
                                                  }
                                                 }"#;
 
+    const REVERSE_SWAP_STATUS_UPDATE_PAYLOAD_JSON: &str = r#"{
+                                                 "template": "reverse_swap_status_update",
+                                                 "data": {
+                                                  "id": "id",
+                                                  "lockup_address": "address"
+                                                 }
+                                                }"#;
+
+    const PAYMENT_FAILED_PAYLOAD_JSON: &str = r#"{
+                                                 "template": "payment_failed",
+                                                 "data": {
+                                                  "payment_hash": "hash"
+                                                 }
+                                                }"#;
+
+    const LNURL_WITHDRAW_COMPLETED_PAYLOAD_JSON: &str = r#"{
+                                                 "template": "lnurl_withdraw_completed",
+                                                 "data": {
+                                                  "payment_hash": "hash"
+                                                 }
+                                                }"#;
+
+    const BACKUP_REMINDER_PAYLOAD_JSON: &str = r#"{
+                                                 "template": "backup_reminder"
+                                                }"#;
+
     #[test]
     fn test_payload_deserialize() {
         let payment_received_payload: Payload =
@@ -677,5 +1227,37 @@ mod tests {
                 data
             } if data.amount_msat == 12345 && data.recipient == "recipient" && data.payer_comment.is_none() && data.id == "id"
         ));
+
+        let reverse_swap_status_update_payload: Payload =
+            serde_json::from_str(REVERSE_SWAP_STATUS_UPDATE_PAYLOAD_JSON).unwrap();
+        assert!(matches!(
+            reverse_swap_status_update_payload,
+            Payload::ReverseSwapStatusUpdate {
+                id,
+                lockup_address
+            } if id == "id" && lockup_address == Some("address".to_string())
+        ));
+
+        let payment_failed_payload: Payload =
+            serde_json::from_str(PAYMENT_FAILED_PAYLOAD_JSON).unwrap();
+        assert!(matches!(
+            payment_failed_payload,
+            Payload::PaymentFailed {
+                payment_hash
+            } if payment_hash == "hash"
+        ));
+
+        let lnurl_withdraw_completed_payload: Payload =
+            serde_json::from_str(LNURL_WITHDRAW_COMPLETED_PAYLOAD_JSON).unwrap();
+        assert!(matches!(
+            lnurl_withdraw_completed_payload,
+            Payload::LnurlWithdrawCompleted {
+                payment_hash
+            } if payment_hash == "hash"
+        ));
+
+        let backup_reminder_payload: Payload =
+            serde_json::from_str(BACKUP_REMINDER_PAYLOAD_JSON).unwrap();
+        assert!(matches!(backup_reminder_payload, Payload::BackupReminder));
     }
 }
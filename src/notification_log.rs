@@ -0,0 +1,57 @@
+use crate::notification_handling::Notification;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime};
+
+/// How long the result of a handled notification is reused for redeliveries of the same payload.
+pub(crate) const DEDUPLICATION_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// How long handled notifications are kept in the log.
+pub(crate) const RETENTION_PERIOD: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The outcome of handling a notification with [`handle_notification`](crate::handle_notification),
+/// as listed by [`Util::list_notification_outcomes`](crate::Util::list_notification_outcomes).
+#[derive(Debug, PartialEq, Clone)]
+pub struct NotificationOutcome {
+    /// Hex encoded SHA-256 hash of the payload, used to recognize redeliveries.
+    pub payload_hash: String,
+    /// The kind of payload, e.g. `PaymentReceived`.
+    pub payload_kind: String,
+    pub received_at: SystemTime,
+    /// How long handling the notification took.
+    pub duration: Duration,
+    /// Whether the payload was a redelivery of an already handled notification, in which case
+    /// the previous result was returned without handling it again.
+    pub is_duplicate: bool,
+    /// The returned notification. Only present if handling the notification succeeded.
+    pub notification: Option<Notification>,
+    /// The error handling the notification failed with, if any. Neither `notification` nor
+    /// `error` are present while the notification is still being handled.
+    pub error: Option<String>,
+}
+
+/// Hashes a notification payload. Equivalent JSON payloads that only differ in formatting result in
+/// the same hash.
+pub(crate) fn hash_payload(payload: &str) -> String {
+    let normalized = serde_json::from_str::<serde_json::Value>(payload)
+        .map(|v| v.to_string())
+        .unwrap_or_else(|_| payload.to_string());
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_payload() {
+        let payload = r#"{"template": "payment_received", "data": {"payment_hash": "hash"}}"#;
+        let reformatted = r#"{
+            "template": "payment_received",
+            "data": { "payment_hash": "hash" }
+        }"#;
+        let other = r#"{"template": "payment_received", "data": {"payment_hash": "other"}}"#;
+
+        assert_eq!(hash_payload(payload), hash_payload(reformatted));
+        assert_ne!(hash_payload(payload), hash_payload(other));
+        assert_eq!(hash_payload(payload).len(), 64);
+    }
+}
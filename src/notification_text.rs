@@ -0,0 +1,295 @@
+use crate::amount::{Amount, FiatValue};
+use crate::notification_handling::Notification;
+use crate::SwapDepositIssue;
+
+/// User-facing text describing a [`Notification`], e.g. to be displayed as a push notification.
+#[derive(Debug, PartialEq, Clone)]
+pub struct NotificationText {
+    pub title: String,
+    pub body: String,
+}
+
+/// The languages notification texts are available in.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Language {
+    English,
+    German,
+}
+
+impl Language {
+    /// Maps an ISO 639-1 language code to a supported language, falling back to English.
+    pub fn from_iso_639_1(code: &str) -> Self {
+        match code.to_lowercase().as_str() {
+            "de" => Language::German,
+            _ => Language::English,
+        }
+    }
+
+    fn catalog(&self) -> &'static Catalog {
+        match self {
+            Language::English => &ENGLISH,
+            Language::German => &GERMAN,
+        }
+    }
+}
+
+/// The texts and number formatting of a language. Bodies contain an `{amount}` placeholder.
+struct Catalog {
+    thousands_separator: char,
+    decimal_separator: char,
+    currency_code_first: bool,
+    payment_received_title: &'static str,
+    payment_received_body: &'static str,
+    swapped_in_title: &'static str,
+    swapped_in_body: &'static str,
+    swapped_out_title: &'static str,
+    swapped_out_body: &'static str,
+    swapped_out_body_without_amount: &'static str,
+    deposit_out_of_range_title: &'static str,
+    deposit_below_minimum_body: &'static str,
+    deposit_above_maximum_body: &'static str,
+    lnurl_invoice_created_title: &'static str,
+    lnurl_invoice_created_body: &'static str,
+    lnurl_withdraw_completed_title: &'static str,
+    lnurl_withdraw_completed_body: &'static str,
+    payment_failed_title: &'static str,
+    payment_failed_body: &'static str,
+}
+
+const ENGLISH: Catalog = Catalog {
+    thousands_separator: ',',
+    decimal_separator: '.',
+    currency_code_first: true,
+    payment_received_title: "Payment received",
+    payment_received_body: "You received {amount}.",
+    swapped_in_title: "On-chain payment received",
+    swapped_in_body: "You received {amount} from an on-chain payment.",
+    swapped_out_title: "On-chain payment sent",
+    swapped_out_body: "Your on-chain payment of {amount} was sent.",
+    swapped_out_body_without_amount: "Your on-chain payment was sent.",
+    deposit_out_of_range_title: "On-chain deposit can't be received",
+    deposit_below_minimum_body:
+        "Your deposit of {amount} is below the minimum. Open the app to recover the funds.",
+    deposit_above_maximum_body:
+        "Your deposit of {amount} is above the maximum. Open the app to recover the funds.",
+    lnurl_invoice_created_title: "Incoming payment",
+    lnurl_invoice_created_body: "A payment of {amount} is on its way.",
+    lnurl_withdraw_completed_title: "Withdrawal received",
+    lnurl_withdraw_completed_body: "You received {amount} from a withdrawal.",
+    payment_failed_title: "Payment failed",
+    payment_failed_body: "Your payment of {amount} could not be completed.",
+};
+
+const GERMAN: Catalog = Catalog {
+    thousands_separator: '.',
+    decimal_separator: ',',
+    currency_code_first: false,
+    payment_received_title: "Zahlung erhalten",
+    payment_received_body: "Du hast {amount} erhalten.",
+    swapped_in_title: "On-Chain-Zahlung erhalten",
+    swapped_in_body: "Du hast {amount} aus einer On-Chain-Zahlung erhalten.",
+    swapped_out_title: "On-Chain-Zahlung gesendet",
+    swapped_out_body: "Deine On-Chain-Zahlung über {amount} wurde gesendet.",
+    swapped_out_body_without_amount: "Deine On-Chain-Zahlung wurde gesendet.",
+    deposit_out_of_range_title: "On-Chain-Einzahlung kann nicht empfangen werden",
+    deposit_below_minimum_body: "Deine Einzahlung von {amount} liegt unter dem Minimum. Öffne die App, um das Guthaben zurückzuholen.",
+    deposit_above_maximum_body: "Deine Einzahlung von {amount} liegt über dem Maximum. Öffne die App, um das Guthaben zurückzuholen.",
+    lnurl_invoice_created_title: "Eingehende Zahlung",
+    lnurl_invoice_created_body: "Eine Zahlung über {amount} ist unterwegs.",
+    lnurl_withdraw_completed_title: "Auszahlung erhalten",
+    lnurl_withdraw_completed_body: "Du hast {amount} aus einer Auszahlung erhalten.",
+    payment_failed_title: "Zahlung fehlgeschlagen",
+    payment_failed_body: "Deine Zahlung über {amount} konnte nicht abgeschlossen werden.",
+};
+
+/// Builds the text of a notification. `to_amount` provides the fiat value of a sat amount, if known.
+///
+/// Notifications that aren't meant to be shown to the user have no text.
+pub(crate) fn notification_text(
+    notification: &Notification,
+    language: Language,
+    to_amount: impl Fn(u64) -> Amount,
+) -> Option<NotificationText> {
+    let catalog = language.catalog();
+    let (title, body, amount_sat) = match notification {
+        Notification::Bolt11PaymentReceived { amount_sat, .. } => (
+            catalog.payment_received_title,
+            catalog.payment_received_body,
+            amount_sat,
+        ),
+        Notification::OnchainPaymentSwappedIn { amount_sat, .. } => (
+            catalog.swapped_in_title,
+            catalog.swapped_in_body,
+            amount_sat,
+        ),
+        Notification::OnchainPaymentSwappedOut {
+            amount_sat: Some(amount_sat),
+            ..
+        } => (
+            catalog.swapped_out_title,
+            catalog.swapped_out_body,
+            amount_sat,
+        ),
+        Notification::OnchainPaymentSwappedOut {
+            amount_sat: None, ..
+        } => {
+            return Some(NotificationText {
+                title: catalog.swapped_out_title.to_string(),
+                body: catalog.swapped_out_body_without_amount.to_string(),
+            })
+        }
+        Notification::OnchainDepositOutOfRange {
+            amount_sat, issue, ..
+        } => {
+            let body = match issue {
+                SwapDepositIssue::BelowMinimum => catalog.deposit_below_minimum_body,
+                SwapDepositIssue::AboveMaximum => catalog.deposit_above_maximum_body,
+            };
+            (catalog.deposit_out_of_range_title, body, amount_sat)
+        }
+        Notification::LnurlInvoiceCreated { amount_sat } => (
+            catalog.lnurl_invoice_created_title,
+            catalog.lnurl_invoice_created_body,
+            amount_sat,
+        ),
+        Notification::LnurlWithdrawCompleted { amount_sat, .. } => (
+            catalog.lnurl_withdraw_completed_title,
+            catalog.lnurl_withdraw_completed_body,
+            amount_sat,
+        ),
+        Notification::OutgoingPaymentFailed { amount_sat, .. } => (
+            catalog.payment_failed_title,
+            catalog.payment_failed_body,
+            amount_sat,
+        ),
+        Notification::OnchainPaymentSwapOutUpdated { .. } | Notification::LocalDataBackedUp {} => {
+            return None
+        }
+    };
+    let amount = format_amount(&to_amount(*amount_sat), catalog);
+    Some(NotificationText {
+        title: title.to_string(),
+        body: body.replace("{amount}", &amount),
+    })
+}
+
+/// Formats an amount as its fiat value followed by the sats, or only the sats if no fiat value
+/// is known, e.g. `CHF 5.70 (10,000 sats)`.
+fn format_amount(amount: &Amount, catalog: &Catalog) -> String {
+    let sats = format!(
+        "{} sats",
+        group_thousands(amount.sats, catalog.thousands_separator)
+    );
+    match &amount.fiat {
+        Some(fiat) => format!("{} ({sats})", format_fiat(fiat, catalog)),
+        None => sats,
+    }
+}
+
+fn format_fiat(fiat: &FiatValue, catalog: &Catalog) -> String {
+    let value = format!(
+        "{}{}{:02}",
+        group_thousands(fiat.minor_units / 100, catalog.thousands_separator),
+        catalog.decimal_separator,
+        fiat.minor_units % 100
+    );
+    if catalog.currency_code_first {
+        format!("{} {value}", fiat.currency_code)
+    } else {
+        format!("{value} {}", fiat.currency_code)
+    }
+}
+
+fn group_thousands(number: u64, separator: char) -> String {
+    let digits = number.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::{AsSats, DisplayRates, ToAmount};
+    use crate::ExchangeRate;
+    use std::time::SystemTime;
+
+    fn chf_rates() -> DisplayRates {
+        DisplayRates::from(Some(ExchangeRate {
+            currency_code: "CHF".to_string(),
+            rate: 1754, // 1 sat = 0.057 CHF cents
+            updated_at: SystemTime::now(),
+            source: "lipa".to_string(),
+        }))
+    }
+
+    #[test]
+    fn test_group_thousands() {
+        assert_eq!(group_thousands(0, ','), "0");
+        assert_eq!(group_thousands(999, ','), "999");
+        assert_eq!(group_thousands(1000, ','), "1,000");
+        assert_eq!(group_thousands(1234567, '.'), "1.234.567");
+    }
+
+    #[test]
+    fn test_notification_text() {
+        let notification = Notification::Bolt11PaymentReceived {
+            amount_sat: 10_000,
+            payment_hash: "hash".to_string(),
+        };
+
+        let text = notification_text(&notification, Language::English, |sats| {
+            sats.as_sats().to_amount_down(&chf_rates())
+        })
+        .unwrap();
+        assert_eq!(text.title, "Payment received");
+        assert_eq!(text.body, "You received CHF 5.70 (10,000 sats).");
+
+        let text = notification_text(&notification, Language::German, |sats| {
+            sats.as_sats().to_amount_down(&chf_rates())
+        })
+        .unwrap();
+        assert_eq!(text.title, "Zahlung erhalten");
+        assert_eq!(text.body, "Du hast 5,70 CHF (10.000 sats) erhalten.");
+
+        let text = notification_text(
+            &Notification::LnurlInvoiceCreated { amount_sat: 1_500 },
+            Language::English,
+            |sats| sats.as_sats().to_amount_down(&DisplayRates::default()),
+        )
+        .unwrap();
+        assert_eq!(text.body, "A payment of 1,500 sats is on its way.");
+
+        let text = notification_text(
+            &Notification::OnchainPaymentSwappedOut {
+                amount_sat: None,
+                payment_hash: None,
+            },
+            Language::English,
+            |sats| sats.as_sats().to_amount_down(&DisplayRates::default()),
+        )
+        .unwrap();
+        assert_eq!(text.body, "Your on-chain payment was sent.");
+
+        assert_eq!(
+            notification_text(
+                &Notification::LocalDataBackedUp {},
+                Language::German,
+                |sats| { sats.as_sats().to_amount_down(&DisplayRates::default()) }
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_language_fallback() {
+        assert_eq!(Language::from_iso_639_1("DE"), Language::German);
+        assert_eq!(Language::from_iso_639_1("en"), Language::English);
+        assert_eq!(Language::from_iso_639_1("fr"), Language::English);
+    }
+}
@@ -1,5 +1,5 @@
-use crate::amount::{Amount, AsSats, ToAmount};
-use crate::exchange_rate_provider::ExchangeRate;
+use crate::amount::{Amount, AsSats, DisplayRates, ToAmount};
+use crate::exchange_rate_provider::{ExchangeRate, POCKET_EXCHANGE_RATE_SOURCE};
 use crate::PocketOfferError;
 
 use crow::{TopupInfo, TopupStatus};
@@ -57,11 +57,12 @@ pub struct OfferInfo {
 }
 
 impl OfferInfo {
-    pub(crate) fn from(topup_info: TopupInfo, current_rate: &Option<ExchangeRate>) -> OfferInfo {
+    pub(crate) fn from(topup_info: TopupInfo, current_rate: &DisplayRates) -> OfferInfo {
         let exchange_rate = ExchangeRate {
             currency_code: topup_info.exchange_rate.currency_code,
             rate: topup_info.exchange_rate.sats_per_unit,
             updated_at: topup_info.exchange_rate.updated_at,
+            source: POCKET_EXCHANGE_RATE_SOURCE.to_string(),
         };
 
         let status = match topup_info.status {
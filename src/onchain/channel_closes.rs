@@ -1,7 +1,10 @@
 use crate::amount::{AsSats, Sats, ToAmount};
 use crate::errors::Result;
 use crate::onchain::swap::Swap;
-use crate::onchain::{get_onchain_resolving_fees, query_onchain_fee_rate};
+use crate::onchain::{
+    get_onchain_resolving_fees, query_onchain_fee_rates, record_onchain_spend, FeePriority,
+    OnchainFeeRates,
+};
 use crate::support::Support;
 use crate::{Amount, OnchainResolvingFees, RuntimeErrorCode, SweepInfo, CLN_DUST_LIMIT_SAT};
 use breez_sdk_core::error::RedeemOnchainError;
@@ -25,10 +28,16 @@ impl ChannelClose {
     ///
     /// Must only be called when there are onchain funds to resolve.
     ///
+    /// Parameters:
+    /// * `fee_priority` - the priority of the resolving tx, determining its fee rate
+    ///
     /// Returns the fee information for the available resolving options.
     ///
     /// Requires network: **yes**
-    pub fn determine_resolving_fees(&self) -> Result<Option<OnchainResolvingFees>> {
+    pub fn determine_resolving_fees(
+        &self,
+        fee_priority: FeePriority,
+    ) -> Result<Option<OnchainResolvingFees>> {
         let onchain_balance = self
             .support
             .sdk
@@ -44,15 +53,22 @@ impl ChannelClose {
             invalid_input("No on-chain funds to resolve")
         );
 
+        let fee_rates = query_onchain_fee_rates(&self.support)?;
+        let sat_per_vbyte = fee_rates.sat_per_vbyte(fee_priority)?;
+        let sweep_fee_rates = fee_rates.clone();
         let prepare_onchain_tx = move |address: String| -> Result<(Sats, Sats, u32)> {
             let sweep_info = self
-                .prepare_sweep(BitcoinAddressData {
-                    address,
-                    network: Network::Bitcoin,
-                    amount_sat: None,
-                    label: None,
-                    message: None,
-                })
+                .prepare_sweep_with_fee_rate(
+                    BitcoinAddressData {
+                        address,
+                        network: Network::Bitcoin,
+                        amount_sat: None,
+                        label: None,
+                        message: None,
+                    },
+                    sat_per_vbyte,
+                    sweep_fee_rates,
+                )
                 .map_to_runtime_error(
                     RuntimeErrorCode::NodeUnavailable,
                     "Failed to prepare sweep funds from channel closes",
@@ -69,6 +85,7 @@ impl ChannelClose {
             &self.support,
             &self.swap,
             onchain_balance,
+            fee_rates,
             prepare_onchain_tx,
         )
     }
@@ -78,19 +95,33 @@ impl ChannelClose {
     /// Parameters:
     /// * `destination` - the destination address to which funds will be sent.
     ///     Can be obtained using [`Util::decode_data`](crate::Util::decode_data)
+    /// * `fee_priority` - the priority of the sweep tx, determining its fee rate
     ///
     /// Returns information on the prepared sweep, including the exact fee that results from
-    /// using the provided fee rate. The method [`ChannelClose::sweep`] can be used to broadcast
-    /// the sweep transaction.
+    /// using the fee rate of the provided priority. The method [`ChannelClose::sweep`] can be
+    /// used to broadcast the sweep transaction.
     ///
     /// Requires network: **yes**
     pub fn prepare_sweep(
         &self,
         destination: BitcoinAddressData,
+        fee_priority: FeePriority,
     ) -> std::result::Result<SweepChannelCloseInfo, RedeemOnchainError> {
-        let address = destination.address;
-        let onchain_fee_rate = query_onchain_fee_rate(&self.support)
+        let fee_rates = query_onchain_fee_rates(&self.support)
             .map_err(|e| RedeemOnchainError::ServiceConnectivity { err: e.to_string() })?;
+        let onchain_fee_rate = fee_rates
+            .sat_per_vbyte(fee_priority)
+            .map_err(|e| RedeemOnchainError::Generic { err: e.to_string() })?;
+        self.prepare_sweep_with_fee_rate(destination, onchain_fee_rate, fee_rates)
+    }
+
+    fn prepare_sweep_with_fee_rate(
+        &self,
+        destination: BitcoinAddressData,
+        onchain_fee_rate: u32,
+        fee_rates: OnchainFeeRates,
+    ) -> std::result::Result<SweepChannelCloseInfo, RedeemOnchainError> {
+        let address = destination.address;
         let res =
             self.support
                 .rt
@@ -111,10 +142,10 @@ impl ChannelClose {
             })?
             .onchain_balance_msat
             .as_msats()
-            .to_amount_down(&None)
+            .sats_round_down()
             .sats;
 
-        let rate = self.support.get_exchange_rate();
+        let rate = self.support.get_display_rates();
 
         // Add the amount that won't be possible to be swept due to CLN's min-emergency limit (546 sats)
         // TODO: remove CLN_DUST_LIMIT_SAT addition if/when
@@ -141,6 +172,7 @@ impl ChannelClose {
             amount: (onchain_balance_sat - res.tx_fee_sat)
                 .as_sats()
                 .to_amount_up(&rate),
+            fee_rates,
         })
     }
 
@@ -154,6 +186,24 @@ impl ChannelClose {
     ///
     /// Requires network: **yes**
     pub fn sweep(&self, sweep_info: SweepChannelCloseInfo) -> Result<String> {
+        self.sweep_to_address(sweep_info.address, sweep_info.onchain_fee_rate)
+    }
+
+    pub(crate) fn sweep_to_address(
+        &self,
+        to_address: String,
+        sat_per_vbyte: u32,
+    ) -> Result<String> {
+        let onchain_balance = self
+            .support
+            .sdk
+            .node_info()
+            .map_to_runtime_error(
+                RuntimeErrorCode::NodeUnavailable,
+                "Failed to read node info",
+            )?
+            .onchain_balance_msat
+            .as_msats();
         let txid = self
             .support
             .rt
@@ -162,13 +212,15 @@ impl ChannelClose {
                 self.support
                     .sdk
                     .redeem_onchain_funds(RedeemOnchainFundsRequest {
-                        to_address: sweep_info.address,
-                        sat_per_vbyte: sweep_info.onchain_fee_rate,
+                        to_address,
+                        sat_per_vbyte,
                     }),
             )
             .map_to_runtime_error(RuntimeErrorCode::NodeUnavailable, "Failed to sweep funds")?
             .txid;
-        Ok(hex::encode(txid))
+        let txid = hex::encode(txid);
+        record_onchain_spend(&self.support, &txid, onchain_balance.sats_round_down());
+        Ok(txid)
     }
 
     /// Automatically swaps on-chain funds back to lightning.
@@ -274,7 +326,9 @@ impl ChannelClose {
                         }),
                 )?;
 
-        Ok(hex::encode(sweep_result.txid))
+        let txid = hex::encode(sweep_result.txid);
+        record_onchain_spend(&self.support, &txid, onchain_balance.sats_round_down());
+        Ok(txid)
     }
 }
 
@@ -284,17 +338,8 @@ pub struct SweepChannelCloseInfo {
     pub onchain_fee_rate: u32,
     pub onchain_fee_amount: Amount,
     pub amount: Amount,
-}
-
-impl From<SweepInfo> for SweepChannelCloseInfo {
-    fn from(value: SweepInfo) -> Self {
-        Self {
-            address: value.address,
-            onchain_fee_rate: value.onchain_fee_rate,
-            onchain_fee_amount: value.onchain_fee_amount,
-            amount: value.amount,
-        }
-    }
+    /// The currently recommended fee rates for each [`FeePriority`].
+    pub fee_rates: OnchainFeeRates,
 }
 
 impl From<SweepChannelCloseInfo> for SweepInfo {
@@ -3,21 +3,133 @@ pub mod reverse_swap;
 pub mod swap;
 
 use crate::amount::{AsSats, Msats, Sats, ToAmount};
+use crate::balance_history::store_onchain_spend;
+use crate::data_store::SweepTransaction;
 use crate::errors::Result;
+use crate::locker::Locker;
+use crate::node_config::WithTimezone;
 use crate::onchain::channel_closes::ChannelClose;
 use crate::onchain::reverse_swap::ReverseSwap;
 use crate::onchain::swap::Swap;
 use crate::support::Support;
-use crate::{OnchainResolvingFees, RuntimeErrorCode, SwapToLightningFees};
-use breez_sdk_core::ReceiveOnchainRequest;
-use log::error;
-use perro::MapToError;
+use crate::util::LogIgnoreError;
+use crate::{
+    FailedSwapInfo, OnchainResolvingFees, RuntimeErrorCode, SwapToLightningFees, TzTime,
+    CLN_DUST_LIMIT_SAT,
+};
+use bitcoin::{Address, Network};
+use breez_sdk_core::{ReceiveOnchainRequest, RefundRequest};
+use log::{debug, error, info, Level};
+use perro::{ensure, invalid_input, MapToError};
+use std::collections::HashSet;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::SystemTime;
+
+/// The priority of an on-chain transaction, determining the fee rate it is broadcast with.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FeePriority {
+    /// Confirmation in the next block.
+    Fastest,
+    /// Confirmation within about half an hour.
+    HalfHour,
+    /// Confirmation within about an hour.
+    Hour,
+    /// Cheaper, but confirmation can take several hours.
+    Economy,
+    /// The minimum fee rate still relayed by nodes. Confirmation can take days.
+    Minimum,
+    /// A custom fee rate. Must not be lower than [`OnchainFeeRates::minimum`].
+    Custom { sat_per_vbyte: u32 },
+}
+
+/// The currently recommended on-chain fee rates in sat/vbyte for each [`FeePriority`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct OnchainFeeRates {
+    pub fastest: u32,
+    pub half_hour: u32,
+    pub hour: u32,
+    pub economy: u32,
+    pub minimum: u32,
+}
+
+impl OnchainFeeRates {
+    pub(crate) fn sat_per_vbyte(&self, priority: FeePriority) -> Result<u32> {
+        let sat_per_vbyte = match priority {
+            FeePriority::Fastest => self.fastest,
+            FeePriority::HalfHour => self.half_hour,
+            FeePriority::Hour => self.hour,
+            FeePriority::Economy => self.economy,
+            FeePriority::Minimum => self.minimum,
+            FeePriority::Custom { sat_per_vbyte } => {
+                ensure!(
+                    sat_per_vbyte >= self.minimum,
+                    invalid_input(format!(
+                        "The fee rate must be at least {} sat/vbyte",
+                        self.minimum
+                    ))
+                );
+                sat_per_vbyte
+            }
+        };
+        Ok(sat_per_vbyte)
+    }
+}
+
+/// How funds from channel closes and failed swaps are resolved by an [`AutoResolutionPolicy`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum AutoResolutionStrategy {
+    /// Swap the funds back to lightning using [`ChannelClose::swap`] or [`Swap::swap`].
+    SwapToLightning,
+    /// Sweep the funds to an on-chain address. Must be a bitcoin mainnet address.
+    SweepToAddress { address: String },
+}
+
+/// An opt-in policy to resolve funds from channel closes and failed swaps without user
+/// interaction. The funds are resolved as soon as the fees drop below the configured limit.
+///
+/// Resolving transactions are broadcast with the [`FeePriority::HalfHour`] fee rate.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AutoResolutionPolicy {
+    pub strategy: AutoResolutionStrategy,
+    /// The maximum total fees, in parts per 10,000 of the resolved amount. For example, `500`
+    /// only resolves funds once the fees are at most 5% of the amount.
+    pub max_fee_permyriad: u32,
+}
+
+impl AutoResolutionPolicy {
+    fn accepts_fees(&self, fee_sat: u64, amount_sat: u64) -> bool {
+        fee_sat.saturating_mul(10_000) <= amount_sat.saturating_mul(self.max_fee_permyriad as u64)
+    }
+}
+
+/// The origin of funds resolved by an [`AutoResolutionPolicy`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum ResolvedFundsSource {
+    ChannelCloses,
+    FailedSwap { swap_address: String },
+}
+
+/// A transaction sweeping the funds of a failed swap (created by [`Swap::sweep`] or
+/// [`Swap::swap`]) that hasn't confirmed for a long time, probably because its fee rate is too
+/// low. Its fee can be bumped using [`Onchain::bump_stuck_transaction_fee`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct StuckTransactionInfo {
+    pub txid: String,
+    /// The address of the failed swap whose funds are swept.
+    pub swap_address: String,
+    /// The fee rate the transaction was broadcast with.
+    pub sat_per_vbyte: u32,
+    pub broadcast_at: TzTime,
+    /// The currently recommended fee rate for replacing the transaction.
+    pub recommended_sat_per_vbyte: u32,
+}
 
 pub struct Onchain {
     swap: Arc<Swap>,
     reverse_swap: Arc<ReverseSwap>,
     channel_close: Arc<ChannelClose>,
+    support: Arc<Support>,
 }
 
 impl Onchain {
@@ -29,6 +141,7 @@ impl Onchain {
             swap,
             reverse_swap,
             channel_close,
+            support,
         }
     }
 
@@ -43,18 +156,321 @@ impl Onchain {
     pub fn channel_close(&self) -> Arc<ChannelClose> {
         Arc::clone(&self.channel_close)
     }
+
+    /// Replaces a stuck sweep transaction by one paying a higher fee rate (RBF).
+    /// Stuck transactions are listed by [`ActionsRequired::list`](crate::ActionsRequired::list).
+    ///
+    /// Parameters:
+    /// * `txid` - the id of the stuck transaction
+    /// * `sat_per_vbyte` - the new fee rate. Must be higher than the one of the stuck transaction.
+    ///   [`StuckTransactionInfo::recommended_sat_per_vbyte`] can be used.
+    ///
+    /// Returns the txid of the replacement transaction.
+    ///
+    /// Requires network: **yes**
+    pub fn bump_stuck_transaction_fee(&self, txid: String, sat_per_vbyte: u32) -> Result<String> {
+        let transaction = self
+            .support
+            .data_store
+            .lock_unwrap()
+            .retrieve_unconfirmed_sweep_transactions()?
+            .into_iter()
+            .find(|t| t.txid == txid)
+            .ok_or_else(|| invalid_input(format!("No unconfirmed sweep transaction {txid}")))?;
+        ensure!(
+            sat_per_vbyte > transaction.sat_per_vbyte,
+            invalid_input(format!(
+                "The fee rate must be higher than {} sat/vbyte",
+                transaction.sat_per_vbyte
+            ))
+        );
+
+        // A refund spends the outputs of the swap address, so it replaces the stuck refund
+        let replacement_txid = self
+            .support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.refund(RefundRequest {
+                swap_address: transaction.swap_address.clone(),
+                to_address: transaction.to_address.clone(),
+                sat_per_vbyte,
+            }))
+            .map_to_runtime_error(
+                RuntimeErrorCode::NodeUnavailable,
+                "Failed to broadcast replacement refund transaction",
+            )?
+            .refund_tx_id;
+
+        self.support
+            .data_store
+            .lock_unwrap()
+            .store_sweep_transaction_replacement(
+                &txid,
+                &SweepTransaction {
+                    txid: replacement_txid.clone(),
+                    sat_per_vbyte,
+                    broadcast_at: SystemTime::now(),
+                    ..transaction
+                },
+            )?;
+        Ok(replacement_txid)
+    }
+
+    /// Sets the policy for resolving funds from channel closes and failed swaps automatically.
+    /// The policy is evaluated periodically while the app is in the foreground. Resolved funds
+    /// are reported through [`EventsCallback::funds_auto_resolved`](crate::EventsCallback::funds_auto_resolved).
+    ///
+    /// Parameters:
+    /// * `policy` - the policy to apply, or `None` to disable automatic resolution
+    ///
+    /// Requires network: **no**
+    pub fn set_auto_resolution_policy(&self, policy: Option<AutoResolutionPolicy>) -> Result<()> {
+        if let Some(policy) = &policy {
+            ensure!(
+                policy.max_fee_permyriad <= 10_000,
+                invalid_input("The fee limit can't exceed the resolved amount")
+            );
+            if let AutoResolutionStrategy::SweepToAddress { address } = &policy.strategy {
+                ensure_mainnet_address(address)?;
+            }
+        }
+        self.support
+            .data_store
+            .lock_unwrap()
+            .append_auto_resolution_policy(policy.as_ref())
+    }
+
+    /// Returns the currently set policy for resolving funds automatically, if any.
+    ///
+    /// Requires network: **no**
+    pub fn get_auto_resolution_policy(&self) -> Result<Option<AutoResolutionPolicy>> {
+        self.support
+            .data_store
+            .lock_unwrap()
+            .retrieve_auto_resolution_policy()
+    }
+
+    /// Resolves funds from channel closes and failed swaps according to the set
+    /// [`AutoResolutionPolicy`]. Failed swaps that were already refunded are skipped.
+    ///
+    /// Returns the resolved funds together with the txid of the resolving tx.
+    pub(crate) fn auto_resolve(&self) -> Result<Vec<(ResolvedFundsSource, String)>> {
+        let policy = match self
+            .support
+            .data_store
+            .lock_unwrap()
+            .retrieve_auto_resolution_policy()?
+        {
+            Some(policy) => policy,
+            None => return Ok(Vec::new()),
+        };
+        let mut resolved = Vec::new();
+
+        for failed_swap in self.swap.list_failed_unresolved()? {
+            let source = ResolvedFundsSource::FailedSwap {
+                swap_address: failed_swap.address.clone(),
+            };
+            match self.auto_resolve_failed_swap(&policy, failed_swap) {
+                Ok(Some(txid)) => resolved.push((source, txid)),
+                Ok(None) => {}
+                Err(e) => error!("Failed to automatically resolve {source:?}: {e}"),
+            }
+        }
+
+        let onchain_balance_sat = self.support.get_node_info()?.onchain_balance.sats;
+        // CLN keeps a dust limit amount on-chain, see `ActionsRequired::list`
+        if onchain_balance_sat > CLN_DUST_LIMIT_SAT {
+            match self.auto_resolve_channel_closes(&policy, onchain_balance_sat) {
+                Ok(Some(txid)) => resolved.push((ResolvedFundsSource::ChannelCloses, txid)),
+                Ok(None) => {}
+                Err(e) => error!("Failed to automatically resolve channel close funds: {e}"),
+            }
+        }
+
+        for (source, txid) in &resolved {
+            info!("Automatically resolved {source:?} with tx {txid}");
+        }
+        Ok(resolved)
+    }
+
+    fn auto_resolve_failed_swap(
+        &self,
+        policy: &AutoResolutionPolicy,
+        failed_swap: FailedSwapInfo,
+    ) -> Result<Option<String>> {
+        let fees = match self
+            .swap
+            .determine_resolving_fees(failed_swap.clone(), FeePriority::HalfHour)?
+        {
+            Some(fees) => fees,
+            None => return Ok(None),
+        };
+        let amount_sat = failed_swap.amount.sats;
+        match &policy.strategy {
+            AutoResolutionStrategy::SwapToLightning => match fees.swap_fees {
+                Some(swap_fees) if policy.accepts_fees(swap_fees.total_fees.sats, amount_sat) => {
+                    self.swap.swap(failed_swap, fees.sats_per_vbyte).map(Some)
+                }
+                _ => {
+                    debug!(
+                        "Fees for swapping failed swap {} back are too high",
+                        failed_swap.address
+                    );
+                    Ok(None)
+                }
+            },
+            AutoResolutionStrategy::SweepToAddress { address } => {
+                if policy.accepts_fees(fees.sweep_onchain_fee_estimate.sats, amount_sat) {
+                    self.swap
+                        .refund(failed_swap.address, address.clone(), fees.sats_per_vbyte)
+                        .map(Some)
+                } else {
+                    debug!(
+                        "Fees for sweeping failed swap {} are too high",
+                        failed_swap.address
+                    );
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    fn auto_resolve_channel_closes(
+        &self,
+        policy: &AutoResolutionPolicy,
+        amount_sat: u64,
+    ) -> Result<Option<String>> {
+        let fees = match self
+            .channel_close
+            .determine_resolving_fees(FeePriority::HalfHour)?
+        {
+            Some(fees) => fees,
+            None => return Ok(None),
+        };
+        match &policy.strategy {
+            AutoResolutionStrategy::SwapToLightning => match fees.swap_fees {
+                Some(swap_fees) if policy.accepts_fees(swap_fees.total_fees.sats, amount_sat) => {
+                    self.channel_close
+                        .swap(fees.sats_per_vbyte)
+                        .map(Some)
+                        .map_to_runtime_error(
+                            RuntimeErrorCode::NodeUnavailable,
+                            "Failed to swap channel close funds",
+                        )
+                }
+                _ => {
+                    debug!("Fees for swapping channel close funds back are too high");
+                    Ok(None)
+                }
+            },
+            AutoResolutionStrategy::SweepToAddress { address } => {
+                if policy.accepts_fees(fees.sweep_onchain_fee_estimate.sats, amount_sat) {
+                    self.channel_close
+                        .sweep_to_address(address.clone(), fees.sats_per_vbyte)
+                        .map(Some)
+                } else {
+                    debug!("Fees for sweeping channel close funds are too high");
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Lists the sweep transactions that have been unconfirmed for longer than
+    /// [`LightningNodeConfig::stuck_transaction_threshold`](crate::LightningNodeConfig::stuck_transaction_threshold).
+    /// Transactions found to be confirmed stop being tracked.
+    pub(crate) fn list_stuck_transactions(&self) -> Result<Vec<StuckTransactionInfo>> {
+        let transactions = self
+            .support
+            .data_store
+            .lock_unwrap()
+            .retrieve_unconfirmed_sweep_transactions()?;
+        if transactions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let refundable_swap_addresses = self
+            .support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.list_refundables())
+            .map_to_runtime_error(
+                RuntimeErrorCode::NodeUnavailable,
+                "Failed to list refundable failed swaps",
+            )?
+            .into_iter()
+            .map(|s| s.bitcoin_address)
+            .collect::<HashSet<_>>();
+
+        let threshold = self.support.node_config.stuck_transaction_threshold;
+        let now = SystemTime::now();
+        let mut stuck_transactions = Vec::new();
+        for transaction in transactions {
+            // Once the refund confirms, the swap address has no funds left to refund
+            if !refundable_swap_addresses.contains(&transaction.swap_address) {
+                self.support
+                    .data_store
+                    .lock_unwrap()
+                    .mark_sweep_transaction_confirmed(&transaction.txid)
+                    .log_ignore_error(Level::Warn, "Failed to mark sweep transaction confirmed");
+                continue;
+            }
+            let is_stuck = now
+                .duration_since(transaction.broadcast_at)
+                .is_ok_and(|age| age > threshold);
+            if is_stuck {
+                stuck_transactions.push(transaction);
+            }
+        }
+        if stuck_transactions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let recommended_fee_rate = query_onchain_fee_rates(&self.support)?.half_hour;
+        let tz_config = self
+            .support
+            .user_preferences
+            .lock_unwrap()
+            .timezone_config
+            .clone();
+        Ok(stuck_transactions
+            .into_iter()
+            .map(|t| StuckTransactionInfo {
+                recommended_sat_per_vbyte: recommended_fee_rate.max(t.sat_per_vbyte + 1),
+                txid: t.txid,
+                swap_address: t.swap_address,
+                sat_per_vbyte: t.sat_per_vbyte,
+                broadcast_at: t.broadcast_at.with_timezone(tz_config.clone()),
+            })
+            .collect())
+    }
+}
+
+/// Starts tracking a broadcast sweep transaction, so it can be reported if it gets stuck.
+fn track_sweep_transaction(support: &Support, transaction: SweepTransaction) {
+    support
+        .data_store
+        .lock_unwrap()
+        .store_sweep_transaction(&transaction)
+        .log_ignore_error(Level::Error, "Failed to store sweep transaction");
+}
+
+fn record_onchain_spend(support: &Support, txid: &str, amount: Sats) {
+    store_onchain_spend(&mut support.data_store.lock_unwrap(), txid, amount.sats)
+        .log_ignore_error(Level::Error, "Failed to store on-chain spend");
 }
 
 fn get_onchain_resolving_fees<F>(
     support: &Support,
     swap: &Swap,
     amount: Msats,
+    fee_rates: OnchainFeeRates,
     prepare_onchain_tx: F,
 ) -> Result<Option<OnchainResolvingFees>>
 where
     F: FnOnce(String) -> Result<(Sats, Sats, u32)>,
 {
-    let rate = support.get_exchange_rate();
+    let rate = support.get_display_rates();
     let lsp_fees = swap.calculate_lsp_fee_for_amount(amount.msats)?;
 
     let swap_info = support
@@ -98,6 +514,7 @@ where
             swap_fees: None,
             sweep_onchain_fee_estimate: onchain_fee.to_amount_up(&rate),
             sats_per_vbyte,
+            fee_rates,
         }));
     }
 
@@ -116,10 +533,11 @@ where
         swap_fees: Some(swap_to_lightning_fees),
         sweep_onchain_fee_estimate: onchain_fee.to_amount_up(&rate),
         sats_per_vbyte,
+        fee_rates,
     }))
 }
 
-fn query_onchain_fee_rate(support: &Support) -> Result<u32> {
+fn query_onchain_fee_rates(support: &Support) -> Result<OnchainFeeRates> {
     let recommended_fees = support
         .rt
         .handle()
@@ -129,5 +547,70 @@ fn query_onchain_fee_rate(support: &Support) -> Result<u32> {
             "Couldn't fetch recommended fees",
         )?;
 
-    Ok(recommended_fees.half_hour_fee as u32)
+    Ok(OnchainFeeRates {
+        fastest: recommended_fees.fastest_fee as u32,
+        half_hour: recommended_fees.half_hour_fee as u32,
+        hour: recommended_fees.hour_fee as u32,
+        economy: recommended_fees.economy_fee as u32,
+        minimum: recommended_fees.minimum_fee as u32,
+    })
+}
+
+/// Ensures that funds can be sent to `address` from the node, which runs on bitcoin mainnet.
+fn ensure_mainnet_address(address: &str) -> Result<()> {
+    Address::from_str(address)
+        .map_to_invalid_input("Invalid bitcoin address")?
+        .require_network(Network::Bitcoin)
+        .map_to_invalid_input("The address isn't a bitcoin mainnet address")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_rate_of_priority() {
+        let fee_rates = OnchainFeeRates {
+            fastest: 20,
+            half_hour: 15,
+            hour: 12,
+            economy: 10,
+            minimum: 5,
+        };
+        assert_eq!(fee_rates.sat_per_vbyte(FeePriority::Fastest).unwrap(), 20);
+        assert_eq!(fee_rates.sat_per_vbyte(FeePriority::HalfHour).unwrap(), 15);
+        assert_eq!(fee_rates.sat_per_vbyte(FeePriority::Hour).unwrap(), 12);
+        assert_eq!(fee_rates.sat_per_vbyte(FeePriority::Economy).unwrap(), 10);
+        assert_eq!(fee_rates.sat_per_vbyte(FeePriority::Minimum).unwrap(), 5);
+        assert_eq!(
+            fee_rates
+                .sat_per_vbyte(FeePriority::Custom { sat_per_vbyte: 7 })
+                .unwrap(),
+            7
+        );
+        assert!(fee_rates
+            .sat_per_vbyte(FeePriority::Custom { sat_per_vbyte: 4 })
+            .is_err());
+    }
+
+    #[test]
+    fn test_auto_resolution_policy_accepts_fees() {
+        let policy = AutoResolutionPolicy {
+            strategy: AutoResolutionStrategy::SwapToLightning,
+            max_fee_permyriad: 500,
+        };
+        assert!(policy.accepts_fees(0, 100_000));
+        assert!(policy.accepts_fees(5_000, 100_000));
+        assert!(!policy.accepts_fees(5_001, 100_000));
+        assert!(!policy.accepts_fees(1, 0));
+    }
+
+    #[test]
+    fn test_ensure_mainnet_address() {
+        assert!(ensure_mainnet_address("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").is_ok());
+        assert!(ensure_mainnet_address("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx").is_err());
+        assert!(ensure_mainnet_address("not an address").is_err());
+        assert!(ensure_mainnet_address("").is_err());
+    }
 }
@@ -1,10 +1,19 @@
 use crate::amount::{AsSats, Permyriad, ToAmount};
 use crate::errors::Result;
+use crate::locker::Locker;
+use crate::onchain::{query_onchain_fee_rates, FeePriority};
+use crate::reverse_swap::reached_stages;
 use crate::support::Support;
-use crate::{ClearWalletInfo, RangeHit, RuntimeErrorCode};
-use breez_sdk_core::{BitcoinAddressData, PayOnchainRequest, PrepareOnchainPaymentRequest};
-use perro::{permanent_failure, MapToError};
+use crate::util::LogIgnoreError;
+use crate::{ClearWalletInfo, OnchainSendInfo, RangeHit, RuntimeErrorCode};
+use breez_sdk_core::{
+    BitcoinAddressData, PayOnchainRequest, PrepareOnchainPaymentRequest,
+    PrepareOnchainPaymentResponse, SwapAmountType,
+};
+use log::Level;
+use perro::{ensure, invalid_input, permanent_failure, MapToError};
 use std::sync::Arc;
+use std::time::SystemTime;
 
 pub struct ReverseSwap {
     support: Arc<Support>,
@@ -42,7 +51,7 @@ impl ReverseSwap {
             .as_msats()
             .sats_round_down()
             .sats;
-        let exchange_rate = self.support.get_exchange_rate();
+        let exchange_rate = self.support.get_display_rates();
 
         // Accomodating lightning network routing fees.
         let routing_fee = Permyriad(
@@ -74,9 +83,13 @@ impl ReverseSwap {
     /// The method [`ReverseSwap::determine_clear_wallet_feasibility`] can be used to check if the balance
     /// is within the required range.
     ///
+    /// Parameters:
+    /// * `fee_priority` - the priority of the claim tx, determining its fee rate
+    ///
     /// Requires network: **yes**
-    pub fn prepare_clear_wallet(&self) -> Result<ClearWalletInfo> {
-        let claim_tx_feerate = self.support.query_onchain_fee_rate()?;
+    pub fn prepare_clear_wallet(&self, fee_priority: FeePriority) -> Result<ClearWalletInfo> {
+        let fee_rates = query_onchain_fee_rates(&self.support)?;
+        let claim_tx_feerate = fee_rates.sat_per_vbyte(fee_priority)?;
         let limits = self
             .support
             .rt
@@ -95,7 +108,7 @@ impl ReverseSwap {
                     .sdk
                     .prepare_onchain_payment(PrepareOnchainPaymentRequest {
                         amount_sat: limits.max_payable_sat,
-                        amount_type: breez_sdk_core::SwapAmountType::Send,
+                        amount_type: SwapAmountType::Send,
                         claim_tx_feerate,
                     }),
             )
@@ -107,7 +120,7 @@ impl ReverseSwap {
         let total_fees_sat = prepare_response.total_fees;
         let onchain_fee_sat = prepare_response.fees_claim + prepare_response.fees_lockup;
         let swap_fee_sat = total_fees_sat - onchain_fee_sat;
-        let exchange_rate = self.support.get_exchange_rate();
+        let exchange_rate = self.support.get_display_rates();
 
         Ok(ClearWalletInfo {
             clear_amount: prepare_response
@@ -117,6 +130,7 @@ impl ReverseSwap {
             total_estimated_fees: total_fees_sat.as_sats().to_amount_up(&exchange_rate),
             onchain_fee: onchain_fee_sat.as_sats().to_amount_up(&exchange_rate),
             swap_fee: swap_fee_sat.as_sats().to_amount_up(&exchange_rate),
+            fee_rates,
             prepare_response,
         })
     }
@@ -135,17 +149,154 @@ impl ReverseSwap {
         clear_wallet_info: ClearWalletInfo,
         destination: BitcoinAddressData,
     ) -> Result<()> {
-        self.support
+        self.start(destination.address, clear_wallet_info.prepare_response)
+    }
+
+    /// Prepares a reverse swap that sends the provided amount to an on-chain address.
+    ///
+    /// The amount sent from the LN channels will be higher than `amount_sat` as it also has to
+    /// cover the lockup, claim and swap fees. This can fail if the resulting amount is outside of
+    /// the limits of the swap service or exceeds what is payable with the current balance.
+    ///
+    /// Parameters:
+    /// * `amount_sat` - the amount to be received on the destination address
+    /// * `destination` - An on-chain address data instance. Can be obtained
+    ///   using [`Util::decode_data`](crate::Util::decode_data).
+    /// * `fee_priority` - the priority of the claim tx, determining its fee rate
+    ///
+    /// Requires network: **yes**
+    pub fn prepare_send(
+        &self,
+        amount_sat: u64,
+        destination: BitcoinAddressData,
+        fee_priority: FeePriority,
+    ) -> Result<OnchainSendInfo> {
+        ensure!(
+            amount_sat > 0,
+            invalid_input("Amount must be greater than 0")
+        );
+        let fee_rates = query_onchain_fee_rates(&self.support)?;
+        let claim_tx_feerate = fee_rates.sat_per_vbyte(fee_priority)?;
+        let limits = self
+            .support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.onchain_payment_limits())
+            .map_to_runtime_error(
+                RuntimeErrorCode::NodeUnavailable,
+                "Failed to get on-chain payment limits",
+            )?;
+        // Accomodating lightning network routing fees.
+        let routing_fee = Permyriad(
+            self.support
+                .node_config
+                .max_routing_fee_config
+                .max_routing_fee_permyriad,
+        )
+        .of(&limits.max_payable_sat.as_sats())
+        .sats_round_up();
+        let max_sender_amount_sat = limits
+            .max_sat
+            .min(limits.max_payable_sat.saturating_sub(routing_fee.sats));
+        // The amount sent is at least the amount received, so a too high amount can be rejected
+        // before asking the swap service.
+        ensure!(
+            amount_sat <= max_sender_amount_sat,
+            invalid_input(format!(
+                "Amount is too high, the maximum including fees is {max_sender_amount_sat} sats"
+            ))
+        );
+        let prepare_response = self
+            .support
+            .rt
+            .handle()
+            .block_on(
+                self.support
+                    .sdk
+                    .prepare_onchain_payment(PrepareOnchainPaymentRequest {
+                        amount_sat,
+                        amount_type: SwapAmountType::Receive,
+                        claim_tx_feerate,
+                    }),
+            )
+            .map_to_runtime_error(
+                RuntimeErrorCode::NodeUnavailable,
+                "Failed to prepare on-chain payment",
+            )?;
+
+        let exchange_rate = self.support.get_display_rates();
+        let sender_amount_sat = prepare_response.sender_amount_sat;
+        ensure!(
+            limits.min_sat <= sender_amount_sat,
+            invalid_input(format!(
+                "Amount is too low, the minimum including fees is {} sats",
+                limits.min_sat
+            ))
+        );
+        ensure!(
+            sender_amount_sat <= max_sender_amount_sat,
+            invalid_input(format!(
+                "Amount is too high, the maximum including fees is {max_sender_amount_sat} sats"
+            ))
+        );
+
+        let total_fees_sat = prepare_response.total_fees;
+        let lockup_fee_sat = prepare_response.fees_lockup;
+        let claim_fee_sat = prepare_response.fees_claim;
+        let swap_fee_sat = total_fees_sat - lockup_fee_sat - claim_fee_sat;
+
+        Ok(OnchainSendInfo {
+            amount: prepare_response
+                .recipient_amount_sat
+                .as_sats()
+                .to_amount_down(&exchange_rate),
+            sender_amount: sender_amount_sat.as_sats().to_amount_up(&exchange_rate),
+            total_estimated_fees: total_fees_sat.as_sats().to_amount_up(&exchange_rate),
+            lockup_fee: lockup_fee_sat.as_sats().to_amount_up(&exchange_rate),
+            claim_fee: claim_fee_sat.as_sats().to_amount_up(&exchange_rate),
+            swap_fee: swap_fee_sat.as_sats().to_amount_up(&exchange_rate),
+            destination,
+            fee_rates,
+            prepare_response,
+        })
+    }
+
+    /// Starts a reverse swap that sends the prepared amount to an on-chain address.
+    ///
+    /// Parameters:
+    /// * `send_info` - An instance of [`OnchainSendInfo`] obtained using
+    ///   [`ReverseSwap::prepare_send`].
+    ///
+    /// Requires network: **yes**
+    pub fn send(&self, send_info: OnchainSendInfo) -> Result<()> {
+        self.start(send_info.destination.address, send_info.prepare_response)
+    }
+
+    fn start(
+        &self,
+        recipient_address: String,
+        prepare_res: PrepareOnchainPaymentResponse,
+    ) -> Result<()> {
+        let reverse_swap = self
+            .support
             .rt
             .handle()
             .block_on(self.support.sdk.pay_onchain(PayOnchainRequest {
-                recipient_address: destination.address,
-                prepare_res: clear_wallet_info.prepare_response,
+                recipient_address,
+                prepare_res,
             }))
             .map_to_runtime_error(
                 RuntimeErrorCode::NodeUnavailable,
                 "Failed to start reverse swap",
-            )?;
+            )?
+            .reverse_swap_info;
+
+        let mut data_store = self.support.data_store.lock_unwrap();
+        for entry in reached_stages(&reverse_swap, None, SystemTime::now()) {
+            data_store
+                .store_reverse_swap_stage(&reverse_swap.id, &entry)
+                .log_ignore_error(Level::Error, "Failed to store reverse swap stage");
+        }
         Ok(())
     }
 }
@@ -1,20 +1,29 @@
 use crate::amount::{AsSats, Sats, ToAmount};
+use crate::balance_history::{store_failed_swap_deposit, store_onchain_spend};
+use crate::data_store::SweepTransaction;
 use crate::errors::Result;
 use crate::locker::Locker;
-use crate::onchain::{get_onchain_resolving_fees, query_onchain_fee_rate};
-use crate::support::Support;
-use crate::util::unix_timestamp_to_system_time;
+use crate::node_config::WithTimezone;
+use crate::onchain::{
+    get_onchain_resolving_fees, query_onchain_fee_rates, track_sweep_transaction, FeePriority,
+    OnchainFeeRates,
+};
+use crate::support::{to_lsp_fee, Support};
+use crate::swap::deposit_issue;
+use crate::util::{unix_timestamp_to_system_time, LogIgnoreError};
 use crate::{
     Amount, CalculateLspFeeResponseV2, FailedSwapInfo, LspFee, OnchainResolvingFees,
-    ResolveFailedSwapInfo, RuntimeErrorCode, SwapAddressInfo,
+    OutOfRangeSwapDeposit, ResolveFailedSwapInfo, RuntimeErrorCode, SwapAddressInfo, SwapDetails,
+    SwapError, SwapState,
 };
-use breez_sdk_core::error::ReceiveOnchainError;
 use breez_sdk_core::{
-    BitcoinAddressData, Network, OpeningFeeParams, PrepareRefundRequest, ReceiveOnchainRequest,
-    RefundRequest,
+    BitcoinAddressData, ListSwapsRequest, Network, OpeningFeeParams, PrepareRefundRequest,
+    ReceiveOnchainRequest, RefundRequest, SwapStatus,
 };
+use log::Level;
 use perro::{ensure, permanent_failure, runtime_error, MapToError};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub struct Swap {
     support: Arc<Support>,
@@ -33,15 +42,32 @@ impl Swap {
     ///
     /// If a swap is in progress, this method will return an error.
     ///
+    /// If the LSP fee terms can't satisfy the limit set through
+    /// [`Config::set_max_lsp_fee`](crate::Config::set_max_lsp_fee) for any amount, this method
+    /// fails with [`SwapError::LspFeeAboveLimit`].
+    ///
     /// Parameters:
     ///
     /// Requires network: **yes**
-    pub fn create(&self) -> std::result::Result<SwapAddressInfo, ReceiveOnchainError> {
+    pub fn create(&self) -> std::result::Result<SwapAddressInfo, SwapError> {
         let lsp_fee_params =
             self.get_lsp_fee_params()
-                .map_err(|_e| ReceiveOnchainError::ServiceConnectivity {
+                .map_err(|_e| SwapError::ServiceConnectivity {
                     err: "Could not retrieve lsp fee params".to_string(),
                 })?;
+        let max_lsp_fee = self
+            .support
+            .data_store
+            .lock_unwrap()
+            .retrieve_max_lsp_fee()
+            .map_err(|e| SwapError::Generic {
+                err: format!("Could not retrieve the LSP fee limit: {e}"),
+            })?;
+        if !max_lsp_fee.is_none_or(|m| m.accepts_fee_params(&lsp_fee_params)) {
+            return Err(SwapError::LspFeeAboveLimit {
+                err: "The LSP fee exceeds the set limit".to_string(),
+            });
+        }
         let swap_info = self
             .support
             .rt
@@ -49,7 +75,7 @@ impl Swap {
             .block_on(self.support.sdk.receive_onchain(ReceiveOnchainRequest {
                 opening_fee_params: Some(lsp_fee_params),
             }))?;
-        let rate = self.support.get_exchange_rate();
+        let rate = self.support.get_display_rates();
 
         Ok(SwapAddressInfo {
             address: swap_info.bitcoin_address,
@@ -67,16 +93,24 @@ impl Swap {
     ///
     /// Must only be called when the failed swap is unresolved.
     ///
+    /// Parameters:
+    /// * `failed_swap_info` - the failed swap to be resolved
+    /// * `fee_priority` - the priority of the resolving tx, determining its fee rate
+    ///
     /// Returns the fee information for the available resolving options.
     ///
     /// Requires network: *yes*
     pub fn determine_resolving_fees(
         &self,
         failed_swap_info: FailedSwapInfo,
+        fee_priority: FeePriority,
     ) -> Result<Option<OnchainResolvingFees>> {
+        let fee_rates = query_onchain_fee_rates(&self.support)?;
+        let sat_per_vbyte = fee_rates.sat_per_vbyte(fee_priority)?;
+        let sweep_fee_rates = fee_rates.clone();
         let failed_swap_closure = failed_swap_info.clone();
         let prepare_onchain_tx = move |address: String| -> Result<(Sats, Sats, u32)> {
-            let sweep_info = self.prepare_sweep(
+            let sweep_info = self.prepare_sweep_with_fee_rate(
                 failed_swap_closure,
                 BitcoinAddressData {
                     address,
@@ -85,6 +119,8 @@ impl Swap {
                     label: None,
                     message: None,
                 },
+                sat_per_vbyte,
+                sweep_fee_rates,
             )?;
 
             Ok((
@@ -97,6 +133,7 @@ impl Swap {
             &self.support,
             self,
             failed_swap_info.amount.to_msats().as_msats(),
+            fee_rates,
             prepare_onchain_tx,
         )
     }
@@ -108,15 +145,28 @@ impl Swap {
     /// * `failed_swap_info` - the failed swap that will be prepared
     /// * `destination` - the destination address to which funds will be sent.
     ///     Can be obtained using [`Util::decode_data`](crate::Util::decode_data)
+    /// * `fee_priority` - the priority of the sweep tx, determining its fee rate
     ///
     /// Requires network: **yes**
     pub fn prepare_sweep(
         &self,
         failed_swap_info: FailedSwapInfo,
         destination: BitcoinAddressData,
+        fee_priority: FeePriority,
+    ) -> Result<SweepFailedSwapInfo> {
+        let fee_rates = query_onchain_fee_rates(&self.support)?;
+        let onchain_fee_rate = fee_rates.sat_per_vbyte(fee_priority)?;
+        self.prepare_sweep_with_fee_rate(failed_swap_info, destination, onchain_fee_rate, fee_rates)
+    }
+
+    fn prepare_sweep_with_fee_rate(
+        &self,
+        failed_swap_info: FailedSwapInfo,
+        destination: BitcoinAddressData,
+        onchain_fee_rate: u32,
+        fee_rates: OnchainFeeRates,
     ) -> Result<SweepFailedSwapInfo> {
         let to_address = destination.address;
-        let onchain_fee_rate = query_onchain_fee_rate(&self.support)?;
         let response = self
             .support
             .rt
@@ -131,7 +181,7 @@ impl Swap {
                 "Failed to prepare a failed swap refund transaction",
             )?;
 
-        let rate = self.support.get_exchange_rate();
+        let rate = self.support.get_display_rates();
         let onchain_fee = response.refund_tx_fee_sat.as_sats().to_amount_up(&rate);
         ensure!(
             failed_swap_info.amount.sats > onchain_fee.sats,
@@ -147,6 +197,7 @@ impl Swap {
             onchain_fee,
             to_address,
             onchain_fee_rate,
+            fee_rates,
         })
     }
 
@@ -164,20 +215,42 @@ impl Swap {
     ///
     /// Requires network: **yes**
     pub fn sweep(&self, sweep_failed_swap_info: SweepFailedSwapInfo) -> Result<String> {
-        Ok(self
+        self.refund(
+            sweep_failed_swap_info.swap_address,
+            sweep_failed_swap_info.to_address,
+            sweep_failed_swap_info.onchain_fee_rate,
+        )
+    }
+
+    pub(crate) fn refund(
+        &self,
+        swap_address: String,
+        to_address: String,
+        sat_per_vbyte: u32,
+    ) -> Result<String> {
+        let failed_swap_info = self
+            .list_failed_unresolved()?
+            .into_iter()
+            .find(|s| s.address == swap_address);
+        let refund_tx_id = self
             .support
             .rt
             .handle()
             .block_on(self.support.sdk.refund(RefundRequest {
-                swap_address: sweep_failed_swap_info.swap_address,
-                to_address: sweep_failed_swap_info.to_address,
-                sat_per_vbyte: sweep_failed_swap_info.onchain_fee_rate,
+                swap_address: swap_address.clone(),
+                to_address: to_address.clone(),
+                sat_per_vbyte,
             }))
             .map_to_runtime_error(
                 RuntimeErrorCode::NodeUnavailable,
                 "Failed to create and broadcast failed swap refund transaction",
             )?
-            .refund_tx_id)
+            .refund_tx_id;
+        if let Some(failed_swap_info) = failed_swap_info {
+            self.record_resolution(&failed_swap_info, &refund_tx_id);
+        }
+        self.track_refund(&refund_tx_id, swap_address, to_address, sat_per_vbyte);
+        Ok(refund_tx_id)
     }
 
     /// Automatically swaps failed swap funds back to lightning.
@@ -187,6 +260,10 @@ impl Swap {
     /// If the current balance doesn't fulfill the limits, this method will return an error.
     /// Before using this method use [`Swap::determine_resolving_fees`] to validate a swap is available.
     ///
+    /// If a new channel is needed and its fee exceeds the limit set through
+    /// [`Config::set_max_lsp_fee`](crate::Config::set_max_lsp_fee), this method will return
+    /// [`RuntimeErrorCode::LspFeeAboveLimit`].
+    ///
     /// Parameters:
     /// * `sat_per_vbyte` - the fee rate to use for the on-chain transaction.
     ///   Can be obtained with [`Swap::determine_resolving_fees`].
@@ -234,6 +311,10 @@ impl Swap {
             .calculate_lsp_fee_for_amount(send_amount_sats, self.get_lsp_fee_params()?)?
             .lsp_fee
             .sats;
+        self.support.ensure_lsp_fee_within_limit(
+            lsp_fees.as_sats().msats,
+            send_amount_sats.as_sats().msats,
+        )?;
 
         ensure!(
             lsp_fees < send_amount_sats,
@@ -248,8 +329,8 @@ impl Swap {
             .rt
             .handle()
             .block_on(self.support.sdk.refund(RefundRequest {
-                swap_address: failed_swap_info.address,
-                to_address: swap_address_info.address,
+                swap_address: failed_swap_info.address.clone(),
+                to_address: swap_address_info.address.clone(),
                 sat_per_vbyte: sats_per_vbyte,
             }))
             .map_to_runtime_error(
@@ -257,9 +338,165 @@ impl Swap {
                 "Couldn't broadcast swap refund transaction",
             )?;
 
+        self.record_resolution(&failed_swap_info, &refund_response.refund_tx_id);
+        self.track_refund(
+            &refund_response.refund_tx_id,
+            failed_swap_info.address,
+            swap_address_info.address,
+            sats_per_vbyte,
+        );
         Ok(refund_response.refund_tx_id)
     }
 
+    /// Caches the deposit of a resolved failed swap along with its spend for the balance history,
+    /// as the deposited amount is no longer known once refunded.
+    fn record_resolution(&self, failed_swap_info: &FailedSwapInfo, refund_tx_id: &str) {
+        let deposited_at = failed_swap_info
+            .created_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let amount_sat = failed_swap_info.amount.sats;
+        let mut data_store = self.support.data_store.lock_unwrap();
+        store_failed_swap_deposit(
+            &mut data_store,
+            &failed_swap_info.address,
+            deposited_at,
+            amount_sat,
+        )
+        .and_then(|()| store_onchain_spend(&mut data_store, refund_tx_id, amount_sat))
+        .log_ignore_error(Level::Error, "Failed to store failed swap resolution");
+    }
+
+    fn track_refund(
+        &self,
+        refund_tx_id: &str,
+        swap_address: String,
+        to_address: String,
+        sat_per_vbyte: u32,
+    ) {
+        track_sweep_transaction(
+            &self.support,
+            SweepTransaction {
+                txid: refund_tx_id.to_string(),
+                swap_address,
+                to_address,
+                sat_per_vbyte,
+                broadcast_at: SystemTime::now(),
+            },
+        );
+    }
+
+    /// Lists all swaps ever created, most recent first.
+    ///
+    /// Requires network: **no**
+    pub fn list(&self) -> Result<Vec<SwapDetails>> {
+        let swaps = self
+            .support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.list_swaps(ListSwapsRequest {
+                status: None,
+                from_timestamp: None,
+                to_timestamp: None,
+                offset: None,
+                limit: None,
+            }))
+            .map_to_runtime_error(RuntimeErrorCode::NodeUnavailable, "Failed to list swaps")?;
+        let block_height = self
+            .support
+            .sdk
+            .node_info()
+            .map_to_runtime_error(
+                RuntimeErrorCode::NodeUnavailable,
+                "Failed to read node info",
+            )?
+            .block_height;
+        let rate = self.support.get_display_rates();
+        let tz_config = self
+            .support
+            .user_preferences
+            .lock_unwrap()
+            .timezone_config
+            .clone();
+
+        let mut swaps = swaps
+            .into_iter()
+            .map(|s| {
+                let state = match s.status {
+                    SwapStatus::Initial => SwapState::Unused,
+                    SwapStatus::WaitingConfirmation => SwapState::AwaitingConfirmation,
+                    SwapStatus::Redeemable | SwapStatus::Redeemed => SwapState::InProgress,
+                    SwapStatus::Completed => SwapState::Completed,
+                    SwapStatus::Refundable if s.refund_tx_ids.is_empty() => SwapState::Refundable,
+                    SwapStatus::Refundable => SwapState::Refunded,
+                };
+                let confirmations = s
+                    .confirmed_at
+                    .map(|height| block_height.saturating_sub(height) + 1);
+                let blocks_until_lock_timeout = s
+                    .confirmed_at
+                    .map(|height| (height + s.lock_height as u32).saturating_sub(block_height));
+                SwapDetails {
+                    address: s.bitcoin_address,
+                    state,
+                    created_at: unix_timestamp_to_system_time(s.created_at as u64)
+                        .with_timezone(tz_config.clone()),
+                    deposited_amount: (s.confirmed_sats + s.unconfirmed_sats)
+                        .as_sats()
+                        .to_amount_down(&rate),
+                    paid_amount: s.paid_msat.as_msats().to_amount_down(&rate),
+                    confirmed_deposit_txids: s.confirmed_tx_ids,
+                    unconfirmed_deposit_txids: s.unconfirmed_tx_ids,
+                    refund_txids: s.refund_tx_ids,
+                    confirmations,
+                    lock_timeout_blocks: s.lock_height as u32,
+                    blocks_until_lock_timeout,
+                }
+            })
+            .collect::<Vec<_>>();
+        swaps.sort_by(|a, b| b.created_at.time.cmp(&a.created_at.time));
+        Ok(swaps)
+    }
+
+    /// Lists deposits to swap addresses that are outside the range accepted by the swap service
+    /// and haven't resulted in a refundable failed swap yet.
+    pub(crate) fn list_out_of_range_deposits(&self) -> Result<Vec<OutOfRangeSwapDeposit>> {
+        let swaps = self
+            .support
+            .rt
+            .handle()
+            .block_on(self.support.sdk.list_swaps(ListSwapsRequest {
+                status: Some(vec![
+                    SwapStatus::WaitingConfirmation,
+                    SwapStatus::Redeemable,
+                ]),
+                from_timestamp: None,
+                to_timestamp: None,
+                offset: None,
+                limit: None,
+            }))
+            .map_to_runtime_error(RuntimeErrorCode::NodeUnavailable, "Failed to list swaps")?;
+        let rate = self.support.get_display_rates();
+
+        Ok(swaps
+            .into_iter()
+            .filter_map(|s| {
+                deposit_issue(&s).map(|issue| OutOfRangeSwapDeposit {
+                    address: s.bitcoin_address,
+                    issue,
+                    deposited_amount: (s.confirmed_sats + s.unconfirmed_sats)
+                        .as_sats()
+                        .to_amount_down(&rate),
+                    min_deposit: (s.min_allowed_deposit as u64).as_sats().to_amount_up(&rate),
+                    max_deposit: (s.max_allowed_deposit as u64)
+                        .as_sats()
+                        .to_amount_down(&rate),
+                })
+            })
+            .collect())
+    }
+
     pub(crate) fn list_failed_unresolved(&self) -> Result<Vec<FailedSwapInfo>> {
         Ok(self
             .support
@@ -277,7 +514,7 @@ impl Swap {
                 amount: s
                     .confirmed_sats
                     .as_sats()
-                    .to_amount_down(&self.support.get_exchange_rate()),
+                    .to_amount_down(&self.support.get_display_rates()),
                 created_at: unix_timestamp_to_system_time(s.created_at as u64),
             })
             .collect())
@@ -303,12 +540,8 @@ impl Swap {
     ///
     /// Requires network: **no**
     pub fn get_lsp_fee(&self) -> Result<LspFee> {
-        let exchange_rate = self.support.get_exchange_rate();
-        let lsp_fee = self.get_lsp_fee_params()?;
-        Ok(LspFee {
-            channel_minimum_fee: lsp_fee.min_msat.as_msats().to_amount_up(&exchange_rate),
-            channel_fee_permyriad: lsp_fee.proportional as u64 / 100,
-        })
+        let exchange_rate = self.support.get_display_rates();
+        Ok(to_lsp_fee(&self.get_lsp_fee_params()?, &exchange_rate))
     }
 
     pub(crate) fn get_lsp_fee_params(&self) -> Result<OpeningFeeParams> {
@@ -331,18 +564,8 @@ pub struct SweepFailedSwapInfo {
     pub to_address: String,
     /// The on-chain fee rate that will be applied. This fee rate results in the `onchain_fee`.
     pub onchain_fee_rate: u32,
-}
-
-impl From<ResolveFailedSwapInfo> for SweepFailedSwapInfo {
-    fn from(value: ResolveFailedSwapInfo) -> Self {
-        Self {
-            swap_address: value.swap_address,
-            recovered_amount: value.recovered_amount,
-            onchain_fee: value.onchain_fee,
-            to_address: value.to_address,
-            onchain_fee_rate: value.onchain_fee_rate,
-        }
-    }
+    /// The currently recommended fee rates for each [`FeePriority`].
+    pub fee_rates: OnchainFeeRates,
 }
 
 impl From<SweepFailedSwapInfo> for ResolveFailedSwapInfo {
@@ -1,11 +1,11 @@
 use std::ops::Add;
 
-use crate::amount::{AsSats, ToAmount};
+use crate::amount::{AsSats, DisplayRates, ToAmount};
 use crate::lightning::lnurl::parse_metadata;
 use crate::node_config::WithTimezone;
 use crate::phone_number::lightning_address_to_phone_number;
 use crate::util::unix_timestamp_to_system_time;
-use crate::{Amount, ExchangeRate, InvoiceDetails, Result, TzConfig, TzTime};
+use crate::{Amount, InvoiceDetails, Result, TzConfig, TzTime};
 
 use breez_sdk_core::{parse_invoice, LnPaymentDetails, PaymentDetails, PaymentStatus};
 use perro::{permanent_failure, MapToError};
@@ -23,6 +23,9 @@ pub enum PaymentState {
     Retried,
     /// The invoice associated with this payment has expired.
     InvoiceExpired,
+    /// The invoice associated with this payment was cancelled through
+    /// [`Bolt11::cancel`](crate::Bolt11::cancel).
+    Cancelled,
 }
 
 impl From<PaymentStatus> for PaymentState {
@@ -39,7 +42,10 @@ impl PaymentState {
     pub(crate) fn is_pending(&self) -> bool {
         match self {
             PaymentState::Created | PaymentState::Retried => true,
-            PaymentState::Succeeded | PaymentState::Failed | PaymentState::InvoiceExpired => false,
+            PaymentState::Succeeded
+            | PaymentState::Failed
+            | PaymentState::InvoiceExpired
+            | PaymentState::Cancelled => false,
         }
     }
 }
@@ -66,7 +72,7 @@ pub struct PaymentInfo {
 impl PaymentInfo {
     pub(crate) fn new(
         breez_payment: breez_sdk_core::Payment,
-        exchange_rate: &Option<ExchangeRate>,
+        exchange_rate: &DisplayRates,
         tz_config: TzConfig,
         personal_note: Option<String>,
     ) -> Result<Self> {
@@ -158,7 +164,7 @@ pub struct IncomingPaymentInfo {
 impl IncomingPaymentInfo {
     pub(crate) fn new(
         breez_payment: breez_sdk_core::Payment,
-        exchange_rate: &Option<ExchangeRate>,
+        exchange_rate: &DisplayRates,
         tz_config: TzConfig,
         personal_note: Option<String>,
         received_on: Option<String>,
@@ -204,7 +210,7 @@ pub struct OutgoingPaymentInfo {
 impl OutgoingPaymentInfo {
     pub(crate) fn new(
         breez_payment: breez_sdk_core::Payment,
-        exchange_rate: &Option<ExchangeRate>,
+        exchange_rate: &DisplayRates,
         tz_config: TzConfig,
         personal_note: Option<String>,
         lipa_lightning_domain: &str,
@@ -1,5 +1,8 @@
-use crate::Amount;
+use crate::data_store::ReverseSwapTimelineEntry;
+use crate::{Amount, TzTime};
 use breez_sdk_core::ReverseSwapStatus;
+use num_enum::TryFromPrimitive;
+use std::time::SystemTime;
 
 /// Information about a successful reverse swap.
 #[derive(PartialEq, Debug)]
@@ -8,10 +11,188 @@ pub struct ReverseSwapInfo {
     /// Total fees paid excluding LN routing fees. Includes onchain
     /// fees and reverse-swap provider fees.
     pub swap_fees_amount: Amount,
+    /// The tx id of the lockup tx, which locks the funds of the swap provider on-chain.
+    ///
+    /// It will only be present once the lockup tx is broadcast.
+    pub lockup_txid: Option<String>,
     /// The tx id of the claim tx, which is the final tx in the reverse swap flow, which send funds
     /// to the targeted on-chain address.
     ///
     /// It will only be present once the claim tx is broadcast.
     pub claim_txid: Option<String>,
     pub status: ReverseSwapStatus,
+    /// The stages reached so far by the reverse swap, in the order they were reached.
+    pub timeline: Vec<ReverseSwapTimelineEvent>,
+    /// The number of confirmations of the claim tx. Only present once the claim tx is confirmed,
+    /// and only if the confirmation was observed while the app was running, as the height of the
+    /// confirming block isn't known otherwise.
+    pub claim_confirmations: Option<u32>,
+}
+
+/// A stage of the reverse swap flow.
+#[derive(PartialEq, Eq, Debug, TryFromPrimitive, Clone, Copy)]
+#[repr(u8)]
+pub enum ReverseSwapStage {
+    /// The reverse swap was started and the swap provider is waiting for the LN payment.
+    Initiated,
+    /// The swap provider broadcast the lockup tx.
+    LockupTxSeen,
+    /// The lockup tx is confirmed. As the claim tx can only be broadcast after that, this stage
+    /// is observed once the claim tx is seen.
+    LockupTxConfirmed,
+    /// The claim tx sending the funds to the destination address was broadcast.
+    ClaimTxBroadcast,
+    /// The claim tx is confirmed.
+    ClaimTxConfirmed,
+    /// The reverse swap was cancelled.
+    Cancelled,
+}
+
+/// An entry in the timeline of a reverse swap.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ReverseSwapTimelineEvent {
+    pub stage: ReverseSwapStage,
+    /// The tx relevant to the stage. Present for the lockup and claim stages.
+    pub txid: Option<String>,
+    /// When the stage was observed by this device. Not present if the stage wasn't observed on
+    /// its own but is implied by a later stage, e.g. because the app wasn't running meanwhile.
+    pub at: Option<TzTime>,
+}
+
+/// Determines the stages a reverse swap has reached given its current state.
+///
+/// The stages observable through the current status get `now` as their time. Earlier stages
+/// implied by the current status are included without a time.
+///
+/// Parameters:
+/// * `claim_confirmed_at_height` - the height of the block confirming the claim tx, if known
+pub(crate) fn reached_stages(
+    reverse_swap: &breez_sdk_core::ReverseSwapInfo,
+    claim_confirmed_at_height: Option<u32>,
+    now: SystemTime,
+) -> Vec<ReverseSwapTimelineEntry> {
+    let implied = |stage, txid: &Option<String>| ReverseSwapTimelineEntry {
+        stage,
+        txid: txid.clone(),
+        block_height: None,
+        occurred_at: None,
+    };
+    let observed = |stage, txid: &Option<String>| ReverseSwapTimelineEntry {
+        occurred_at: Some(now),
+        ..implied(stage, txid)
+    };
+    let lockup_txid = &reverse_swap.lockup_txid;
+    let claim_txid = &reverse_swap.claim_txid;
+    match reverse_swap.status {
+        ReverseSwapStatus::Initial => vec![observed(ReverseSwapStage::Initiated, &None)],
+        ReverseSwapStatus::InProgress => vec![
+            implied(ReverseSwapStage::Initiated, &None),
+            observed(ReverseSwapStage::LockupTxSeen, lockup_txid),
+        ],
+        ReverseSwapStatus::CompletedSeen => vec![
+            implied(ReverseSwapStage::Initiated, &None),
+            implied(ReverseSwapStage::LockupTxSeen, lockup_txid),
+            observed(ReverseSwapStage::LockupTxConfirmed, lockup_txid),
+            observed(ReverseSwapStage::ClaimTxBroadcast, claim_txid),
+        ],
+        ReverseSwapStatus::CompletedConfirmed => vec![
+            implied(ReverseSwapStage::Initiated, &None),
+            implied(ReverseSwapStage::LockupTxSeen, lockup_txid),
+            implied(ReverseSwapStage::LockupTxConfirmed, lockup_txid),
+            implied(ReverseSwapStage::ClaimTxBroadcast, claim_txid),
+            ReverseSwapTimelineEntry {
+                block_height: claim_confirmed_at_height,
+                ..observed(ReverseSwapStage::ClaimTxConfirmed, claim_txid)
+            },
+        ],
+        ReverseSwapStatus::Cancelled => vec![
+            implied(ReverseSwapStage::Initiated, &None),
+            observed(ReverseSwapStage::Cancelled, &None),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reached_stages() {
+        let mut reverse_swap = breez_sdk_core::ReverseSwapInfo {
+            id: "id".to_string(),
+            claim_pubkey: "address".to_string(),
+            lockup_txid: None,
+            claim_txid: None,
+            onchain_amount_sat: 100_000,
+            status: ReverseSwapStatus::Initial,
+        };
+        let now = SystemTime::now();
+        let stages = |r: &breez_sdk_core::ReverseSwapInfo| {
+            reached_stages(r, Some(800_000), now)
+                .into_iter()
+                .map(|e| (e.stage, e.txid, e.block_height, e.occurred_at))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            stages(&reverse_swap),
+            vec![(ReverseSwapStage::Initiated, None, None, Some(now))]
+        );
+
+        reverse_swap.status = ReverseSwapStatus::Cancelled;
+        assert_eq!(
+            stages(&reverse_swap),
+            vec![
+                (ReverseSwapStage::Initiated, None, None, None),
+                (ReverseSwapStage::Cancelled, None, None, Some(now)),
+            ]
+        );
+
+        let lockup = Some("lockup".to_string());
+        let claim = Some("claim".to_string());
+        reverse_swap.lockup_txid = lockup.clone();
+        reverse_swap.claim_txid = claim.clone();
+        reverse_swap.status = ReverseSwapStatus::CompletedSeen;
+        assert_eq!(
+            stages(&reverse_swap),
+            vec![
+                (ReverseSwapStage::Initiated, None, None, None),
+                (ReverseSwapStage::LockupTxSeen, lockup.clone(), None, None),
+                (
+                    ReverseSwapStage::LockupTxConfirmed,
+                    lockup.clone(),
+                    None,
+                    Some(now)
+                ),
+                (
+                    ReverseSwapStage::ClaimTxBroadcast,
+                    claim.clone(),
+                    None,
+                    Some(now)
+                ),
+            ]
+        );
+
+        reverse_swap.status = ReverseSwapStatus::CompletedConfirmed;
+        assert_eq!(
+            stages(&reverse_swap),
+            vec![
+                (ReverseSwapStage::Initiated, None, None, None),
+                (ReverseSwapStage::LockupTxSeen, lockup.clone(), None, None),
+                (ReverseSwapStage::LockupTxConfirmed, lockup, None, None),
+                (
+                    ReverseSwapStage::ClaimTxBroadcast,
+                    claim.clone(),
+                    None,
+                    None
+                ),
+                (
+                    ReverseSwapStage::ClaimTxConfirmed,
+                    claim,
+                    Some(800_000),
+                    Some(now)
+                ),
+            ]
+        );
+    }
 }
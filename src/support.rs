@@ -1,25 +1,28 @@
-use crate::amount::{AsSats, ToAmount};
+use crate::amount::{AsSats, DisplayRates, ToAmount};
 use crate::analytics::AnalyticsInterceptor;
 use crate::async_runtime::AsyncRuntime;
 use crate::data_store::DataStore;
 use crate::errors::Result;
+use crate::exchange_rate_provider::{is_older_than, ExchangeRateProviderChain};
 use crate::locker::Locker;
 use crate::phone_number::PhoneNumberPrefixParser;
 use crate::task_manager::TaskManager;
 use crate::util::LogIgnoreError;
 use crate::{
-    CalculateLspFeeResponseV2, ChannelsInfo, ExchangeRate, LightningNodeConfig, NodeInfo, Offer,
-    RuntimeErrorCode, UserPreferences,
+    CalculateLspFeeResponseV2, ChannelsInfo, ExchangeRate, LightningNodeConfig, LspFee, NodeInfo,
+    Offer, RuntimeErrorCode, UserPreferences,
 };
 use breez_sdk_core::{
     BreezServices, OpeningFeeParams, ReportIssueRequest, ReportPaymentFailureDetails,
     UnspentTransactionOutput,
 };
+use chrono::DateTime;
 use crow::OfferManager;
 use honeybadger::Auth;
 use log::{debug, Level};
-use perro::MapToError;
+use perro::{ensure, runtime_error, MapToError};
 use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[allow(dead_code)]
 pub(crate) struct Support {
@@ -31,6 +34,7 @@ pub(crate) struct Support {
     pub rt: Arc<AsyncRuntime>,
     pub data_store: Arc<Mutex<DataStore>>,
     pub task_manager: Arc<Mutex<TaskManager>>,
+    pub exchange_rate_providers: Arc<ExchangeRateProviderChain>,
     pub allowed_countries_country_iso_3166_1_alpha_2: Vec<String>,
     pub phone_number_prefix_parser: PhoneNumberPrefixParser,
     pub persistence_encryption_key: [u8; 32],
@@ -51,7 +55,7 @@ impl Support {
     ///
     /// Requires network: **no**
     pub fn get_exchange_rate(&self) -> Option<ExchangeRate> {
-        let rates = self.task_manager.lock_unwrap().get_exchange_rates();
+        let rates = self.get_exchange_rates();
         let currency_code = self.user_preferences.lock_unwrap().fiat_currency.clone();
         rates
             .iter()
@@ -59,8 +63,76 @@ impl Support {
             .cloned()
     }
 
+    /// Get the current exchange rates, leaving out the ones older than
+    /// [`ExchangeRateStalenessConfig::discard_after`](crate::ExchangeRateStalenessConfig::discard_after).
     pub fn get_exchange_rates(&self) -> Vec<ExchangeRate> {
-        self.task_manager.lock_unwrap().get_exchange_rates()
+        let mut rates = self.task_manager.lock_unwrap().get_exchange_rates();
+        if let Some(discard_after) = self
+            .node_config
+            .exchange_rate_staleness_config
+            .discard_after
+        {
+            let now = SystemTime::now();
+            rates.retain(|r| !is_older_than(r, discard_after, now));
+        }
+        rates
+    }
+
+    /// Get the exchange rates of the selected fiat currency and of the configured secondary
+    /// fiat currencies, used to compute the fiat values of new [`Amount`](crate::Amount)s.
+    ///
+    /// Requires network: **no**
+    pub fn get_display_rates(&self) -> DisplayRates {
+        let rates = self.get_exchange_rates();
+        let user_preferences = self.user_preferences.lock_unwrap();
+        DisplayRates::select(
+            &rates,
+            &user_preferences.fiat_currency,
+            &user_preferences.secondary_fiat_currencies,
+        )
+        .with_stale_after(self.node_config.exchange_rate_staleness_config.stale_after)
+    }
+
+    /// Get the exchange rates to compute the fiat values of a past payment.
+    ///
+    /// For the secondary fiat currencies, the exchange rates snapshot taken when the payment was
+    /// made is used where available, falling back to the current exchange rates.
+    ///
+    /// Parameters:
+    /// * `payment_hash` - hex representation of the payment hash
+    /// * `primary_rate` - the rate to use for the selected fiat currency
+    ///
+    /// Requires network: **no**
+    pub fn get_payment_display_rates(
+        &self,
+        payment_hash: &str,
+        primary_rate: Option<ExchangeRate>,
+    ) -> Result<DisplayRates> {
+        let current_rates = self.get_display_rates();
+        if current_rates.secondary.is_empty() {
+            return Ok(primary_rate.into());
+        }
+
+        let historic_rates = self
+            .data_store
+            .lock_unwrap()
+            .retrieve_payment_exchange_rates(payment_hash)?;
+        let secondary = current_rates
+            .secondary
+            .into_iter()
+            .map(|current| {
+                historic_rates
+                    .iter()
+                    .find(|r| r.currency_code == current.currency_code)
+                    .cloned()
+                    .unwrap_or(current)
+            })
+            .collect();
+        Ok(DisplayRates {
+            primary: primary_rate,
+            secondary,
+            stale_after: None,
+        })
     }
 
     /// Request some basic info about the node
@@ -71,7 +143,7 @@ impl Support {
             RuntimeErrorCode::NodeUnavailable,
             "Failed to read node info",
         )?;
-        let rate = self.get_exchange_rate();
+        let rate = self.get_display_rates();
 
         Ok(NodeInfo {
             node_pubkey: node_state.id,
@@ -107,6 +179,27 @@ impl Support {
         Ok(node_state.utxos)
     }
 
+    /// Ensures that an LSP fee for receiving the given amount doesn't exceed the limit set through
+    /// [`Config::set_max_lsp_fee`](crate::Config::set_max_lsp_fee).
+    pub(crate) fn ensure_lsp_fee_within_limit(
+        &self,
+        fee_msat: u64,
+        amount_msat: u64,
+    ) -> Result<()> {
+        if fee_msat == 0 {
+            return Ok(());
+        }
+        let max_lsp_fee = self.data_store.lock_unwrap().retrieve_max_lsp_fee()?;
+        ensure!(
+            max_lsp_fee.is_none_or(|m| m.accepts_fee(fee_msat, amount_msat)),
+            runtime_error(
+                RuntimeErrorCode::LspFeeAboveLimit,
+                format!("The LSP fee of {fee_msat} msats exceeds the set limit")
+            )
+        );
+        Ok(())
+    }
+
     /// Calculate the actual LSP fee for the given amount of an incoming payment,
     /// providing the fee params that the LSP offers.
     /// Returns 0 if no new channel is required.
@@ -129,34 +222,17 @@ impl Support {
             .max_receivable_single_payment
             .sats;
         let lsp_fee = if amount_sat > max_receivable {
-            let lsp_fee_sat = amount_sat * lsp_fee_param.proportional as u64 / 1_000_000;
-            let lsp_fee_msat_rounded_to_sat = lsp_fee_sat * 1000;
-
-            std::cmp::max(lsp_fee_msat_rounded_to_sat, lsp_fee_param.min_msat)
+            lsp_fee_msat(amount_sat, &lsp_fee_param)
         } else {
             0
         };
 
         Ok(CalculateLspFeeResponseV2 {
-            lsp_fee: lsp_fee.as_msats().to_amount_up(&self.get_exchange_rate()),
+            lsp_fee: lsp_fee.as_msats().to_amount_up(&self.get_display_rates()),
             lsp_fee_params: lsp_fee_param,
         })
     }
 
-    /// Query the current recommended on-chain fee rate.
-    pub(crate) fn query_onchain_fee_rate(&self) -> Result<u32> {
-        let recommended_fees = self
-            .rt
-            .handle()
-            .block_on(self.sdk.recommended_fees())
-            .map_to_runtime_error(
-                RuntimeErrorCode::NodeUnavailable,
-                "Couldn't fetch recommended fees",
-            )?;
-
-        Ok(recommended_fees.half_hour_fee as u32)
-    }
-
     pub fn report_send_payment_issue(&self, payment_hash: String) {
         debug!("Reporting failure of payment: {payment_hash}");
         let data = ReportPaymentFailureDetails {
@@ -179,3 +255,40 @@ impl Support {
             .log_ignore_error(Level::Error, "Failed to persist payment info")
     }
 }
+
+/// Until when the LSP honors the fee offer. Offers with an invalid timestamp are treated as
+/// expired.
+pub(crate) fn lsp_fee_valid_until(lsp_fee_param: &OpeningFeeParams) -> SystemTime {
+    DateTime::parse_from_rfc3339(&lsp_fee_param.valid_until)
+        .map(SystemTime::from)
+        .unwrap_or(UNIX_EPOCH)
+}
+
+pub(crate) fn to_lsp_fee(lsp_fee_param: &OpeningFeeParams, rate: &DisplayRates) -> LspFee {
+    LspFee {
+        channel_minimum_fee: lsp_fee_param.min_msat.as_msats().to_amount_up(rate),
+        channel_fee_permyriad: lsp_fee_param.proportional as u64 / 100,
+        valid_until: lsp_fee_valid_until(lsp_fee_param),
+    }
+}
+
+/// The fee charged by the LSP for opening a channel to receive `amount_sat`.
+pub(crate) fn lsp_fee_msat(amount_sat: u64, lsp_fee_param: &OpeningFeeParams) -> u64 {
+    let lsp_fee_sat = amount_sat * lsp_fee_param.proportional as u64 / 1_000_000;
+    let lsp_fee_msat_rounded_to_sat = lsp_fee_sat * 1000;
+
+    std::cmp::max(lsp_fee_msat_rounded_to_sat, lsp_fee_param.min_msat)
+}
+
+/// Fee params offered by an LSP, for use in tests.
+#[cfg(test)]
+pub(crate) fn fee_params(min_msat: u64, proportional: u32) -> OpeningFeeParams {
+    OpeningFeeParams {
+        min_msat,
+        proportional,
+        valid_until: "2030-01-01T00:00:00Z".to_string(),
+        max_idle_time: 0,
+        max_client_to_self_delay: 0,
+        promise: String::new(),
+    }
+}
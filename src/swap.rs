@@ -2,6 +2,7 @@ use crate::amount::Amount;
 use crate::node_config::TzTime;
 
 use breez_sdk_core::OpeningFeeParams;
+use num_enum::TryFromPrimitive;
 use std::time::SystemTime;
 
 /// Information about a pending or successful swap.
@@ -12,6 +13,50 @@ pub struct SwapInfo {
     pub paid_amount: Amount,
 }
 
+/// The state of a swap address as listed by [`Swap::list`](crate::Swap::list).
+///
+/// A swap address is only meant to be used once. Funds sent to an address of a swap that is
+/// already completed or refunded won't be swapped, the swap becomes [`SwapState::Refundable`]
+/// again instead and the funds have to be recovered on-chain.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum SwapState {
+    /// No funds were sent to the address yet.
+    Unused,
+    /// Funds were sent to the address, but the deposit tx isn't confirmed yet.
+    AwaitingConfirmation,
+    /// The deposit tx is confirmed and the funds are being swapped into LN.
+    InProgress,
+    /// The funds were received in LN.
+    Completed,
+    /// The swap failed and the funds can be recovered on-chain.
+    Refundable,
+    /// The funds were recovered on-chain.
+    Refunded,
+}
+
+/// A swap created by the local wallet as returned by [`Swap::list`](crate::Swap::list).
+#[derive(PartialEq, Debug)]
+pub struct SwapDetails {
+    pub address: String,
+    pub state: SwapState,
+    pub created_at: TzTime,
+    /// The total amount sent to the address, including unconfirmed deposits.
+    pub deposited_amount: Amount,
+    /// The amount received in LN. Zero until the swap is completed.
+    pub paid_amount: Amount,
+    pub confirmed_deposit_txids: Vec<String>,
+    pub unconfirmed_deposit_txids: Vec<String>,
+    pub refund_txids: Vec<String>,
+    /// The number of confirmations of the deposit. Only present once the deposit is confirmed.
+    pub confirmations: Option<u32>,
+    /// The number of blocks after the confirmation of the deposit after which the swap can't be
+    /// completed anymore and the funds can only be recovered on-chain.
+    pub lock_timeout_blocks: u32,
+    /// The number of blocks left until the lock timeout is reached.
+    /// Only present once the deposit is confirmed.
+    pub blocks_until_lock_timeout: Option<u32>,
+}
+
 /// Information about a generated swap address
 pub struct SwapAddressInfo {
     /// Funds sent to this address will be swapped into LN to be received by the local wallet
@@ -24,6 +69,46 @@ pub struct SwapAddressInfo {
     pub swap_fee: Amount,
 }
 
+/// The reason why a deposit to a swap address can't be swapped into LN.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, TryFromPrimitive)]
+#[repr(u8)]
+pub enum SwapDepositIssue {
+    /// Less than [`SwapAddressInfo::min_deposit`] was sent to the address.
+    BelowMinimum,
+    /// More than [`SwapAddressInfo::max_deposit`] was sent to the address.
+    AboveMaximum,
+}
+
+/// A deposit to a swap address outside the range accepted by the swap service.
+///
+/// Such a deposit won't be swapped into LN. Once it's confirmed, the swap fails and the funds
+/// have to be recovered on-chain or swapped back to LN as an
+/// [`ActionRequiredItem::UnresolvedFailedSwap`](crate::ActionRequiredItem::UnresolvedFailedSwap).
+#[derive(PartialEq, Debug)]
+pub struct OutOfRangeSwapDeposit {
+    pub address: String,
+    pub issue: SwapDepositIssue,
+    /// The total amount sent to the address, including unconfirmed deposits.
+    pub deposited_amount: Amount,
+    pub min_deposit: Amount,
+    pub max_deposit: Amount,
+}
+
+/// Checks whether the funds sent to a swap address are within the range accepted by the swap
+/// service. Unconfirmed deposits are taken into account.
+pub(crate) fn deposit_issue(swap: &breez_sdk_core::SwapInfo) -> Option<SwapDepositIssue> {
+    let deposited_sat = swap.confirmed_sats + swap.unconfirmed_sats;
+    if deposited_sat == 0 {
+        None
+    } else if deposited_sat < swap.min_allowed_deposit as u64 {
+        Some(SwapDepositIssue::BelowMinimum)
+    } else if deposited_sat > swap.max_allowed_deposit as u64 {
+        Some(SwapDepositIssue::AboveMaximum)
+    } else {
+        None
+    }
+}
+
 /// Information about a failed swap
 #[derive(Clone)]
 pub struct FailedSwapInfo {
@@ -57,3 +142,52 @@ pub struct SwapToLightningFees {
     pub total_fees: Amount,
     pub lsp_fee_params: OpeningFeeParams,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use breez_sdk_core::SwapStatus;
+
+    #[test]
+    fn test_deposit_issue() {
+        let mut swap = breez_sdk_core::SwapInfo {
+            bitcoin_address: "address".to_string(),
+            created_at: 0,
+            lock_height: 288,
+            payment_hash: vec![],
+            preimage: vec![],
+            private_key: vec![],
+            public_key: vec![],
+            swapper_public_key: vec![],
+            script: vec![],
+            bolt11: None,
+            paid_msat: 0,
+            total_incoming_txs: 0,
+            confirmed_sats: 0,
+            unconfirmed_sats: 0,
+            status: SwapStatus::Initial,
+            refund_tx_ids: vec![],
+            unconfirmed_tx_ids: vec![],
+            confirmed_tx_ids: vec![],
+            min_allowed_deposit: 1_000,
+            max_allowed_deposit: 100_000,
+            max_swapper_payable: 100_000,
+            last_redeem_error: None,
+            channel_opening_fees: None,
+            confirmed_at: None,
+        };
+        assert_eq!(deposit_issue(&swap), None);
+
+        swap.unconfirmed_sats = 999;
+        assert_eq!(deposit_issue(&swap), Some(SwapDepositIssue::BelowMinimum));
+
+        swap.unconfirmed_sats = 1_000;
+        assert_eq!(deposit_issue(&swap), None);
+
+        swap.confirmed_sats = 99_000;
+        assert_eq!(deposit_issue(&swap), None);
+
+        swap.confirmed_sats = 99_001;
+        assert_eq!(deposit_issue(&swap), Some(SwapDepositIssue::AboveMaximum));
+    }
+}
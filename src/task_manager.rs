@@ -1,16 +1,26 @@
+use crate::amount::DisplayRates;
 use crate::async_runtime::{Handle, RepeatingTaskHandle};
-use crate::data_store::{BackupStatus, DataStore};
+use crate::channel::{detect_channel_closes, fetch_channels};
+use crate::data_store::{BackupStatus, DataStore, LspFeeOfferKind};
 use crate::errors::Result;
-use crate::exchange_rate_provider::{ExchangeRate, ExchangeRateProvider};
+use crate::exchange_rate_alert::evaluate_exchange_rate_alerts;
+use crate::exchange_rate_provider::{is_older_than, ExchangeRate, ExchangeRateProvider};
+use crate::lightning::outbox::flush_outbox;
 use crate::locker::Locker;
-use crate::{BreezHealthCheckStatus, EventsCallback, RuntimeErrorCode};
+use crate::onchain::Onchain;
+use crate::support::lsp_fee_valid_until;
+use crate::{
+    BreezHealthCheckStatus, EventsCallback, ExchangeRateStalenessConfig, RuntimeErrorCode,
+    UserPreferences,
+};
 use std::env;
 
 use crate::backup::BackupManager;
 use breez_sdk_core::{BreezServices, OpeningFeeParams};
-use log::{debug, error};
+use log::{debug, error, warn};
 use perro::OptionToError;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::SystemTime;
 use tokio::time::Duration;
 
 pub(crate) struct TaskPeriods {
@@ -19,20 +29,60 @@ pub(crate) struct TaskPeriods {
     pub update_lsp_fee: Option<Duration>,
     pub backup: Option<Duration>,
     pub health_status_check: Option<Duration>,
+    pub flush_outbox: Option<Duration>,
+    pub auto_resolve_funds: Option<Duration>,
+}
+
+/// Offers are refreshed once they expire within this margin, so a cached offer stays usable for a
+/// while after it is handed out.
+const LSP_FEE_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+/// Offers are refreshed at least this often, to pick up fee changes of the LSP.
+const LSP_FEE_MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+struct LspFeeOffers {
+    cheaper: Option<OpeningFeeParams>,
+    longer_valid: Option<OpeningFeeParams>,
+    /// When the offers were last fetched from the LSP. `None` if they were loaded from the
+    /// local db.
+    fetched_at: Option<SystemTime>,
+}
+
+impl LspFeeOffers {
+    fn needs_refresh(&self, now: SystemTime) -> bool {
+        let expires_soon = |offer: &Option<OpeningFeeParams>| {
+            offer
+                .as_ref()
+                .is_none_or(|o| lsp_fee_valid_until(o) < now + LSP_FEE_REFRESH_MARGIN)
+        };
+        let is_old = self.fetched_at.is_none_or(|t| t + LSP_FEE_MAX_AGE <= now);
+        expires_soon(&self.cheaper) || expires_soon(&self.longer_valid) || is_old
+    }
+}
+
+fn valid_offer(offer: &Option<OpeningFeeParams>) -> Option<OpeningFeeParams> {
+    offer
+        .clone()
+        .filter(|o| lsp_fee_valid_until(o) > SystemTime::now())
 }
 
 pub(crate) struct TaskManager {
     runtime_handle: Handle,
     exchange_rate_provider: Arc<dyn ExchangeRateProvider>,
     exchange_rates: Arc<Mutex<Vec<ExchangeRate>>>,
+    exchange_rate_staleness_config: ExchangeRateStalenessConfig,
+    exchange_rates_stale: Arc<Mutex<bool>>,
     data_store: Arc<Mutex<DataStore>>,
+    user_preferences: Arc<Mutex<UserPreferences>>,
     sdk: Arc<BreezServices>,
-    lsp_fee_cheaper: Arc<Mutex<Option<OpeningFeeParams>>>,
-    lsp_fee_longer_valid: Arc<Mutex<Option<OpeningFeeParams>>>,
+    lsp_fee_offers: Arc<Mutex<LspFeeOffers>>,
     backup_manager: Arc<BackupManager>,
     events_callback: Arc<Box<dyn EventsCallback>>,
     breez_health_status: Arc<Mutex<Option<BreezHealthCheckStatus>>>,
     breez_sdk_api_key: String,
+    /// Weak to avoid a reference cycle, as [`Onchain`] indirectly holds the task manager.
+    onchain: Option<Weak<Onchain>>,
+    /// Outbox payments that were being sent when the app was stopped and are yet to be resolved.
+    interrupted_outbox_payment_ids: Arc<Mutex<Vec<u64>>>,
 
     task_handles: Vec<RepeatingTaskHandle>,
 }
@@ -40,9 +90,11 @@ pub(crate) struct TaskManager {
 const FOREGROUND_PERIODS: TaskPeriods = TaskPeriods {
     update_exchange_rates: Some(Duration::from_secs(60)),
     sync_breez: Some(Duration::from_secs(10 * 60)),
-    update_lsp_fee: Some(Duration::from_secs(10 * 60)),
+    update_lsp_fee: Some(Duration::from_secs(60)),
     backup: Some(Duration::from_secs(30)),
     health_status_check: Some(Duration::from_secs(70)),
+    flush_outbox: Some(Duration::from_secs(30)),
+    auto_resolve_funds: Some(Duration::from_secs(10 * 60)),
 };
 
 const BACKGROUND_PERIODS: TaskPeriods = TaskPeriods {
@@ -51,56 +103,82 @@ const BACKGROUND_PERIODS: TaskPeriods = TaskPeriods {
     update_lsp_fee: None,
     backup: None,
     health_status_check: None,
+    flush_outbox: None,
+    auto_resolve_funds: None,
 };
 impl TaskManager {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         runtime_handle: Handle,
-        exchange_rate_provider: Box<dyn ExchangeRateProvider>,
+        exchange_rate_provider: Arc<dyn ExchangeRateProvider>,
+        exchange_rate_staleness_config: ExchangeRateStalenessConfig,
         data_store: Arc<Mutex<DataStore>>,
+        user_preferences: Arc<Mutex<UserPreferences>>,
         sdk: Arc<BreezServices>,
         backup_manager: BackupManager,
         events_callback: Arc<Box<dyn EventsCallback>>,
         breez_sdk_api_key: String,
     ) -> Result<Self> {
         let exchange_rates = data_store.lock_unwrap().get_all_exchange_rates()?;
+        // Nothing is sent before the tasks start, so these were interrupted by the app stopping
+        let interrupted_outbox_payment_ids = data_store
+            .lock_unwrap()
+            .retrieve_sending_outbox_payment_ids()?;
+        let lsp_fee_offers = LspFeeOffers {
+            cheaper: valid_offer(
+                &data_store
+                    .lock_unwrap()
+                    .retrieve_lsp_fee_offer(LspFeeOfferKind::Cheaper)?,
+            ),
+            longer_valid: valid_offer(
+                &data_store
+                    .lock_unwrap()
+                    .retrieve_lsp_fee_offer(LspFeeOfferKind::LongerValid)?,
+            ),
+            fetched_at: None,
+        };
 
         Ok(Self {
             runtime_handle,
-            exchange_rate_provider: Arc::from(exchange_rate_provider),
+            exchange_rate_provider,
             exchange_rates: Arc::new(Mutex::new(exchange_rates)),
+            exchange_rate_staleness_config,
+            exchange_rates_stale: Arc::new(Mutex::new(false)),
             data_store,
+            user_preferences,
             sdk,
-            lsp_fee_cheaper: Arc::new(Mutex::new(None)),
-            lsp_fee_longer_valid: Arc::new(Mutex::new(None)),
+            lsp_fee_offers: Arc::new(Mutex::new(lsp_fee_offers)),
             backup_manager: Arc::new(backup_manager),
             events_callback,
             breez_health_status: Arc::new(Mutex::new(None)),
             task_handles: Vec::new(),
             breez_sdk_api_key,
+            onchain: None,
+            interrupted_outbox_payment_ids: Arc::new(Mutex::new(interrupted_outbox_payment_ids)),
         })
     }
 
+    /// Enables the automatic resolution of on-chain funds. Takes effect on the next restart of
+    /// the tasks.
+    pub fn set_onchain(&mut self, onchain: &Arc<Onchain>) {
+        self.onchain = Some(Arc::downgrade(onchain));
+    }
+
     pub fn get_exchange_rates(&self) -> Vec<ExchangeRate> {
         self.exchange_rates.lock_unwrap().clone()
     }
 
     pub fn get_cheaper_lsp_fee(&self) -> Result<OpeningFeeParams> {
-        self.lsp_fee_cheaper
-            .lock_unwrap()
-            .clone()
-            .ok_or_runtime_error(
-                RuntimeErrorCode::LspServiceUnavailable,
-                "Cached cheaper LSP fee isn't available",
-            )
+        valid_offer(&self.lsp_fee_offers.lock_unwrap().cheaper).ok_or_runtime_error(
+            RuntimeErrorCode::LspServiceUnavailable,
+            "Cached cheaper LSP fee isn't available",
+        )
     }
     pub fn get_longer_valid_lsp_fee(&self) -> Result<OpeningFeeParams> {
-        self.lsp_fee_longer_valid
-            .lock_unwrap()
-            .clone()
-            .ok_or_runtime_error(
-                RuntimeErrorCode::LspServiceUnavailable,
-                "Cached longer valid LSP fee isn't available",
-            )
+        valid_offer(&self.lsp_fee_offers.lock_unwrap().longer_valid).ok_or_runtime_error(
+            RuntimeErrorCode::LspServiceUnavailable,
+            "Cached longer valid LSP fee isn't available",
+        )
     }
 
     pub fn foreground(&mut self) {
@@ -146,16 +224,35 @@ impl TaskManager {
             self.task_handles
                 .push(self.start_health_status_check(period));
         }
+
+        // Flush outbox
+        if let Some(period) = periods.flush_outbox {
+            self.task_handles.push(self.start_outbox_flush(period));
+        }
+
+        // Auto resolve on-chain funds
+        if let (Some(period), Some(onchain)) = (periods.auto_resolve_funds, &self.onchain) {
+            self.task_handles
+                .push(self.start_funds_auto_resolution(period, Weak::clone(onchain)));
+        }
     }
 
     fn start_breez_sync(&self, period: Duration) -> RepeatingTaskHandle {
         let sdk = Arc::clone(&self.sdk);
+        let data_store = Arc::clone(&self.data_store);
+        let events_callback = Arc::clone(&self.events_callback);
         self.runtime_handle.spawn_repeating_task(period, move || {
             let sdk = Arc::clone(&sdk);
+            let data_store = Arc::clone(&data_store);
+            let events_callback = Arc::clone(&events_callback);
             async move {
                 debug!("Starting breez sdk sync");
                 if let Err(e) = sdk.sync().await {
                     error!("Failed to sync breez sdk: {e}");
+                    return;
+                }
+                if let Err(e) = report_channel_closes(&sdk, &data_store, &**events_callback).await {
+                    error!("Failed to detect channel closes: {e}");
                 }
             }
         })
@@ -165,10 +262,17 @@ impl TaskManager {
         let exchange_rate_provider = Arc::clone(&self.exchange_rate_provider);
         let exchange_rates = Arc::clone(&self.exchange_rates);
         let data_store = Arc::clone(&self.data_store);
+        let user_preferences = Arc::clone(&self.user_preferences);
+        let stale_after = self.exchange_rate_staleness_config.stale_after;
+        let exchange_rates_stale = Arc::clone(&self.exchange_rates_stale);
+        let events_callback = Arc::clone(&self.events_callback);
         self.runtime_handle.spawn_repeating_task(period, move || {
             let exchange_rate_provider = Arc::clone(&exchange_rate_provider);
             let exchange_rates = Arc::clone(&exchange_rates);
             let data_store = Arc::clone(&data_store);
+            let user_preferences = Arc::clone(&user_preferences);
+            let exchange_rates_stale = Arc::clone(&exchange_rates_stale);
+            let events_callback = Arc::clone(&events_callback);
             async move {
                 debug!("Starting exchange rate update task");
                 match tokio::task::spawn_blocking(move || {
@@ -178,6 +282,7 @@ impl TaskManager {
                 {
                     Ok(Ok(rates)) => {
                         persist_exchange_rates(&data_store, &rates);
+                        evaluate_exchange_rate_alerts(&data_store, &rates, &**events_callback);
                         *exchange_rates.lock_unwrap() = rates;
                         debug!("Updated exchange rates successfully");
                     }
@@ -188,49 +293,45 @@ impl TaskManager {
                         error!("Update exchange rates task panicked: {e}");
                     }
                 }
+
+                let fiat_currency = user_preferences.lock_unwrap().fiat_currency.clone();
+                let is_stale = exchange_rates
+                    .lock_unwrap()
+                    .iter()
+                    .find(|r| r.currency_code == fiat_currency)
+                    .is_some_and(|r| is_older_than(r, stale_after, SystemTime::now()));
+                let was_stale =
+                    std::mem::replace(&mut *exchange_rates_stale.lock_unwrap(), is_stale);
+                if is_stale != was_stale {
+                    events_callback.exchange_rate_staleness_changed_to(is_stale);
+                }
             }
         })
     }
 
+    /// Updates the cached LSP fees right away, e.g. after switching to another LSP.
+    pub fn request_lsp_fee_update(&self) {
+        self.runtime_handle.spawn(update_lsp_fee(
+            Arc::clone(&self.sdk),
+            Arc::clone(&self.data_store),
+            Arc::clone(&self.lsp_fee_offers),
+        ));
+    }
+
     fn start_lsp_fee_update(&self, period: Duration) -> RepeatingTaskHandle {
         let sdk = Arc::clone(&self.sdk);
-        let lsp_fee_longer_valid = Arc::clone(&self.lsp_fee_longer_valid);
-        let lsp_fee_cheaper = Arc::clone(&self.lsp_fee_cheaper);
+        let data_store = Arc::clone(&self.data_store);
+        let lsp_fee_offers = Arc::clone(&self.lsp_fee_offers);
         self.runtime_handle.spawn_repeating_task(period, move || {
             let sdk = Arc::clone(&sdk);
-            let lsp_fee_longer_valid = Arc::clone(&lsp_fee_longer_valid);
-            let lsp_fee_cheaper = Arc::clone(&lsp_fee_cheaper);
-
+            let data_store = Arc::clone(&data_store);
+            let lsp_fee_offers = Arc::clone(&lsp_fee_offers);
             async move {
-                debug!("Starting lsp fee update task");
-                match sdk.lsp_info().await {
-                    Ok(lsp_information) => {
-                        match lsp_information
-                            .opening_fee_params_list
-                            .get_cheapest_opening_fee_params()
-                        {
-                            Ok(opening_fee_params) => {
-                                *lsp_fee_cheaper.lock_unwrap() = Some(opening_fee_params);
-                            }
-                            Err(e) => {
-                                error!("Failed to retrieve cheapest opening fee params: {e}");
-                            }
-                        };
-                        match lsp_information
-                            .opening_fee_params_list
-                            .get_48h_opening_fee_params()
-                        {
-                            Ok(opening_fee_params) => {
-                                *lsp_fee_longer_valid.lock_unwrap() = Some(opening_fee_params);
-                            }
-                            Err(e) => {
-                                error!("Failed to retrieve 48 hours opening fee params: {e}");
-                            }
-                        };
-                    }
-                    Err(e) => {
-                        error!("Failed to update lsp fee: {e}");
-                    }
+                let needs_refresh = lsp_fee_offers
+                    .lock_unwrap()
+                    .needs_refresh(SystemTime::now());
+                if needs_refresh {
+                    update_lsp_fee(sdk, data_store, lsp_fee_offers).await;
                 }
             }
         })
@@ -302,12 +403,167 @@ impl TaskManager {
             }
         })
     }
+
+    fn start_outbox_flush(&self, period: Duration) -> RepeatingTaskHandle {
+        let sdk = Arc::clone(&self.sdk);
+        let data_store = Arc::clone(&self.data_store);
+        let user_preferences = Arc::clone(&self.user_preferences);
+        let exchange_rates = Arc::clone(&self.exchange_rates);
+        let events_callback = Arc::clone(&self.events_callback);
+        let interrupted_payment_ids = Arc::clone(&self.interrupted_outbox_payment_ids);
+        self.runtime_handle.spawn_repeating_task(period, move || {
+            let sdk = Arc::clone(&sdk);
+            let data_store = Arc::clone(&data_store);
+            let user_preferences = Arc::clone(&user_preferences);
+            let exchange_rates = Arc::clone(&exchange_rates);
+            let events_callback = Arc::clone(&events_callback);
+            let interrupted_payment_ids = Arc::clone(&interrupted_payment_ids);
+            async move {
+                debug!("Starting outbox flush task");
+                if let Err(e) = flush_outbox(
+                    &sdk,
+                    &data_store,
+                    &user_preferences,
+                    &exchange_rates,
+                    &**events_callback,
+                    &interrupted_payment_ids,
+                )
+                .await
+                {
+                    error!("Failed to flush outbox: {e}");
+                }
+            }
+        })
+    }
+
+    fn start_funds_auto_resolution(
+        &self,
+        period: Duration,
+        onchain: Weak<Onchain>,
+    ) -> RepeatingTaskHandle {
+        let events_callback = Arc::clone(&self.events_callback);
+        self.runtime_handle.spawn_repeating_task(period, move || {
+            let onchain = Weak::clone(&onchain);
+            let events_callback = Arc::clone(&events_callback);
+            async move {
+                debug!("Starting funds auto resolution task");
+                match tokio::task::spawn_blocking(move || match onchain.upgrade() {
+                    Some(onchain) => onchain.auto_resolve(),
+                    None => Ok(Vec::new()),
+                })
+                .await
+                {
+                    Ok(Ok(resolved)) => {
+                        for (source, txid) in resolved {
+                            events_callback.funds_auto_resolved(source, txid);
+                        }
+                    }
+                    Ok(Err(e)) => {
+                        error!("Failed to auto resolve funds: {e}");
+                    }
+                    Err(e) => {
+                        error!("Funds auto resolution task panicked: {e}");
+                    }
+                }
+            }
+        })
+    }
+}
+
+async fn report_channel_closes(
+    sdk: &BreezServices,
+    data_store: &Mutex<DataStore>,
+    events_callback: &dyn EventsCallback,
+) -> Result<()> {
+    // Amounts aren't needed, so no exchange rates are used.
+    let channels = fetch_channels(sdk, &DisplayRates::default()).await?;
+    // Read and write the known channels under one lock, so a concurrent LSP migration mark isn't
+    // overwritten.
+    let mut data_store = data_store.lock_unwrap();
+    let known_channels = data_store.retrieve_known_channels()?;
+    let (closes, updated_channels) = detect_channel_closes(&known_channels, channels);
+    for channel in updated_channels {
+        data_store.store_known_channel(&channel)?;
+    }
+    drop(data_store);
+
+    for (channel_id, reason) in closes {
+        warn!("Channel {channel_id} is closing: {reason:?}");
+        events_callback.channel_closed(channel_id, reason);
+    }
+    Ok(())
+}
+
+async fn update_lsp_fee(
+    sdk: Arc<BreezServices>,
+    data_store: Arc<Mutex<DataStore>>,
+    lsp_fee_offers: Arc<Mutex<LspFeeOffers>>,
+) {
+    debug!("Starting lsp fee update task");
+    match sdk.lsp_info().await {
+        Ok(lsp_information) => {
+            lsp_fee_offers.lock_unwrap().fetched_at = Some(SystemTime::now());
+            match lsp_information
+                .opening_fee_params_list
+                .get_cheapest_opening_fee_params()
+            {
+                Ok(opening_fee_params) => {
+                    persist_lsp_fee_offer(
+                        &data_store,
+                        LspFeeOfferKind::Cheaper,
+                        &opening_fee_params,
+                    );
+                    lsp_fee_offers.lock_unwrap().cheaper = Some(opening_fee_params);
+                }
+                Err(e) => {
+                    error!("Failed to retrieve cheapest opening fee params: {e}");
+                }
+            };
+            match lsp_information
+                .opening_fee_params_list
+                .get_48h_opening_fee_params()
+            {
+                Ok(opening_fee_params) => {
+                    persist_lsp_fee_offer(
+                        &data_store,
+                        LspFeeOfferKind::LongerValid,
+                        &opening_fee_params,
+                    );
+                    lsp_fee_offers.lock_unwrap().longer_valid = Some(opening_fee_params);
+                }
+                Err(e) => {
+                    error!("Failed to retrieve 48 hours opening fee params: {e}");
+                }
+            };
+        }
+        Err(e) => {
+            error!("Failed to update lsp fee: {e}");
+        }
+    }
+}
+
+fn persist_lsp_fee_offer(
+    data_store: &Mutex<DataStore>,
+    kind: LspFeeOfferKind,
+    fee_params: &OpeningFeeParams,
+) {
+    if let Err(e) = data_store
+        .lock_unwrap()
+        .store_lsp_fee_offer(kind, fee_params)
+    {
+        error!("Failed to persist LSP fee offer {kind:?}: {e}");
+    }
 }
 
 fn persist_exchange_rates(data_store: &Arc<Mutex<DataStore>>, rates: &[ExchangeRate]) {
     let mut data_store = data_store.lock_unwrap();
     for rate in rates {
-        match data_store.update_exchange_rate(&rate.currency_code, rate.rate, rate.updated_at) {
+        match data_store.update_exchange_rate(
+            &rate.currency_code,
+            rate.rate,
+            rate.updated_at,
+            &rate.source,
+        ) {
             Ok(_) => {}
             Err(e) => {
                 error!("Failed to update exchange rate in db: {e}")
@@ -329,6 +585,8 @@ fn get_foreground_periods() -> TaskPeriods {
                 update_lsp_fee: Some(period),
                 backup: Some(period),
                 health_status_check: Some(period),
+                flush_outbox: Some(period),
+                auto_resolve_funds: Some(period),
             }
         }
         Err(_) => FOREGROUND_PERIODS,
@@ -340,3 +598,51 @@ impl Drop for TaskManager {
         self.request_shutdown_all();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::support::fee_params;
+    use chrono::{DateTime, Utc};
+
+    fn offer_valid_until(valid_until: SystemTime) -> Option<OpeningFeeParams> {
+        Some(OpeningFeeParams {
+            valid_until: DateTime::<Utc>::from(valid_until).to_rfc3339(),
+            ..fee_params(2_000_000, 4_000)
+        })
+    }
+
+    #[test]
+    fn test_lsp_fee_offers_needs_refresh() {
+        let now = SystemTime::now();
+        let in_an_hour = now + Duration::from_secs(60 * 60);
+
+        let offers = LspFeeOffers {
+            cheaper: offer_valid_until(in_an_hour),
+            longer_valid: offer_valid_until(in_an_hour),
+            fetched_at: Some(now),
+        };
+        assert!(!offers.needs_refresh(now));
+        assert!(offers.needs_refresh(now + LSP_FEE_MAX_AGE));
+
+        let loaded_from_db = LspFeeOffers {
+            fetched_at: None,
+            ..offers
+        };
+        assert!(loaded_from_db.needs_refresh(now));
+
+        let expiring_soon = LspFeeOffers {
+            cheaper: offer_valid_until(now + Duration::from_secs(60)),
+            longer_valid: offer_valid_until(in_an_hour),
+            fetched_at: Some(now),
+        };
+        assert!(expiring_soon.needs_refresh(now));
+
+        let missing = LspFeeOffers {
+            cheaper: offer_valid_until(in_an_hour),
+            longer_valid: None,
+            fetched_at: Some(now),
+        };
+        assert!(missing.needs_refresh(now));
+    }
+}
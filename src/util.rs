@@ -1,5 +1,7 @@
+use crate::channel::{apply_lsp_migrations, fetch_channels, ChannelInfo};
 use crate::errors::Result;
 use crate::locker::Locker;
+use crate::notification_log::NotificationOutcome;
 use crate::support::Support;
 use crate::{
     BreezHealthCheckStatus, DecodeDataError, DecodedData, ExchangeRate, InvoiceDetails,
@@ -42,14 +44,14 @@ impl Util {
                 Ok(DecodedData::Bolt11Invoice {
                     invoice_details: InvoiceDetails::from_ln_invoice(
                         invoice,
-                        &self.support.get_exchange_rate(),
+                        &self.support.get_display_rates(),
                     ),
                 })
             }
             Ok(InputType::LnUrlPay { data }) => Ok(DecodedData::LnUrlPay {
                 lnurl_pay_details: LnUrlPayDetails::from_lnurl_pay_request_data(
                     data,
-                    &self.support.get_exchange_rate(),
+                    &self.support.get_display_rates(),
                 )?,
             }),
             Ok(InputType::BitcoinAddress { address }) => Ok(DecodedData::OnchainAddress {
@@ -64,7 +66,7 @@ impl Util {
             Ok(InputType::LnUrlWithdraw { data }) => Ok(DecodedData::LnUrlWithdraw {
                 lnurl_withdraw_details: LnUrlWithdrawDetails::from_lnurl_withdraw_request_data(
                     data,
-                    &self.support.get_exchange_rate(),
+                    &self.support.get_display_rates(),
                 ),
             }),
             Ok(InputType::NodeId { .. }) => Err(DecodeDataError::Unsupported {
@@ -113,6 +115,49 @@ impl Util {
         self.support.get_node_info()
     }
 
+    /// Lists the channels of the node, including closed ones.
+    ///
+    /// The LSP opening fees are best-effort. The node doesn't record which received payment
+    /// caused which channel open, so the non-zero fees of received payments are matched to the
+    /// channels opened by the LSP in the order of their funding. If the number of such payments
+    /// and channels differs, e.g. because an open failed after the fee was charged, no channel
+    /// gets an opening fee.
+    ///
+    /// Requires network: **yes**
+    pub fn list_channels(&self) -> Result<Vec<ChannelInfo>> {
+        let rate = self.support.get_display_rates();
+        let mut channels = self
+            .support
+            .rt
+            .handle()
+            .block_on(fetch_channels(&self.support.sdk, &rate))?;
+        let known_channels = self
+            .support
+            .data_store
+            .lock_unwrap()
+            .retrieve_known_channels()?;
+        apply_lsp_migrations(&mut channels, &known_channels);
+        Ok(channels)
+    }
+
+    /// Lists the outcomes of the most recently handled notifications, most recent first. Useful for
+    /// diagnosing notification handling, as [`handle_notification`](crate::handle_notification)
+    /// runs outside of the app.
+    ///
+    /// Parameters:
+    /// * `number_of_outcomes` - the maximum number of outcomes to list
+    ///
+    /// Requires network: **no**
+    pub fn list_notification_outcomes(
+        &self,
+        number_of_outcomes: u32,
+    ) -> Result<Vec<NotificationOutcome>> {
+        self.support
+            .data_store
+            .lock_unwrap()
+            .retrieve_notification_outcomes(number_of_outcomes)
+    }
+
     /// Get exchange rate on the BTC/default currency pair
     /// Please keep in mind that this method doesn't make any network calls. It simply retrieves
     /// previously fetched values that are frequently updated by a background task.
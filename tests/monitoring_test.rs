@@ -4,8 +4,9 @@ mod setup;
 use crate::setup::{start_specific_node, Environment, NodeType};
 use std::fs::OpenOptions;
 use uniffi_lipalightninglib::{
-    Activity, BreezHealthCheckStatus, EventsCallback, InvoiceCreationMetadata, LightningNode,
-    PaymentMetadata, PaymentState,
+    Activity, BreezHealthCheckStatus, ChannelCloseReason, EventsCallback, ExchangeRate,
+    ExchangeRateAlert, InvoiceCreationMetadata, LightningNode, PaymentMetadata, PaymentState,
+    ResolvedFundsSource, SwapDepositIssue,
 };
 
 use anyhow::Result;
@@ -81,8 +82,8 @@ impl EventsCallback for ReturnFundsEventsHandler {
         self.received_payment_sender.send(payment_hash).unwrap();
     }
 
-    fn channel_closed(&self, channel_id: String, reason: String) {
-        panic!("A channel was closed! Channel ID {channel_id} was closed due to {reason}");
+    fn channel_closed(&self, channel_id: String, reason: ChannelCloseReason) {
+        panic!("A channel was closed! Channel ID {channel_id} was closed due to {reason:?}");
     }
 
     fn payment_sent(&self, payment_hash: String, _: String) {
@@ -93,10 +94,18 @@ impl EventsCallback for ReturnFundsEventsHandler {
         panic!("An outgoing payment has failed! Its hash is {payment_hash}");
     }
 
+    fn queued_payment_failed(&self, queued_payment_id: u64) {
+        panic!("A queued payment has failed! Its id is {queued_payment_id}");
+    }
+
     fn swap_received(&self, _payment_hash: String) {
         // do nothing
     }
 
+    fn swap_deposit_out_of_range(&self, _swap_address: String, _issue: SwapDepositIssue) {
+        // do nothing
+    }
+
     fn reverse_swap_sent(&self, _reverse_swap_id: String) {
         // do nothing
     }
@@ -113,6 +122,18 @@ impl EventsCallback for ReturnFundsEventsHandler {
         // do nothing
     }
 
+    fn exchange_rate_alert_triggered(&self, _alert: ExchangeRateAlert, _: ExchangeRate) {
+        // do nothing
+    }
+
+    fn exchange_rate_staleness_changed_to(&self, _is_stale: bool) {
+        // do nothing
+    }
+
+    fn funds_auto_resolved(&self, _source: ResolvedFundsSource, _txid: String) {
+        // do nothing
+    }
+
     fn synced(&self) {
         // do nothing
     }
@@ -1,4 +1,7 @@
-use uniffi_lipalightninglib::{BreezHealthCheckStatus, EventsCallback};
+use uniffi_lipalightninglib::{
+    BreezHealthCheckStatus, ChannelCloseReason, EventsCallback, ExchangeRate, ExchangeRateAlert,
+    ResolvedFundsSource, SwapDepositIssue,
+};
 
 pub struct PrintEventsHandler {}
 
@@ -7,8 +10,8 @@ impl EventsCallback for PrintEventsHandler {
         println!("Received a payment with hash {payment_hash}");
     }
 
-    fn channel_closed(&self, channel_id: String, reason: String) {
-        println!("A channel was closed! Channel ID {channel_id} was closed due to {reason}");
+    fn channel_closed(&self, channel_id: String, reason: ChannelCloseReason) {
+        println!("A channel was closed! Channel ID {channel_id} was closed due to {reason:?}");
     }
 
     fn payment_sent(&self, payment_hash: String, payment_preimage: String) {
@@ -19,10 +22,18 @@ impl EventsCallback for PrintEventsHandler {
         println!("An outgoing payment has failed! Its hash is {payment_hash}");
     }
 
+    fn queued_payment_failed(&self, queued_payment_id: u64) {
+        println!("A queued payment has failed! Its id is {queued_payment_id}");
+    }
+
     fn swap_received(&self, payment_hash: String) {
         println!("A swap has been received! Its hash is {payment_hash}");
     }
 
+    fn swap_deposit_out_of_range(&self, swap_address: String, issue: SwapDepositIssue) {
+        println!("A swap deposit to {swap_address} is out of the accepted range: {issue:?}");
+    }
+
     fn reverse_swap_sent(&self, reverse_swap_id: String) {
         println!("A reverse swap has been sent! Its id is {reverse_swap_id}");
     }
@@ -39,5 +50,24 @@ impl EventsCallback for PrintEventsHandler {
         println!("The Breez SDK health status changed to {status:?}");
     }
 
+    fn exchange_rate_alert_triggered(&self, alert: ExchangeRateAlert, exchange_rate: ExchangeRate) {
+        println!(
+            "Exchange rate alert {} triggered! {} is {:?} {} at {} sats per unit",
+            alert.id,
+            alert.currency_code,
+            alert.direction,
+            alert.threshold_minor_units,
+            exchange_rate.rate
+        );
+    }
+
+    fn exchange_rate_staleness_changed_to(&self, is_stale: bool) {
+        println!("The exchange rate is stale: {is_stale}");
+    }
+
+    fn funds_auto_resolved(&self, source: ResolvedFundsSource, txid: String) {
+        println!("Funds from {source:?} were automatically resolved with tx {txid}");
+    }
+
     fn synced(&self) {}
 }
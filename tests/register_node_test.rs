@@ -4,7 +4,8 @@ mod setup;
 use crate::print_events_handler::PrintEventsHandler;
 
 use uniffi_lipalightninglib::{
-    generate_secret, LightningNodeConfig, ReceiveLimitsConfig, TzConfig,
+    generate_secret, ExchangeRateStalenessConfig, LightningNodeConfig, ReceiveLimitsConfig,
+    TzConfig,
 };
 use uniffi_lipalightninglib::{
     BreezSdkConfig, LightningNode, MaxRoutingFeeConfig, RemoteServicesConfig,
@@ -14,6 +15,7 @@ use log::Level;
 use serial_test::file_serial;
 use std::fs;
 use std::string::ToString;
+use std::time::Duration;
 
 const LOCAL_PERSISTENCE_PATH: &str = ".3l_local_test";
 
@@ -64,6 +66,11 @@ fn test_register_node() {
             min_receive_channel_open_fee_multiplier: 2.0,
         },
         topup_referral_code_max_length: 40,
+        exchange_rate_staleness_config: ExchangeRateStalenessConfig {
+            stale_after: Duration::from_secs(30 * 60),
+            discard_after: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+        },
+        stuck_transaction_threshold: Duration::from_secs(3 * 60 * 60),
     };
 
     let events_handler = PrintEventsHandler {};
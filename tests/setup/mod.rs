@@ -1,14 +1,15 @@
 use crate::print_events_handler::PrintEventsHandler;
 
 use uniffi_lipalightninglib::{
-    mnemonic_to_secret, AnalyticsConfig, BreezSdkConfig, EventsCallback, LightningNode,
-    LightningNodeConfig, MaxRoutingFeeConfig, ReceiveLimitsConfig, RemoteServicesConfig,
-    RuntimeErrorCode, TzConfig,
+    mnemonic_to_secret, AnalyticsConfig, BreezSdkConfig, EventsCallback,
+    ExchangeRateStalenessConfig, LightningNode, LightningNodeConfig, MaxRoutingFeeConfig,
+    ReceiveLimitsConfig, RemoteServicesConfig, RuntimeErrorCode, TzConfig,
 };
 
 use log::Level;
 use std::fs;
 use std::string::ToString;
+use std::time::Duration;
 
 type Result<T> = std::result::Result<T, perro::Error<RuntimeErrorCode>>;
 
@@ -146,6 +147,11 @@ pub fn start_specific_node(
             min_receive_channel_open_fee_multiplier: 2.0,
         },
         topup_referral_code_max_length: MAX_REFERRAL_CODE_LENGTH,
+        exchange_rate_staleness_config: ExchangeRateStalenessConfig {
+            stale_after: Duration::from_secs(30 * 60),
+            discard_after: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+        },
+        stuck_transaction_threshold: Duration::from_secs(3 * 60 * 60),
     };
 
     let node = LightningNode::new(config, events_callback)?;
@@ -169,6 +169,8 @@ fn offer_info_from_actions_required_list(list: &[ActionRequiredItem]) -> Vec<Off
             ActionRequiredItem::UncompletedOffer { offer } => Some(offer.clone()),
             ActionRequiredItem::UnresolvedFailedSwap { .. } => None,
             ActionRequiredItem::ChannelClosesFundsAvailable { .. } => None,
+            ActionRequiredItem::CancelledInvoicePaid { .. } => None,
+            ActionRequiredItem::StuckTransaction { .. } => None,
         })
         .collect::<Vec<_>>()
 }